@@ -1,5 +1,7 @@
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use anyhow::{anyhow, bail, Result};
@@ -19,11 +21,11 @@ use goxlr_profile_loader::components::mute::MuteFunction;
 use goxlr_types::{
     Button, ChannelName, DeviceType, DisplayModeComponents, EffectBankPresets, EffectKey,
     EncoderName, FaderName, HardTuneSource, InputDevice as BasicInputDevice, MicrophoneParamKey,
-    Mix, MuteState, OutputDevice as BasicOutputDevice, RobotRange, SampleBank, SampleButtons,
-    SamplePlaybackMode, VersionNumber, WaterfallDirection,
+    MuteState, OutputDevice as BasicOutputDevice, RobotRange, SampleBank, SampleButtons,
+    SamplePlaybackMode, VersionNumber,
 };
-use goxlr_usb::animation::{AnimationMode, WaterFallDir};
-use goxlr_usb::buttonstate::{ButtonStates, Buttons};
+use goxlr_usb::animation::AnimationMode;
+use goxlr_usb::buttonstate::Buttons;
 use goxlr_usb::channelstate::ChannelState;
 use goxlr_usb::channelstate::ChannelState::{Muted, Unmuted};
 use goxlr_usb::device::base::FullGoXLRDevice;
@@ -35,7 +37,8 @@ use crate::events::EventTriggers::TTSMessage;
 use crate::files::find_file_in_path;
 use crate::mic_profile::{MicProfileAdapter, DEFAULT_MIC_PROFILE_NAME};
 use crate::profile::{
-    usb_to_standard_button, version_newer_or_equal_to, ProfileAdapter, DEFAULT_PROFILE_NAME,
+    usb_to_standard_button, version_newer_or_equal_to, ApplyOptions, ProfileAdapter,
+    DEFAULT_PROFILE_NAME,
 };
 use crate::SettingsHandle;
 
@@ -54,6 +57,9 @@ pub struct Device<'a> {
     settings: &'a SettingsHandle,
     global_events: Sender<EventTriggers>,
 
+    // Set when the daemon starts shutting down, see apply_profile_commands..
+    stopping: Arc<AtomicBool>,
+
     last_sample_error: Option<String>,
 }
 
@@ -89,6 +95,7 @@ impl<'a> Device<'a> {
         mic_profile_directory: &Path,
         settings_handle: &'a SettingsHandle,
         global_events: Sender<EventTriggers>,
+        stopping: Arc<AtomicBool>,
     ) -> Result<Device<'a>> {
         debug!("New Device Loading..");
 
@@ -157,10 +164,17 @@ impl<'a> Device<'a> {
             audio_handler,
             settings: settings_handle,
             global_events,
+            stopping,
 
             last_sample_error: None,
         };
 
+        // The Mini's faders aren't motorised, so rather than setting volumes which don't match
+        // where the faders physically are, pull their positions into the profile first.
+        if device.hardware.device_type == DeviceType::Mini {
+            device.sync_hardware_state()?;
+        }
+
         device.apply_profile(None).await?;
         device.apply_mic_profile().await?;
 
@@ -356,7 +370,8 @@ impl<'a> Device<'a> {
         for button in self.last_buttons {
             if !self.button_states[button].hold_handled {
                 let now = self.get_epoch_ms();
-                if (now - self.button_states[button].press_time) > self.hold_time.into() {
+                let hold_time = self.hold_time_for(button);
+                if (now - self.button_states[button].press_time) > hold_time.into() {
                     if let Err(error) = self.on_button_hold(button).await {
                         error!("{}", error);
                     }
@@ -445,6 +460,19 @@ impl<'a> Device<'a> {
         Ok(())
     }
 
+    // The profile can override the device's hold time for the mute buttons..
+    fn hold_time_for(&self, button: Buttons) -> u16 {
+        let duration = match button {
+            Buttons::Fader1Mute => self.profile.get_mute_hold_duration(FaderName::A),
+            Buttons::Fader2Mute => self.profile.get_mute_hold_duration(FaderName::B),
+            Buttons::Fader3Mute => self.profile.get_mute_hold_duration(FaderName::C),
+            Buttons::Fader4Mute => self.profile.get_mute_hold_duration(FaderName::D),
+            Buttons::MicrophoneMute => self.profile.get_mute_chat_hold_duration(),
+            _ => None,
+        };
+        duration.unwrap_or(self.hold_time)
+    }
+
     async fn on_button_hold(&mut self, button: Buttons) -> Result<()> {
         debug!("Handling Button Hold: {:?}", button);
 
@@ -1403,6 +1431,17 @@ impl<'a> Device<'a> {
         Ok(value_changed)
     }
 
+    fn sync_hardware_state(&mut self) -> Result<()> {
+        let state = self.goxlr.get_hardware_state()?;
+        if self.profile.apply_hardware_state(&state)? {
+            for (fader, volume) in state.fader_volumes {
+                let channel = self.profile.get_fader_assignment(fader);
+                self.update_submix_for(channel, volume)?;
+            }
+        }
+        Ok(())
+    }
+
     fn update_submix_for(&mut self, channel: ChannelName, volume: u8) -> Result<()> {
         if self.device_supports_submixes() && self.profile.is_submix_enabled() {
             if let Some(mix) = self.profile.get_submix_from_channel(channel) {
@@ -1516,10 +1555,7 @@ impl<'a> Device<'a> {
     }
 
     pub async fn get_mic_level(&mut self) -> Result<f64> {
-        let level = self.goxlr.get_microphone_level()?;
-
-        let db = ((f64::log(level.into(), 10.) * 20.) - 72.2).clamp(-72.2, 0.);
-        Ok(db)
+        Ok(self.goxlr.get_levels()?.microphone)
     }
 
     pub async fn perform_command(&mut self, command: GoXLRCommand) -> Result<()> {
@@ -2330,13 +2366,7 @@ impl<'a> Device<'a> {
                 let profile = ProfileAdapter::from_named(profile_name, &profile_directory)?;
                 debug!("Profile Loaded, Applying Colours..");
                 self.profile.load_colour_profile(profile);
-
-                if self.device_supports_animations() {
-                    self.load_animation(false).await?;
-                } else {
-                    self.load_colour_map().await?;
-                }
-                self.update_button_states()?;
+                self.apply_lighting().await?;
             }
             GoXLRCommand::SaveProfile() => {
                 let profile_directory = self.settings.get_profile_directory().await;
@@ -2570,23 +2600,11 @@ impl<'a> Device<'a> {
     }
 
     fn update_button_states(&mut self) -> Result<()> {
-        let button_states = self.create_button_states();
+        let button_states = self.profile.get_button_states();
         self.goxlr.set_button_states(button_states)?;
         Ok(())
     }
 
-    fn create_button_states(&self) -> [ButtonStates; 24] {
-        let mut result = [ButtonStates::DimmedColour1; 24];
-
-        for button in Buttons::iter() {
-            result[button as usize] = self.profile.get_button_colour_state(button);
-        }
-
-        // Replace the Cough Button button data with correct data.
-        result[Buttons::MicrophoneMute as usize] = self.profile.get_mute_chat_button_colour_state();
-        result
-    }
-
     // This applies routing for a single input channel..
     fn apply_channel_routing(
         &mut self,
@@ -3002,39 +3020,13 @@ impl<'a> Device<'a> {
 
         let use_1_3_40_format = self.device_supports_animations();
         let colour_map = self.profile.get_colour_map(use_1_3_40_format, blank_mute);
-
-        if use_1_3_40_format {
-            self.goxlr.set_button_colours_1_3_40(colour_map)?;
-        } else {
-            let mut map: [u8; 328] = [0; 328];
-            map.copy_from_slice(&colour_map[0..328]);
-            self.goxlr.set_button_colours(map)?;
-        }
+        self.goxlr.set_colour_map(&colour_map, use_1_3_40_format)?;
 
         Ok(())
     }
 
     async fn load_animation(&mut self, map_set: bool) -> Result<()> {
-        let enabled = self.profile.get_animation_mode() != goxlr_types::AnimationMode::None;
-
-        // This one is kinda weird, we go from profile -> types -> usb..
-        let mode = match self.profile.get_animation_mode() {
-            goxlr_types::AnimationMode::RetroRainbow => AnimationMode::RetroRainbow,
-            goxlr_types::AnimationMode::RainbowDark => AnimationMode::RainbowDark,
-            goxlr_types::AnimationMode::RainbowBright => AnimationMode::RainbowBright,
-            goxlr_types::AnimationMode::Simple => AnimationMode::Simple,
-            goxlr_types::AnimationMode::Ripple => AnimationMode::Ripple,
-            goxlr_types::AnimationMode::None => AnimationMode::None,
-        };
-
-        let mod1 = self.profile.get_animation_mod1();
-        let mod2 = self.profile.get_animation_mod2();
-        let waterfall = match self.profile.get_animation_waterfall() {
-            WaterfallDirection::Down => WaterFallDir::Down,
-            WaterfallDirection::Up => WaterFallDir::Up,
-            WaterfallDirection::Off => WaterFallDir::Off,
-        };
-
+        let (enabled, mode, mod1, mod2, waterfall) = self.profile.get_usb_animation();
         self.goxlr
             .set_animation_mode(enabled, mode, mod1, mod2, waterfall)?;
 
@@ -3049,37 +3041,109 @@ impl<'a> Device<'a> {
         Ok(())
     }
 
+    /// Sends only the lighting from the profile (colours, animation, fader display modes and
+    /// button states), none of the audio settings are touched so nothing can be heard.
+    async fn apply_lighting(&mut self) -> Result<()> {
+        if self.device_supports_animations() {
+            self.load_animation(false).await?;
+        } else {
+            self.load_colour_map().await?;
+        }
+
+        // The fader display style lives in the fader's colour map, so may have changed..
+        self.set_all_fader_display_from_profile()?;
+        self.update_button_states()
+    }
+
     async fn apply_profile(&mut self, current: Option<CurrentState>) -> Result<()> {
         // Set volumes first, applying mute may modify stuff..
         debug!("Applying Profile..");
 
+        // Force Mic Fader Assignment
+        match FaderName::iter().find(|&f| self.profile.get_fader_assignment(f) == ChannelName::Mic)
+        {
+            Some(fader) => self.profile.set_mic_fader(fader)?,
+            None => self.profile.clear_mic_fader(),
+        }
+
+        match current {
+            Some(current) => self.apply_profile_changes(current).await?,
+            None => {
+                // Nothing to compare against, so send everything the profile can build in one go..
+                let options = self.get_apply_options().await;
+                self.apply_profile_commands(options)?;
+            }
+        }
+
+        if self.hardware.device_type == DeviceType::Full {
+            for fader in FaderName::iter() {
+                self.apply_scribble(fader).await?;
+            }
+        }
+
+        debug!("Applying Routing..");
+        // For profile load, we should configure all the input channels from the profile,
+        // this is split so we can do tweaks in places where needed.
+        for input in BasicInputDevice::iter() {
+            self.apply_routing(input).await?;
+        }
+
+        debug!("Applying Voice FX");
+        self.apply_voice_fx()?;
+
+        // Drop this to the end so it doesn't directly interfere with profile loading..
+        debug!("Validating Sampler Configuration..");
+        self.validate_sampler().await?;
+
+        self.profile.mark_applied();
+        Ok(())
+    }
+
+    /// Sends the profile's command list (see ProfileAdapter::to_commands) to the device. This is a
+    /// few dozen commands, so progress is logged as it goes, and the rest are skipped if the
+    /// daemon is asked to shut down part way through.
+    fn apply_profile_commands(&mut self, options: ApplyOptions) -> Result<()> {
+        // A held cough button can't still be active on load..
+        let (mute_toggle, muted_to_x, _, _) = self.profile.get_mute_chat_button_state();
+        if !mute_toggle && muted_to_x {
+            self.profile.set_mute_chat_button_on(false);
+            self.profile.set_mute_chat_button_blink(false);
+        }
+
+        if options.submixes {
+            self.profile.sync_linked_submix_volumes()?;
+        }
+
+        let commands = self.profile.to_commands(options)?;
+        let mut progress = |done, total| debug!("Applied {} of {} Profile Commands", done, total);
+        self.goxlr
+            .execute_commands(&commands, &mut progress, &self.stopping)
+    }
+
+    async fn get_apply_options(&self) -> ApplyOptions {
+        let device_mini = self.hardware.device_type == DeviceType::Mini;
+        let lock_faders = self.settings.get_device_lock_faders(self.serial()).await;
+
+        ApplyOptions {
+            colour_format_1_3_40: self.device_supports_animations(),
+            blank_mute: device_mini || lock_faders,
+            animations: self.device_supports_animations(),
+            submixes: self.device_supports_submixes(),
+        }
+    }
+
+    // Applies the profile against the current device state, only sending what's changed..
+    async fn apply_profile_changes(&mut self, current: CurrentState) -> Result<()> {
         debug!("Setting Faders..");
-        let mut mic_assigned_to_fader = false;
-        //
-        // // Prepare the faders, and configure channel mute states
         for fader in FaderName::iter() {
             let assignment = self.profile.get_fader_assignment(fader);
 
-            if let Some(current) = &current {
-                if current.faders[fader] != assignment {
-                    debug!("Setting Fader {} to {:?}", fader, assignment);
-                    self.goxlr.set_fader(fader, assignment)?;
-                } else {
-                    debug!("Fader Already Assigned, ignoring");
-                }
-            } else {
+            if current.faders[fader] != assignment {
                 debug!("Setting Fader {} to {:?}", fader, assignment);
                 self.goxlr.set_fader(fader, assignment)?;
+            } else {
+                debug!("Fader Already Assigned, ignoring");
             }
-
-            // Force Mic Fader Assignment
-            if assignment == ChannelName::Mic {
-                mic_assigned_to_fader = true;
-                self.profile.set_mic_fader(fader)?;
-            }
-        }
-        if !mic_assigned_to_fader {
-            self.profile.clear_mic_fader();
         }
 
         debug!("Setting Mute States..");
@@ -3089,30 +3153,15 @@ impl<'a> Device<'a> {
                 self.apply_cough_from_profile()?;
             } else if let Some(fader) = self.profile.get_fader_from_channel(channel) {
                 debug!("Channel {} on Fader, Loading State from Profile", channel);
-                if let Some(current) = &current {
-                    self.apply_mute_from_profile(fader, Some(current.mute_state[channel]))?;
-                } else {
-                    self.apply_mute_from_profile(fader, None)?;
-                }
-            } else if let Some(current) = &current {
-                if current.mute_state[channel] != Unmuted {
-                    debug!("Channel {} not on Fader, but muted. Unmuting..", channel);
-                    self.goxlr.set_channel_state(channel, Unmuted)?;
-                }
-            } else {
-                debug!("Unknown Channel state for {}, Unmuting.", channel);
+                self.apply_mute_from_profile(fader, Some(current.mute_state[channel]))?;
+            } else if current.mute_state[channel] != Unmuted {
+                debug!("Channel {} not on Fader, but muted. Unmuting..", channel);
                 self.goxlr.set_channel_state(channel, Unmuted)?;
             }
         }
 
         debug!("Setting Channel Volumes..");
-        let volumes = if let Some(current) = &current {
-            self.get_load_volume_order(Some(current.volumes))
-        } else {
-            self.get_load_volume_order(None)
-        };
-
-        for channel in volumes {
+        for channel in self.get_load_volume_order(current.volumes) {
             let channel_volume = self.profile.get_channel_volume(channel);
 
             debug!("Setting volume for {} to {}", channel, channel_volume);
@@ -3136,33 +3185,11 @@ impl<'a> Device<'a> {
             self.set_fader_display_from_profile(fader)?;
         }
 
-        if self.hardware.device_type == DeviceType::Full {
-            for fader in FaderName::iter() {
-                self.apply_scribble(fader).await?;
-            }
-        }
-
         debug!("Updating button states..");
-        self.update_button_states()?;
-
-        debug!("Applying Routing..");
-        // For profile load, we should configure all the input channels from the profile,
-        // this is split so we can do tweaks in places where needed.
-        for input in BasicInputDevice::iter() {
-            self.apply_routing(input).await?;
-        }
-
-        debug!("Applying Voice FX");
-        self.apply_voice_fx()?;
-
-        // Drop this to the end so it doesn't directly interfere with profile loading..
-        debug!("Validating Sampler Configuration..");
-        self.validate_sampler().await?;
-
-        Ok(())
+        self.update_button_states()
     }
 
-    fn get_load_volume_order(&self, volumes: Option<EnumMap<ChannelName, u8>>) -> Vec<ChannelName> {
+    fn get_load_volume_order(&self, volumes: EnumMap<ChannelName, u8>) -> Vec<ChannelName> {
         // This method exists primarily to 'smooth' the loading of new volumes, in situations
         // where you're starting with a Headphone volume of 100 and a System volume of 20 and are
         // finishing at Headphone 20, System 100 there's an (albeit) brief period during load where
@@ -3174,37 +3201,31 @@ impl<'a> Device<'a> {
 
         let mut order = vec![];
 
-        if let Some(volumes) = volumes {
-            let headphone_volume = self.profile.get_channel_volume(ChannelName::Headphones);
-            let lineout_volume = self.profile.get_channel_volume(ChannelName::LineOut);
+        let headphone_volume = self.profile.get_channel_volume(ChannelName::Headphones);
+        let lineout_volume = self.profile.get_channel_volume(ChannelName::LineOut);
 
-            if volumes[ChannelName::Headphones] > headphone_volume {
-                order.push(ChannelName::Headphones);
-            }
-            if volumes[ChannelName::LineOut] > lineout_volume {
-                order.push(ChannelName::LineOut);
-            }
-
-            // Grab all the other channels in order, and push them..
-            ChannelName::iter().for_each(|channel| {
-                if channel != ChannelName::Headphones && channel != ChannelName::LineOut {
-                    order.push(channel);
-                }
-            });
+        if volumes[ChannelName::Headphones] > headphone_volume {
+            order.push(ChannelName::Headphones);
+        }
+        if volumes[ChannelName::LineOut] > lineout_volume {
+            order.push(ChannelName::LineOut);
+        }
 
-            // Headphones and Line out are technically the last in the list, so we could, in theory
-            // handle them in the above iter, however, they're placed here separately just in case
-            // the ChannelName enum list needs to change in the future which could break this.
-            if volumes[ChannelName::Headphones] <= headphone_volume {
-                order.push(ChannelName::Headphones);
-            }
-            if volumes[ChannelName::LineOut] <= lineout_volume {
-                order.push(ChannelName::LineOut);
+        // Grab all the other channels in order, and push them..
+        ChannelName::iter().for_each(|channel| {
+            if channel != ChannelName::Headphones && channel != ChannelName::LineOut {
+                order.push(channel);
             }
-        } else {
-            // We don't have reference volumes, just send all the channel names..
-            debug!("No reference volumes, sending channels in order");
-            return ChannelName::iter().collect();
+        });
+
+        // Headphones and Line out are technically the last in the list, so we could, in theory
+        // handle them in the above iter, however, they're placed here separately just in case
+        // the ChannelName enum list needs to change in the future which could break this.
+        if volumes[ChannelName::Headphones] <= headphone_volume {
+            order.push(ChannelName::Headphones);
+        }
+        if volumes[ChannelName::LineOut] <= lineout_volume {
+            order.push(ChannelName::LineOut);
         }
 
         order
@@ -3295,17 +3316,15 @@ impl<'a> Device<'a> {
 
     fn load_encoder_effects(&mut self) -> Result<()> {
         // For now, we'll simply set the knob positions, more to come!
-        let mut value = self.profile.get_pitch_encoder_position();
-        self.goxlr.set_encoder_value(EncoderName::Pitch, value)?;
-
-        value = self.profile.get_echo_value();
-        self.goxlr.set_encoder_value(EncoderName::Echo, value)?;
-
-        value = self.profile.get_gender_value();
-        self.goxlr.set_encoder_value(EncoderName::Gender, value)?;
-
-        value = self.profile.get_reverb_value();
-        self.goxlr.set_encoder_value(EncoderName::Reverb, value)?;
+        let values = self.profile.get_encoder_values();
+        self.goxlr
+            .set_encoder_value(EncoderName::Pitch, values.pitch)?;
+        self.goxlr
+            .set_encoder_value(EncoderName::Echo, values.echo as i8)?;
+        self.goxlr
+            .set_encoder_value(EncoderName::Gender, values.gender)?;
+        self.goxlr
+            .set_encoder_value(EncoderName::Reverb, values.reverb as i8)?;
 
         Ok(())
     }
@@ -3339,43 +3358,12 @@ impl<'a> Device<'a> {
             return Ok(());
         }
 
-        let mut mix_a: [u8; 4] = [0x0c; 4];
-        let mut mix_b: [u8; 4] = [0x0c; 4];
-
-        let mut index = 0;
         let submix_enabled = self.profile.is_submix_enabled();
 
-        // This is kinda awkward, but we'll run with it..
-        for device in BasicOutputDevice::iter() {
-            if device == BasicOutputDevice::Headphones {
-                // We need to make sure the monitor is on the right side..
-                if submix_enabled {
-                    let mix = self.profile.get_submix_channel(device);
-                    self.goxlr.set_monitored_mix(mix)?;
-                } else {
-                    self.goxlr.set_monitored_mix(Mix::A)?;
-                }
-
-                // Monitor Mix handled, move to the next channel
-                continue;
-            }
-            if submix_enabled {
-                // We need to place this on the correct mix..
-                match self.profile.get_submix_channel(device) {
-                    Mix::A => mix_a[index] = (device as u8) * 2,
-                    Mix::B => mix_b[index] = (device as u8) * 2,
-                }
-            } else {
-                // Force this channel to A..
-                mix_a[index] = (device as u8) * 2;
-            }
-            index += 1;
-        }
-
-        let submix = [mix_a, mix_b].concat();
-
-        // This should always be successful, in theory :D
-        self.goxlr.set_channel_mixes(submix.try_into().unwrap())?;
+        // We need to make sure the monitor is on the right side..
+        let (outputs, monitored) = self.profile.get_channel_mixes();
+        self.goxlr.set_monitored_mix(monitored)?;
+        self.goxlr.set_channel_mixes(&outputs)?;
 
         if submix_enabled && apply_volumes {
             for channel in ChannelName::iter() {
@@ -3386,10 +3374,10 @@ impl<'a> Device<'a> {
         // If submixes are enabled, the Mic Monitor should be at 100% as monitoring
         // is supposed to be handled by the mix.
         if submix_enabled {
-            self.goxlr.set_volume(ChannelName::MicMonitor, 255)?;
+            self.goxlr.set_microphone_monitor(255)?;
         } else {
             let volume = self.profile.get_channel_volume(ChannelName::MicMonitor);
-            self.goxlr.set_volume(ChannelName::MicMonitor, volume)?;
+            self.goxlr.set_microphone_monitor(volume)?;
         }
 
         Ok(())
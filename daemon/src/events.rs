@@ -31,6 +31,7 @@ pub struct DaemonState {
     // Shutdown Handlers
     pub shutdown: Shutdown,
     pub shutdown_blocking: Arc<AtomicBool>,
+    pub devices_stopping: Arc<AtomicBool>,
 
     // Settings Handle..
     pub settings_handle: SettingsHandle,
@@ -51,6 +52,7 @@ pub async fn spawn_event_handler(
                 // Ctrl+C is a generic capture, although we should also check for SIGTERM under Linux..
                 if !triggered_device_stop {
                     triggered_device_stop = true;
+                    state.devices_stopping.store(true, Ordering::Relaxed);
                     let _ = device_stop_tx.send(()).await;
                 }
             },
@@ -63,6 +65,7 @@ pub async fn spawn_event_handler(
                         debug!("Shutdown Phase 1 Triggered..");
                         if !triggered_device_stop {
                             triggered_device_stop = true;
+                            state.devices_stopping.store(true, Ordering::Relaxed);
                             let _ = device_stop_tx.send(()).await;
                         }
                     }
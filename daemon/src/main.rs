@@ -227,6 +227,10 @@ async fn main() -> Result<()> {
     let shutdown = Shutdown::new();
     let shutdown_blocking = Arc::new(AtomicBool::new(false));
 
+    // Set as soon as a shutdown is requested, so long running device work (such as applying a
+    // profile) can stop early rather than hold up the device shutdown..
+    let devices_stopping = Arc::new(AtomicBool::new(false));
+
     // Configure Showing the Tray Icon
     let show_tray = Arc::new(AtomicBool::new(settings.get_show_tray_icon().await));
     if let Some(override_tray) = args.disable_tray {
@@ -256,6 +260,7 @@ async fn main() -> Result<()> {
         usb_rx,
         file_rx,
         device_stop_rx,
+        devices_stopping.clone(),
         broadcast_tx.clone(),
         global_tx.clone(),
         shutdown.clone(),
@@ -306,6 +311,7 @@ async fn main() -> Result<()> {
         show_tray,
         shutdown,
         shutdown_blocking,
+        devices_stopping,
 
         settings_handle: settings.clone(),
         http_settings: http_settings.clone(),
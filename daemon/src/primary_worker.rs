@@ -9,12 +9,14 @@ use goxlr_ipc::{
     PathTypes, Paths, UsbProductInformation,
 };
 use goxlr_types::DeviceType;
-use goxlr_usb::device::base::GoXLRDevice;
+use goxlr_usb::device::base::{DeviceIdentifier, GoXLRDevice};
 use goxlr_usb::device::{find_devices, from_device};
 use goxlr_usb::{PID_GOXLR_FULL, PID_GOXLR_MINI};
 use json_patch::diff;
 use log::{error, info, warn};
 use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::broadcast::Sender as BroadcastSender;
 use tokio::sync::mpsc::{Receiver, Sender};
@@ -40,6 +42,7 @@ pub async fn spawn_usb_handler(
     mut command_rx: DeviceReceiver,
     mut file_rx: Receiver<PathTypes>,
     mut device_stop_rx: Receiver<()>,
+    devices_stopping: Arc<AtomicBool>,
     broadcast_tx: BroadcastSender<PatchEvent>,
     global_tx: Sender<EventTriggers>,
     mut shutdown: Shutdown,
@@ -79,13 +82,9 @@ pub async fn spawn_usb_handler(
                     let existing_serials: Vec<String> = get_all_serials(&devices);
                     let bus_number = device.bus_number();
                     let address = device.address();
+                    let device_identifier = device.device_identifier();
 
-                    let mut device_identifier = None;
-                    if let Some(identifier) = device.identifier() {
-                        device_identifier = Some(identifier.clone());
-                    }
-
-                    match load_device(device, existing_serials, disconnect_sender.clone(), event_sender.clone(), global_tx.clone(), &settings).await {
+                    match load_device(device, existing_serials, disconnect_sender.clone(), event_sender.clone(), global_tx.clone(), devices_stopping.clone(), &settings).await {
                         Ok(device) => {
                             devices.insert(device.serial().to_owned(), device);
                             change_found = true;
@@ -96,7 +95,7 @@ pub async fn spawn_usb_handler(
                                 bus_number, address, e
                             );
                             ignore_list
-                                .insert((bus_number, address, device_identifier), Instant::now() + Duration::from_secs(10));
+                                .insert(device_identifier, Instant::now() + Duration::from_secs(10));
                         }
                     };
                 }
@@ -360,7 +359,7 @@ async fn update_files(files: Files, file_type: PathTypes, file_manager: &mut Fil
 
 fn find_new_device(
     current_status: &DaemonStatus,
-    devices_to_ignore: &HashMap<(u8, u8, Option<String>), Instant>,
+    devices_to_ignore: &HashMap<DeviceIdentifier, Instant>,
 ) -> Option<GoXLRDevice> {
     let now = Instant::now();
 
@@ -375,16 +374,9 @@ fn find_new_device(
             }
             d.hardware.usb_device.bus_number == device.bus_number()
                 && d.hardware.usb_device.address == device.address()
-        }) && !devices_to_ignore
-            .iter()
-            .any(|((bus_number, address, identifier), expires)| {
-                if let Some(identifier) = identifier {
-                    if let Some(device_identifier) = device.identifier() {
-                        return identifier == device_identifier && expires > &now;
-                    }
-                }
-                *bus_number == device.bus_number() && *address == device.address() && expires > &now
-            })
+        }) && devices_to_ignore
+            .get(&device.device_identifier())
+            .is_none_or(|expires| expires <= &now)
     })
 }
 
@@ -404,6 +396,7 @@ async fn load_device(
     disconnect_sender: Sender<String>,
     event_sender: Sender<String>,
     global_events: Sender<EventTriggers>,
+    stopping: Arc<AtomicBool>,
     settings: &SettingsHandle,
 ) -> Result<Device<'_>> {
     let device_copy = device.clone();
@@ -462,6 +455,7 @@ async fn load_device(
         &mic_profile_directory,
         settings,
         global_events,
+        stopping,
     )
     .await?;
     settings
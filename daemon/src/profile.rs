@@ -21,6 +21,7 @@ use goxlr_profile_loader::components::colours::{
     Colour, ColourDisplay, ColourMap, ColourOffStyle, ColourState,
 };
 use goxlr_profile_loader::components::echo::{EchoEncoder, EchoStyle};
+use goxlr_profile_loader::components::effect_base::EffectType;
 use goxlr_profile_loader::components::gender::{GenderEncoder, GenderStyle};
 use goxlr_profile_loader::components::hardtune::{HardTuneEffect, HardTuneSource, HardTuneStyle};
 use goxlr_profile_loader::components::megaphone::{MegaphoneEffect, MegaphoneStyle};
@@ -33,7 +34,9 @@ use goxlr_profile_loader::components::robot::{RobotEffect, RobotStyle};
 use goxlr_profile_loader::components::sample::{PlayOrder, PlaybackMode, SampleBank, Track};
 use goxlr_profile_loader::components::simple::SimpleElements;
 use goxlr_profile_loader::components::submix::mix_routing_tree::Mix;
-use goxlr_profile_loader::profile::{Profile, ProfileSettings};
+use goxlr_profile_loader::profile::{
+    EncoderValues, HardwareState as ProfileHardwareState, Profile, ProfileSettings,
+};
 use goxlr_profile_loader::SampleButtons::{BottomLeft, BottomRight, Clear, TopLeft, TopRight};
 use goxlr_profile_loader::{Faders, Preset, SampleButtons};
 use goxlr_scribbles::get_scribble;
@@ -44,9 +47,11 @@ use goxlr_types::{
     OutputDevice, SamplePlayOrder, SamplePlaybackMode, SamplerColourTargets, SimpleColourTargets,
     SubMixChannelName, VersionNumber,
 };
-use goxlr_usb::buttonstate::{ButtonStates, Buttons};
+use goxlr_usb::animation::{AnimationMode as UsbAnimationMode, WaterFallDir};
+use goxlr_usb::buttonstate::{ButtonStates, Buttons, HardwareState};
 use goxlr_usb::channelstate::ChannelState;
 use goxlr_usb::colouring::ColourTargets;
+use goxlr_usb::requests::{self, Request};
 
 use crate::audio::{AudioFile, AudioHandler};
 use crate::device::CurrentState;
@@ -55,6 +60,15 @@ use crate::files::can_create_new_file;
 pub const DEFAULT_PROFILE_NAME: &str = "Default";
 const DEFAULT_PROFILE: &[u8] = include_bytes!("../profiles/Default.goxlr");
 
+/// What the attached device supports, used to build the commands in ProfileAdapter::to_commands
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ApplyOptions {
+    pub colour_format_1_3_40: bool,
+    pub blank_mute: bool,
+    pub animations: bool,
+    pub submixes: bool,
+}
+
 #[derive(Debug)]
 pub struct ProfileAdapter {
     name: String,
@@ -121,6 +135,12 @@ impl ProfileAdapter {
         Ok(())
     }
 
+    /// Called once the whole profile has been sent to the device, after which the profile's
+    /// dirty components only cover changes the device hasn't seen.
+    pub fn mark_applied(&mut self) {
+        self.profile.settings_mut().clear_dirty();
+    }
+
     pub fn write_preset(&mut self, name: String, directory: &Path) -> Result<()> {
         let path = directory.join(format!("{name}.preset"));
         self.profile.save_preset(path)?;
@@ -198,6 +218,186 @@ impl ProfileAdapter {
         }
     }
 
+    /// Pulls the state reported by the device into the profile, see
+    /// ProfileSettings::apply_hardware_state for what can be synced.
+    pub fn apply_hardware_state(&mut self, state: &HardwareState) -> Result<bool> {
+        let mut profile_state = ProfileHardwareState::default();
+        for (fader, volume) in state.fader_volumes {
+            profile_state.fader_volumes[standard_to_profile_fader(fader)] = volume;
+        }
+        self.profile
+            .settings_mut()
+            .apply_hardware_state(&profile_state)
+    }
+
+    /// Returns the ordered list of commands (and their bodies) needed to apply this profile to a
+    /// device from scratch, following the same order as the device's profile load. The current
+    /// device state isn't taken into account, so everything is included. Scribbles, routing and
+    /// the voice effects need more than the profile to build (icons, transient routing and the
+    /// mic profile), so are left to the caller.
+    ///
+    /// The bodies come from goxlr_usb::requests, the same place the GoXLRCommands setters get
+    /// them from.
+    pub fn to_commands(&self, options: ApplyOptions) -> Result<Vec<Request>> {
+        let mut commands = vec![];
+
+        for fader in FaderName::iter() {
+            let channel = self.get_fader_assignment(fader);
+            commands.push(requests::set_fader(fader, channel));
+        }
+
+        // Channel mute states, anything which isn't muted to all is handled by the router..
+        for channel in ChannelName::iter() {
+            let state = if channel == ChannelName::Mic {
+                self.get_mic_mute_state()
+            } else {
+                self.get_channel_mute_state(channel)
+            };
+            commands.push(requests::set_channel_state(channel, state));
+        }
+
+        // Volumes, with no reference volumes these go straight through the list..
+        for channel in ChannelName::iter() {
+            let volume = self.get_channel_volume(channel);
+            commands.push(requests::set_volume(channel, volume));
+        }
+
+        if options.submixes {
+            self.push_submix_commands(&mut commands);
+        }
+
+        // Lighting..
+        let colour_map = self.get_colour_map(options.colour_format_1_3_40, options.blank_mute);
+        commands.push(requests::set_colour_map(
+            &colour_map,
+            options.colour_format_1_3_40,
+        ));
+
+        if options.animations {
+            let (enabled, mode, mod1, mod2, waterfall) = self.get_usb_animation();
+            commands.push(requests::set_animation_mode(
+                enabled, mode, mod1, mod2, waterfall,
+            )?);
+        }
+
+        for fader in FaderName::iter() {
+            let gradient = self.is_fader_gradient(fader);
+            let meter = self.is_fader_meter(fader);
+            commands.push(requests::set_fader_display_mode(fader, gradient, meter));
+        }
+
+        commands.push(requests::set_button_states(self.get_button_states()));
+        Ok(commands)
+    }
+
+    fn push_submix_commands(&self, commands: &mut Vec<Request>) {
+        let submix_enabled = self.is_submix_enabled();
+
+        let (outputs, monitored) = self.get_channel_mixes();
+        commands.push(requests::set_monitored_mix(monitored));
+        commands.push(requests::set_channel_mixes(&outputs));
+
+        if submix_enabled {
+            for channel in ChannelName::iter() {
+                if let Some(mix) = self.get_submix_from_channel(channel) {
+                    // Linked mixes follow their channel..
+                    let volume = match self.submix_linked(mix) {
+                        true => self.linked_submix_volume(channel, mix),
+                        false => self.get_submix_volume(mix),
+                    };
+                    commands.push(requests::set_sub_volume(mix, volume));
+                }
+            }
+        }
+
+        // With submixes enabled, monitoring is handled by the mix so the Mic Monitor goes to 100%
+        let monitor = match submix_enabled {
+            true => 255,
+            false => self.get_channel_volume(ChannelName::MicMonitor),
+        };
+        commands.push(requests::set_volume(ChannelName::MicMonitor, monitor));
+    }
+
+    /// Returns the mix each output should be on, along with the mix the headphones monitor.
+    /// With submixes disabled, everything is on mix A.
+    pub fn get_channel_mixes(&self) -> (Vec<(OutputDevice, goxlr_types::Mix)>, goxlr_types::Mix) {
+        let submix_enabled = self.is_submix_enabled();
+        let mix = |device| match submix_enabled {
+            true => self.get_submix_channel(device),
+            false => goxlr_types::Mix::A,
+        };
+
+        let outputs = OutputDevice::iter()
+            .filter(|device| *device != OutputDevice::Headphones)
+            .map(|device| (device, mix(device)))
+            .collect();
+        (outputs, mix(OutputDevice::Headphones))
+    }
+
+    fn linked_submix_volume(&self, channel: ChannelName, mix: SubMixChannelName) -> u8 {
+        (self.get_channel_volume(channel) as f64 * self.get_submix_ratio(mix)) as u8
+    }
+
+    // Stores the volumes of any linked submixes against the profile, so they're saved with
+    // what was sent to the device..
+    pub fn sync_linked_submix_volumes(&mut self) -> Result<()> {
+        if !self.is_submix_enabled() {
+            return Ok(());
+        }
+
+        for channel in ChannelName::iter() {
+            if let Some(mix) = self.get_submix_from_channel(channel) {
+                if self.submix_linked(mix) {
+                    let volume = self.linked_submix_volume(channel, mix);
+                    if volume != self.get_submix_volume(mix) {
+                        self.set_submix_volume(mix, volume)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The profile's animation settings as the usb crate takes them (enabled, mode, the two
+    /// modifiers and the waterfall direction).
+    pub fn get_usb_animation(&self) -> (bool, UsbAnimationMode, u8, u8, WaterFallDir) {
+        let mode = self.get_animation_mode();
+        let enabled = mode != goxlr_types::AnimationMode::None;
+
+        // This one is kinda weird, we go from profile -> types -> usb..
+        let mode = match mode {
+            goxlr_types::AnimationMode::RetroRainbow => UsbAnimationMode::RetroRainbow,
+            goxlr_types::AnimationMode::RainbowDark => UsbAnimationMode::RainbowDark,
+            goxlr_types::AnimationMode::RainbowBright => UsbAnimationMode::RainbowBright,
+            goxlr_types::AnimationMode::Simple => UsbAnimationMode::Simple,
+            goxlr_types::AnimationMode::Ripple => UsbAnimationMode::Ripple,
+            goxlr_types::AnimationMode::None => UsbAnimationMode::None,
+        };
+        let waterfall = match self.get_animation_waterfall() {
+            goxlr_types::WaterfallDirection::Down => WaterFallDir::Down,
+            goxlr_types::WaterfallDirection::Up => WaterFallDir::Up,
+            goxlr_types::WaterfallDirection::Off => WaterFallDir::Off,
+        };
+
+        (
+            enabled,
+            mode,
+            self.get_animation_mod1(),
+            self.get_animation_mod2(),
+            waterfall,
+        )
+    }
+
+    fn get_mic_mute_state(&self) -> ChannelState {
+        let (_, muted_to_x, muted_to_all, mute_function) = self.get_mute_chat_button_state();
+        if muted_to_all || (muted_to_x && mute_function == MuteFunction::All) {
+            return ChannelState::Muted;
+        }
+
+        // Not muted by the cough button, but may be muted by its fader..
+        self.get_channel_mute_state(ChannelName::Mic)
+    }
+
     fn get_channel_mute_state(&self, channel: ChannelName) -> ChannelState {
         // Is this assigned to a fader?
         if let Some(fader) = self.get_fader_from_channel(channel) {
@@ -319,10 +519,10 @@ impl ProfileAdapter {
     }
 
     pub fn set_fader_assignment(&mut self, fader: FaderName, channel: ChannelName) {
-        self.profile
-            .settings_mut()
-            .fader_mut(standard_to_profile_fader(fader))
-            .set_channel(standard_to_profile_channel(channel));
+        self.profile.settings_mut().assign_fader_channel(
+            standard_to_profile_fader(fader),
+            standard_to_profile_channel(channel),
+        );
     }
 
     pub fn switch_fader_assignment(&mut self, fader_one: FaderName, fader_two: FaderName) {
@@ -341,7 +541,7 @@ impl ProfileAdapter {
 
         self.profile
             .settings_mut()
-            .mute_buttons()
+            .mute_buttons_mut()
             .swap(profile_fader_one, profile_fader_two);
     }
 
@@ -392,12 +592,10 @@ impl ProfileAdapter {
         fader: FaderName,
         display: BasicColourDisplay,
     ) -> Result<()> {
-        let colours = self
-            .profile
+        self.profile
             .settings_mut()
             .fader_mut(standard_to_profile_fader(fader))
-            .colour_map_mut();
-        colours.set_fader_display(standard_to_profile_fader_display(display))
+            .set_display_mode(standard_to_profile_fader_display(display))
     }
 
     // We have a return type here, as there's string parsing involved..
@@ -407,14 +605,11 @@ impl ProfileAdapter {
         top: String,
         bottom: String,
     ) -> Result<()> {
-        let colours = self
-            .profile
-            .settings_mut()
-            .fader_mut(standard_to_profile_fader(fader))
-            .colour_map_mut();
-        colours.set_colour(0, Colour::fromrgb(top.as_str())?)?;
-        colours.set_colour(1, Colour::fromrgb(bottom.as_str())?)?;
-        Ok(())
+        self.profile.settings_mut().set_fader_colours(
+            standard_to_profile_fader(fader),
+            Colour::fromrgb(top.as_str())?,
+            Colour::fromrgb(bottom.as_str())?,
+        )
     }
 
     pub fn get_scribble_image(&self, fader: FaderName, path: &Path) -> [u8; 1024] {
@@ -858,9 +1053,7 @@ impl ProfileAdapter {
             amount: self.get_active_hardtune_profile().amount(),
             rate: self.get_active_hardtune_profile().rate(),
             window: self.get_active_hardtune_profile().window(),
-            source: profile_to_standard_hard_tune_source(
-                &self.get_active_hardtune_profile().get_source_value(),
-            ),
+            source: profile_to_standard_hard_tune_source(&self.get_active_hardtune_source_value()),
         };
 
         let is_enabled = self.is_fx_enabled();
@@ -965,6 +1158,15 @@ impl ProfileAdapter {
     }
 
     /** Regular Mute button handlers */
+    /// The profile's hold duration for a fader's mute button, if it overrides the device setting.
+    pub fn get_mute_hold_duration(&self, fader: FaderName) -> Option<u16> {
+        self.get_mute_button(fader).hold_duration()
+    }
+
+    pub fn get_mute_chat_hold_duration(&self) -> Option<u16> {
+        self.profile.settings().mute_chat().hold_duration()
+    }
+
     fn get_mute_button(&self, fader: FaderName) -> &MuteButton {
         self.profile
             .settings()
@@ -1323,54 +1525,33 @@ impl ProfileAdapter {
 
     pub fn set_megaphone(&mut self, enabled: bool) -> Result<()> {
         let current = self.profile.settings().context().selected_effects();
-
-        self.profile
-            .settings_mut()
-            .megaphone_effect_mut()
-            .get_preset_mut(current)
-            .set_state(enabled);
         self.profile
             .settings_mut()
-            .megaphone_effect_mut()
-            .colour_map_mut()
-            .set_state_on(enabled)
+            .set_effect_enabled(current, EffectType::Megaphone, enabled)
     }
 
     pub fn set_robot(&mut self, enable: bool) -> Result<()> {
         let current = self.profile.settings().context().selected_effects();
         self.profile
             .settings_mut()
-            .robot_effect_mut()
-            .get_preset_mut(current)
-            .set_state(enable);
-        self.profile
-            .settings_mut()
-            .robot_effect_mut()
-            .colour_map_mut()
-            .set_state_on(enable)
+            .set_effect_enabled(current, EffectType::Robot, enable)
     }
 
     pub fn set_hardtune(&mut self, enabled: bool) -> Result<()> {
         let current = self.profile.settings().context().selected_effects();
-
-        self.profile
-            .settings_mut()
-            .hardtune_effect_mut()
-            .get_preset_mut(current)
-            .set_state(enabled);
         self.profile
             .settings_mut()
-            .hardtune_effect_mut()
-            .colour_map_mut()
-            .set_state_on(enabled)
+            .set_effect_enabled(current, EffectType::HardTune, enabled)
     }
 
     pub fn set_effects(&mut self, enabled: bool) -> Result<()> {
-        self.profile
-            .settings_mut()
-            .simple_element_mut(SimpleElements::FxClear)
-            .colour_map_mut()
-            .set_state_on(enabled)
+        self.profile.settings_mut().set_fx_enabled(enabled)?;
+        Ok(())
+    }
+
+    pub fn get_encoder_values(&self) -> EncoderValues {
+        let current = self.profile.settings().context().selected_effects();
+        self.profile.settings().encoder_values(current)
     }
 
     pub fn get_pitch_knob_position(&self) -> i8 {
@@ -1592,7 +1773,7 @@ impl ProfileAdapter {
     }
 
     pub fn get_hardtune_source(&self) -> goxlr_types::HardTuneSource {
-        profile_to_standard_hard_tune_source(&self.get_active_hardtune_profile().get_source_value())
+        profile_to_standard_hard_tune_source(&self.get_active_hardtune_source_value())
     }
 
     pub fn get_active_hardtune_profile(&self) -> &HardTuneEffect {
@@ -1611,18 +1792,20 @@ impl ProfileAdapter {
             .get_preset_mut(current)
     }
 
-    pub fn is_active_hardtune_source_all(&self) -> bool {
-        if let Some(source) = self.get_active_hardtune_profile().source() {
-            return source == &HardTuneSource::All;
-        }
+    pub fn get_active_hardtune_source_value(&self) -> HardTuneSource {
+        let current = self.profile.settings().context().selected_effects();
+        self.profile
+            .settings()
+            .hardtune_effect()
+            .resolved_source(current)
+    }
 
-        // If it's not set, assume default behaviour of 'All'
-        true
+    pub fn is_active_hardtune_source_all(&self) -> bool {
+        self.get_active_hardtune_source_value() == HardTuneSource::All
     }
 
     pub fn get_active_hardtune_source(&self) -> InputDevice {
-        let source = self.get_active_hardtune_profile().source();
-        match source.unwrap() {
+        match self.get_active_hardtune_source_value() {
             HardTuneSource::Music => InputDevice::Music,
             HardTuneSource::Game => InputDevice::Game,
             HardTuneSource::LineIn => InputDevice::LineIn,
@@ -1634,11 +1817,7 @@ impl ProfileAdapter {
     }
 
     pub fn is_fx_enabled(&self) -> bool {
-        self.profile
-            .settings()
-            .simple_element(SimpleElements::FxClear)
-            .colour_map()
-            .get_state()
+        self.profile.settings().fx_enabled()
     }
 
     pub fn is_megaphone_enabled(&self, ignore_fx_state: bool) -> bool {
@@ -1712,13 +1891,13 @@ impl ProfileAdapter {
         button: goxlr_types::SampleButtons,
     ) -> SamplePlaybackMode {
         let bank = self.profile.settings().context().selected_sample();
-        let stack = self
+        let mode = self
             .profile
             .settings()
             .sample_button(standard_to_profile_sample_button(button))
-            .get_stack(bank);
+            .playback_mode(bank);
 
-        profile_to_standard_sample_playback_mode(stack.get_playback_mode())
+        profile_to_standard_sample_playback_mode(mode)
     }
 
     pub fn sync_sample_if_active(&mut self, target: SamplerColourTargets) -> Result<()> {
@@ -1949,14 +2128,25 @@ impl ProfileAdapter {
             warn!("Global Colour Setting not Implemented");
 
             // Set the config value anyway..
-            let colour_map = self
+            return self
                 .profile
                 .settings_mut()
-                .simple_element_mut(SimpleElements::GlobalColour)
-                .colour_map_mut();
-            colour_map.set_colour(0, Colour::fromrgb(colour_one.as_str())?)?;
+                .set_global_colour(Colour::fromrgb(colour_one.as_str())?);
+        }
 
-            return Ok(());
+        // Scribbles may have their fader linked, so go through the profile to keep them in sync..
+        let scribble = match target {
+            SimpleColourTargets::Scribble1 => Some(Faders::A),
+            SimpleColourTargets::Scribble2 => Some(Faders::B),
+            SimpleColourTargets::Scribble3 => Some(Faders::C),
+            SimpleColourTargets::Scribble4 => Some(Faders::D),
+            _ => None,
+        };
+        if let Some(fader) = scribble {
+            return self
+                .profile
+                .settings_mut()
+                .set_scribble_colour(fader, Colour::fromrgb(colour_one.as_str())?);
         }
 
         let colour_target = standard_to_profile_simple_colour(target);
@@ -2019,8 +2209,10 @@ impl ProfileAdapter {
         self.profile
             .settings_mut()
             .sample_button_mut(standard_to_profile_sample_button(button))
-            .get_stack_mut(standard_to_profile_sample_bank(bank))
-            .set_playback_mode(Some(standard_to_profile_sample_playback_mode(mode)));
+            .set_playback_mode(
+                standard_to_profile_sample_bank(bank),
+                standard_to_profile_sample_playback_mode(mode),
+            );
     }
 
     pub fn set_sampler_play_order(
@@ -2043,12 +2235,7 @@ impl ProfileAdapter {
         file: String,
     ) -> &mut Track {
         // Create a new 'Track' (Oddly, positions are a percentage :D)..
-        let track = Track {
-            track: file,
-            start_position: 0.0,
-            end_position: 100.0,
-            normalized_gain: 1.0,
-        };
+        let track = Track::new(file, 0.0, 100.0, 1.0);
 
         // Add this to the list, then return the track..
         self.profile
@@ -2404,6 +2591,10 @@ impl ProfileAdapter {
             self.sync_sample_if_active(target)?;
         }
 
+        // Everything above went straight to the colour maps, so linked faders are brought back
+        // in line with their scribbles once here, rather than on every change..
+        self.profile.settings_mut().sync_linked_fader_colours()?;
+
         Ok(())
     }
 
@@ -2534,6 +2725,18 @@ impl ProfileAdapter {
     }
 
     /** Generic Stuff **/
+    pub fn get_button_states(&self) -> [ButtonStates; 24] {
+        let mut result = [ButtonStates::DimmedColour1; 24];
+
+        for button in Buttons::iter() {
+            result[button as usize] = self.get_button_colour_state(button);
+        }
+
+        // Replace the Cough Button button data with correct data.
+        result[Buttons::MicrophoneMute as usize] = self.get_mute_chat_button_colour_state();
+        result
+    }
+
     pub fn get_button_colour_state(&self, button: Buttons) -> ButtonStates {
         let colour_map = self.get_button_colour_map(button);
 
@@ -3383,3 +3586,122 @@ pub fn version_newer_or_equal_to(version: &VersionNumber, comparison: VersionNum
 
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use enumset::EnumSet;
+    use goxlr_types::EncoderName;
+    use goxlr_usb::commands::Command;
+
+    fn default_profile() -> ProfileAdapter {
+        let reader = Cursor::new(DEFAULT_PROFILE);
+        ProfileAdapter::from_reader(DEFAULT_PROFILE_NAME.to_string(), reader).unwrap()
+    }
+
+    #[test]
+    fn commands_follow_the_profile_load_order() {
+        let options = ApplyOptions {
+            colour_format_1_3_40: true,
+            blank_mute: false,
+            animations: true,
+            submixes: true,
+        };
+        let profile = default_profile();
+        let commands = profile.to_commands(options).unwrap();
+
+        // Fader assignments, then mute states, then volumes..
+        let faders = FaderName::iter().count();
+        let channels = ChannelName::iter().count();
+        for (index, fader) in FaderName::iter().enumerate() {
+            let channel = profile.get_fader_assignment(fader);
+            assert_eq!(commands[index], requests::set_fader(fader, channel));
+        }
+        for (index, channel) in ChannelName::iter().enumerate() {
+            let (command, body) = &commands[faders + index];
+            assert_eq!(*command, Command::SetChannelState(channel));
+            assert_eq!(body.len(), 1);
+
+            let (command, body) = &commands[faders + channels + index];
+            assert_eq!(*command, Command::SetChannelVolume(channel));
+            assert_eq!(*body, vec![profile.get_channel_volume(channel)]);
+        }
+
+        let position = |target: Command| commands.iter().position(|(c, _)| *c == target);
+        let mixes = position(Command::SetChannelMixes).unwrap();
+        let colour_map = position(Command::SetColourMap()).unwrap();
+        let animation = position(Command::SetAnimationMode).unwrap();
+        assert!(position(Command::SetMonitoredMix).unwrap() < mixes);
+        assert!(mixes < colour_map && colour_map < animation);
+        assert_eq!(commands[mixes].1.len(), 8);
+        assert_eq!(commands[animation].1.len(), 5);
+
+        let colours = profile.get_colour_map(true, false);
+        assert_eq!(commands[colour_map].1, colours.to_vec());
+
+        let (command, _) = commands.last().unwrap();
+        assert_eq!(*command, Command::SetButtonStates());
+    }
+
+    #[test]
+    fn applying_clears_the_dirty_components() {
+        let mut profile = default_profile();
+        profile.set_channel_volume(ChannelName::Mic, 10).unwrap();
+        assert!(!profile.profile.settings().dirty_components().is_empty());
+
+        // Building the commands doesn't send them, so the changes are still outstanding..
+        profile.to_commands(ApplyOptions::default()).unwrap();
+        assert!(!profile.profile.settings().dirty_components().is_empty());
+
+        profile.mark_applied();
+        assert!(profile.profile.settings().dirty_components().is_empty());
+    }
+
+    #[test]
+    fn hardware_fader_positions_set_the_assigned_channels() {
+        let mut profile = default_profile();
+        let state = HardwareState {
+            fader_volumes: [
+                (FaderName::A, 10),
+                (FaderName::B, 20),
+                (FaderName::C, 30),
+                (FaderName::D, 40),
+            ],
+            encoders: [
+                (EncoderName::Pitch, 0),
+                (EncoderName::Gender, 0),
+                (EncoderName::Reverb, 0),
+                (EncoderName::Echo, 0),
+            ],
+            pressed: EnumSet::empty(),
+        };
+
+        assert!(profile.apply_hardware_state(&state).unwrap());
+        for (fader, volume) in state.fader_volumes {
+            let channel = profile.get_fader_assignment(fader);
+            assert_eq!(profile.get_channel_volume(channel), volume);
+        }
+
+        // Nothing has moved since, so there's nothing left to sync..
+        assert!(!profile.apply_hardware_state(&state).unwrap());
+    }
+
+    #[test]
+    fn older_firmware_skips_newer_commands() {
+        let commands = default_profile()
+            .to_commands(ApplyOptions::default())
+            .unwrap();
+        assert!(!commands.iter().any(|(command, _)| matches!(
+            command,
+            Command::SetAnimationMode
+                | Command::SetChannelMixes
+                | Command::SetMonitoredMix
+                | Command::SetSubChannelVolume(_)
+        )));
+
+        let colour_map = commands
+            .iter()
+            .find(|(command, _)| *command == Command::SetColourMap());
+        assert_eq!(colour_map.unwrap().1.len(), 328);
+    }
+}
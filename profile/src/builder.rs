@@ -0,0 +1,348 @@
+use anyhow::{anyhow, bail, Result};
+use strum::IntoEnumIterator;
+
+use crate::components::colours::Colour;
+use crate::components::echo::EchoEncoder;
+use crate::components::gender::GenderEncoder;
+use crate::components::hardtune::HardTuneEffect;
+use crate::components::megaphone::MegaphoneEffect;
+use crate::components::mixer::FullChannelList;
+use crate::components::pitch::PitchEncoder;
+use crate::components::reverb::ReverbEncoder;
+use crate::components::robot::RobotEffect;
+use crate::components::sample::{SampleBank, Track};
+use crate::components::simple::SimpleElements;
+use crate::profile::{ComponentId, ProfileSettings};
+use crate::{Faders, Preset, SampleButtons};
+
+// The smallest document ProfileSettings will load, everything else is created from defaults..
+const EMPTY_PROFILE: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+    <ValueTreeRoot version=\"2\"><AppTree/></ValueTreeRoot>";
+
+// The channels assigned to the faders when building from scratch, matching the Default profile..
+const DEFAULT_FADERS: [(Faders, FullChannelList); 4] = [
+    (Faders::A, FullChannelList::Mic),
+    (Faders::B, FullChannelList::Music),
+    (Faders::C, FullChannelList::Chat),
+    (Faders::D, FullChannelList::System),
+];
+
+/**
+ * Builds a ProfileSettings in code, rather than by parsing a profile. Anything not set through
+ * the builder comes from the starting point, either a template (normally the Default profile
+ * shipped with the utility), or the defaults of each component.
+ *
+ * Each step is checked as it's applied, but to keep the calls chainable the first error is held
+ * until build(), after which every other step is skipped. build() also writes the result out and
+ * loads it back in, so a built profile is always one which can be saved and reloaded.
+ */
+pub struct ProfileSettingsBuilder {
+    settings: ProfileSettings,
+    error: Option<anyhow::Error>,
+}
+
+impl ProfileSettingsBuilder {
+    /// Starts from nothing, with every component created from its defaults..
+    pub fn new() -> Result<Self> {
+        let mut settings = ProfileSettings::load(EMPTY_PROFILE.as_bytes())?;
+
+        let mut components = vec![
+            ComponentId::Root,
+            ComponentId::Mixer,
+            ComponentId::MuteChat,
+            ComponentId::Megaphone,
+            ComponentId::Robot,
+            ComponentId::HardTune,
+            ComponentId::Reverb,
+            ComponentId::Echo,
+            ComponentId::Pitch,
+            ComponentId::Gender,
+            ComponentId::Animation,
+            ComponentId::Context,
+            ComponentId::Submixes,
+            ComponentId::MixRouting,
+        ];
+        for fader in Faders::iter() {
+            components.push(ComponentId::Fader(fader));
+            components.push(ComponentId::MuteButton(fader));
+            components.push(ComponentId::Scribble(fader));
+        }
+        components.extend(Preset::iter().map(ComponentId::Effects));
+        components.extend(SampleButtons::iter().map(ComponentId::Sampler));
+        components.extend(SimpleElements::iter().map(ComponentId::SimpleElement));
+
+        for component in components {
+            settings.reset_component(component)?;
+        }
+
+        // Every sample button should have a (possibly empty) stack for each bank..
+        for button in SampleButtons::iter() {
+            for bank in SampleBank::iter() {
+                settings
+                    .sample_button_mut(button)
+                    .get_stack_or_default_mut(bank);
+            }
+        }
+
+        for (fader, channel) in DEFAULT_FADERS {
+            settings.fader_mut(fader).set_channel(channel);
+        }
+
+        Ok(Self::from_template(settings))
+    }
+
+    /// Starts from an existing profile, anything missing from it is recreated first..
+    pub fn from_template(mut settings: ProfileSettings) -> Self {
+        let error = settings.repair().err();
+        Self { settings, error }
+    }
+
+    /// Assigns a channel to a fader, and sets the colour of the top of the fader.
+    pub fn fader(mut self, fader: Faders, channel: FullChannelList, colour: Colour) -> Self {
+        self.apply(|settings| {
+            for (other, existing) in settings.faders().iter() {
+                if let Some(existing) = existing {
+                    if other != fader && existing.channel() == channel {
+                        bail!("{:?} is already assigned to Fader {:?}", channel, other);
+                    }
+                }
+            }
+
+            let bottom = *settings.fader(fader).colour_map().colour_or_default(1);
+            settings.assign_fader_channel(fader, channel);
+            settings.set_fader_colours(fader, colour, bottom)
+        });
+        self
+    }
+
+    pub fn mixer_volume(mut self, channel: FullChannelList, volume: u8) -> Self {
+        self.apply(|settings| settings.mixer_mut().set_channel_volume(channel, volume));
+        self
+    }
+
+    /// Configures an effect bank, any error returned by the closure is returned from build().
+    pub fn effect_bank<F>(mut self, preset: Preset, configure: F) -> Self
+    where
+        F: FnOnce(&mut EffectBankBuilder) -> Result<()>,
+    {
+        self.apply(|settings| configure(&mut EffectBankBuilder { settings, preset }));
+        self
+    }
+
+    /// Adds a track to the end of a button's stack in a bank.
+    pub fn sample(mut self, button: SampleButtons, bank: SampleBank, track: Track) -> Self {
+        self.apply(|settings| {
+            if button == SampleButtons::Clear {
+                bail!("The Clear button can't hold samples");
+            }
+            if track.track().trim().is_empty() {
+                bail!("Sample track has no file name");
+            }
+            if !(0.0..=100.0).contains(&track.start_position())
+                || !(0.0..=100.0).contains(&track.end_position())
+                || track.start_position() > track.end_position()
+            {
+                bail!(
+                    "Invalid positions for {}: {} - {}",
+                    track.track(),
+                    track.start_position(),
+                    track.end_position()
+                );
+            }
+
+            settings
+                .sample_button_mut(button)
+                .get_stack_or_default_mut(bank)
+                .add_track(track);
+            Ok(())
+        });
+        self
+    }
+
+    /// For anything the builder doesn't have a method for..
+    pub fn with<F>(mut self, configure: F) -> Self
+    where
+        F: FnOnce(&mut ProfileSettings) -> Result<()>,
+    {
+        self.apply(configure);
+        self
+    }
+
+    pub fn build(mut self) -> Result<ProfileSettings> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        // Make sure what we've built can be written, and read back in..
+        let mut buffer = vec![];
+        self.settings.write_to(&mut buffer)?;
+        ProfileSettings::load(buffer.as_slice())
+            .map_err(|e| anyhow!("Built profile failed to reload: {}", e))?;
+
+        self.settings.clear_dirty();
+        Ok(self.settings)
+    }
+
+    fn apply<F>(&mut self, step: F)
+    where
+        F: FnOnce(&mut ProfileSettings) -> Result<()>,
+    {
+        if self.error.is_none() {
+            self.error = step(&mut self.settings).err();
+        }
+    }
+}
+
+/// Gives access to a single effect bank's settings across each of the effects..
+pub struct EffectBankBuilder<'a> {
+    settings: &'a mut ProfileSettings,
+    preset: Preset,
+}
+
+impl EffectBankBuilder<'_> {
+    pub fn name(&mut self, name: &str) -> Result<()> {
+        self.settings
+            .effects_mut(self.preset)
+            .set_name(name.to_string())
+    }
+
+    pub fn reverb(&mut self) -> &mut ReverbEncoder {
+        self.settings
+            .reverb_encoder_mut()
+            .get_preset_mut(self.preset)
+    }
+
+    pub fn echo(&mut self) -> &mut EchoEncoder {
+        self.settings.echo_encoder_mut().get_preset_mut(self.preset)
+    }
+
+    pub fn pitch(&mut self) -> &mut PitchEncoder {
+        self.settings
+            .pitch_encoder_mut()
+            .get_preset_mut(self.preset)
+    }
+
+    pub fn gender(&mut self) -> &mut GenderEncoder {
+        self.settings
+            .gender_encoder_mut()
+            .get_preset_mut(self.preset)
+    }
+
+    pub fn megaphone(&mut self) -> &mut MegaphoneEffect {
+        self.settings
+            .megaphone_effect_mut()
+            .get_preset_mut(self.preset)
+    }
+
+    pub fn robot(&mut self) -> &mut RobotEffect {
+        self.settings.robot_effect_mut().get_preset_mut(self.preset)
+    }
+
+    pub fn hardtune(&mut self) -> &mut HardTuneEffect {
+        self.settings
+            .hardtune_effect_mut()
+            .get_preset_mut(self.preset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(name: &str) -> Track {
+        Track::new(name.to_string(), 10.0, 90.0, 1.0)
+    }
+
+    #[test]
+    fn built_profiles_write_and_reload() {
+        let red = Colour::fromrgb("FF0000").unwrap();
+        let mut built = ProfileSettingsBuilder::new()
+            .unwrap()
+            .fader(Faders::A, FullChannelList::LineIn, red)
+            .mixer_volume(FullChannelList::Music, 200)
+            .effect_bank(Preset::Preset2, |bank| {
+                bank.name("Studio")?;
+                bank.reverb().set_knob_position(12)?;
+                bank.gender().set_knob_position(-5)
+            })
+            .sample(SampleButtons::TopLeft, SampleBank::B, track("kick.wav"))
+            .sample(SampleButtons::TopLeft, SampleBank::B, track("snare.wav"))
+            .build()
+            .unwrap();
+
+        let mut written = vec![];
+        built.write_to(&mut written).unwrap();
+        let reloaded = ProfileSettings::load(written.as_slice()).unwrap();
+
+        for fader in Faders::iter() {
+            assert_eq!(
+                reloaded.fader(fader).channel(),
+                built.fader(fader).channel()
+            );
+        }
+        assert_eq!(reloaded.fader(Faders::A).channel(), FullChannelList::LineIn);
+        assert_eq!(
+            *reloaded.fader(Faders::A).colour_map().colour_or_default(0),
+            red
+        );
+        assert_eq!(reloaded.fader(Faders::B).channel(), FullChannelList::Music);
+
+        assert_eq!(reloaded.mixer().channel_volume(FullChannelList::Music), 200);
+        assert_eq!(reloaded.effects(Preset::Preset2).name(), "Studio");
+        assert_eq!(reloaded.reverb_encoder(), built.reverb_encoder());
+        assert_eq!(reloaded.gender_encoder(), built.gender_encoder());
+        assert_eq!(
+            reloaded
+                .reverb_encoder()
+                .get_preset(Preset::Preset2)
+                .knob_position(),
+            12
+        );
+
+        let tracks: Vec<_> = reloaded
+            .sample_button(SampleButtons::TopLeft)
+            .get_stack(SampleBank::B)
+            .get_tracks()
+            .iter()
+            .map(|track| (track.track(), track.start_position()))
+            .collect();
+        assert_eq!(tracks, [("kick.wav", 10.0), ("snare.wav", 10.0)]);
+        for button in SampleButtons::iter() {
+            assert_eq!(reloaded.sample_button(button), built.sample_button(button));
+        }
+    }
+
+    #[test]
+    fn invalid_steps_fail_the_build() {
+        let red = Colour::fromrgb("FF0000").unwrap();
+
+        // Music is already on Fader B..
+        let result = ProfileSettingsBuilder::new()
+            .unwrap()
+            .fader(Faders::A, FullChannelList::Music, red)
+            .build();
+        assert!(result.is_err());
+
+        let result = ProfileSettingsBuilder::new()
+            .unwrap()
+            .sample(SampleButtons::Clear, SampleBank::A, track("kick.wav"))
+            .build();
+        assert!(result.is_err());
+
+        let result = ProfileSettingsBuilder::new()
+            .unwrap()
+            .sample(
+                SampleButtons::TopLeft,
+                SampleBank::A,
+                Track::new("kick.wav".to_string(), 80.0, 20.0, 1.0),
+            )
+            .build();
+        assert!(result.is_err());
+
+        let result = ProfileSettingsBuilder::new()
+            .unwrap()
+            .effect_bank(Preset::Preset1, |bank| bank.reverb().set_knob_position(40))
+            .build();
+        assert!(result.is_err());
+    }
+}
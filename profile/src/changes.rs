@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Mutex, PoisonError};
+
+use strum::Display;
+
+/**
+ * When mutating a profile through one of the scoped `with_*` methods on ProfileSettings, any
+ * values which changed are reported through the registered change handler. Fields are named
+ * after the attributes they're stored as in the profile XML, and values are their XML
+ * representation.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangeEvent {
+    pub section: ChangeSection,
+    pub field: String,
+    pub value: String,
+}
+
+#[derive(Debug, Display, Copy, Clone, PartialEq, Eq)]
+pub enum ChangeSection {
+    Mixer,
+    Fader,
+    Effects,
+    Colours,
+}
+
+// Handlers only need to be Send, but ProfileSettings is shared across await points in the daemon
+// so has to stay Sync. Events are only sent from &mut self, so the Mutex is never actually locked..
+pub(crate) struct ChangeHandler(Mutex<Box<dyn Fn(ChangeEvent) + Send>>);
+
+impl ChangeHandler {
+    pub(crate) fn new(handler: Box<dyn Fn(ChangeEvent) + Send>) -> Self {
+        Self(Mutex::new(handler))
+    }
+
+    /// Compares two snapshots of a section, and fires an event for every field which differs.
+    pub(crate) fn emit_changes(
+        &mut self,
+        section: ChangeSection,
+        before: &HashMap<String, String>,
+        after: HashMap<String, String>,
+    ) {
+        let mut changes: Vec<(String, String)> = after
+            .into_iter()
+            .filter(|(field, value)| before.get(field) != Some(value))
+            .collect();
+
+        // HashMap ordering is random, keep the output stable for listeners..
+        changes.sort();
+
+        let handler = self.0.get_mut().unwrap_or_else(PoisonError::into_inner);
+        for (field, value) in changes {
+            handler(ChangeEvent {
+                section,
+                field,
+                value,
+            });
+        }
+    }
+}
+
+impl Debug for ChangeHandler {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ChangeHandler")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::sync::mpsc;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::components::mixer::FullChannelList;
+    use crate::profile::ProfileSettings;
+
+    fn collector(settings: &mut ProfileSettings) -> Arc<Mutex<Vec<ChangeEvent>>> {
+        let events = Arc::new(Mutex::new(vec![]));
+        let sink = events.clone();
+        settings.set_change_handler(Box::new(move |event| sink.lock().unwrap().push(event)));
+        events
+    }
+
+    fn load() -> ProfileSettings {
+        ProfileSettings::load(include_str!("../test-data/profile.xml").as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn reports_changed_fields() {
+        let mut settings = load();
+        let events = collector(&mut settings);
+
+        settings
+            .with_mixer(|mixer| mixer.set_channel_volume(FullChannelList::Mic, 100))
+            .unwrap();
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![ChangeEvent {
+                section: ChangeSection::Mixer,
+                field: String::from("micLevel"),
+                value: String::from("100"),
+            }]
+        );
+    }
+
+    #[test]
+    fn unchanged_values_are_not_reported() {
+        let mut settings = load();
+        let events = collector(&mut settings);
+
+        let volume = settings.mixer().channel_volume(FullChannelList::Mic);
+        settings
+            .with_mixer(|mixer| mixer.set_channel_volume(FullChannelList::Mic, volume))
+            .unwrap();
+        assert!(events.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn cleared_handlers_are_not_called() {
+        let mut settings = load();
+        let events = collector(&mut settings);
+        settings.clear_change_handler();
+
+        settings
+            .with_mixer(|mixer| mixer.set_channel_volume(FullChannelList::Mic, 100))
+            .unwrap();
+        assert!(events.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn changes_are_emitted_in_order() {
+        let events = Arc::new(Mutex::new(vec![]));
+        let sink = events.clone();
+        let mut handler = ChangeHandler::new(Box::new(move |event: ChangeEvent| {
+            sink.lock().unwrap().push(event.field)
+        }));
+
+        let before = HashMap::from([(String::from("a"), String::from("1"))]);
+        let after = HashMap::from([
+            (String::from("c"), String::from("1")),
+            (String::from("a"), String::from("2")),
+            (String::from("b"), String::from("1")),
+        ]);
+        handler.emit_changes(ChangeSection::Mixer, &before, after);
+        assert_eq!(*events.lock().unwrap(), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn handlers_only_need_to_be_send() {
+        let mut settings = load();
+        let (sender, receiver) = mpsc::channel();
+
+        // A RefCell isn't Sync, but can be moved into a handler..
+        let fields = RefCell::new(vec![]);
+        settings.set_change_handler(Box::new(move |event| {
+            fields.borrow_mut().push(event.field);
+            sender.send(fields.borrow().len()).unwrap();
+        }));
+
+        settings
+            .with_mixer(|mixer| mixer.set_channel_volume(FullChannelList::Mic, 100))
+            .unwrap();
+        assert_eq!(receiver.try_recv(), Ok(1));
+
+        // And the profile can still be shared between threads..
+        fn is_sync<T: Send + Sync>(_: &T) {}
+        is_sync(&settings);
+    }
+}
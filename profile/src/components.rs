@@ -3,6 +3,7 @@ pub mod browser;
 pub mod colours;
 pub mod context;
 pub mod echo;
+pub mod effect_base;
 pub mod effects;
 pub mod fader;
 pub mod gender;
@@ -13,10 +14,12 @@ pub mod mute;
 pub mod mute_chat;
 pub mod pitch;
 pub mod preset_writer;
+pub mod registry;
 pub mod reverb;
 pub mod robot;
 pub mod root;
 pub mod sample;
 pub mod scribble;
+pub mod shutdown;
 pub mod simple;
 pub mod submix;
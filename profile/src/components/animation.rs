@@ -1,5 +1,5 @@
 use crate::components::animation::AnimationMode::{RainbowBright, RainbowDark, RetroRainbow};
-use crate::profile::Attribute;
+use crate::profile::Attributes;
 use anyhow::{bail, Result};
 use log::warn;
 use quick_xml::events::{BytesStart, Event};
@@ -27,7 +27,7 @@ impl AnimationTree {
         }
     }
 
-    pub fn parse_animation(&mut self, attributes: &Vec<Attribute>) -> Result<()> {
+    pub fn parse_animation(&mut self, attributes: &Attributes) -> Result<()> {
         for attr in attributes {
             if attr.name == "animationMode" {
                 match AnimationMode::iter().nth(attr.value.parse()?) {
@@ -6,7 +6,7 @@ use quick_xml::events::{BytesStart, Event};
 use quick_xml::Writer;
 
 use crate::components::colours::ColourMap;
-use crate::profile::Attribute;
+use crate::profile::Attributes;
 
 #[derive(thiserror::Error, Debug)]
 #[allow(clippy::enum_variant_names)]
@@ -53,7 +53,7 @@ impl BrowserPreviewTree {
         }
     }
 
-    pub fn parse_browser(&mut self, attributes: &Vec<Attribute>) -> Result<(), ParseError> {
+    pub fn parse_browser(&mut self, attributes: &Attributes) -> Result<(), ParseError> {
         for attr in attributes {
             if attr.name == "playing" {
                 self.playing = attr.value.parse()?;
@@ -19,12 +19,35 @@ pub enum ParseError {
 
     #[error("[COLOURS] Expected Length: 8 (RRGGBB), Got: {0}")]
     InvalidRGBLength(String),
+
+    #[error("[COLOURS] Expected hexadecimal digits, Got: {0}")]
+    InvalidHex(String),
 }
 use crate::components::colours::ColourDisplay::{Gradient, GradientMeter, Meter};
+use crate::components::effect_base::{EffectType, EncoderType};
+use crate::components::simple::SimpleElements;
 use crate::profile::Attribute;
+use crate::{Faders, Preset, SampleButtons};
 use strum::{Display, EnumString};
 
-#[derive(Debug)]
+/**
+ * Identifies which part of the device a ColourMap belongs to, used when walking all of the
+ * colour maps in a profile.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LightingTarget {
+    MuteChat,
+    Fader(Faders),
+    MuteButton(Faders),
+    Scribble(Faders),
+    EffectButton(Preset),
+    Effect(EffectType),
+    Encoder(EncoderType),
+    Sampler(SampleButtons),
+    Simple(SimpleElements),
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct ColourMap {
     // The colour attribute prefix (for parsing)..
     prefix: String,
@@ -73,6 +96,10 @@ impl ColourMap {
         }
     }
 
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
     pub fn read_colours(&mut self, attribute: &Attribute) -> Result<bool, ParseError> {
         let mut attr_key = format!("{}offStyle", &self.prefix);
         if attribute.name == attr_key {
@@ -206,6 +233,11 @@ impl ColourMap {
         }
     }
 
+    /// Checks whether a colour string can be sent to the device, see Colour::try_from_device_string.
+    pub fn is_valid_colour(value: &str) -> bool {
+        Colour::try_from_device_string(value).is_ok()
+    }
+
     pub fn colour(&self, index: u8) -> &Colour {
         self.colour_list.as_ref().unwrap()[index as usize]
             .as_ref()
@@ -328,12 +360,7 @@ impl ColourMap {
     }
 }
 
-const DEFAULT_COLOUR: Colour = Colour {
-    red: 0,
-    green: 0,
-    blue: 0,
-    alpha: 0,
-};
+const DEFAULT_COLOUR: Colour = Colour::OFF;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, EnumString, Display)]
 pub enum ColourOffStyle {
@@ -371,7 +398,7 @@ pub enum ColourState {
     On,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Colour {
     red: u8,
     green: u8,
@@ -380,6 +407,29 @@ pub struct Colour {
 }
 
 impl Colour {
+    // This is what the official app writes when a colour is 'unset', the GoXLR treats it as off.
+    pub const OFF: Colour = Colour::from_argb_parts(0x00, 0x00, 0x00, 0x00);
+    pub const WHITE: Colour = Colour::from_argb_parts(0xFF, 0xFF, 0xFF, 0xFF);
+
+    // The palette used by the default profiles which ship with the GoXLR..
+    pub const TEAL: Colour = Colour::from_argb_parts(0xFF, 0x00, 0xFF, 0xFF);
+    pub const RED: Colour = Colour::from_argb_parts(0xFF, 0xFF, 0x00, 0x00);
+    pub const VAPORWAVE_BLUE: Colour = Colour::from_argb_parts(0xFF, 0x00, 0x3F, 0xFA);
+    pub const VAPORWAVE_PINK: Colour = Colour::from_argb_parts(0xFF, 0xFF, 0x00, 0xC8);
+
+    // The 'Default' profile uses Teal for the global colour, and white as the accent for buttons.
+    pub const DEFAULT_GLOBAL: Colour = Colour::TEAL;
+    pub const DEFAULT_ACCENT: Colour = Colour::WHITE;
+
+    pub const fn from_argb_parts(alpha: u8, red: u8, green: u8, blue: u8) -> Self {
+        Self {
+            red,
+            green,
+            blue,
+            alpha,
+        }
+    }
+
     pub fn new(argb: &str) -> Result<Self, ParseError> {
         if argb == "0" || argb.is_empty() {
             return Ok(DEFAULT_COLOUR);
@@ -388,6 +438,8 @@ impl Colour {
         if argb.len() != 8 {
             return Err(ParseError::InvalidARGBLength(argb.to_string()));
         }
+        Colour::check_hex(argb)?;
+
         Ok(Self {
             alpha: u8::from_str_radix(&argb[0..2], 16)?,
             red: u8::from_str_radix(&argb[2..4], 16)?,
@@ -400,6 +452,7 @@ impl Colour {
         if rgb.len() != 6 {
             return Err(ParseError::InvalidRGBLength(rgb.to_string()));
         }
+        Colour::check_hex(rgb)?;
 
         Ok(Self {
             red: u8::from_str_radix(&rgb[0..2], 16)?,
@@ -409,6 +462,25 @@ impl Colour {
         })
     }
 
+    /// Parses a colour in either of the forms the device accepts, RRGGBB (which is treated as
+    /// fully opaque) or AARRGGBB. Unlike Colour::new, an empty string or "0" is rejected.
+    pub fn try_from_device_string(value: &str) -> Result<Self, ParseError> {
+        match value.len() {
+            6 => Colour::fromrgb(value),
+            8 => Colour::new(value),
+            _ => Err(ParseError::InvalidRGBLength(value.to_string())),
+        }
+    }
+
+    // from_str_radix will happily accept a leading '+', and slicing a string containing multibyte
+    // characters will panic, so make sure we're only dealing with hex digits..
+    fn check_hex(value: &str) -> Result<(), ParseError> {
+        if !value.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(ParseError::InvalidHex(value.to_string()));
+        }
+        Ok(())
+    }
+
     pub fn from(colour: &Colour) -> Self {
         Self {
             red: colour.red,
@@ -11,7 +11,7 @@ use quick_xml::Writer;
 
 use crate::components::colours::ColourMap;
 use crate::components::sample::SampleBank;
-use crate::profile::Attribute;
+use crate::profile::Attributes;
 use crate::Preset;
 
 #[derive(thiserror::Error, Debug)]
@@ -59,7 +59,7 @@ impl Context {
         }
     }
 
-    pub fn parse_context(&mut self, attributes: &Vec<Attribute>) -> Result<(), ParseError> {
+    pub fn parse_context(&mut self, attributes: &Attributes) -> Result<(), ParseError> {
         for attr in attributes {
             if attr.name == "numselected" {
                 self.selected = attr.value.parse()?;
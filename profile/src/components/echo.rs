@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::io::Write;
 use std::os::raw::c_float;
+use std::str::FromStr;
 
 use enum_map::{Enum, EnumMap};
 use strum::{EnumIter, EnumProperty, IntoEnumIterator};
@@ -10,8 +11,9 @@ use quick_xml::events::{BytesEnd, BytesStart, Event};
 use quick_xml::Writer;
 
 use crate::components::colours::ColourMap;
+use crate::components::effect_base::{strip_defaults, EncoderBase, EncoderPressAction};
 
-use crate::profile::Attribute;
+use crate::profile::Attributes;
 use crate::Preset;
 
 #[derive(thiserror::Error, Debug)]
@@ -35,11 +37,12 @@ pub enum ParseError {
  * presets, we'll use an EnumMap to define the 'presets' as they'll be useful for the other various
  * 'types' of presets (encoders and effects).
  */
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct EchoEncoderBase {
     colour_map: ColourMap,
     preset_map: EnumMap<Preset, EchoEncoder>,
     active_set: u8, // Not sure what this does?
+    press_action: EncoderPressAction,
 }
 
 impl EchoEncoderBase {
@@ -49,16 +52,22 @@ impl EchoEncoderBase {
             colour_map: ColourMap::new(colour_map),
             preset_map: EnumMap::default(),
             active_set: 0,
+            press_action: EncoderPressAction::None,
         }
     }
 
-    pub fn parse_echo_root(&mut self, attributes: &Vec<Attribute>) -> Result<(), ParseError> {
+    pub fn parse_echo_root(&mut self, attributes: &Attributes) -> Result<(), ParseError> {
         for attr in attributes {
             if attr.name == "active_set" {
                 self.active_set = attr.value.parse()?;
                 continue;
             }
 
+            if attr.name == "pressAction" {
+                self.press_action = EncoderPressAction::from_str(&attr.value)?;
+                continue;
+            }
+
             if !self.colour_map.read_colours(attr)? {
                 println!("[EchoEncoder] Unparsed Attribute: {}", attr.name);
             }
@@ -70,7 +79,7 @@ impl EchoEncoderBase {
     pub fn parse_echo_preset(
         &mut self,
         preset_enum: Preset,
-        attributes: &Vec<Attribute>,
+        attributes: &Attributes,
     ) -> Result<()> {
         let mut preset = EchoEncoder::new();
         for attr in attributes {
@@ -145,11 +154,14 @@ impl EchoEncoderBase {
         Ok(())
     }
 
-    pub fn write_echo<W: Write>(&self, writer: &mut Writer<W>) -> Result<()> {
+    pub fn write_echo<W: Write>(&self, writer: &mut Writer<W>, minimal: bool) -> Result<()> {
         let mut elem = BytesStart::new("echoEncoder");
 
         let mut attributes: HashMap<String, String> = HashMap::default();
         attributes.insert("active_set".to_string(), format!("{}", self.active_set));
+        if self.press_action != EncoderPressAction::None {
+            attributes.insert("pressAction".to_string(), self.press_action.to_string());
+        }
         self.colour_map.write_colours(&mut attributes);
 
         // Write out the attributes etc for this element, but don't close it yet..
@@ -160,11 +172,15 @@ impl EchoEncoderBase {
         writer.write_event(Event::Start(elem))?;
 
         // Because all of these are seemingly 'guaranteed' to exist, we can straight dump..
+        let defaults = Self::default_preset_attributes();
         for preset in Preset::iter() {
             let tag_name = format!("echoEncoder{}", preset.get_str("tagSuffix").unwrap());
             let mut sub_elem = BytesStart::new(tag_name.as_str());
 
-            let sub_attributes = self.get_preset_attributes(preset);
+            let mut sub_attributes = self.get_preset_attributes(preset);
+            if minimal {
+                strip_defaults(&mut sub_attributes, &defaults);
+            }
             for (key, value) in &sub_attributes {
                 sub_elem.push_attribute((key.as_str(), value.as_str()));
             }
@@ -222,6 +238,14 @@ impl EchoEncoderBase {
         &mut self.colour_map
     }
 
+    pub fn press_action(&self) -> EncoderPressAction {
+        self.press_action
+    }
+
+    pub fn set_press_action(&mut self, action: EncoderPressAction) {
+        self.press_action = action;
+    }
+
     pub fn get_preset(&self, preset: Preset) -> &EchoEncoder {
         &self.preset_map[preset]
     }
@@ -229,9 +253,38 @@ impl EchoEncoderBase {
     pub fn get_preset_mut(&mut self, preset: Preset) -> &mut EchoEncoder {
         &mut self.preset_map[preset]
     }
+
+    /// The feedback amount (DELAY_FB_CONTROL) for a bank, as a percentage.
+    pub fn feedback(&self, preset: Preset) -> u8 {
+        self.preset_map[preset].feedback_control()
+    }
+    pub fn set_feedback(&mut self, preset: Preset, value: u8) -> Result<()> {
+        self.preset_map[preset].set_feedback(value)
+    }
+
+    /// The left and right delay times (DELAY_TIME_L and DELAY_TIME_R) for a bank, in ms.
+    pub fn delay(&self, preset: Preset) -> (u16, u16) {
+        let encoder = &self.preset_map[preset];
+        (encoder.time_left(), encoder.time_right())
+    }
+
+    /// Sets both delay times, if either is invalid neither is changed.
+    pub fn set_delay(&mut self, preset: Preset, left: u16, right: u16) -> Result<()> {
+        let encoder = &mut self.preset_map[preset];
+        let (previous_left, previous_right) = (encoder.time_left, encoder.time_right);
+
+        let result = encoder
+            .set_time_left(left)
+            .and_then(|_| encoder.set_time_right(right));
+        if result.is_err() {
+            encoder.time_left = previous_left;
+            encoder.time_right = previous_right;
+        }
+        result
+    }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct EchoEncoder {
     knob_position: i8,
     style: EchoStyle,
@@ -324,6 +377,27 @@ impl EchoEncoder {
         Ok(())
     }
 
+    /// Returns true if the style's parameters are still at the factory values for the style.
+    pub fn is_default(&self) -> bool {
+        let preset = EchoPreset::get_preset(self.style);
+
+        // Not every style defines the delay times, in which case they're left as they were..
+        let time_left = preset.time_left.unwrap_or(self.time_left);
+        let time_right = preset.time_right.unwrap_or(self.time_right);
+
+        self.source == preset.source
+            && self.div_l == preset.div_l
+            && self.div_r == preset.div_r
+            && self.feedback_left == preset.feedback_left
+            && self.feedback_right == preset.feedback_right
+            && self.feedback_control == preset.feedback_control
+            && self.xfb_l_to_r == preset.xfb_l_to_r
+            && self.xfb_r_to_l == preset.xfb_r_to_l
+            && self.filter_style == preset.filter_style
+            && self.time_left == time_left
+            && self.time_right == time_right
+    }
+
     pub fn source(&self) -> u8 {
         self.source
     }
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+use std::io::Write;
+
+use anyhow::Result;
+use quick_xml::Writer;
+use strum::{Display, EnumIter, EnumProperty, EnumString, IntoEnumIterator};
+
+use crate::components::colours::ColourMap;
+use crate::components::echo::EchoEncoderBase;
+use crate::components::gender::GenderEncoderBase;
+use crate::components::hardtune::HardtuneEffectBase;
+use crate::components::megaphone::MegaphoneEffectBase;
+use crate::components::pitch::PitchEncoderBase;
+use crate::components::reverb::ReverbEncoderBase;
+use crate::components::robot::RobotEffectBase;
+use crate::profile::Attributes;
+use crate::Preset;
+
+#[derive(Debug, Display, EnumIter, EnumProperty, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum EncoderType {
+    #[strum(props(tagName = "reverbEncoder"))]
+    Reverb,
+
+    #[strum(props(tagName = "echoEncoder"))]
+    Echo,
+
+    #[strum(props(tagName = "pitchEncoder"))]
+    Pitch,
+
+    #[strum(props(tagName = "genderEncoder"))]
+    Gender,
+}
+
+/// What should happen when an encoder is pressed in, stored on the encoder's root element.
+#[derive(Debug, Default, Display, EnumString, EnumIter, Copy, Clone, PartialEq, Eq)]
+pub enum EncoderPressAction {
+    #[default]
+    #[strum(to_string = "none")]
+    None,
+
+    #[strum(to_string = "resetValue")]
+    ResetValue,
+
+    #[strum(to_string = "cycleStyle")]
+    CycleStyle,
+}
+
+/// The result of pressing an encoder, the encoder value is what the hardware should be set to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct EncoderPressOutcome {
+    pub action: EncoderPressAction,
+    pub encoder_value: i8,
+}
+
+#[derive(Debug, Display, EnumIter, EnumProperty, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum EffectType {
+    #[strum(props(tagName = "megaphoneEffect"))]
+    Megaphone,
+
+    #[strum(props(tagName = "robotEffect"))]
+    Robot,
+
+    #[strum(props(tagName = "hardtuneEffect"))]
+    HardTune,
+}
+
+/**
+ * The four encoders (Reverb, Echo, Pitch and Gender) all share the same layout in the profile, a
+ * root element holding the colour map, and one child element per preset. This trait allows them
+ * to be handled generically, rather than needing a match arm for each.
+ *
+ * Writing goes through a `dyn Write` so that the trait remains usable as a trait object.
+ */
+pub trait EncoderBase {
+    fn colour_map(&self) -> &ColourMap;
+    fn colour_map_mut(&mut self) -> &mut ColourMap;
+
+    fn parse_root(&mut self, attributes: &Attributes) -> Result<()>;
+    fn parse_preset(&mut self, preset: Preset, attributes: &Attributes) -> Result<()>;
+
+    fn get_preset_attributes(&self, preset: Preset) -> HashMap<String, String>;
+    fn write(&self, writer: &mut Writer<&mut dyn Write>, minimal: bool) -> Result<()>;
+
+    /// The attributes a preset ends up with when it's loaded from an element with none at all,
+    /// these are left out when writing a minimal profile.
+    fn default_preset_attributes() -> HashMap<String, String>
+    where
+        Self: Sized;
+}
+
+/**
+ * The same as EncoderBase, but for the three effects (Megaphone, Robot and HardTune), which are
+ * toggled by their button rather than adjusted with an encoder.
+ */
+pub trait EffectBase {
+    fn colour_map(&self) -> &ColourMap;
+    fn colour_map_mut(&mut self) -> &mut ColourMap;
+
+    fn parse_root(&mut self, attributes: &Attributes) -> Result<()>;
+    fn parse_preset(&mut self, preset: Preset, attributes: &Attributes) -> Result<()>;
+
+    fn get_preset_attributes(&self, preset: Preset) -> HashMap<String, String>;
+    fn write(&self, writer: &mut Writer<&mut dyn Write>, minimal: bool) -> Result<()>;
+
+    /// The attributes a preset ends up with when it's loaded from an element with none at all,
+    /// these are left out when writing a minimal profile.
+    fn default_preset_attributes() -> HashMap<String, String>
+    where
+        Self: Sized;
+}
+
+/// Returns the style following the current one, wrapping back around to the first.
+pub(crate) fn next_style<T: IntoEnumIterator + PartialEq>(current: T) -> T {
+    let mut styles = T::iter().skip_while(|style| *style != current).skip(1);
+    styles.next().unwrap_or_else(|| T::iter().next().unwrap())
+}
+
+/// Removes any attribute which matches its default, a preset missing the attribute will load
+/// with that value anyway. Used when writing minimal profiles.
+pub(crate) fn strip_defaults(
+    attributes: &mut HashMap<String, String>,
+    defaults: &HashMap<String, String>,
+) {
+    attributes.retain(|key, value| defaults.get(key) != Some(value));
+}
+
+// Each base already has these as inherent methods, named after the component, so the trait
+// implementations just forward to them, and the preset defaults come from parsing an empty
+// element. Most of the parsers return a ParseError, but a couple already return an anyhow Error,
+// where the conversion isn't needed..
+macro_rules! impl_base {
+    ($trait:ident for $base:ty, $parse_root:ident, $parse_preset:ident, $write:ident) => {
+        #[allow(clippy::needless_question_mark)]
+        impl $trait for $base {
+            fn colour_map(&self) -> &ColourMap {
+                self.colour_map()
+            }
+            fn colour_map_mut(&mut self) -> &mut ColourMap {
+                self.colour_map_mut()
+            }
+            fn parse_root(&mut self, attributes: &Attributes) -> Result<()> {
+                Ok(self.$parse_root(attributes)?)
+            }
+            fn parse_preset(&mut self, preset: Preset, attributes: &Attributes) -> Result<()> {
+                Ok(self.$parse_preset(preset, attributes)?)
+            }
+            fn get_preset_attributes(&self, preset: Preset) -> HashMap<String, String> {
+                self.get_preset_attributes(preset)
+            }
+            fn write(&self, writer: &mut Writer<&mut dyn Write>, minimal: bool) -> Result<()> {
+                self.$write(writer, minimal)
+            }
+            fn default_preset_attributes() -> HashMap<String, String> {
+                let mut base = Self::new(String::new());
+                let _ = base.$parse_preset(Preset::Preset1, &Attributes::default());
+                base.get_preset_attributes(Preset::Preset1)
+            }
+        }
+    };
+}
+
+impl_base!(
+    EncoderBase for ReverbEncoderBase,
+    parse_reverb_root, parse_reverb_preset, write_reverb
+);
+impl_base!(
+    EncoderBase for EchoEncoderBase,
+    parse_echo_root, parse_echo_preset, write_echo
+);
+impl_base!(
+    EncoderBase for PitchEncoderBase,
+    parse_pitch_root, parse_pitch_preset, write_pitch
+);
+impl_base!(
+    EncoderBase for GenderEncoderBase,
+    parse_gender_root, parse_gender_preset, write_gender
+);
+
+impl_base!(
+    EffectBase for MegaphoneEffectBase,
+    parse_megaphone_root, parse_megaphone_preset, write_megaphone
+);
+impl_base!(
+    EffectBase for RobotEffectBase,
+    parse_robot_root, parse_robot_preset, write_robot
+);
+impl_base!(
+    EffectBase for HardtuneEffectBase,
+    parse_hardtune_root, parse_hardtune_preset, write_hardtune
+);
+
+#[cfg(test)]
+mod tests {
+    use crate::components::colours::Colour;
+    use crate::profile::ProfileSettings;
+
+    #[test]
+    fn encoders_and_effects_can_be_mutated_generically() {
+        let xml = include_str!("../../test-data/profile.xml");
+        let mut settings = ProfileSettings::load(xml.as_bytes()).unwrap();
+
+        let colour = Colour::fromrgb("112233").unwrap();
+        for (_, encoder) in settings.encoders_mut() {
+            encoder.colour_map_mut().set_colour(0, colour).unwrap();
+        }
+        for (_, effect) in settings.effect_bases_mut() {
+            effect.colour_map_mut().set_colour(0, colour).unwrap();
+        }
+
+        // Make sure the change survives being written out by the generic writers..
+        let mut written = vec![];
+        settings.write_to(&mut written).unwrap();
+        let settings = ProfileSettings::load(written.as_slice()).unwrap();
+
+        assert_eq!(settings.encoders().count(), 4);
+        assert_eq!(settings.effect_bases().count(), 3);
+        for (encoder, base) in settings.encoders() {
+            assert_eq!(base.colour_map().colour(0).to_rgb(), "112233", "{encoder}");
+        }
+        for (effect, base) in settings.effect_bases() {
+            assert_eq!(base.colour_map().colour(0).to_rgb(), "112233", "{effect}");
+        }
+    }
+}
@@ -7,7 +7,7 @@ use quick_xml::Writer;
 use strum::EnumProperty;
 
 use crate::components::colours::ColourMap;
-use crate::profile::Attribute;
+use crate::profile::Attributes;
 use crate::Preset;
 
 #[derive(thiserror::Error, Debug)]
@@ -26,7 +26,7 @@ pub enum ParseError {
     InvalidColours(#[from] crate::components::colours::ParseError),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Effects {
     element_name: String,
     colour_map: ColourMap,
@@ -47,7 +47,7 @@ impl Effects {
         }
     }
 
-    pub fn parse_effect(&mut self, attributes: &Vec<Attribute>) -> Result<(), ParseError> {
+    pub fn parse_effect(&mut self, attributes: &Attributes) -> Result<(), ParseError> {
         for attr in attributes {
             if attr.name.ends_with("Name") {
                 self.name = attr.value.clone();
@@ -7,9 +7,9 @@ use anyhow::Result;
 use quick_xml::events::{BytesStart, Event};
 use quick_xml::Writer;
 
-use crate::components::colours::ColourMap;
+use crate::components::colours::{ColourDisplay, ColourMap};
 use crate::components::mixer::FullChannelList;
-use crate::profile::Attribute;
+use crate::profile::Attributes;
 
 #[derive(thiserror::Error, Debug)]
 #[allow(clippy::enum_variant_names)]
@@ -42,7 +42,7 @@ impl Fader {
         }
     }
 
-    pub fn parse_fader(&mut self, attributes: &Vec<Attribute>) -> Result<(), ParseError> {
+    pub fn parse_fader(&mut self, attributes: &Attributes) -> Result<(), ParseError> {
         for attr in attributes {
             if attr.name.ends_with("listIndex") {
                 let mut found = false;
@@ -78,6 +78,16 @@ impl Fader {
     ) -> Result<()> {
         let mut elem = BytesStart::new(element_name.as_str());
 
+        let attributes = self.get_attributes(&element_name);
+        for (key, value) in &attributes {
+            elem.push_attribute((key.as_str(), value.as_str()));
+        }
+
+        writer.write_event(Event::Empty(elem))?;
+        Ok(())
+    }
+
+    pub fn get_attributes(&self, element_name: &str) -> HashMap<String, String> {
         let mut attributes: HashMap<String, String> = HashMap::default();
         attributes.insert(
             format!("{element_name}listIndex"),
@@ -85,14 +95,8 @@ impl Fader {
         );
 
         self.colour_map
-            .write_colours_with_prefix(element_name.clone(), &mut attributes);
-
-        for (key, value) in &attributes {
-            elem.push_attribute((key.as_str(), value.as_str()));
-        }
-
-        writer.write_event(Event::Empty(elem))?;
-        Ok(())
+            .write_colours_with_prefix(element_name.to_string(), &mut attributes);
+        attributes
     }
 
     pub fn channel(&self) -> FullChannelList {
@@ -102,6 +106,17 @@ impl Fader {
         self.channel = channel;
     }
 
+    /// How the fader's LEDs are drawn. A fader without a display set in the profile is shown
+    /// by the device as TwoColour.
+    pub fn display_mode(&self) -> ColourDisplay {
+        self.colour_map
+            .fader_display()
+            .unwrap_or(ColourDisplay::TwoColour)
+    }
+    pub fn set_display_mode(&mut self, mode: ColourDisplay) -> Result<()> {
+        self.colour_map.set_fader_display(mode)
+    }
+
     pub fn colour_map(&self) -> &ColourMap {
         &self.colour_map
     }
@@ -109,3 +124,67 @@ impl Fader {
         &mut self.colour_map
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::ProfileSettings;
+    use crate::Faders;
+
+    const PROFILE: &str = include_str!("../../test-data/profile.xml");
+
+    const MODES: [ColourDisplay; 4] = [
+        ColourDisplay::Gradient,
+        ColourDisplay::Meter,
+        ColourDisplay::GradientMeter,
+        ColourDisplay::TwoColour,
+    ];
+
+    fn save(settings: &mut ProfileSettings) -> String {
+        let mut written = vec![];
+        settings.write_to(&mut written).unwrap();
+        String::from_utf8(written).unwrap()
+    }
+
+    #[test]
+    fn display_modes_round_trip() {
+        for mode in MODES {
+            let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+            for fader in Faders::iter() {
+                settings.fader_mut(fader).set_display_mode(mode).unwrap();
+            }
+
+            let written = save(&mut settings);
+            let reloaded = ProfileSettings::load(written.as_bytes()).unwrap();
+            for fader in Faders::iter() {
+                assert_eq!(reloaded.fader(fader).display_mode(), mode, "{fader:?}");
+
+                let attribute = format!("FaderMeter{}Display=\"{mode}\"", fader as u8);
+                assert!(written.contains(&attribute), "{attribute}");
+            }
+        }
+    }
+
+    #[test]
+    fn display_modes_are_set_per_fader() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        for (fader, mode) in Faders::iter().zip(MODES) {
+            settings.fader_mut(fader).set_display_mode(mode).unwrap();
+        }
+
+        let reloaded = ProfileSettings::load(save(&mut settings).as_bytes()).unwrap();
+        for (fader, mode) in Faders::iter().zip(MODES) {
+            assert_eq!(reloaded.fader(fader).display_mode(), mode, "{fader:?}");
+        }
+    }
+
+    #[test]
+    fn a_missing_display_mode_is_two_colour() {
+        let xml = PROFILE.replace("FaderMeter0Display=\"GRADIENT\"", "");
+        let settings = ProfileSettings::load(xml.as_bytes()).unwrap();
+        assert_eq!(
+            settings.fader(Faders::A).display_mode(),
+            ColourDisplay::TwoColour
+        );
+    }
+}
@@ -1,6 +1,5 @@
 use std::collections::HashMap;
 use std::io::Write;
-use std::os::raw::c_float;
 use std::str::FromStr;
 
 use enum_map::EnumMap;
@@ -11,9 +10,10 @@ use quick_xml::events::{BytesEnd, BytesStart, Event};
 use quick_xml::Writer;
 
 use crate::components::colours::ColourMap;
+use crate::components::effect_base::{strip_defaults, EffectBase};
 use crate::components::hardtune::HardTuneSource::All;
 use crate::components::hardtune::HardTuneStyle::Natural;
-use crate::profile::Attribute;
+use crate::profile::Attributes;
 use crate::Preset;
 
 #[derive(thiserror::Error, Debug)]
@@ -30,6 +30,9 @@ pub enum ParseError {
 
     #[error("[HardTune] Invalid colours: {0}")]
     InvalidColours(#[from] crate::components::colours::ParseError),
+
+    #[error("[HardTune] Unknown Key Source: {0}")]
+    InvalidKeySource(String),
 }
 
 /**
@@ -37,7 +40,7 @@ pub enum ParseError {
  * presets, we'll use an EnumMap to define the 'presets' as they'll be useful for the other various
  * 'types' of presets (encoders and effects).
  */
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct HardtuneEffectBase {
     colour_map: ColourMap,
     preset_map: EnumMap<Preset, HardTuneEffect>,
@@ -54,7 +57,7 @@ impl HardtuneEffectBase {
         }
     }
 
-    pub fn parse_hardtune_root(&mut self, attributes: &Vec<Attribute>) -> Result<(), ParseError> {
+    pub fn parse_hardtune_root(&mut self, attributes: &Attributes) -> Result<(), ParseError> {
         for attr in attributes {
             // I honestly have no idea why this lives here :D
             if attr.name == "HARDTUNE_SOURCE" {
@@ -73,7 +76,7 @@ impl HardtuneEffectBase {
     pub fn parse_hardtune_preset(
         &mut self,
         preset_enum: Preset,
-        attributes: &Vec<Attribute>,
+        attributes: &Attributes,
     ) -> Result<(), ParseError> {
         let mut preset = HardTuneEffect::new();
         for attr in attributes {
@@ -92,27 +95,29 @@ impl HardtuneEffectBase {
             }
 
             if attr.name == "HARDTUNE_KEYSOURCE" {
-                preset.key_source = attr.value.parse::<c_float>()? as u8;
+                preset.key_source = HardTuneKeySource::iter()
+                    .find(|source| source.get_str("uiIndex").unwrap() == attr.value)
+                    .ok_or_else(|| ParseError::InvalidKeySource(attr.value.clone()))?;
                 continue;
             }
             if attr.name == "HARDTUNE_AMOUNT" {
-                preset.amount = attr.value.parse::<c_float>()? as u8;
+                preset.amount = attr.value.parse()?;
                 continue;
             }
             if attr.name == "HARDTUNE_WINDOW" {
-                preset.window = attr.value.parse::<c_float>()? as u16;
+                preset.window = attr.value.parse()?;
                 continue;
             }
             if attr.name == "HARDTUNE_RATE" {
-                preset.rate = attr.value.parse::<c_float>()? as u8;
+                preset.rate = attr.value.parse()?;
                 continue;
             }
             if attr.name == "HARDTUNE_SCALE" {
-                preset.scale = attr.value.parse::<c_float>()? as u8;
+                preset.scale = attr.value.parse()?;
                 continue;
             }
             if attr.name == "HARDTUNE_PITCH_AMT" {
-                preset.pitch_amt = attr.value.parse::<c_float>()? as u8;
+                preset.pitch_amt = attr.value.parse()?;
                 continue;
             }
             if attr.name == "HARDTUNE_SOURCE" {
@@ -127,7 +132,7 @@ impl HardtuneEffectBase {
         Ok(())
     }
 
-    pub fn write_hardtune<W: Write>(&self, writer: &mut Writer<W>) -> Result<()> {
+    pub fn write_hardtune<W: Write>(&self, writer: &mut Writer<W>, minimal: bool) -> Result<()> {
         let mut elem = BytesStart::new("hardtuneEffect");
 
         let mut attributes: HashMap<String, String> = HashMap::default();
@@ -142,11 +147,15 @@ impl HardtuneEffectBase {
         writer.write_event(Event::Start(elem))?;
 
         // Because all of these are seemingly 'guaranteed' to exist, we can straight dump..
+        let defaults = Self::default_preset_attributes();
         for preset in Preset::iter() {
             let tag_name = format!("hardtuneEffect{}", preset.get_str("tagSuffix").unwrap());
             let mut sub_elem = BytesStart::new(tag_name.as_str());
 
-            let sub_attributes = self.get_preset_attributes(preset);
+            let mut sub_attributes = self.get_preset_attributes(preset);
+            if minimal {
+                strip_defaults(&mut sub_attributes, &defaults);
+            }
             for (key, value) in &sub_attributes {
                 sub_elem.push_attribute((key.as_str(), value.as_str()));
             }
@@ -177,7 +186,7 @@ impl HardtuneEffectBase {
         );
         attributes.insert(
             "HARDTUNE_KEYSOURCE".to_string(),
-            format!("{}", value.key_source),
+            value.key_source.get_str("uiIndex").unwrap().to_string(),
         );
         attributes.insert("HARDTUNE_AMOUNT".to_string(), format!("{}", value.amount));
         attributes.insert("HARDTUNE_WINDOW".to_string(), format!("{}", value.window));
@@ -203,6 +212,19 @@ impl HardtuneEffectBase {
         &mut self.colour_map
     }
 
+    pub fn base_source(&self) -> HardTuneSource {
+        self.source
+    }
+    pub fn set_base_source(&mut self, source: HardTuneSource) {
+        self.source = source;
+    }
+
+    /// Returns the source a preset will actually use, if the preset doesn't define its own source
+    /// the official app falls back to the base source defined on the hardtuneEffect tag.
+    pub fn resolved_source(&self, preset: Preset) -> HardTuneSource {
+        self.preset_map[preset].source.unwrap_or(self.source)
+    }
+
     pub fn get_preset(&self, preset: Preset) -> &HardTuneEffect {
         &self.preset_map[preset]
     }
@@ -212,13 +234,13 @@ impl HardtuneEffectBase {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct HardTuneEffect {
     // State here determines if the hardtune is on or off when this preset is loaded.
     state: bool,
 
     style: HardTuneStyle,
-    key_source: u8,
+    key_source: HardTuneKeySource,
     amount: u8,
     window: u16,
     rate: u8,
@@ -232,7 +254,7 @@ impl HardTuneEffect {
         Self {
             state: false,
             style: Default::default(),
-            key_source: 0,
+            key_source: Default::default(),
             amount: 0,
             window: 0,
             rate: 0,
@@ -265,9 +287,29 @@ impl HardTuneEffect {
         Ok(())
     }
 
-    pub fn key_source(&self) -> u8 {
+    /// Returns true if the style's parameters are still at the factory values for the style.
+    pub fn is_default(&self) -> bool {
+        let preset = HardtunePreset::get_preset(self.style);
+        self.amount == preset.amount
+            && self.window == preset.window
+            && self.rate == preset.rate
+            && self.scale == preset.scale
+            && self.pitch_amt == preset.pitch_amt
+    }
+
+    /// The input HardTune listens to when working out which key to correct towards, note that
+    /// this is a channel rather than a musical key, the key itself is detected at runtime.
+    pub fn key_source(&self) -> HardTuneKeySource {
         self.key_source
     }
+    pub fn set_key_source(&mut self, key_source: HardTuneKeySource) {
+        self.key_source = key_source;
+    }
+
+    #[deprecated(note = "Use key_source() instead")]
+    pub fn key_source_raw(&self) -> u8 {
+        self.key_source as u8
+    }
 
     pub fn amount(&self) -> u8 {
         self.amount
@@ -323,6 +365,8 @@ impl HardTuneEffect {
     pub fn source(&self) -> &Option<HardTuneSource> {
         &self.source
     }
+
+    #[deprecated(note = "Ignores the base source, use HardtuneEffectBase::resolved_source()")]
     pub fn get_source_value(&self) -> HardTuneSource {
         if let Some(source) = self.source {
             return source;
@@ -333,6 +377,7 @@ impl HardTuneEffect {
         self.source = Some(source);
     }
 
+    #[deprecated(note = "Ignores the base source, use HardtuneEffectBase::resolved_source()")]
     pub fn get_source(&self) -> HardTuneSource {
         if let Some(source) = self.source {
             return source;
@@ -341,7 +386,7 @@ impl HardTuneEffect {
     }
 }
 
-#[derive(Default, Debug, EnumIter, EnumProperty, Clone, Copy)]
+#[derive(Default, Debug, EnumIter, EnumProperty, Clone, Copy, PartialEq)]
 pub enum HardTuneStyle {
     #[default]
     #[strum(props(uiIndex = "0"))]
@@ -373,6 +418,26 @@ pub enum HardTuneSource {
     System,
 }
 
+// Which input is used to detect the key, the official app currently only ever writes 0.
+#[derive(Default, Debug, EnumIter, EnumProperty, PartialEq, Eq, Copy, Clone)]
+pub enum HardTuneKeySource {
+    #[default]
+    #[strum(props(uiIndex = "0"))]
+    All,
+
+    #[strum(props(uiIndex = "1"))]
+    Music,
+
+    #[strum(props(uiIndex = "2"))]
+    Game,
+
+    #[strum(props(uiIndex = "3"))]
+    LineIn,
+
+    #[strum(props(uiIndex = "4"))]
+    System,
+}
+
 struct HardtunePreset {
     amount: u8,
     window: u16,
@@ -408,3 +473,226 @@ impl HardtunePreset {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::ProfileSettings;
+
+    const PROFILE: &str = include_str!("../../test-data/profile.xml");
+
+    // The test profile, with one of the attributes on the first HardTune preset changed..
+    fn with_preset1(attribute: &str, value: &str) -> String {
+        let preset = PROFILE.find("<hardtuneEffectpreset1").unwrap();
+        let start = preset + PROFILE[preset..].find(&format!("{attribute}=\"")).unwrap();
+        let start = start + attribute.len() + 2;
+        let end = start + PROFILE[start..].find('"').unwrap();
+        format!("{}{}{}", &PROFILE[..start], value, &PROFILE[end..])
+    }
+
+    fn preset1(xml: &str) -> Result<HardTuneEffect> {
+        let settings = ProfileSettings::load(xml.as_bytes())?;
+        Ok(settings
+            .hardtune_effect()
+            .get_preset(Preset::Preset1)
+            .clone())
+    }
+
+    fn round_trip(settings: &mut ProfileSettings) -> (String, HardTuneEffect) {
+        let mut written = vec![];
+        settings.write_to(&mut written).unwrap();
+        let written = String::from_utf8(written).unwrap();
+        let reloaded = preset1(&written).unwrap();
+        (written, reloaded)
+    }
+
+    #[test]
+    fn values_round_trip() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        let preset = settings
+            .hardtune_effect_mut()
+            .get_preset_mut(Preset::Preset1);
+        preset.set_style(HardTuneStyle::Hard).unwrap();
+        preset.set_state(true);
+        preset.set_amount(42).unwrap();
+        preset.set_window(300).unwrap();
+        preset.set_rate(77).unwrap();
+        preset.set_scale(3);
+        preset.set_key_source(HardTuneKeySource::Game);
+
+        let expected = settings
+            .hardtune_effect()
+            .get_preset(Preset::Preset1)
+            .clone();
+        let (written, reloaded) = round_trip(&mut settings);
+        assert_eq!(reloaded, expected);
+        for attribute in [
+            "HARDTUNE_STYLE=\"2\"",
+            "HARDTUNE_AMOUNT=\"42\"",
+            "HARDTUNE_WINDOW=\"300\"",
+            "HARDTUNE_RATE=\"77\"",
+            "HARDTUNE_SCALE=\"3\"",
+            "HARDTUNE_KEYSOURCE=\"2\"",
+        ] {
+            assert!(written.contains(attribute), "{attribute}");
+        }
+    }
+
+    #[test]
+    fn legacy_float_values_are_read_as_ints() {
+        type Value = fn(&HardTuneEffect) -> u16;
+        let values: [(&str, u16, Value); 5] = [
+            ("HARDTUNE_AMOUNT", 42, |preset| preset.amount().into()),
+            ("HARDTUNE_WINDOW", 300, |preset| preset.window()),
+            ("HARDTUNE_RATE", 77, |preset| preset.rate().into()),
+            ("HARDTUNE_SCALE", 3, |preset| preset.scale().into()),
+            ("HARDTUNE_PITCH_AMT", 0, |preset| preset.pitch_amt().into()),
+        ];
+
+        for (attribute, expected, read) in values {
+            for value in [
+                expected.to_string(),
+                format!("{expected}.000000"),
+                format!("{expected}.5"),
+            ] {
+                let xml = with_preset1(attribute, &value);
+                let mut settings = ProfileSettings::load(xml.as_bytes()).unwrap();
+                let preset = settings.hardtune_effect().get_preset(Preset::Preset1);
+                assert_eq!(read(preset), expected, "{attribute}={value}");
+
+                // And they're always written back as ints..
+                let (written, reloaded) = round_trip(&mut settings);
+                assert_eq!(read(&reloaded), expected, "{attribute}={value}");
+                assert!(written.contains(&format!("{attribute}=\"{expected}\"")));
+            }
+            assert!(
+                preset1(&with_preset1(attribute, "-1")).is_err(),
+                "{attribute}"
+            );
+        }
+    }
+
+    #[test]
+    fn styles_and_sources_round_trip() {
+        for style in HardTuneStyle::iter() {
+            let index = style.get_str("uiIndex").unwrap();
+            for value in [index.to_string(), format!("{index}.000000")] {
+                let xml = with_preset1("HARDTUNE_STYLE", &value);
+                assert_eq!(*preset1(&xml).unwrap().style(), style, "{value}");
+            }
+        }
+
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        for source in [
+            HardTuneSource::All,
+            HardTuneSource::Music,
+            HardTuneSource::Game,
+            HardTuneSource::LineIn,
+            HardTuneSource::System,
+        ] {
+            let preset = settings
+                .hardtune_effect_mut()
+                .get_preset_mut(Preset::Preset1);
+            preset.set_source(source);
+
+            let (_, reloaded) = round_trip(&mut settings);
+            assert_eq!(*reloaded.source(), Some(source));
+        }
+    }
+
+    #[test]
+    fn key_sources_are_read_from_their_index() {
+        for source in HardTuneKeySource::iter() {
+            let index = source.get_str("uiIndex").unwrap();
+
+            // Older versions of the app wrote these as floats..
+            for value in [
+                index.to_string(),
+                format!("{index}.000000"),
+                format!("{index}.5"),
+            ] {
+                let xml = with_preset1("HARDTUNE_KEYSOURCE", &value);
+                assert_eq!(preset1(&xml).unwrap().key_source(), source, "{value}");
+            }
+        }
+    }
+
+    #[test]
+    fn key_sources_round_trip() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        for source in HardTuneKeySource::iter() {
+            let preset = settings
+                .hardtune_effect_mut()
+                .get_preset_mut(Preset::Preset1);
+            preset.set_key_source(source);
+
+            let (_, reloaded) = round_trip(&mut settings);
+            assert_eq!(reloaded.key_source(), source);
+        }
+    }
+
+    #[test]
+    fn unknown_key_sources_are_rejected() {
+        for value in ["5", "-1", "1.5.0", "Music"] {
+            let xml = with_preset1("HARDTUNE_KEYSOURCE", value);
+            assert!(preset1(&xml).is_err(), "{value}");
+        }
+    }
+
+    #[test]
+    fn preset_sources_override_the_base() {
+        // The base source is ALL, preset 2 sets its own..
+        let settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        let hardtune = settings.hardtune_effect();
+        assert_eq!(hardtune.base_source(), HardTuneSource::All);
+        assert_eq!(
+            hardtune.get_preset(Preset::Preset2).source(),
+            &Some(HardTuneSource::Music)
+        );
+        assert_eq!(
+            hardtune.resolved_source(Preset::Preset2),
+            HardTuneSource::Music
+        );
+    }
+
+    #[test]
+    fn presets_without_a_source_use_the_base() {
+        // Preset 6 doesn't set a source, so it follows the base..
+        let settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        let hardtune = settings.hardtune_effect();
+        assert_eq!(hardtune.get_preset(Preset::Preset6).source(), &None);
+        assert_eq!(
+            hardtune.resolved_source(Preset::Preset6),
+            HardTuneSource::All
+        );
+    }
+
+    #[test]
+    fn base_source_changes_are_written_and_followed() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        settings
+            .hardtune_effect_mut()
+            .set_base_source(HardTuneSource::Game);
+
+        let mut written = vec![];
+        settings.write_to(&mut written).unwrap();
+        let reloaded = ProfileSettings::load(written.as_slice()).unwrap();
+        let hardtune = reloaded.hardtune_effect();
+        assert_eq!(hardtune.base_source(), HardTuneSource::Game);
+
+        // Only presets without their own source pick up the new base, preset 1 sets ALL itself..
+        assert_eq!(
+            hardtune.resolved_source(Preset::Preset6),
+            HardTuneSource::Game
+        );
+        assert_eq!(
+            hardtune.resolved_source(Preset::Preset1),
+            HardTuneSource::All
+        );
+        assert_eq!(
+            hardtune.resolved_source(Preset::Preset2),
+            HardTuneSource::Music
+        );
+        assert_eq!(hardtune.get_preset(Preset::Preset6).source(), &None);
+    }
+}
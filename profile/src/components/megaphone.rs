@@ -10,8 +10,9 @@ use quick_xml::events::{BytesEnd, BytesStart, Event};
 use quick_xml::Writer;
 
 use crate::components::colours::ColourMap;
+use crate::components::effect_base::{strip_defaults, EffectBase};
 use crate::components::megaphone::MegaphoneStyle::Megaphone;
-use crate::profile::Attribute;
+use crate::profile::Attributes;
 use crate::Preset;
 
 #[derive(thiserror::Error, Debug)]
@@ -35,7 +36,7 @@ pub enum ParseError {
  * presets, we'll use an EnumMap to define the 'presets' as they'll be useful for the other various
  * 'types' of presets (encoders and effects).
  */
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct MegaphoneEffectBase {
     colour_map: ColourMap,
     preset_map: EnumMap<Preset, MegaphoneEffect>,
@@ -50,7 +51,7 @@ impl MegaphoneEffectBase {
         }
     }
 
-    pub fn parse_megaphone_root(&mut self, attributes: &Vec<Attribute>) -> Result<(), ParseError> {
+    pub fn parse_megaphone_root(&mut self, attributes: &Attributes) -> Result<(), ParseError> {
         for attr in attributes {
             if !self.colour_map.read_colours(attr)? {
                 println!("[megaphoneEffect] Unparsed Attribute: {}", attr.name);
@@ -63,7 +64,7 @@ impl MegaphoneEffectBase {
     pub fn parse_megaphone_preset(
         &mut self,
         preset_enum: Preset,
-        attributes: &Vec<Attribute>,
+        attributes: &Attributes,
     ) -> Result<(), ParseError> {
         let mut preset = MegaphoneEffect::new();
         for attr in attributes {
@@ -72,11 +73,8 @@ impl MegaphoneEffectBase {
                 continue;
             }
             if attr.name == "MEGAPHONE_STYLE" {
-                for style in MegaphoneStyle::iter() {
-                    if style.get_str("uiIndex").unwrap() == attr.value {
-                        preset.style = style;
-                        break;
-                    }
+                if let Some(style) = MegaphoneStyle::from_ui_index(&attr.value) {
+                    preset.style = style;
                 }
                 continue;
             }
@@ -150,7 +148,7 @@ impl MegaphoneEffectBase {
         Ok(())
     }
 
-    pub fn write_megaphone<W: Write>(&self, writer: &mut Writer<W>) -> Result<()> {
+    pub fn write_megaphone<W: Write>(&self, writer: &mut Writer<W>, minimal: bool) -> Result<()> {
         let mut elem = BytesStart::new("megaphoneEffect");
 
         let mut attributes: HashMap<String, String> = HashMap::default();
@@ -164,11 +162,15 @@ impl MegaphoneEffectBase {
         writer.write_event(Event::Start(elem))?;
 
         // Because all of these are seemingly 'guaranteed' to exist, we can straight dump..
+        let defaults = Self::default_preset_attributes();
         for preset in Preset::iter() {
             let tag_name = format!("megaphoneEffect{}", preset.get_str("tagSuffix").unwrap());
             let mut sub_elem = BytesStart::new(tag_name.as_str());
 
-            let sub_attributes = self.get_preset_attributes(preset);
+            let mut sub_attributes = self.get_preset_attributes(preset);
+            if minimal {
+                strip_defaults(&mut sub_attributes, &defaults);
+            }
             for (key, value) in &sub_attributes {
                 sub_elem.push_attribute((key.as_str(), value.as_str()));
             }
@@ -195,7 +197,7 @@ impl MegaphoneEffectBase {
         );
         attributes.insert(
             "MEGAPHONE_STYLE".to_string(),
-            value.style.get_str("uiIndex").unwrap().to_string(),
+            value.style.to_ui_index().to_string(),
         );
         attributes.insert(
             "TRANS_DIST_AMT".to_string(),
@@ -280,7 +282,7 @@ impl MegaphoneEffectBase {
  * by several values, but still need to work out the mapping.
  *
  */
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct MegaphoneEffect {
     // State here determines if the megaphone is on or off when this preset is loaded.
     state: bool,
@@ -356,6 +358,25 @@ impl MegaphoneEffect {
         Ok(())
     }
 
+    /// Returns true if the style's parameters are still at the factory values for the style.
+    pub fn is_default(&self) -> bool {
+        let preset = MegaphonePreset::get_preset(self.style);
+        self.trans_dist_amt == preset.trans_dist_amt
+            && self.trans_hp == preset.trans_hp
+            && self.trans_lp == preset.trans_lp
+            && self.trans_pregain == preset.trans_pregain
+            && self.trans_postgain == preset.trans_postgain
+            && self.trans_dist_type == preset.trans_dist_type
+            && self.trans_presence_gain == preset.trans_presence_gain
+            && self.trans_presence_fc == preset.trans_presence_fc
+            && self.trans_presence_bw == preset.trans_presence_bw
+            && self.trans_beatbox_enabled == preset.trans_beatbox_enabled
+            && self.trans_filter_control == preset.trans_filter_control
+            && self.trans_filter == preset.trans_filter
+            && self.trans_drive_pot_gain_comp_mid == preset.trans_drive_pot_gain_comp_mid
+            && self.trans_drive_pot_gain_comp_max == preset.trans_drive_pot_gain_comp_max
+    }
+
     pub fn trans_dist_amt(&self) -> u8 {
         self.trans_dist_amt
     }
@@ -463,7 +484,7 @@ impl MegaphoneEffect {
     }
 }
 
-#[derive(Default, Debug, EnumIter, EnumProperty, Copy, Clone)]
+#[derive(Default, Debug, EnumIter, EnumProperty, Copy, Clone, PartialEq, Eq)]
 pub enum MegaphoneStyle {
     #[default]
     #[strum(props(uiIndex = "0"))]
@@ -485,6 +506,16 @@ pub enum MegaphoneStyle {
     Tweed,
 }
 
+impl MegaphoneStyle {
+    pub fn from_ui_index(index: &str) -> Option<Self> {
+        MegaphoneStyle::iter().find(|style| style.to_ui_index() == index)
+    }
+
+    pub fn to_ui_index(&self) -> &'static str {
+        self.get_str("uiIndex").unwrap()
+    }
+}
+
 struct MegaphonePreset {
     trans_dist_amt: u8,
     trans_hp: u8,
@@ -604,3 +635,73 @@ impl MegaphonePreset {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::ProfileSettings;
+
+    const PROFILE: &str = include_str!("../../test-data/profile.xml");
+
+    const STYLES: [(MegaphoneStyle, &str); 6] = [
+        (MegaphoneStyle::Megaphone, "0"),
+        (MegaphoneStyle::Radio, "1"),
+        (MegaphoneStyle::OnThePhone, "2"),
+        (MegaphoneStyle::Overdrive, "3"),
+        (MegaphoneStyle::BuzzCutt, "4"),
+        (MegaphoneStyle::Tweed, "5"),
+    ];
+
+    // The test profile, with the first preset's style changed..
+    fn with_style(index: &str) -> String {
+        let preset = PROFILE.find("<megaphoneEffectpreset1").unwrap();
+        let start = preset + PROFILE[preset..].find("MEGAPHONE_STYLE=\"").unwrap();
+        let start = start + "MEGAPHONE_STYLE=\"".len();
+        let end = start + PROFILE[start..].find('"').unwrap();
+        format!("{}{}{}", &PROFILE[..start], index, &PROFILE[end..])
+    }
+
+    // The first preset's element, as written out..
+    fn written_preset1(settings: &mut ProfileSettings) -> String {
+        let mut written = vec![];
+        settings.write_to(&mut written).unwrap();
+        let written = String::from_utf8(written).unwrap();
+        let start = written.find("<megaphoneEffectpreset1").unwrap();
+        let end = start + written[start..].find("/>").unwrap();
+        written[start..end].to_string()
+    }
+
+    #[test]
+    fn styles_match_their_ui_index() {
+        for (style, index) in STYLES {
+            assert_eq!(style.to_ui_index(), index);
+            assert_eq!(MegaphoneStyle::from_ui_index(index), Some(style));
+        }
+        assert_eq!(MegaphoneStyle::iter().count(), STYLES.len());
+        assert_eq!(MegaphoneStyle::from_ui_index("6"), None);
+    }
+
+    #[test]
+    fn styles_are_read_from_the_ui_index() {
+        for (style, index) in STYLES {
+            let settings = ProfileSettings::load(with_style(index).as_bytes()).unwrap();
+            let preset = settings.megaphone_effect().get_preset(Preset::Preset1);
+            assert_eq!(preset.style(), &style, "{index}");
+        }
+    }
+
+    #[test]
+    fn styles_are_written_as_the_ui_index() {
+        for (style, index) in STYLES {
+            let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+            let preset = settings
+                .megaphone_effect_mut()
+                .get_preset_mut(Preset::Preset1);
+            preset.set_style(style).unwrap();
+
+            let attribute = format!("MEGAPHONE_STYLE=\"{index}\"");
+            let written = written_preset1(&mut settings);
+            assert!(written.contains(&attribute), "{style:?}: {written}");
+        }
+    }
+}
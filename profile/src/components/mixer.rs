@@ -10,7 +10,8 @@ use quick_xml::Writer;
 
 use crate::components::colours::ColourMap;
 use crate::components::mixer::FullChannelList::LineOut;
-use crate::profile::Attribute;
+use crate::components::registry::MIXER_TREE;
+use crate::profile::Attributes;
 
 #[derive(thiserror::Error, Debug)]
 #[allow(clippy::enum_variant_names)]
@@ -55,7 +56,7 @@ impl Mixers {
         }
     }
 
-    pub fn parse_mixers(&mut self, attributes: &Vec<Attribute>) -> Result<(), ParseError> {
+    pub fn parse_mixers(&mut self, attributes: &Attributes) -> Result<(), ParseError> {
         for attr in attributes {
             if attr.name.ends_with("Level") {
                 let mut found = false;
@@ -118,40 +119,59 @@ impl Mixers {
         Ok(())
     }
 
-    pub fn write_mixers<W: Write>(&self, writer: &mut Writer<W>) -> Result<()> {
-        let mut elem = BytesStart::new("mixerTree");
+    pub fn write_mixers<W: Write>(&self, writer: &mut Writer<W>, ordered: bool) -> Result<()> {
+        let mut elem = BytesStart::new(MIXER_TREE);
 
-        // Create the values..
-        let mut attributes: HashMap<String, String> = HashMap::default();
-        for volume in FullChannelList::iter() {
+        // Set the attributes into the XML object..
+        if ordered {
+            for (key, value) in &self.get_ordered_attributes() {
+                elem.push_attribute((key.as_str(), value.as_str()));
+            }
+        } else {
+            for (key, value) in &self.get_attributes() {
+                elem.push_attribute((key.as_str(), value.as_str()));
+            }
+        }
+
+        writer.write_event(Event::Empty(elem))?;
+        Ok(())
+    }
+
+    pub fn get_attributes(&self) -> HashMap<String, String> {
+        self.get_ordered_attributes().into_iter().collect()
+    }
+
+    /// The attributes in the order the official app writes them, channel levels first, then the
+    /// routing table (by input, then output), followed by the colours.
+    pub fn get_ordered_attributes(&self) -> Vec<(String, String)> {
+        let mut attributes = Vec::new();
+        for volume in OFFICIAL_VOLUME_ORDER {
             let key = format!("{}Level", volume.get_str("Name").unwrap());
             let value = format!("{}", self.volume_table[volume]);
 
-            attributes.insert(key, value);
+            attributes.push((key, value));
         }
 
-        for input in InputChannels::iter() {
+        for input in OFFICIAL_INPUT_ORDER {
             // Get the map for this channel..
             let input_text = input.get_str("Name").unwrap();
             let table = self.mixer_table[input];
 
-            for output in OutputChannels::iter() {
+            for output in OFFICIAL_OUTPUT_ORDER {
                 let key = format!("{}To{}", input_text, output.get_str("Name").unwrap());
                 let value = format!("{}", table[output]);
 
-                attributes.insert(key, value);
+                attributes.push((key, value));
             }
         }
 
-        self.colour_map.write_colours(&mut attributes);
+        let mut colours = HashMap::new();
+        self.colour_map.write_colours(&mut colours);
 
-        // Set the attributes into the XML object..
-        for (key, value) in &attributes {
-            elem.push_attribute((key.as_str(), value.as_str()));
-        }
-
-        writer.write_event(Event::Empty(elem))?;
-        Ok(())
+        let mut colours: Vec<(String, String)> = colours.into_iter().collect();
+        colours.sort();
+        attributes.extend(colours);
+        attributes
     }
 
     pub fn mixer_table(&self) -> &EnumMap<InputChannels, EnumMap<OutputChannels, u16>> {
@@ -171,8 +191,52 @@ impl Mixers {
         self.volume_table[channel] = volume;
         Ok(())
     }
+
+    // The Mic Monitor (sidetone) is the hardware path from the mic directly to the headphones,
+    // stored as mic2headphoneSubLevel.
+    pub fn mic_monitor_volume(&self) -> u8 {
+        self.volume_table[FullChannelList::MicMonitor]
+    }
+
+    pub fn set_mic_monitor_volume(&mut self, volume: u8) {
+        self.volume_table[FullChannelList::MicMonitor] = volume;
+    }
 }
 
+// The official app doesn't write the volumes or routing in the order they're declared below..
+const OFFICIAL_VOLUME_ORDER: [FullChannelList; 11] = [
+    FullChannelList::Headphones,
+    FullChannelList::MicMonitor,
+    FullChannelList::Mic,
+    FullChannelList::Chat,
+    FullChannelList::Game,
+    FullChannelList::Console,
+    FullChannelList::Music,
+    FullChannelList::LineIn,
+    FullChannelList::System,
+    FullChannelList::Sample,
+    FullChannelList::LineOut,
+];
+
+const OFFICIAL_INPUT_ORDER: [InputChannels; 8] = [
+    InputChannels::Mic,
+    InputChannels::LineIn,
+    InputChannels::System,
+    InputChannels::Game,
+    InputChannels::Chat,
+    InputChannels::Music,
+    InputChannels::Sample,
+    InputChannels::Console,
+];
+
+const OFFICIAL_OUTPUT_ORDER: [OutputChannels; 5] = [
+    OutputChannels::Broadcast,
+    OutputChannels::ChatMic,
+    OutputChannels::Headphones,
+    OutputChannels::Sampler,
+    OutputChannels::LineOut,
+];
+
 #[derive(Debug, EnumIter, Enum, EnumProperty, Clone, Copy)]
 pub enum InputChannels {
     #[strum(props(Name = "mic"))]
@@ -200,7 +264,7 @@ pub enum InputChannels {
     Sample,
 }
 
-#[derive(Debug, EnumIter, Enum, EnumProperty, Clone, Copy, PartialEq)]
+#[derive(Debug, EnumIter, Enum, EnumProperty, Clone, Copy, PartialEq, Eq)]
 pub enum OutputChannels {
     #[strum(props(Name = "HP"))]
     Headphones,
@@ -221,41 +285,140 @@ pub enum OutputChannels {
 /**
  * There are a couple of volumes that aren't part of the general mixer, so this needs mapping..
  */
-#[derive(Copy, Clone, Debug, Enum, EnumIter, EnumProperty)]
+#[derive(Copy, Clone, Debug, Enum, EnumIter, EnumProperty, PartialEq, Eq)]
 pub enum FullChannelList {
     // Base Mixer Channels
-    #[strum(props(Name = "mic", faderIndex = "0"))]
+    #[strum(props(Name = "mic", faderIndex = "0", displayName = "Mic"))]
     Mic,
 
-    #[strum(props(Name = "chat", faderIndex = "1"))]
+    #[strum(props(Name = "chat", faderIndex = "1", displayName = "Chat"))]
     Chat,
 
-    #[strum(props(Name = "music", faderIndex = "2"))]
+    #[strum(props(Name = "music", faderIndex = "2", displayName = "Music"))]
     Music,
 
-    #[strum(props(Name = "game", faderIndex = "3"))]
+    #[strum(props(Name = "game", faderIndex = "3", displayName = "Game"))]
     Game,
 
-    #[strum(props(Name = "console", faderIndex = "4"))]
+    #[strum(props(Name = "console", faderIndex = "4", displayName = "Console"))]
     Console,
 
-    #[strum(props(Name = "lineIn", faderIndex = "5"))]
+    #[strum(props(Name = "lineIn", faderIndex = "5", displayName = "Line In"))]
     LineIn,
 
-    #[strum(props(Name = "system", faderIndex = "6"))]
+    #[strum(props(Name = "system", faderIndex = "6", displayName = "System"))]
     System,
 
-    #[strum(props(Name = "sample", faderIndex = "7"))]
+    #[strum(props(Name = "sample", faderIndex = "7", displayName = "Sample"))]
     Sample,
 
     // Extra Volume Mixers
-    #[strum(props(Name = "headphone", faderIndex = "8"))]
+    #[strum(props(Name = "headphone", faderIndex = "8", displayName = "Headphones"))]
     Headphones,
 
     // Not Present in the Fader 'Source' List..
-    #[strum(props(Name = "mic2headphoneSub", faderIndex = "-1"))]
+    #[strum(props(
+        Name = "mic2headphoneSub",
+        faderIndex = "-1",
+        displayName = "Mic Monitor"
+    ))]
     MicMonitor,
 
-    #[strum(props(Name = "lineOut", faderIndex = "9"))]
+    #[strum(props(Name = "lineOut", faderIndex = "9", displayName = "Line Out"))]
     LineOut,
 }
+
+impl FullChannelList {
+    /// The name shown for this channel on a scribble when it follows the fader's channel.
+    pub fn display_name(&self) -> &'static str {
+        self.get_str("displayName").unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::ProfileSettings;
+
+    const PROFILE: &str = include_str!("../../test-data/profile.xml");
+
+    fn reload(settings: &mut ProfileSettings) -> (String, ProfileSettings) {
+        let mut written = vec![];
+        settings.write_to(&mut written).unwrap();
+        let written = String::from_utf8(written).unwrap();
+        let reloaded = ProfileSettings::load(written.as_bytes()).unwrap();
+        (written, reloaded)
+    }
+
+    #[test]
+    fn mic_monitor_volumes_round_trip() {
+        // The test profile has the sidetone at 255, so every one of these is a change..
+        for volume in [0, 1, 64, 128, 200, 254] {
+            let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+            let mic = settings.mixer().channel_volume(FullChannelList::Mic);
+            settings.mixer_mut().set_mic_monitor_volume(volume);
+
+            let (written, reloaded) = reload(&mut settings);
+            assert!(written.contains(&format!("mic2headphoneSubLevel=\"{volume}\"")));
+            assert_eq!(reloaded.mixer().mic_monitor_volume(), volume);
+            assert_eq!(reloaded.mixer().channel_volume(FullChannelList::Mic), mic);
+        }
+    }
+
+    #[test]
+    fn monitored_outputs_are_saved_as_the_headphones() {
+        for output in OutputChannels::iter() {
+            let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+            settings
+                .submixes_mut()
+                .monitor_tree_mut()
+                .set_monitored_output(output);
+            assert_eq!(settings.monitored_output(), output, "{output:?}");
+
+            // The official app expects to load with the headphones monitored, so that's what's
+            // written, but the loaded settings keep monitoring whatever they were..
+            let (written, reloaded) = reload(&mut settings);
+            assert!(written.contains("monitoredOutput=\"0\""), "{output:?}");
+            assert_eq!(reloaded.monitored_output(), OutputChannels::Headphones);
+            assert_eq!(settings.monitored_output(), output, "{output:?}");
+        }
+    }
+
+    #[test]
+    fn monitored_outputs_are_loaded() {
+        for output in OutputChannels::iter() {
+            let tree = format!("<monitorTree monitoredOutput=\"{}\"/>", output as usize);
+            let xml = PROFILE.replace("<mixerTree ", &format!("{tree}\n  <mixerTree "));
+            let settings = ProfileSettings::load(xml.as_bytes()).unwrap();
+            assert_eq!(settings.monitored_output(), output, "{output:?}");
+        }
+    }
+
+    #[test]
+    fn official_orders_cover_every_channel() {
+        let volumes: Vec<_> = OFFICIAL_VOLUME_ORDER
+            .iter()
+            .map(|c| c.into_usize())
+            .collect();
+        let mut sorted = volumes.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(sorted.len(), FullChannelList::iter().count());
+
+        let mut inputs: Vec<_> = OFFICIAL_INPUT_ORDER
+            .iter()
+            .map(|c| c.into_usize())
+            .collect();
+        inputs.sort();
+        inputs.dedup();
+        assert_eq!(inputs.len(), InputChannels::iter().count());
+
+        let mut outputs: Vec<_> = OFFICIAL_OUTPUT_ORDER
+            .iter()
+            .map(|c| c.into_usize())
+            .collect();
+        outputs.sort();
+        outputs.dedup();
+        assert_eq!(outputs.len(), OutputChannels::iter().count());
+    }
+}
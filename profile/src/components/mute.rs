@@ -4,12 +4,15 @@ use std::io::Write;
 use enum_map_derive::Enum;
 use strum::{EnumIter, EnumProperty, IntoEnumIterator};
 
-use anyhow::Result;
+use std::ops::RangeInclusive;
+
+use anyhow::{bail, Result};
 use quick_xml::events::{BytesStart, Event};
 use quick_xml::Writer;
 
 use crate::components::colours::ColourMap;
-use crate::profile::Attribute;
+use crate::components::mixer::{FullChannelList, OutputChannels};
+use crate::profile::Attributes;
 
 #[derive(thiserror::Error, Debug)]
 #[allow(clippy::enum_variant_names)]
@@ -25,8 +28,14 @@ pub enum ParseError {
 
     #[error("[MUTE] Invalid colours: {0}")]
     InvalidColours(#[from] crate::components::colours::ParseError),
+
+    #[error("[MUTE] Invalid hold duration: {0}")]
+    InvalidHoldDuration(String),
 }
 
+/// The shortest and longest time (in ms) a mute button can be set to need holding for..
+pub const HOLD_DURATION_RANGE: RangeInclusive<u16> = 100..=5000;
+
 #[derive(Debug)]
 pub struct MuteButton {
     colour_map: ColourMap,
@@ -36,6 +45,10 @@ pub struct MuteButton {
     // Labelled as 'fromMuteAllFlag' in the XML, honestly, not sure what this does either,
     // it's either 1, 0 or simply not there.
     from_mute_all: Option<bool>,
+
+    // How long the button needs holding to mute to all, not part of the official profile so only
+    // written when set, otherwise the utility's per-device setting is used..
+    hold_duration: Option<u16>,
 }
 
 impl MuteButton {
@@ -46,20 +59,15 @@ impl MuteButton {
             mute_function: MuteFunction::All,
             previous_volume: 0,
             from_mute_all: None,
+            hold_duration: None,
         }
     }
 
-    pub fn parse_button(&mut self, attributes: &Vec<Attribute>) -> Result<(), ParseError> {
+    pub fn parse_button(&mut self, attributes: &Attributes) -> Result<(), ParseError> {
         for attr in attributes {
             if attr.name.ends_with("Function") {
                 let mut found = false;
 
-                // First catch this seemingly legacy value..
-                if attr.value == "All" {
-                    self.mute_function = MuteFunction::All;
-                    continue;
-                }
-
                 for function in MuteFunction::iter() {
                     if function.get_str("Value").unwrap() == attr.value {
                         self.mute_function = function;
@@ -80,6 +88,14 @@ impl MuteButton {
                 continue;
             }
 
+            if attr.name.ends_with("holdDuration") {
+                let hold_duration = Some(attr.value.parse()?);
+                validate_hold_duration(hold_duration)
+                    .map_err(|e| ParseError::InvalidHoldDuration(e.to_string()))?;
+                self.hold_duration = hold_duration;
+                continue;
+            }
+
             if attr.name == "fromMuteAllFlag" {
                 if attr.value == "0" {
                     self.from_mute_all = Some(false);
@@ -122,6 +138,13 @@ impl MuteButton {
             );
         }
 
+        if let Some(hold_duration) = self.hold_duration {
+            attributes.insert(
+                format!("{element_name}holdDuration"),
+                format!("{hold_duration}"),
+            );
+        }
+
         self.colour_map
             .write_colours_with_prefix(element_name.clone(), &mut attributes);
 
@@ -154,6 +177,30 @@ impl MuteButton {
     pub fn previous_volume(&self) -> u8 {
         self.previous_volume
     }
+
+    pub fn hold_duration(&self) -> Option<u16> {
+        self.hold_duration
+    }
+
+    /// Sets how long (in ms) the button needs holding, or None to use the utility's default.
+    pub fn set_hold_duration(&mut self, hold_duration: Option<u16>) -> Result<()> {
+        validate_hold_duration(hold_duration)?;
+        self.hold_duration = hold_duration;
+        Ok(())
+    }
+}
+
+pub(crate) fn validate_hold_duration(hold_duration: Option<u16>) -> Result<()> {
+    if let Some(duration) = hold_duration {
+        if !HOLD_DURATION_RANGE.contains(&duration) {
+            bail!(
+                "Hold duration should be between {} and {}ms",
+                HOLD_DURATION_RANGE.start(),
+                HOLD_DURATION_RANGE.end()
+            );
+        }
+    }
+    Ok(())
 }
 
 // MuteChat
@@ -174,3 +221,52 @@ pub enum MuteFunction {
     #[strum(props(Value = "Mute to Line Out", uiIndex = "4"))]
     ToLineOut,
 }
+
+impl MuteFunction {
+    /// The output the channel is removed from, or None if it's muted everywhere..
+    pub fn output(&self) -> Option<OutputChannels> {
+        match self {
+            MuteFunction::All => None,
+            MuteFunction::ToStream => Some(OutputChannels::Broadcast),
+            MuteFunction::ToVoiceChat => Some(OutputChannels::ChatMic),
+            MuteFunction::ToPhones => Some(OutputChannels::Headphones),
+            MuteFunction::ToLineOut => Some(OutputChannels::LineOut),
+        }
+    }
+}
+
+/**
+ * What pressing a fader's mute button actually does, the channel assigned to the fader combined
+ * with the button's configured function..
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MuteAction {
+    /// The channel is muted on every output
+    MuteAll(FullChannelList),
+
+    /// The channel is only removed from the given output
+    MuteFrom(FullChannelList, OutputChannels),
+}
+
+impl MuteAction {
+    pub fn new(channel: FullChannelList, function: MuteFunction) -> Self {
+        match function.output() {
+            None => MuteAction::MuteAll(channel),
+            Some(output) => MuteAction::MuteFrom(channel, output),
+        }
+    }
+
+    pub fn channel(&self) -> FullChannelList {
+        match self {
+            MuteAction::MuteAll(channel) => *channel,
+            MuteAction::MuteFrom(channel, _) => *channel,
+        }
+    }
+
+    pub fn output(&self) -> Option<OutputChannels> {
+        match self {
+            MuteAction::MuteAll(_) => None,
+            MuteAction::MuteFrom(_, output) => Some(*output),
+        }
+    }
+}
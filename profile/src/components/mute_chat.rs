@@ -2,13 +2,14 @@ use std::collections::HashMap;
 use std::io::Write;
 
 use enum_map::Enum;
-use strum::EnumProperty;
+use strum::{EnumProperty, IntoEnumIterator};
 
 use anyhow::{anyhow, Result};
 
 use crate::components::colours::{ColourMap, ColourState};
-use crate::components::mute::MuteFunction;
+use crate::components::mute::{validate_hold_duration, MuteFunction};
 use crate::components::mute_chat::CoughToggle::Hold;
+use crate::Faders;
 
 #[derive(thiserror::Error, Debug)]
 #[allow(clippy::enum_variant_names)]
@@ -24,8 +25,11 @@ pub enum ParseError {
 
     #[error("[COUGH] Invalid colours: {0}")]
     InvalidColours(#[from] crate::components::colours::ParseError),
+
+    #[error("[COUGH] Invalid hold duration: {0}")]
+    InvalidHoldDuration(String),
 }
-use crate::profile::Attribute;
+use crate::profile::Attributes;
 use quick_xml::events::{BytesStart, Event};
 use quick_xml::Writer;
 use std::str::FromStr;
@@ -46,6 +50,9 @@ pub struct MuteChat {
     cough_behaviour: CoughToggle,
     cough_mute_source: MuteFunction,
     cough_button_on: bool,
+
+    // Not part of the official profile, see MuteButton::hold_duration..
+    hold_duration: Option<u16>,
 }
 
 impl MuteChat {
@@ -59,10 +66,11 @@ impl MuteChat {
             cough_behaviour: Hold,
             cough_mute_source: MuteFunction::All,
             cough_button_on: false,
+            hold_duration: None,
         }
     }
 
-    pub fn parse_mute_chat(&mut self, attributes: &Vec<Attribute>) -> Result<(), ParseError> {
+    pub fn parse_mute_chat(&mut self, attributes: &Attributes) -> Result<(), ParseError> {
         for attr in attributes {
             if attr.name == "micIsAnActiveFader" {
                 self.mic_fader_id = attr.value.parse()?;
@@ -70,16 +78,17 @@ impl MuteChat {
             }
 
             if attr.name == "coughButtonToggleSetting" {
-                self.cough_behaviour = if attr.value == "0" {
-                    Hold
-                } else {
-                    CoughToggle::Toggle
-                };
+                self.cough_behaviour = CoughToggle::from_ui_index(&attr.value);
                 continue;
             }
 
             if attr.name == "coughButtonMuteSourceSelection" {
-                self.cough_mute_source = MuteFunction::from_usize(attr.value.parse()?);
+                // Don't trust the index, a bad value would otherwise panic in from_usize..
+                let index: usize = attr.value.parse()?;
+                if index >= MuteFunction::iter().count() {
+                    return Err(ParseError::ExpectedEnum(strum::ParseError::VariantNotFound));
+                }
+                self.cough_mute_source = MuteFunction::from_usize(index);
                 continue;
             }
 
@@ -88,6 +97,14 @@ impl MuteChat {
                 continue;
             }
 
+            if attr.name == "holdDuration" {
+                let hold_duration = Some(attr.value.parse()?);
+                validate_hold_duration(hold_duration)
+                    .map_err(|e| ParseError::InvalidHoldDuration(e.to_string()))?;
+                self.hold_duration = hold_duration;
+                continue;
+            }
+
             if attr.name == "blink" {
                 self.blink = ColourState::from_str(&attr.value)?;
                 continue;
@@ -112,11 +129,7 @@ impl MuteChat {
         );
         attributes.insert(
             "coughButtonToggleSetting".to_string(),
-            if self.cough_behaviour == Hold {
-                "0".to_string()
-            } else {
-                "1".to_string()
-            },
+            self.cough_behaviour.to_ui_index().to_string(),
         );
         attributes.insert(
             "coughButtonMuteSourceSelection".to_string(),
@@ -134,6 +147,9 @@ impl MuteChat {
             },
         );
         attributes.insert("blink".to_string(), self.blink.to_string());
+        if let Some(hold_duration) = self.hold_duration {
+            attributes.insert("holdDuration".to_string(), format!("{hold_duration}"));
+        }
 
         self.colour_map.write_colours(&mut attributes);
 
@@ -154,10 +170,18 @@ impl MuteChat {
     pub fn is_cough_toggle(&self) -> bool {
         self.cough_behaviour == CoughToggle::Toggle
     }
+    pub fn is_cough_hold(&self) -> bool {
+        self.cough_behaviour == Hold
+    }
 
     pub fn mic_fader_id(&self) -> u8 {
         self.mic_fader_id
     }
+
+    /// The fader the microphone is assigned to, if any..
+    pub fn mic_fader(&self) -> Option<Faders> {
+        Faders::iter().nth(self.mic_fader_id.into())
+    }
     pub fn blink(&self) -> &ColourState {
         &self.blink
     }
@@ -165,6 +189,17 @@ impl MuteChat {
         &self.cough_behaviour
     }
 
+    pub fn hold_duration(&self) -> Option<u16> {
+        self.hold_duration
+    }
+
+    /// Sets how long (in ms) the button needs holding, or None to use the utility's default.
+    pub fn set_hold_duration(&mut self, hold_duration: Option<u16>) -> Result<()> {
+        validate_hold_duration(hold_duration)?;
+        self.hold_duration = hold_duration;
+        Ok(())
+    }
+
     pub fn cough_mute_source(&self) -> &MuteFunction {
         &self.cough_mute_source
     }
@@ -209,13 +244,104 @@ impl MuteChat {
         self.mic_fader_id = 4;
     }
 
+    pub fn set_mic_fader(&mut self, fader: Option<Faders>) {
+        match fader {
+            Some(fader) => self.mic_fader_id = fader as u8,
+            None => self.clear_mic_fader_id(),
+        }
+    }
+
     pub fn set_cough_behaviour(&mut self, cough_behaviour: CoughToggle) {
         self.cough_behaviour = cough_behaviour;
     }
 }
 
-#[derive(PartialEq, Eq, Debug)]
+/**
+ * Behaviour of the Cough (Mute Chat) button. When set to Hold, the mute source is only muted
+ * while the button is held down, when set to Toggle, a press will flip the mute state (and the
+ * current state is stored in 'coughButtonIsOn').
+ */
+#[derive(Debug, Copy, Clone, EnumProperty, PartialEq, Eq)]
 pub enum CoughToggle {
+    #[strum(props(uiIndex = "0"))]
     Hold,
+
+    #[strum(props(uiIndex = "1"))]
     Toggle,
 }
+
+impl CoughToggle {
+    // The official app only ever writes 0 or 1, treat anything non-zero as a toggle..
+    pub fn from_ui_index(value: &str) -> Self {
+        if value == "0" {
+            Hold
+        } else {
+            CoughToggle::Toggle
+        }
+    }
+
+    pub fn to_ui_index(&self) -> &'static str {
+        self.get_str("uiIndex").unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::ProfileSettings;
+
+    const PROFILE: &str = include_str!("../../test-data/profile.xml");
+
+    fn reload(settings: &mut ProfileSettings) -> ProfileSettings {
+        let mut written = vec![];
+        settings.write_to(&mut written).unwrap();
+        ProfileSettings::load(written.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn cough_behaviours_round_trip() {
+        for behaviour in [Hold, CoughToggle::Toggle] {
+            for source in MuteFunction::iter() {
+                for button_on in [false, true] {
+                    let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+                    let mute_chat = settings.mute_chat_mut();
+                    mute_chat.set_cough_behaviour(behaviour);
+                    mute_chat.set_cough_mute_source(source);
+                    mute_chat.set_cough_button_on(button_on);
+                    mute_chat.set_hold_duration(Some(1000)).unwrap();
+                    mute_chat.set_mic_fader(Some(Faders::B));
+
+                    let reloaded = reload(&mut settings);
+                    let mute_chat = reloaded.mute_chat();
+                    let case = format!("{behaviour:?} {source:?} {button_on}");
+                    assert_eq!(mute_chat.cough_behaviour(), &behaviour, "{case}");
+                    assert_eq!(mute_chat.is_cough_hold(), behaviour == Hold, "{case}");
+                    assert_eq!(mute_chat.cough_mute_source(), &source, "{case}");
+                    assert_eq!(mute_chat.cough_button_on(), button_on, "{case}");
+                    assert_eq!(mute_chat.hold_duration(), Some(1000), "{case}");
+                    assert_eq!(mute_chat.mic_fader(), Some(Faders::B), "{case}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unassigned_mic_faders_round_trip() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        settings.mute_chat_mut().set_mic_fader(Some(Faders::D));
+        settings.mute_chat_mut().set_mic_fader(None);
+
+        let reloaded = reload(&mut settings);
+        assert_eq!(reloaded.mute_chat().mic_fader(), None);
+        assert_eq!(reloaded.mute_chat().mic_fader_id(), 4);
+    }
+
+    #[test]
+    fn unknown_cough_mute_sources_are_rejected() {
+        let xml = PROFILE.replace(
+            "coughButtonMuteSourceSelection=\"0\"",
+            "coughButtonMuteSourceSelection=\"9\"",
+        );
+        assert!(ProfileSettings::load(xml.as_bytes()).is_err());
+    }
+}
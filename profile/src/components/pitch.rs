@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::io::Write;
 use std::os::raw::c_float;
+use std::str::FromStr;
 
 use enum_map::{Enum, EnumMap};
 use strum::{EnumIter, EnumProperty, IntoEnumIterator};
@@ -10,7 +11,8 @@ use quick_xml::events::{BytesEnd, BytesStart, Event};
 use quick_xml::Writer;
 
 use crate::components::colours::ColourMap;
-use crate::profile::Attribute;
+use crate::components::effect_base::{strip_defaults, EncoderBase, EncoderPressAction};
+use crate::profile::Attributes;
 use crate::Preset;
 
 #[derive(thiserror::Error, Debug)]
@@ -34,11 +36,12 @@ pub enum ParseError {
  * presets, we'll use an EnumMap to define the 'presets' as they'll be useful for the other various
  * 'types' of presets (encoders and effects).
  */
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct PitchEncoderBase {
     colour_map: ColourMap,
     preset_map: EnumMap<Preset, PitchEncoder>,
     active_set: u8, // Not sure what this does?
+    press_action: EncoderPressAction,
 }
 
 impl PitchEncoderBase {
@@ -48,16 +51,22 @@ impl PitchEncoderBase {
             colour_map: ColourMap::new(colour_map),
             preset_map: EnumMap::default(),
             active_set: 0,
+            press_action: EncoderPressAction::None,
         }
     }
 
-    pub fn parse_pitch_root(&mut self, attributes: &Vec<Attribute>) -> Result<(), ParseError> {
+    pub fn parse_pitch_root(&mut self, attributes: &Attributes) -> Result<(), ParseError> {
         for attr in attributes {
             if attr.name == "active_set" {
                 self.active_set = attr.value.parse()?;
                 continue;
             }
 
+            if attr.name == "pressAction" {
+                self.press_action = EncoderPressAction::from_str(&attr.value)?;
+                continue;
+            }
+
             if !self.colour_map.read_colours(attr)? {
                 println!("[PitchEncoder] Unparsed Attribute: {}", attr.name);
             }
@@ -69,7 +78,7 @@ impl PitchEncoderBase {
     pub fn parse_pitch_preset(
         &mut self,
         preset_enum: Preset,
-        attributes: &Vec<Attribute>,
+        attributes: &Attributes,
     ) -> Result<(), ParseError> {
         let mut preset = PitchEncoder::new();
         for attr in attributes {
@@ -80,13 +89,6 @@ impl PitchEncoderBase {
                         break;
                     }
                 }
-
-                // Set the threshold based on the style..
-                match preset.style {
-                    PitchStyle::Narrow => preset.threshold = -36,
-                    PitchStyle::Wide => preset.threshold = -26,
-                }
-
                 continue;
             }
 
@@ -113,15 +115,24 @@ impl PitchEncoderBase {
             println!("[PitchEncoder] Unparsed Child Attribute: {}", &attr.name);
         }
 
+        // Set the threshold based on the style, a minimal profile may not include the style..
+        match preset.style {
+            PitchStyle::Narrow => preset.threshold = -36,
+            PitchStyle::Wide => preset.threshold = -26,
+        }
+
         self.preset_map[preset_enum] = preset;
         Ok(())
     }
 
-    pub fn write_pitch<W: Write>(&self, writer: &mut Writer<W>) -> Result<()> {
+    pub fn write_pitch<W: Write>(&self, writer: &mut Writer<W>, minimal: bool) -> Result<()> {
         let mut elem = BytesStart::new("pitchEncoder");
 
         let mut attributes: HashMap<String, String> = HashMap::default();
         attributes.insert("active_set".to_string(), format!("{}", self.active_set));
+        if self.press_action != EncoderPressAction::None {
+            attributes.insert("pressAction".to_string(), self.press_action.to_string());
+        }
         self.colour_map.write_colours(&mut attributes);
 
         // Write out the attributes etc for this element, but don't close it yet..
@@ -132,11 +143,15 @@ impl PitchEncoderBase {
         writer.write_event(Event::Start(elem))?;
 
         // Because all of these are seemingly 'guaranteed' to exist, we can straight dump..
+        let defaults = Self::default_preset_attributes();
         for preset in Preset::iter() {
             let tag_name = format!("pitchEncoder{}", preset.get_str("tagSuffix").unwrap());
             let mut sub_elem = BytesStart::new(tag_name.as_str());
 
-            let sub_attributes = self.get_preset_attributes(preset);
+            let mut sub_attributes = self.get_preset_attributes(preset);
+            if minimal {
+                strip_defaults(&mut sub_attributes, &defaults);
+            }
             for (key, value) in &sub_attributes {
                 sub_elem.push_attribute((key.as_str(), value.as_str()));
             }
@@ -185,6 +200,14 @@ impl PitchEncoderBase {
         &mut self.colour_map
     }
 
+    pub fn press_action(&self) -> EncoderPressAction {
+        self.press_action
+    }
+
+    pub fn set_press_action(&mut self, action: EncoderPressAction) {
+        self.press_action = action;
+    }
+
     pub fn get_preset(&self, preset: Preset) -> &PitchEncoder {
         &self.preset_map[preset]
     }
@@ -194,7 +217,7 @@ impl PitchEncoderBase {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct PitchEncoder {
     knob_position: i8,
     style: PitchStyle,
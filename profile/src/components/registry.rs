@@ -0,0 +1,247 @@
+use std::str::FromStr;
+
+use strum::{EnumProperty, IntoEnumIterator};
+
+use crate::components::effect_base::{EffectType, EncoderType};
+use crate::components::sample::SampleBank;
+use crate::components::simple::SimpleElements;
+use crate::{FaderElement, Faders, Preset, SampleButtons};
+
+// Elements which only ever appear once, under a fixed name..
+pub const VALUE_TREE_ROOT: &str = "ValueTreeRoot";
+pub const APP_TREE: &str = "AppTree";
+pub const BROWSER_PREVIEW_TREE: &str = "browserPreviewTree";
+pub const ANIMATION_TREE: &str = "animationTree";
+pub const MIX_ROUTING_TREE: &str = "mixRoutingTree";
+pub const SUBMIXER_TREE: &str = "submixerTree";
+pub const MONITOR_TREE: &str = "monitorTree";
+pub const LINKING_TREE: &str = "linkingTree";
+pub const MIXER_TREE: &str = "mixerTree";
+pub const SELECTED_CONTEXT: &str = "selectedContext";
+pub const MUTE_CHAT: &str = "muteChat";
+pub const SHUTDOWN_BEHAVIOUR: &str = "shutdownBehaviour";
+pub const SAMPLER_ROOT: &str = "samplerRoot";
+
+/**
+ * Identifies which component owns an element in the profile. Most components have a single
+ * fixed name, the rest are a prefix followed by an index (mute1, FaderMeter0, effects1,
+ * megaphoneEffectpreset1, sampleStackA), and carry that index here.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ComponentKind {
+    Root,
+    AppTree,
+    Browser,
+    Animation,
+    MixRouting,
+    Submixer,
+    Monitor,
+    Linking,
+    Mixer,
+    Context,
+    MuteChat,
+    ShutdownBehaviour,
+    SamplerRoot,
+    MuteButton(Faders),
+    Fader(Faders),
+    Scribble(Faders),
+    Effects(Preset),
+    EffectRoot(EffectType),
+    EffectPreset(EffectType, Preset),
+    EncoderRoot(EncoderType),
+    EncoderPreset(EncoderType, Preset),
+    Sampler(SampleButtons),
+
+    // Sample stacks belong to whichever sampler element they're nested inside..
+    SampleStack(SampleBank),
+    Simple(SimpleElements),
+}
+
+impl ComponentKind {
+    /// Returns the component which owns the tag, or None if it isn't one we know about.
+    pub fn from_tag(tag: &str) -> Option<ComponentKind> {
+        let kind = match tag {
+            VALUE_TREE_ROOT => ComponentKind::Root,
+            APP_TREE => ComponentKind::AppTree,
+            BROWSER_PREVIEW_TREE => ComponentKind::Browser,
+            ANIMATION_TREE => ComponentKind::Animation,
+            MIX_ROUTING_TREE => ComponentKind::MixRouting,
+            SUBMIXER_TREE => ComponentKind::Submixer,
+            MONITOR_TREE => ComponentKind::Monitor,
+            LINKING_TREE => ComponentKind::Linking,
+            MIXER_TREE => ComponentKind::Mixer,
+            SELECTED_CONTEXT => ComponentKind::Context,
+            MUTE_CHAT => ComponentKind::MuteChat,
+            SHUTDOWN_BEHAVIOUR => ComponentKind::ShutdownBehaviour,
+            SAMPLER_ROOT => ComponentKind::SamplerRoot,
+            _ => return ComponentKind::from_indexed_tag(tag),
+        };
+        Some(kind)
+    }
+
+    fn from_indexed_tag(tag: &str) -> Option<ComponentKind> {
+        if let Some(fader) = FaderElement::Mute.fader(tag) {
+            return Some(ComponentKind::MuteButton(fader));
+        }
+        if let Some(fader) = FaderElement::FaderMeter.fader(tag) {
+            return Some(ComponentKind::Fader(fader));
+        }
+        if let Some(fader) = FaderElement::Scribble.fader(tag) {
+            return Some(ComponentKind::Scribble(fader));
+        }
+
+        if let Some(preset) = Preset::iter().find(|p| p.get_str("contextTitle") == Some(tag)) {
+            return Some(ComponentKind::Effects(preset));
+        }
+
+        for effect in EffectType::iter() {
+            if let Some(suffix) = tag.strip_prefix(effect.get_str("tagName").unwrap()) {
+                return match suffix {
+                    "" => Some(ComponentKind::EffectRoot(effect)),
+                    _ => preset_from_suffix(suffix)
+                        .map(|preset| ComponentKind::EffectPreset(effect, preset)),
+                };
+            }
+        }
+
+        for encoder in EncoderType::iter() {
+            if let Some(suffix) = tag.strip_prefix(encoder.get_str("tagName").unwrap()) {
+                return match suffix {
+                    "" => Some(ComponentKind::EncoderRoot(encoder)),
+                    _ => preset_from_suffix(suffix)
+                        .map(|preset| ComponentKind::EncoderPreset(encoder, preset)),
+                };
+            }
+        }
+
+        if let Some(button) = SampleButtons::iter().find(|b| sampler_tag(*b) == tag) {
+            return Some(ComponentKind::Sampler(button));
+        }
+
+        if let Some(bank) = SampleBank::iter().find(|b| b.get_str("contextTitle") == Some(tag)) {
+            return Some(ComponentKind::SampleStack(bank));
+        }
+
+        SimpleElements::from_str(tag)
+            .ok()
+            .map(ComponentKind::Simple)
+    }
+
+    /// The name of the element this component is written as.
+    pub fn tag_name(&self) -> String {
+        match self {
+            ComponentKind::Root => VALUE_TREE_ROOT.to_string(),
+            ComponentKind::AppTree => APP_TREE.to_string(),
+            ComponentKind::Browser => BROWSER_PREVIEW_TREE.to_string(),
+            ComponentKind::Animation => ANIMATION_TREE.to_string(),
+            ComponentKind::MixRouting => MIX_ROUTING_TREE.to_string(),
+            ComponentKind::Submixer => SUBMIXER_TREE.to_string(),
+            ComponentKind::Monitor => MONITOR_TREE.to_string(),
+            ComponentKind::Linking => LINKING_TREE.to_string(),
+            ComponentKind::Mixer => MIXER_TREE.to_string(),
+            ComponentKind::Context => SELECTED_CONTEXT.to_string(),
+            ComponentKind::MuteChat => MUTE_CHAT.to_string(),
+            ComponentKind::ShutdownBehaviour => SHUTDOWN_BEHAVIOUR.to_string(),
+            ComponentKind::SamplerRoot => SAMPLER_ROOT.to_string(),
+            ComponentKind::MuteButton(fader) => FaderElement::Mute.tag_name(*fader),
+            ComponentKind::Fader(fader) => FaderElement::FaderMeter.tag_name(*fader),
+            ComponentKind::Scribble(fader) => FaderElement::Scribble.tag_name(*fader),
+            ComponentKind::Effects(preset) => preset.get_str("contextTitle").unwrap().to_string(),
+            ComponentKind::EffectRoot(effect) => effect.get_str("tagName").unwrap().to_string(),
+            ComponentKind::EffectPreset(effect, preset) => format!(
+                "{}{}",
+                effect.get_str("tagName").unwrap(),
+                preset.get_str("tagSuffix").unwrap()
+            ),
+            ComponentKind::EncoderRoot(encoder) => encoder.get_str("tagName").unwrap().to_string(),
+            ComponentKind::EncoderPreset(encoder, preset) => format!(
+                "{}{}",
+                encoder.get_str("tagName").unwrap(),
+                preset.get_str("tagSuffix").unwrap()
+            ),
+            ComponentKind::Sampler(button) => sampler_tag(*button),
+            ComponentKind::SampleStack(bank) => bank.get_str("contextTitle").unwrap().to_string(),
+            ComponentKind::Simple(element) => element.to_string(),
+        }
+    }
+}
+
+// The sampler element names line up with the button names (sampleTopLeft, sampleClear)..
+fn sampler_tag(button: SampleButtons) -> String {
+    format!("sample{}", button)
+}
+
+fn preset_from_suffix(suffix: &str) -> Option<Preset> {
+    Preset::iter().find(|preset| preset.get_str("tagSuffix") == Some(suffix))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read};
+
+    use quick_xml::events::Event;
+    use quick_xml::Reader;
+
+    use super::*;
+
+    const PROFILE: &str = include_str!("../../test-data/profile.xml");
+    const OFFICIAL: &[u8] = include_bytes!("../../../defaults/resources/profiles/Default.goxlr");
+
+    fn tags(xml: &str) -> Vec<String> {
+        let mut reader = Reader::from_str(xml);
+        let mut tags = vec![];
+        loop {
+            match reader.read_event().unwrap() {
+                Event::Start(element) | Event::Empty(element) => {
+                    tags.push(String::from_utf8(element.name().as_ref().to_vec()).unwrap());
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        tags
+    }
+
+    fn assert_all_tags_resolve(xml: &str) {
+        let tags = tags(xml);
+        assert!(!tags.is_empty());
+
+        for tag in tags {
+            match ComponentKind::from_tag(&tag) {
+                Some(kind) => assert_eq!(kind.tag_name(), tag),
+                None => panic!("<{tag}> doesn't resolve to a component"),
+            }
+        }
+    }
+
+    #[test]
+    fn every_fixture_tag_resolves() {
+        assert_all_tags_resolve(PROFILE);
+    }
+
+    #[test]
+    fn every_official_profile_tag_resolves() {
+        let mut archive = zip::ZipArchive::new(Cursor::new(OFFICIAL)).unwrap();
+        let mut xml = String::new();
+        archive
+            .by_name("profile.xml")
+            .unwrap()
+            .read_to_string(&mut xml)
+            .unwrap();
+        assert_all_tags_resolve(&xml);
+    }
+
+    #[test]
+    fn unknown_tags_are_not_resolved() {
+        for tag in [
+            "",
+            "mute",
+            "mute5",
+            "effects7",
+            "FaderMeter",
+            "sampleStackD",
+        ] {
+            assert_eq!(ComponentKind::from_tag(tag), None, "{tag}");
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::io::Write;
 use std::os::raw::c_float;
+use std::str::FromStr;
 
 use enum_map::{Enum, EnumMap};
 use strum::{EnumIter, EnumProperty, IntoEnumIterator};
@@ -10,8 +11,9 @@ use quick_xml::events::{BytesEnd, BytesStart, Event};
 use quick_xml::Writer;
 
 use crate::components::colours::ColourMap;
+use crate::components::effect_base::{strip_defaults, EncoderBase, EncoderPressAction};
 use crate::components::reverb::ReverbStyle::Library;
-use crate::profile::Attribute;
+use crate::profile::Attributes;
 use crate::Preset;
 
 #[derive(thiserror::Error, Debug)]
@@ -35,11 +37,12 @@ pub enum ParseError {
  * presets, we'll use an EnumMap to define the 'presets' as they'll be useful for the other various
  * 'types' of presets (encoders and effects).
  */
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ReverbEncoderBase {
     colour_map: ColourMap,
     preset_map: EnumMap<Preset, ReverbEncoder>,
     active_set: u8, // Not sure what this does?
+    press_action: EncoderPressAction,
 }
 
 impl ReverbEncoderBase {
@@ -49,16 +52,22 @@ impl ReverbEncoderBase {
             colour_map: ColourMap::new(colour_map),
             preset_map: EnumMap::default(),
             active_set: 0,
+            press_action: EncoderPressAction::None,
         }
     }
 
-    pub fn parse_reverb_root(&mut self, attributes: &Vec<Attribute>) -> Result<(), ParseError> {
+    pub fn parse_reverb_root(&mut self, attributes: &Attributes) -> Result<(), ParseError> {
         for attr in attributes {
             if attr.name == "active_set" {
                 self.active_set = attr.value.parse()?;
                 continue;
             }
 
+            if attr.name == "pressAction" {
+                self.press_action = EncoderPressAction::from_str(&attr.value)?;
+                continue;
+            }
+
             if !self.colour_map.read_colours(attr)? {
                 println!("[ReverbEncoder] Unparsed Attribute: {}", attr.name);
             }
@@ -70,16 +79,13 @@ impl ReverbEncoderBase {
     pub fn parse_reverb_preset(
         &mut self,
         preset_enum: Preset,
-        attributes: &Vec<Attribute>,
+        attributes: &Attributes,
     ) -> Result<(), ParseError> {
         let mut preset = ReverbEncoder::new();
         for attr in attributes {
             if attr.name == "REVERB_STYLE" {
-                for style in ReverbStyle::iter() {
-                    if style.get_str("uiIndex").unwrap() == attr.value {
-                        preset.style = style;
-                        break;
-                    }
+                if let Some(style) = ReverbStyle::from_ui_index(&attr.value) {
+                    preset.style = style;
                 }
                 continue;
             }
@@ -154,11 +160,14 @@ impl ReverbEncoderBase {
         Ok(())
     }
 
-    pub fn write_reverb<W: Write>(&self, writer: &mut Writer<W>) -> Result<()> {
+    pub fn write_reverb<W: Write>(&self, writer: &mut Writer<W>, minimal: bool) -> Result<()> {
         let mut elem = BytesStart::new("reverbEncoder");
 
         let mut attributes: HashMap<String, String> = HashMap::default();
         attributes.insert("active_set".to_string(), format!("{}", self.active_set));
+        if self.press_action != EncoderPressAction::None {
+            attributes.insert("pressAction".to_string(), self.press_action.to_string());
+        }
         self.colour_map.write_colours(&mut attributes);
 
         // Write out the attributes etc for this element, but don't close it yet..
@@ -169,11 +178,15 @@ impl ReverbEncoderBase {
         writer.write_event(Event::Start(elem))?;
 
         // Because all of these are seemingly 'guaranteed' to exist, we can straight dump..
+        let defaults = Self::default_preset_attributes();
         for preset in Preset::iter() {
             let tag_name = format!("reverbEncoder{}", preset.get_str("tagSuffix").unwrap());
             let mut sub_elem = BytesStart::new(tag_name.as_str());
 
-            let sub_attributes = self.get_preset_attributes(preset);
+            let mut sub_attributes = self.get_preset_attributes(preset);
+            if minimal {
+                strip_defaults(&mut sub_attributes, &defaults);
+            }
             for (key, value) in &sub_attributes {
                 sub_elem.push_attribute((key.as_str(), value.as_str()));
             }
@@ -196,7 +209,7 @@ impl ReverbEncoderBase {
         );
         attributes.insert(
             "REVERB_STYLE".to_string(),
-            value.style.get_str("uiIndex").unwrap().to_string(),
+            value.style.to_ui_index().to_string(),
         );
         attributes.insert("REVERB_TYPE".to_string(), format!("{}", value.reverb_type));
         attributes.insert("REVERB_DECAY".to_string(), format!("{}", value.decay));
@@ -245,6 +258,14 @@ impl ReverbEncoderBase {
         &mut self.colour_map
     }
 
+    pub fn press_action(&self) -> EncoderPressAction {
+        self.press_action
+    }
+
+    pub fn set_press_action(&mut self, action: EncoderPressAction) {
+        self.press_action = action;
+    }
+
     pub fn get_preset(&self, preset: Preset) -> &ReverbEncoder {
         &self.preset_map[preset]
     }
@@ -253,7 +274,7 @@ impl ReverbEncoderBase {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct ReverbEncoder {
     knob_position: i8,
     style: ReverbStyle,
@@ -342,6 +363,22 @@ impl ReverbEncoder {
         Ok(())
     }
 
+    /// Returns true if the style's parameters are still at the factory values for the style.
+    pub fn is_default(&self) -> bool {
+        let preset = ReverbPreset::get_preset(self.style);
+        self.reverb_type == preset.reverb_type
+            && self.decay == preset.decay
+            && self.pre_delay == preset.pre_delay
+            && self.diffuse == preset.diffuse
+            && self.low_color == preset.low_color
+            && self.high_color == preset.high_color
+            && self.high_factor == preset.high_factor
+            && self.mod_speed == preset.mod_speed
+            && self.mod_depth == preset.mod_depth
+            && self.early_level == preset.early_level
+            && self.tail_level == preset.tail_level
+    }
+
     pub fn reverb_type(&self) -> u8 {
         self.reverb_type
     }
@@ -500,7 +537,7 @@ impl ReverbEncoder {
     }
 }
 
-#[derive(Default, Debug, EnumIter, Enum, EnumProperty, Copy, Clone)]
+#[derive(Default, Debug, EnumIter, Enum, EnumProperty, Copy, Clone, PartialEq, Eq)]
 pub enum ReverbStyle {
     #[default]
     #[strum(props(uiIndex = "0"))]
@@ -522,6 +559,16 @@ pub enum ReverbStyle {
     HockeyArena,
 }
 
+impl ReverbStyle {
+    pub fn from_ui_index(index: &str) -> Option<Self> {
+        ReverbStyle::iter().find(|style| style.to_ui_index() == index)
+    }
+
+    pub fn to_ui_index(&self) -> &'static str {
+        self.get_str("uiIndex").unwrap()
+    }
+}
+
 struct ReverbPreset {
     reverb_type: u8,
     decay: u16,
@@ -620,3 +667,73 @@ impl ReverbPreset {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::ProfileSettings;
+
+    const PROFILE: &str = include_str!("../../test-data/profile.xml");
+
+    const STYLES: [(ReverbStyle, &str); 6] = [
+        (ReverbStyle::Library, "0"),
+        (ReverbStyle::DarkBloom, "1"),
+        (ReverbStyle::MusicClub, "2"),
+        (ReverbStyle::RealPlate, "3"),
+        (ReverbStyle::Chapel, "4"),
+        (ReverbStyle::HockeyArena, "5"),
+    ];
+
+    // The test profile, with the first preset's style changed..
+    fn with_style(index: &str) -> String {
+        let preset = PROFILE.find("<reverbEncoderpreset1").unwrap();
+        let start = preset + PROFILE[preset..].find("REVERB_STYLE=\"").unwrap();
+        let start = start + "REVERB_STYLE=\"".len();
+        let end = start + PROFILE[start..].find('"').unwrap();
+        format!("{}{}{}", &PROFILE[..start], index, &PROFILE[end..])
+    }
+
+    // The first preset's element, as written out..
+    fn written_preset1(settings: &mut ProfileSettings) -> String {
+        let mut written = vec![];
+        settings.write_to(&mut written).unwrap();
+        let written = String::from_utf8(written).unwrap();
+        let start = written.find("<reverbEncoderpreset1").unwrap();
+        let end = start + written[start..].find("/>").unwrap();
+        written[start..end].to_string()
+    }
+
+    #[test]
+    fn styles_match_their_ui_index() {
+        for (style, index) in STYLES {
+            assert_eq!(style.to_ui_index(), index);
+            assert_eq!(ReverbStyle::from_ui_index(index), Some(style));
+        }
+        assert_eq!(ReverbStyle::iter().count(), STYLES.len());
+        assert_eq!(ReverbStyle::from_ui_index("6"), None);
+    }
+
+    #[test]
+    fn styles_are_read_from_the_ui_index() {
+        for (style, index) in STYLES {
+            let settings = ProfileSettings::load(with_style(index).as_bytes()).unwrap();
+            let preset = settings.reverb_encoder().get_preset(Preset::Preset1);
+            assert_eq!(preset.style(), &style, "{index}");
+        }
+    }
+
+    #[test]
+    fn styles_are_written_as_the_ui_index() {
+        for (style, index) in STYLES {
+            let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+            let preset = settings
+                .reverb_encoder_mut()
+                .get_preset_mut(Preset::Preset1);
+            preset.set_style(style).unwrap();
+
+            let attribute = format!("REVERB_STYLE=\"{index}\"");
+            let written = written_preset1(&mut settings);
+            assert!(written.contains(&attribute), "{style:?}: {written}");
+        }
+    }
+}
@@ -10,8 +10,9 @@ use quick_xml::events::{BytesEnd, BytesStart, Event};
 use quick_xml::Writer;
 
 use crate::components::colours::ColourMap;
+use crate::components::effect_base::{strip_defaults, EffectBase};
 use crate::components::robot::RobotStyle::Robot1;
-use crate::profile::Attribute;
+use crate::profile::Attributes;
 use crate::Preset;
 
 #[derive(thiserror::Error, Debug)]
@@ -35,7 +36,7 @@ pub enum ParseError {
  * presets, we'll use an EnumMap to define the 'presets' as they'll be useful for the other various
  * 'types' of presets (encoders and effects).
  */
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct RobotEffectBase {
     colour_map: ColourMap,
     preset_map: EnumMap<Preset, RobotEffect>,
@@ -50,7 +51,7 @@ impl RobotEffectBase {
         }
     }
 
-    pub fn parse_robot_root(&mut self, attributes: &Vec<Attribute>) -> Result<()> {
+    pub fn parse_robot_root(&mut self, attributes: &Attributes) -> Result<()> {
         for attr in attributes {
             if !self.colour_map.read_colours(attr)? {
                 println!("[robotEffect] Unparsed Attribute: {}", attr.name);
@@ -63,7 +64,7 @@ impl RobotEffectBase {
     pub fn parse_robot_preset(
         &mut self,
         preset_enum: Preset,
-        attributes: &Vec<Attribute>,
+        attributes: &Attributes,
     ) -> Result<(), ParseError> {
         let mut preset = RobotEffect::new();
         for attr in attributes {
@@ -143,7 +144,7 @@ impl RobotEffectBase {
         Ok(())
     }
 
-    pub fn write_robot<W: Write>(&self, writer: &mut Writer<W>) -> Result<()> {
+    pub fn write_robot<W: Write>(&self, writer: &mut Writer<W>, minimal: bool) -> Result<()> {
         let mut elem = BytesStart::new("robotEffect");
 
         let mut attributes: HashMap<String, String> = HashMap::default();
@@ -157,11 +158,15 @@ impl RobotEffectBase {
         writer.write_event(Event::Start(elem))?;
 
         // Because all of these are seemingly 'guaranteed' to exist, we can straight dump..
+        let defaults = Self::default_preset_attributes();
         for preset in Preset::iter() {
             let tag_name = format!("robotEffect{}", preset.get_str("tagSuffix").unwrap());
             let mut sub_elem = BytesStart::new(tag_name.as_str());
 
-            let sub_attributes = self.get_preset_attributes(preset);
+            let mut sub_attributes = self.get_preset_attributes(preset);
+            if minimal {
+                strip_defaults(&mut sub_attributes, &defaults);
+            }
             for (key, value) in &sub_attributes {
                 sub_elem.push_attribute((key.as_str(), value.as_str()));
             }
@@ -258,7 +263,7 @@ impl RobotEffectBase {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct RobotEffect {
     // State here determines if the robot effect is on or off when this preset is loaded.
     state: bool,
@@ -335,6 +340,24 @@ impl RobotEffect {
         Ok(())
     }
 
+    /// Returns true if the style's parameters are still at the factory values for the style.
+    pub fn is_default(&self) -> bool {
+        let preset = RobotPresets::get_preset(self.style);
+        self.synthosc_pulse_width == preset.synthosc_pulse_width
+            && self.synthosc_waveform == preset.synthosc_waveform
+            && self.vocoder_gate_threshold == preset.vocoder_gate_threshold
+            && self.dry_mix == preset.dry_mix
+            && self.vocoder_low_freq == preset.vocoder_low_freq
+            && self.vocoder_low_gain == preset.vocoder_low_gain
+            && self.vocoder_low_bw == preset.vocoder_low_bw
+            && self.vocoder_mid_freq == preset.vocoder_mid_freq
+            && self.vocoder_mid_gain == preset.vocoder_mid_gain
+            && self.vocoder_mid_bw == preset.vocoder_mid_bw
+            && self.vocoder_high_freq == preset.vocoder_high_freq
+            && self.vocoder_high_gain == preset.vocoder_high_gain
+            && self.vocoder_high_bw == preset.vocoder_high_bw
+    }
+
     pub fn synthosc_pulse_width(&self) -> u8 {
         self.synthosc_pulse_width
     }
@@ -479,9 +502,111 @@ impl RobotEffect {
         self.vocoder_high_bw = value;
         Ok(())
     }
+
+    pub fn band(&self, range: RobotRange) -> RobotBand {
+        match range {
+            RobotRange::Low => RobotBand {
+                freq: self.vocoder_low_freq,
+                gain: self.vocoder_low_gain,
+                width: self.vocoder_low_bw,
+            },
+            RobotRange::Medium => RobotBand {
+                freq: self.vocoder_mid_freq,
+                gain: self.vocoder_mid_gain,
+                width: self.vocoder_mid_bw,
+            },
+            RobotRange::High => RobotBand {
+                freq: self.vocoder_high_freq,
+                gain: self.vocoder_high_gain,
+                width: self.vocoder_high_bw,
+            },
+        }
+    }
+
+    /// Iterates the three vocoder bands, from low to high.
+    pub fn bands(&self) -> impl Iterator<Item = (RobotRange, RobotBand)> + '_ {
+        RobotRange::iter().map(|range| (range, self.band(range)))
+    }
+
+    /// Sets all of a band's values, if any are out of range the band is left unchanged.
+    pub fn set_band(&mut self, range: RobotRange, band: RobotBand) -> Result<()> {
+        let previous = self.band(range);
+        let result = self
+            .set_band_freq(range, band.freq)
+            .and_then(|_| self.set_band_gain(range, band.gain))
+            .and_then(|_| self.set_band_width(range, band.width));
+
+        if result.is_err() {
+            self.restore_band(range, previous);
+        }
+        result
+    }
+
+    pub fn set_band_freq(&mut self, range: RobotRange, value: u8) -> Result<()> {
+        match range {
+            RobotRange::Low => self.set_vocoder_low_freq(value),
+            RobotRange::Medium => self.set_vocoder_mid_freq(value),
+            RobotRange::High => self.set_vocoder_high_freq(value),
+        }
+    }
+
+    pub fn set_band_gain(&mut self, range: RobotRange, value: i8) -> Result<()> {
+        match range {
+            RobotRange::Low => self.set_vocoder_low_gain(value),
+            RobotRange::Medium => self.set_vocoder_mid_gain(value),
+            RobotRange::High => self.set_vocoder_high_gain(value),
+        }
+    }
+
+    pub fn set_band_width(&mut self, range: RobotRange, value: u8) -> Result<()> {
+        match range {
+            RobotRange::Low => self.set_vocoder_low_bw(value),
+            RobotRange::Medium => self.set_vocoder_mid_bw(value),
+            RobotRange::High => self.set_vocoder_high_bw(value),
+        }
+    }
+
+    // The previous values came from this band, so skip the validation..
+    fn restore_band(&mut self, range: RobotRange, band: RobotBand) {
+        let (freq, gain, width) = match range {
+            RobotRange::Low => (
+                &mut self.vocoder_low_freq,
+                &mut self.vocoder_low_gain,
+                &mut self.vocoder_low_bw,
+            ),
+            RobotRange::Medium => (
+                &mut self.vocoder_mid_freq,
+                &mut self.vocoder_mid_gain,
+                &mut self.vocoder_mid_bw,
+            ),
+            RobotRange::High => (
+                &mut self.vocoder_high_freq,
+                &mut self.vocoder_high_gain,
+                &mut self.vocoder_high_bw,
+            ),
+        };
+        *freq = band.freq;
+        *gain = band.gain;
+        *width = band.width;
+    }
 }
 
-#[derive(Default, Debug, EnumIter, EnumProperty, Copy, Clone)]
+#[derive(Debug, EnumIter, Copy, Clone, PartialEq, Eq)]
+pub enum RobotRange {
+    Low,
+    Medium,
+    High,
+}
+
+/// One of the vocoder's three bands, the frequency is an index rather than a value in Hz.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RobotBand {
+    pub freq: u8,
+    pub gain: i8,
+    pub width: u8,
+}
+
+#[derive(Default, Debug, EnumIter, EnumProperty, Copy, Clone, PartialEq)]
 pub enum RobotStyle {
     #[default]
     #[strum(props(uiIndex = "0"))]
@@ -564,3 +689,128 @@ impl RobotPresets {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::ProfileSettings;
+
+    const PROFILE: &str = include_str!("../../test-data/profile.xml");
+
+    // A valid band for each range, with every value moved away from the test profile's..
+    fn band(range: RobotRange) -> RobotBand {
+        match range {
+            RobotRange::Low => RobotBand {
+                freq: 12,
+                gain: -7,
+                width: 3,
+            },
+            RobotRange::Medium => RobotBand {
+                freq: 150,
+                gain: 5,
+                width: 31,
+            },
+            RobotRange::High => RobotBand {
+                freq: 233,
+                gain: -12,
+                width: 0,
+            },
+        }
+    }
+
+    fn reload(settings: &mut ProfileSettings) -> (String, RobotEffect) {
+        let mut written = vec![];
+        settings.write_to(&mut written).unwrap();
+        let written = String::from_utf8(written).unwrap();
+        let reloaded = ProfileSettings::load(written.as_bytes()).unwrap();
+        let preset = reloaded.robot_effect().get_preset(Preset::Preset1).clone();
+        (written, preset)
+    }
+
+    #[test]
+    fn each_band_round_trips() {
+        for range in RobotRange::iter() {
+            let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+            let preset = settings.robot_effect_mut().get_preset_mut(Preset::Preset1);
+            let untouched: Vec<_> = preset
+                .bands()
+                .filter(|(other, _)| *other != range)
+                .collect();
+            preset.set_band(range, band(range)).unwrap();
+
+            let (written, reloaded) = reload(&mut settings);
+            assert_eq!(reloaded.band(range), band(range), "{range:?}");
+
+            // Only the band that was set has changed..
+            let others: Vec<_> = reloaded
+                .bands()
+                .filter(|(other, _)| *other != range)
+                .collect();
+            assert_eq!(others, untouched, "{range:?}");
+
+            let prefix = match range {
+                RobotRange::Low => "ROBOT_VOCODER_LOW",
+                RobotRange::Medium => "ROBOT_VOCODER_MID",
+                RobotRange::High => "ROBOT_VOCODER_HIGH",
+            };
+            let RobotBand { freq, gain, width } = band(range);
+            for attribute in [
+                format!("{prefix}_FREQ=\"{freq}\""),
+                format!("{prefix}_GAIN=\"{gain}\""),
+                format!("{prefix}_BW=\"{width}\""),
+            ] {
+                assert!(written.contains(&attribute), "{attribute}");
+            }
+        }
+    }
+
+    #[test]
+    fn bands_are_iterated_from_low_to_high() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        let preset = settings.robot_effect_mut().get_preset_mut(Preset::Preset1);
+        for range in RobotRange::iter() {
+            preset.set_band(range, band(range)).unwrap();
+        }
+
+        let ranges: Vec<_> = preset.bands().map(|(range, _)| range).collect();
+        assert_eq!(
+            ranges,
+            [RobotRange::Low, RobotRange::Medium, RobotRange::High]
+        );
+        assert!(preset.bands().all(|(range, value)| value == band(range)));
+    }
+
+    #[test]
+    fn invalid_bands_are_left_unchanged() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        let preset = settings.robot_effect_mut().get_preset_mut(Preset::Preset1);
+        for range in RobotRange::iter() {
+            let before = preset.band(range);
+
+            // Each value is checked against its own band's range, even when the others are fine..
+            let invalid = [
+                RobotBand {
+                    freq: 241,
+                    ..band(range)
+                },
+                RobotBand {
+                    gain: 13,
+                    ..band(range)
+                },
+                RobotBand {
+                    width: 33,
+                    ..band(range)
+                },
+            ];
+            for value in invalid {
+                assert!(preset.set_band(range, value).is_err(), "{range:?}");
+                assert_eq!(preset.band(range), before, "{range:?}");
+            }
+        }
+
+        // The frequency ranges don't overlap much, so a low frequency isn't a valid high one..
+        let low = band(RobotRange::Low);
+        assert!(preset.set_band_freq(RobotRange::High, low.freq).is_err());
+        assert!(preset.set_band_freq(RobotRange::Medium, low.freq).is_err());
+    }
+}
@@ -2,11 +2,12 @@ use std::collections::HashMap;
 use std::ffi::c_float;
 use std::io::Write;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use quick_xml::events::{BytesEnd, BytesStart, Event};
 use quick_xml::Writer;
 
-use crate::profile::Attribute;
+use crate::components::registry::{APP_TREE, VALUE_TREE_ROOT};
+use crate::profile::Attributes;
 
 #[derive(thiserror::Error, Debug)]
 #[allow(clippy::enum_variant_names)]
@@ -22,6 +23,9 @@ pub enum ParseError {
 
     #[error("[ROOT] Invalid colours: {0}")]
     InvalidColours(#[from] crate::components::colours::ParseError),
+
+    #[error("[ROOT] Screen brightness should be a percentage: {0}")]
+    InvalidScreenBrightness(u8),
 }
 
 /**
@@ -33,6 +37,14 @@ pub struct RootElement {
     version: u8,
     loudness: u8,
     device: u64,
+
+    // Not part of the official format, the brightness (as a percentage) of the scribble screens.
+    // Only written when set.
+    screen_brightness: Option<u8>,
+
+    // The official app doesn't write any other attributes here, but tools which add metadata
+    // (author, creation date etc) tend to put it on the root, so keep it for them.
+    extra_attributes: HashMap<String, String>,
 }
 
 impl Default for RootElement {
@@ -47,10 +59,12 @@ impl RootElement {
             version: 0,
             loudness: 0,
             device: 0,
+            screen_brightness: None,
+            extra_attributes: Default::default(),
         }
     }
 
-    pub fn parse_root(&mut self, attributes: &Vec<Attribute>) -> Result<(), ParseError> {
+    pub fn parse_root(&mut self, attributes: &Attributes) -> Result<(), ParseError> {
         for attr in attributes {
             if attr.name == "version" {
                 self.version = attr.value.parse::<c_float>()? as u8;
@@ -64,14 +78,27 @@ impl RootElement {
 
             if attr.name == "device" {
                 self.device = attr.value.parse()?;
+                continue;
+            }
+
+            if attr.name == "screenBrightness" {
+                let brightness = attr.value.parse()?;
+                if brightness > 100 {
+                    return Err(ParseError::InvalidScreenBrightness(brightness));
+                }
+                self.screen_brightness = Some(brightness);
+                continue;
             }
+
+            self.extra_attributes
+                .insert(attr.name.clone(), attr.value.clone());
         }
 
         Ok(())
     }
 
-    pub fn write_initial<W: Write>(&self, writer: &mut Writer<W>) -> Result<()> {
-        let mut elem = BytesStart::new("ValueTreeRoot");
+    pub fn write_initial<W: Write>(&self, writer: &mut Writer<W>, ordered: bool) -> Result<()> {
+        let mut elem = BytesStart::new(VALUE_TREE_ROOT);
 
         // Create the hashmap of values..
         let mut attributes: HashMap<String, String> = HashMap::default();
@@ -79,8 +106,36 @@ impl RootElement {
         attributes.insert("loudness".to_string(), format!("{}", self.loudness));
         attributes.insert("device".to_string(), format!("{}", self.device));
 
-        for (key, value) in &attributes {
-            elem.push_attribute((key.as_str(), value.as_str()));
+        if ordered {
+            // The official app always writes these in this order..
+            for key in ["version", "loudness", "device"] {
+                elem.push_attribute((key, attributes[key].as_str()));
+            }
+
+            if let Some(brightness) = self.screen_brightness {
+                elem.push_attribute(("screenBrightness", format!("{brightness}").as_str()));
+            }
+
+            // Anything extra goes on the end, sorted so the output is stable..
+            let mut extra: Vec<_> = self.extra_attributes.iter().collect();
+            extra.sort();
+            for (key, value) in extra {
+                elem.push_attribute((key.as_str(), value.as_str()));
+            }
+        } else {
+            if let Some(brightness) = self.screen_brightness {
+                attributes.insert("screenBrightness".to_string(), format!("{brightness}"));
+            }
+            for (key, value) in &self.extra_attributes {
+                attributes.insert(key.clone(), value.clone());
+            }
+
+            // Sorted, so that saving the same profile twice produces the same file..
+            let mut attributes: Vec<_> = attributes.iter().collect();
+            attributes.sort();
+            for (key, value) in attributes {
+                elem.push_attribute((key.as_str(), value.as_str()));
+            }
         }
         writer.write_event(Event::Start(elem))?;
 
@@ -89,7 +144,7 @@ impl RootElement {
     }
 
     pub fn write_final<W: Write>(&self, writer: &mut Writer<W>) -> Result<()> {
-        let mut elem = BytesStart::new("AppTree");
+        let mut elem = BytesStart::new(APP_TREE);
 
         let mut attributes: HashMap<String, String> = HashMap::default();
         attributes.insert("ConnectedDeviceID".to_string(), format!("{}", &self.device));
@@ -98,11 +153,54 @@ impl RootElement {
         }
 
         writer.write_event(Event::Empty(elem))?;
-        writer.write_event(Event::End(BytesEnd::new("ValueTreeRoot")))?;
+        writer.write_event(Event::End(BytesEnd::new(VALUE_TREE_ROOT)))?;
         Ok(())
     }
 
     pub fn get_version(&self) -> u8 {
         self.version
     }
+
+    pub fn loudness(&self) -> u8 {
+        self.loudness
+    }
+
+    pub fn device(&self) -> u64 {
+        self.device
+    }
+
+    /// The brightness of the scribble screens as a percentage, full brightness if it's not set.
+    pub fn screen_brightness(&self) -> u8 {
+        self.screen_brightness.unwrap_or(100)
+    }
+    pub fn set_screen_brightness(&mut self, brightness: u8) -> Result<()> {
+        if brightness > 100 {
+            bail!("Screen brightness should be a percentage! {}", brightness);
+        }
+        self.screen_brightness = Some(brightness);
+        Ok(())
+    }
+
+    /// Returns any additional attribute stored on the root, such as metadata added by other tools.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.extra_attributes.get(name).map(String::as_str)
+    }
+
+    pub fn extra_attributes(&self) -> &HashMap<String, String> {
+        &self.extra_attributes
+    }
+
+    /// Sets (or with None, removes) an additional attribute, the standard version, loudness and
+    /// device attributes (along with our own screenBrightness) can't be set this way.
+    pub fn set_attribute(&mut self, name: &str, value: Option<String>) -> Result<()> {
+        if ["version", "loudness", "device", "screenBrightness"].contains(&name) {
+            bail!("{} is a reserved attribute", name);
+        }
+
+        match value {
+            Some(value) => self.extra_attributes.insert(name.to_string(), value),
+            None => self.extra_attributes.remove(name),
+        };
+        Ok(())
+    }
 }
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::io::Write;
+use std::ops::RangeInclusive;
 use std::str::FromStr;
 
 use anyhow::{bail, Result};
@@ -9,11 +10,19 @@ use quick_xml::events::{BytesEnd, BytesStart, Event};
 use quick_xml::Writer;
 use rand::seq::SliceRandom;
 use ritelinked::LinkedHashMap;
-use strum::{Display, EnumIter, EnumProperty, EnumString};
+use strum::{Display, EnumIter, EnumProperty, EnumString, IntoEnumIterator};
 
 use crate::components::colours::ColourMap;
 use crate::components::sample::PlayOrder::{Random, Sequential};
-use crate::profile::Attribute;
+use crate::profile::Attributes;
+
+// Positions are percentages of the track, by default the whole thing is played..
+const DEFAULT_START_POSITION: f32 = 0.;
+const DEFAULT_END_POSITION: f32 = 100.;
+
+/// The range of linear gain a track can be normalised with, anything louder than double is more
+/// likely to be a bad analysis than a quiet sample..
+pub const NORMALIZED_GAIN_RANGE: RangeInclusive<f64> = 0.0..=2.0;
 
 #[derive(thiserror::Error, Debug)]
 #[allow(clippy::enum_variant_names)]
@@ -37,12 +46,17 @@ pub enum ParseError {
  * 'types' of presets (encoders and effects).
  */
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct SampleBase {
     element_name: String,
     colour_map: ColourMap,
     state: String, // Seems to be "Empty" most of the time..
     sample_stack: HashMap<SampleBank, SampleStack>,
+
+    // The profile format doesn't define any recording settings (buffer length, recording source),
+    // those are handled by the utility's own settings. Should an app version add any attributes
+    // we don't know about, they're kept here so they survive a save.
+    extra_attributes: HashMap<String, String>,
 }
 
 impl SampleBase {
@@ -53,10 +67,11 @@ impl SampleBase {
             colour_map: ColourMap::new(colour_map),
             state: "Empty".to_string(),
             sample_stack: Default::default(),
+            extra_attributes: Default::default(),
         }
     }
 
-    pub fn parse_sample_root(&mut self, attributes: &Vec<Attribute>) -> Result<(), ParseError> {
+    pub fn parse_sample_root(&mut self, attributes: &Attributes) -> Result<(), ParseError> {
         for attr in attributes {
             if attr.name.ends_with("state") && self.element_name != "sampleClear" {
                 if attr.value != "Empty" && attr.value != "Stopped" {
@@ -68,13 +83,15 @@ impl SampleBase {
 
             if !self.colour_map.read_colours(attr)? {
                 println!("[Sampler] Unparsed Attribute: {}", attr.name);
+                self.extra_attributes
+                    .insert(attr.name.clone(), attr.value.clone());
             }
         }
 
         Ok(())
     }
 
-    pub fn parse_sample_stack(&mut self, id: char, attributes: &Vec<Attribute>) -> Result<()> {
+    pub fn parse_sample_stack(&mut self, id: char, attributes: &Attributes) -> Result<()> {
         // The easiest way to handle this is to parse everything into key-value pairs, then try
         // to locate all the settings for each track inside it..
         let mut map: HashMap<String, String> = HashMap::default();
@@ -107,14 +124,19 @@ impl SampleBase {
         if let Some(track_count) = map.get(key.as_str()) {
             let track_count: u8 = track_count.parse()?;
             for i in 0..track_count {
-                if let (Some(track), Some(start), Some(end), Some(gain)) = (
+                if let (Some(track), Some(gain)) = (
                     map.get(&format!("track_{i}")),
-                    map.get(&format!("track_{i}StartPosition")),
-                    map.get(&format!("track_{i}EndPosition")),
                     map.get(&format!("track_{i}NormalizedGain")),
                 ) {
-                    let mut start: f32 = start.parse()?;
-                    let mut end: f32 = end.parse()?;
+                    // Minimal profiles leave out positions which cover the whole track..
+                    let mut start: f32 = match map.get(&format!("track_{i}StartPosition")) {
+                        Some(start) => start.parse()?,
+                        None => DEFAULT_START_POSITION,
+                    };
+                    let mut end: f32 = match map.get(&format!("track_{i}EndPosition")) {
+                        Some(end) => end.parse()?,
+                        None => DEFAULT_END_POSITION,
+                    };
 
                     if start > 100. {
                         start = 100.;
@@ -138,7 +160,10 @@ impl SampleBase {
                         end = start;
                     }
 
-                    let track = Track::new(track.to_string(), start, end, gain.parse()?);
+                    let mut track = Track::new(track.to_string(), start, end, gain.parse()?);
+                    if let Some(target) = map.get(&format!("track_{i}NormalizationTarget")) {
+                        track.normalization_target = Some(target.parse()?);
+                    }
                     sample_stack.tracks.push(track);
                 }
             }
@@ -150,10 +175,10 @@ impl SampleBase {
         Ok(())
     }
 
-    pub fn write_sample<W: Write>(&self, writer: &mut Writer<W>) -> Result<()> {
+    pub fn write_sample<W: Write>(&self, writer: &mut Writer<W>, minimal: bool) -> Result<()> {
         let mut elem = BytesStart::new(self.element_name.as_str());
 
-        let mut attributes: HashMap<String, String> = HashMap::default();
+        let mut attributes: HashMap<String, String> = self.extra_attributes.clone();
         self.colour_map.write_colours(&mut attributes);
 
         // TODO: Solve the 'State' problem properly..
@@ -173,14 +198,21 @@ impl SampleBase {
             );
         }
 
-        // Write out the attributes etc for this element, but don't close it yet..
-        for (key, value) in &attributes {
+        // Write out the attributes etc for this element, but don't close it yet. These are
+        // sorted so that saving the same profile twice produces the same file..
+        let mut attributes: Vec<_> = attributes.iter().collect();
+        attributes.sort();
+        for (key, value) in attributes {
             elem.push_attribute((key.as_str(), value.as_str()));
         }
         writer.write_event(Event::Start(elem))?;
 
         // Now onto the damn stacks..
-        for (key, value) in &self.sample_stack {
+        for key in SampleBank::iter() {
+            let value = match self.sample_stack.get(&key) {
+                Some(value) => value,
+                None => continue,
+            };
             let sub_element_name = format!("sampleStack{key}");
 
             let mut sub_elem = BytesStart::new(sub_element_name.as_str());
@@ -204,18 +236,29 @@ impl SampleBase {
             }
 
             for i in 0..value.tracks.len() {
+                let track = value.tracks.get(i).unwrap();
                 sub_attributes.insert(
                     format!("track_{i}NormalizedGain"),
-                    format!("{}", value.tracks.get(i).unwrap().normalized_gain),
-                );
-                sub_attributes.insert(
-                    format!("track_{i}StartPosition"),
-                    format!("{}", value.tracks.get(i).unwrap().start_position),
-                );
-                sub_attributes.insert(
-                    format!("track_{i}EndPosition"),
-                    format!("{}", value.tracks.get(i).unwrap().end_position),
+                    format!("{}", track.normalized_gain),
                 );
+                if !minimal || track.start_position != DEFAULT_START_POSITION {
+                    sub_attributes.insert(
+                        format!("track_{i}StartPosition"),
+                        format!("{}", track.start_position),
+                    );
+                }
+                if !minimal || track.end_position != DEFAULT_END_POSITION {
+                    sub_attributes.insert(
+                        format!("track_{i}EndPosition"),
+                        format!("{}", track.end_position),
+                    );
+                }
+                if let Some(target) = value.tracks.get(i).unwrap().normalization_target {
+                    sub_attributes.insert(
+                        format!("track_{i}NormalizationTarget"),
+                        format!("{}", target),
+                    );
+                }
             }
 
             if let Some(output) = &value.playback_mode {
@@ -250,15 +293,51 @@ impl SampleBase {
         &mut self.colour_map
     }
 
+    pub fn extra_attributes(&self) -> &HashMap<String, String> {
+        &self.extra_attributes
+    }
+
+    /// Every track assigned to this button, across all banks, in bank order..
+    pub fn tracks(&self) -> impl Iterator<Item = &Track> {
+        SampleBank::iter()
+            .filter_map(|bank| self.sample_stack.get(&bank))
+            .flat_map(|stack| stack.tracks.iter())
+    }
+
+    pub fn tracks_mut(&mut self) -> impl Iterator<Item = &mut Track> {
+        self.sample_stack
+            .values_mut()
+            .flat_map(|stack| stack.tracks.iter_mut())
+    }
+
     pub fn get_stack(&self, bank: SampleBank) -> &SampleStack {
         self.sample_stack.get(&bank).unwrap()
     }
     pub fn get_stack_mut(&mut self, bank: SampleBank) -> &mut SampleStack {
         self.sample_stack.get_mut(&bank).unwrap()
     }
+
+    /// The playback mode for a bank, a bank with no stack in the profile behaves as PlayNext.
+    pub fn playback_mode(&self, bank: SampleBank) -> PlaybackMode {
+        match self.sample_stack.get(&bank) {
+            Some(stack) => stack.get_playback_mode(),
+            None => PlaybackMode::PlayNext,
+        }
+    }
+
+    /// As with get_stack_mut, but creates an empty stack if the profile doesn't have one.
+    pub fn get_stack_or_default_mut(&mut self, bank: SampleBank) -> &mut SampleStack {
+        self.sample_stack.entry(bank).or_default()
+    }
+
+    /// Sets the playback mode for a bank, creating an empty stack if the profile doesn't have one.
+    pub fn set_playback_mode(&mut self, bank: SampleBank, mode: PlaybackMode) {
+        self.get_stack_or_default_mut(bank)
+            .set_playback_mode(Some(mode));
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct SampleStack {
     tracks: Vec<Track>,
     playback_mode: Option<PlaybackMode>,
@@ -387,12 +466,17 @@ impl SampleStack {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Track {
     pub track: String,
     pub start_position: f32,
     pub end_position: f32,
+
+    // This is a linear multiplier (1.0 being unchanged), not dB..
     pub normalized_gain: f64,
+
+    // The loudness target (in LUFS) the gain was calculated against, not used by the official app
+    pub normalization_target: Option<f64>,
 }
 
 impl Track {
@@ -407,6 +491,7 @@ impl Track {
             start_position,
             end_position,
             normalized_gain,
+            normalization_target: None,
         }
     }
 
@@ -422,6 +507,35 @@ impl Track {
     pub fn normalized_gain(&self) -> f64 {
         self.normalized_gain
     }
+    pub fn normalization_target(&self) -> Option<f64> {
+        self.normalization_target
+    }
+
+    pub fn set_normalized_gain(&mut self, gain: f64) -> Result<()> {
+        if !NORMALIZED_GAIN_RANGE.contains(&gain) {
+            bail!(
+                "Gain should be a linear multiplier between {} and {}! {}",
+                NORMALIZED_GAIN_RANGE.start(),
+                NORMALIZED_GAIN_RANGE.end(),
+                gain
+            );
+        }
+        self.normalized_gain = gain;
+        Ok(())
+    }
+
+    pub fn set_normalization_target(&mut self, target: Option<f64>) -> Result<()> {
+        if let Some(target) = target {
+            if !target.is_finite() {
+                bail!(
+                    "Normalization Target should be a valid LUFS value! {}",
+                    target
+                );
+            }
+        }
+        self.normalization_target = target;
+        Ok(())
+    }
 
     pub fn set_start_position(&mut self, start: f32) -> Result<()> {
         if !(0. ..=100.).contains(&start) {
@@ -450,7 +564,7 @@ impl Track {
     }
 }
 
-#[derive(Debug, Copy, Clone, Enum, EnumProperty)]
+#[derive(Debug, Copy, Clone, Enum, EnumProperty, EnumIter, Eq, PartialEq)]
 pub enum PlaybackMode {
     #[strum(props(index = "0"))]
     PlayNext,
@@ -485,3 +599,103 @@ pub enum SampleBank {
     #[strum(props(contextTitle = "sampleStackC"))]
     C,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::ProfileSettings;
+    use crate::SampleButtons;
+
+    const PROFILE: &str = include_str!("../../test-data/profile.xml");
+
+    // The clear button doesn't hold any samples..
+    const PADS: [SampleButtons; 4] = [
+        SampleButtons::TopLeft,
+        SampleButtons::TopRight,
+        SampleButtons::BottomLeft,
+        SampleButtons::BottomRight,
+    ];
+
+    fn slots() -> impl Iterator<Item = (SampleButtons, SampleBank)> {
+        PADS.into_iter()
+            .flat_map(|button| SampleBank::iter().map(move |bank| (button, bank)))
+    }
+
+    fn save_and_reload(settings: &mut ProfileSettings) -> (String, ProfileSettings) {
+        let mut written = vec![];
+        settings.write_to(&mut written).unwrap();
+        let written = String::from_utf8(written).unwrap();
+        let reloaded = ProfileSettings::load(written.as_bytes()).unwrap();
+        (written, reloaded)
+    }
+
+    #[test]
+    fn playback_modes_round_trip() {
+        for mode in PlaybackMode::iter() {
+            let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+            for (button, bank) in slots() {
+                settings
+                    .sample_button_mut(button)
+                    .set_playback_mode(bank, mode);
+            }
+
+            let (written, reloaded) = save_and_reload(&mut settings);
+            for (button, bank) in slots() {
+                let loaded = reloaded.sample_button(button).playback_mode(bank);
+                assert_eq!(loaded, mode, "{button} {bank}");
+            }
+
+            let attribute = format!("playbackMode=\"{}\"", mode.get_str("index").unwrap());
+            assert!(written.contains(&attribute), "{attribute}");
+        }
+    }
+
+    #[test]
+    fn playback_modes_are_set_per_bank() {
+        let modes = [
+            (SampleBank::A, PlaybackMode::Loop),
+            (SampleBank::B, PlaybackMode::FadeOnRelease),
+            (SampleBank::C, PlaybackMode::PlayStop),
+        ];
+
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        for (bank, mode) in modes {
+            let button = settings.sample_button_mut(SampleButtons::BottomRight);
+            button.set_playback_mode(bank, mode);
+        }
+
+        let (_, reloaded) = save_and_reload(&mut settings);
+        let button = reloaded.sample_button(SampleButtons::BottomRight);
+        for (bank, mode) in modes {
+            assert_eq!(button.playback_mode(bank), mode, "{bank}");
+        }
+    }
+
+    #[test]
+    fn banks_without_a_stack_play_next() {
+        let mut button = SampleBase::new(String::from("sampleTopLeft"));
+        assert_eq!(button.playback_mode(SampleBank::A), PlaybackMode::PlayNext);
+
+        // Setting a mode creates the stack to hold it..
+        button.set_playback_mode(SampleBank::A, PlaybackMode::StopOnRelease);
+        assert_eq!(
+            button.get_stack(SampleBank::A).get_playback_mode(),
+            PlaybackMode::StopOnRelease
+        );
+    }
+
+    #[test]
+    fn normalized_gain_is_range_checked() {
+        let mut track = Track::new("sample.wav".to_string(), 0., 100., 1.0);
+
+        track.set_normalized_gain(0.0).unwrap();
+        assert_eq!(track.normalized_gain(), 0.0);
+        track.set_normalized_gain(2.0).unwrap();
+        assert_eq!(track.normalized_gain(), 2.0);
+
+        for gain in [-0.01, 2.01, f64::NAN, f64::INFINITY] {
+            assert!(track.set_normalized_gain(gain).is_err(), "{gain}");
+        }
+        assert_eq!(track.normalized_gain(), 2.0);
+    }
+}
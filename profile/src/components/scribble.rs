@@ -8,7 +8,7 @@ use quick_xml::Writer;
 
 use crate::components::colours::ColourMap;
 use crate::components::scribble::ScribbleStyle::{Inverted, Normal};
-use crate::profile::Attribute;
+use crate::profile::Attributes;
 
 #[derive(thiserror::Error, Debug)]
 #[allow(clippy::enum_variant_names)]
@@ -56,6 +56,14 @@ pub struct Scribble {
 
     // Filename in the .goxlr zip file to the prepared bitmap
     bitmap_file: String,
+
+    // Not part of the official format, whether the bottom text should be updated to the name of
+    // the channel when the fader is reassigned. Only written when set.
+    follows_channel: bool,
+
+    // Also not part of the official format, whether the fader's colours should follow this
+    // scribble's backlight. Only written when set.
+    fader_colours_linked: bool,
 }
 
 impl Scribble {
@@ -72,10 +80,12 @@ impl Scribble {
             alpha: 0.0,
             style: Normal,
             bitmap_file: "".to_string(),
+            follows_channel: false,
+            fader_colours_linked: false,
         }
     }
 
-    pub fn parse_scribble(&mut self, attributes: &Vec<Attribute>) -> Result<(), ParseError> {
+    pub fn parse_scribble(&mut self, attributes: &Attributes) -> Result<(), ParseError> {
         for attr in attributes {
             if attr.name.ends_with("iconFile") {
                 if attr.value.clone() == "" {
@@ -120,6 +130,16 @@ impl Scribble {
                 continue;
             }
 
+            if attr.name.ends_with("followsChannel") {
+                self.follows_channel = attr.value == "1";
+                continue;
+            }
+
+            if attr.name.ends_with("linkFaderColours") {
+                self.fader_colours_linked = attr.value == "1";
+                continue;
+            }
+
             // Send the rest out for colouring..
             if !self.colour_map.read_colours(attr)? {
                 println!("[SCRIBBLE] Unparsed Attribute: {}", attr.name);
@@ -168,6 +188,20 @@ impl Scribble {
             self.bitmap_file.clone(),
         );
 
+        if self.follows_channel {
+            attributes.insert(
+                format!("{}followsChannel", self.element_name),
+                "1".to_string(),
+            );
+        }
+
+        if self.fader_colours_linked {
+            attributes.insert(
+                format!("{}linkFaderColours", self.element_name),
+                "1".to_string(),
+            );
+        }
+
         self.colour_map.write_colours(&mut attributes);
 
         for (key, value) in &attributes {
@@ -221,6 +255,27 @@ impl Scribble {
     pub fn set_scribble_inverted(&mut self, inverted: bool) {
         self.style = if inverted { Inverted } else { Normal }
     }
+
+    pub fn follows_channel(&self) -> bool {
+        self.follows_channel
+    }
+    pub fn set_follows_channel(&mut self, follows_channel: bool) {
+        self.follows_channel = follows_channel;
+    }
+
+    pub fn fader_colours_linked(&self) -> bool {
+        self.fader_colours_linked
+    }
+    pub fn set_fader_colours_linked(&mut self, linked: bool) {
+        self.fader_colours_linked = linked;
+    }
+
+    pub fn bitmap_file(&self) -> &str {
+        &self.bitmap_file
+    }
+    pub fn set_bitmap_file(&mut self, bitmap_file: String) {
+        self.bitmap_file = bitmap_file;
+    }
 }
 
 #[derive(PartialEq, Eq, Debug)]
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::io::Write;
+
+use anyhow::Result;
+use log::warn;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Writer;
+use strum::{EnumIter, EnumProperty, IntoEnumIterator};
+
+use crate::components::colours::Colour;
+use crate::components::registry::SHUTDOWN_BEHAVIOUR;
+use crate::profile::Attributes;
+
+#[derive(thiserror::Error, Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum ParseError {
+    #[error("[SHUTDOWN] Expected enum: {0}")]
+    ExpectedEnum(#[from] strum::ParseError),
+
+    #[error("[SHUTDOWN] Invalid colours: {0}")]
+    InvalidColours(#[from] crate::components::colours::ParseError),
+}
+
+/**
+ * This element isn't part of the official app's profile format, it describes what the utility
+ * should do with the device when it detaches. The official app ignores elements it doesn't know,
+ * so it's only written when it's been explicitly set..
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShutdownBehaviour {
+    action: ShutdownAction,
+
+    // An optional colour to apply to the device on shutdown, None leaves the lighting alone
+    // (unless the action turns it off).
+    colour: Option<Colour>,
+}
+
+impl ShutdownBehaviour {
+    pub fn new(action: ShutdownAction) -> Self {
+        Self {
+            action,
+            colour: None,
+        }
+    }
+
+    pub fn parse_shutdown(&mut self, attributes: &Attributes) -> Result<(), ParseError> {
+        for attr in attributes {
+            if attr.name == "action" {
+                let mut found = false;
+                for action in ShutdownAction::iter() {
+                    if action.get_str("Value").unwrap() == attr.value {
+                        self.action = action;
+                        found = true;
+                        break;
+                    }
+                }
+
+                if !found {
+                    return Err(ParseError::ExpectedEnum(strum::ParseError::VariantNotFound));
+                }
+                continue;
+            }
+
+            if attr.name == "colour" {
+                if !attr.value.is_empty() {
+                    self.colour = Some(Colour::fromrgb(&attr.value)?);
+                }
+                continue;
+            }
+
+            warn!("[shutdownBehaviour] Unparsed Attribute: {}", attr.name);
+        }
+
+        Ok(())
+    }
+
+    pub fn write_shutdown<W: Write>(&self, writer: &mut Writer<W>) -> Result<()> {
+        let mut elem = BytesStart::new(SHUTDOWN_BEHAVIOUR);
+
+        let mut attributes: HashMap<String, String> = HashMap::default();
+        attributes.insert(
+            "action".to_string(),
+            self.action.get_str("Value").unwrap().to_string(),
+        );
+
+        if let Some(colour) = &self.colour {
+            attributes.insert("colour".to_string(), colour.to_rgb());
+        }
+
+        for (key, value) in &attributes {
+            elem.push_attribute((key.as_str(), value.as_str()));
+        }
+
+        writer.write_event(Event::Empty(elem))?;
+        Ok(())
+    }
+
+    pub fn action(&self) -> ShutdownAction {
+        self.action
+    }
+    pub fn set_action(&mut self, action: ShutdownAction) {
+        self.action = action;
+    }
+
+    pub fn colour(&self) -> Option<&Colour> {
+        self.colour.as_ref()
+    }
+    pub fn set_colour(&mut self, colour: Option<Colour>) {
+        self.colour = colour;
+    }
+}
+
+#[derive(Debug, Copy, Clone, EnumIter, EnumProperty, PartialEq, Eq)]
+pub enum ShutdownAction {
+    /// Leave the device exactly as it is
+    #[strum(props(Value = "keep"))]
+    KeepState,
+
+    /// Turn off all the lighting on the device
+    #[strum(props(Value = "lightingOff"))]
+    LightingOff,
+
+    /// Mute the microphone
+    #[strum(props(Value = "muteMic"))]
+    MuteMic,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::ProfileSettings;
+
+    const PROFILE: &str = include_str!("../../test-data/profile.xml");
+
+    fn round_trip(settings: &mut ProfileSettings) -> (String, ProfileSettings) {
+        let mut written = vec![];
+        settings.write_to(&mut written).unwrap();
+        let reloaded = ProfileSettings::load(written.as_slice()).unwrap();
+        (String::from_utf8(written).unwrap(), reloaded)
+    }
+
+    #[test]
+    fn profiles_without_a_shutdown_behaviour_are_unchanged() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        assert!(settings.shutdown_behaviour().is_none());
+
+        let (written, reloaded) = round_trip(&mut settings);
+        assert!(!written.contains(SHUTDOWN_BEHAVIOUR));
+        assert!(reloaded.shutdown_behaviour().is_none());
+    }
+
+    #[test]
+    fn shutdown_behaviours_round_trip() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+
+        for action in ShutdownAction::iter() {
+            for colour in [None, Some(Colour::fromrgb("FF8000").unwrap())] {
+                let mut behaviour = ShutdownBehaviour::new(action);
+                behaviour.set_colour(colour);
+                settings.set_shutdown_behaviour(Some(behaviour.clone()));
+
+                let (_, reloaded) = round_trip(&mut settings);
+                assert_eq!(reloaded.shutdown_behaviour(), Some(&behaviour));
+            }
+        }
+    }
+}
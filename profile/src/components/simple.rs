@@ -1,7 +1,8 @@
 use std::collections::HashMap;
 use std::io::Write;
+use std::str::FromStr;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 
 use enum_map::Enum;
 use quick_xml::events::{BytesStart, Event};
@@ -9,7 +10,7 @@ use quick_xml::Writer;
 use strum::{Display, EnumIter, EnumString};
 
 use crate::components::colours::ColourMap;
-use crate::profile::Attribute;
+use crate::profile::Attributes;
 
 #[derive(thiserror::Error, Debug)]
 #[allow(clippy::enum_variant_names)]
@@ -25,16 +26,24 @@ pub enum ParseError {
 
     #[error("[SIMPLE] Invalid colours: {0}")]
     InvalidColours(#[from] crate::components::colours::ParseError),
+
+    #[error("[SIMPLE] Invalid bleep volume: {0}")]
+    InvalidBleepVolume(String),
 }
 
 /**
- * These have no special properties, they are literally just button colours..
+ * These are mostly just button colours, the exception being the swear button which can
+ * optionally store how it responds to being pressed, and how loud the bleep should be.
  */
 #[derive(Debug)]
 pub struct SimpleElement {
     // Ok.
     element_name: String,
     colour_map: ColourMap,
+
+    // Only present if they were in the profile, or have been set since..
+    bleep_behaviour: Option<BleepBehaviour>,
+    bleep_volume: Option<i8>,
 }
 
 impl SimpleElement {
@@ -43,11 +52,26 @@ impl SimpleElement {
         Self {
             element_name,
             colour_map: ColourMap::new(colour_map),
+            bleep_behaviour: None,
+            bleep_volume: None,
         }
     }
 
-    pub fn parse_simple(&mut self, attributes: &Vec<Attribute>) -> Result<(), ParseError> {
+    pub fn parse_simple(&mut self, attributes: &Attributes) -> Result<(), ParseError> {
         for attr in attributes {
+            if attr.name == format!("{}bleepBehaviour", self.element_name) {
+                self.bleep_behaviour = Some(BleepBehaviour::from_str(&attr.value)?);
+                continue;
+            }
+
+            if attr.name == format!("{}bleepVolume", self.element_name) {
+                let volume = Some(attr.value.parse()?);
+                validate_bleep_volume(volume)
+                    .map_err(|e| ParseError::InvalidBleepVolume(e.to_string()))?;
+                self.bleep_volume = volume;
+                continue;
+            }
+
             if !self.colour_map.read_colours(attr)? {
                 println!("[{}] Unparsed Attribute: {}", self.element_name, attr.name);
             }
@@ -62,7 +86,23 @@ impl SimpleElement {
         let mut attributes: HashMap<String, String> = HashMap::default();
         self.colour_map.write_colours(&mut attributes);
 
-        for (key, value) in &attributes {
+        // Nothing is written for these unless they've been set, older profiles don't have them..
+        if let Some(behaviour) = self.bleep_behaviour {
+            attributes.insert(
+                format!("{}bleepBehaviour", self.element_name),
+                behaviour.to_string(),
+            );
+        }
+        if let Some(volume) = self.bleep_volume {
+            attributes.insert(
+                format!("{}bleepVolume", self.element_name),
+                format!("{volume}"),
+            );
+        }
+
+        let mut attributes: Vec<_> = attributes.iter().collect();
+        attributes.sort();
+        for (key, value) in attributes {
             elem.push_attribute((key.as_str(), value.as_str()));
         }
 
@@ -80,9 +120,43 @@ impl SimpleElement {
     pub fn colour_map_mut(&mut self) -> &mut ColourMap {
         &mut self.colour_map
     }
+
+    pub fn bleep_behaviour(&self) -> Option<BleepBehaviour> {
+        self.bleep_behaviour
+    }
+    pub fn set_bleep_behaviour(&mut self, behaviour: Option<BleepBehaviour>) {
+        self.bleep_behaviour = behaviour;
+    }
+
+    pub fn bleep_volume(&self) -> Option<i8> {
+        self.bleep_volume
+    }
+    pub fn set_bleep_volume(&mut self, volume: Option<i8>) -> Result<()> {
+        validate_bleep_volume(volume)?;
+        self.bleep_volume = volume;
+        Ok(())
+    }
+}
+
+fn validate_bleep_volume(volume: Option<i8>) -> Result<()> {
+    if let Some(volume) = volume {
+        if !(-36..=0).contains(&volume) {
+            return Err(anyhow!("Bleep volume should be between -36 and 0"));
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Display, EnumString, Clone, Copy, PartialEq, Eq)]
+pub enum BleepBehaviour {
+    #[strum(to_string = "Hold")]
+    Hold,
+
+    #[strum(to_string = "Toggle")]
+    Toggle,
 }
 
-#[derive(Debug, Display, EnumString, EnumIter, Enum, Clone, Copy)]
+#[derive(Debug, Display, EnumString, EnumIter, Enum, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum SimpleElements {
     #[strum(to_string = "sampleBankA")]
     SampleBankA,
@@ -1,7 +1,9 @@
 use crate::components::mixer::InputChannels;
-use crate::profile::Attribute;
+use crate::components::registry::LINKING_TREE;
+use crate::profile::Attributes;
 use anyhow::Result;
 use enum_map::EnumMap;
+use log::warn;
 use quick_xml::events::{BytesStart, Event};
 use quick_xml::Writer;
 use std::collections::HashMap;
@@ -12,6 +14,7 @@ use strum::{EnumProperty, IntoEnumIterator};
 pub struct LinkingTree {
     linked_list: EnumMap<InputChannels, bool>,
     linked_ratio: EnumMap<InputChannels, f64>,
+    extra_attributes: HashMap<String, String>,
 }
 
 impl Default for LinkingTree {
@@ -25,6 +28,7 @@ impl LinkingTree {
         Self {
             linked_list: LinkingTree::get_default_linked_list(),
             linked_ratio: LinkingTree::get_default_linked_ratio(),
+            extra_attributes: Default::default(),
         }
     }
 
@@ -44,7 +48,7 @@ impl LinkingTree {
         linked
     }
 
-    pub fn parse_links(&mut self, attributes: &Vec<Attribute>) -> Result<()> {
+    pub fn parse_links(&mut self, attributes: &Attributes) -> Result<()> {
         for attr in attributes {
             if attr.name.ends_with("Linked") {
                 let mut found = false;
@@ -65,7 +69,9 @@ impl LinkingTree {
                 }
 
                 if !found {
-                    println!("Unable to find Channel: {channel}");
+                    warn!("Unable to find Channel: {channel}, preserving attribute");
+                    self.extra_attributes
+                        .insert(attr.name.clone(), attr.value.clone());
                 }
                 continue;
             }
@@ -89,17 +95,23 @@ impl LinkingTree {
                 }
 
                 if !found {
-                    println!("Unable to find Channel: {channel}");
+                    warn!("Unable to find Channel: {channel}, preserving attribute");
+                    self.extra_attributes
+                        .insert(attr.name.clone(), attr.value.clone());
                 }
                 continue;
             }
+
+            // Anything else gets kept as-is, and written back out..
+            self.extra_attributes
+                .insert(attr.name.clone(), attr.value.clone());
         }
 
         Ok(())
     }
 
     pub fn write_linking_tree<W: Write>(&self, writer: &mut Writer<W>) -> Result<()> {
-        let mut elem = BytesStart::new("linkingTree");
+        let mut elem = BytesStart::new(LINKING_TREE);
 
         // This one's actually incredibly straight forward :)
         let mut attributes: HashMap<String, String> = HashMap::default();
@@ -117,6 +129,10 @@ impl LinkingTree {
             attributes.insert(key, value);
         }
 
+        for (key, value) in &self.extra_attributes {
+            attributes.insert(key.clone(), value.clone());
+        }
+
         // Set the attributes into the XML object..
         for (key, value) in &attributes {
             elem.push_attribute((key.as_str(), value.as_str()));
@@ -138,6 +154,10 @@ impl LinkingTree {
         Ok(())
     }
 
+    pub fn extra_attributes(&self) -> &HashMap<String, String> {
+        &self.extra_attributes
+    }
+
     pub fn set_link_ratio(&mut self, channel: InputChannels, ratio: f64) -> Result<()> {
         self.linked_ratio[channel] = ratio;
         Ok(())
@@ -3,7 +3,8 @@ use std::collections::HashMap;
 use std::io::Write;
 
 use crate::components::mixer::OutputChannels;
-use crate::profile::Attribute;
+use crate::components::registry::MIX_ROUTING_TREE;
+use crate::profile::Attributes;
 use enum_map::{Enum, EnumMap};
 use quick_xml::events::{BytesStart, Event};
 use quick_xml::Writer;
@@ -27,7 +28,7 @@ impl MixRoutingTree {
         }
     }
 
-    pub fn parse_mix_tree(&mut self, attributes: &Vec<Attribute>) -> Result<()> {
+    pub fn parse_mix_tree(&mut self, attributes: &Attributes) -> Result<()> {
         for attr in attributes {
             // Normally, I'd add some fancy code to iterate the OutputChannel, but for the tree here
             // they have different names to anywhere else, so we'll do it by hand.
@@ -64,7 +65,7 @@ impl MixRoutingTree {
     }
 
     pub fn write_mix_tree<W: Write>(&self, writer: &mut Writer<W>) -> Result<()> {
-        let mut elem = BytesStart::new("mixRoutingTree");
+        let mut elem = BytesStart::new(MIX_ROUTING_TREE);
 
         // This one's actually incredibly straight forward :)
         let mut attributes: HashMap<String, String> = HashMap::default();
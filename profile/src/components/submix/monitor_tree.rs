@@ -1,8 +1,10 @@
 use crate::components::mixer::{InputChannels, OutputChannels};
+use crate::components::registry::MONITOR_TREE;
 use crate::components::submix::mix_routing_tree::Mix;
-use crate::profile::Attribute;
+use crate::profile::Attributes;
 use anyhow::Result;
 use enum_map::EnumMap;
+use log::warn;
 use quick_xml::events::{BytesStart, Event};
 use quick_xml::Writer;
 use std::collections::HashMap;
@@ -14,6 +16,7 @@ pub struct MonitorTree {
     monitored_output: OutputChannels,
     headphone_mix: Mix,
     routing: EnumMap<InputChannels, u16>,
+    extra_attributes: HashMap<String, String>,
 }
 
 impl Default for MonitorTree {
@@ -36,6 +39,7 @@ impl MonitorTree {
             monitored_output: OutputChannels::Headphones,
             headphone_mix: Mix::A,
             routing: MonitorTree::get_default_routing(),
+            extra_attributes: Default::default(),
         }
     }
 
@@ -47,7 +51,7 @@ impl MonitorTree {
         routing
     }
 
-    pub fn parse_monitor_tree(&mut self, attributes: &Vec<Attribute>) -> Result<()> {
+    pub fn parse_monitor_tree(&mut self, attributes: &Attributes) -> Result<()> {
         for attr in attributes {
             if attr.name == "monitoredOutput" {
                 if let Some(output) = OutputChannels::iter().nth(attr.value.parse()?) {
@@ -72,21 +76,32 @@ impl MonitorTree {
                 let value: u16 = attr.value.parse()?;
 
                 // We need to find the two matching channels..
+                let mut found = false;
                 for input_channel in InputChannels::iter() {
                     if input_channel.get_str("Name").unwrap() == input {
                         self.routing[input_channel] = value;
+                        found = true;
                         break;
                     }
                 }
 
+                if !found {
+                    warn!("Unable to find Channel: {input}, preserving attribute");
+                    self.extra_attributes
+                        .insert(attr.name.clone(), attr.value.clone());
+                }
                 continue;
             }
+
+            // Anything else gets kept as-is, and written back out..
+            self.extra_attributes
+                .insert(attr.name.clone(), attr.value.clone());
         }
         Ok(())
     }
 
     pub fn write_monitor_tree<W: Write>(&self, writer: &mut Writer<W>) -> Result<()> {
-        let mut elem = BytesStart::new("monitorTree");
+        let mut elem = BytesStart::new(MONITOR_TREE);
 
         let mut attributes: HashMap<String, String> = HashMap::default();
         attributes.insert(
@@ -104,6 +119,10 @@ impl MonitorTree {
             attributes.insert(key, self.routing[channel].to_string());
         }
 
+        for (key, value) in &self.extra_attributes {
+            attributes.insert(key.clone(), value.clone());
+        }
+
         for (key, value) in &attributes {
             elem.push_attribute((key.as_str(), value.as_str()));
         }
@@ -125,6 +144,10 @@ impl MonitorTree {
         &mut self.routing
     }
 
+    pub fn extra_attributes(&self) -> &HashMap<String, String> {
+        &self.extra_attributes
+    }
+
     pub fn set_monitored_output(&mut self, monitored_output: OutputChannels) {
         self.monitored_output = monitored_output;
     }
@@ -1,9 +1,11 @@
 use crate::components::mixer::InputChannels;
+use crate::components::registry::SUBMIXER_TREE;
 use crate::components::submix::linking_tree::LinkingTree;
 use crate::components::submix::monitor_tree::MonitorTree;
-use crate::profile::Attribute;
+use crate::profile::Attributes;
 use anyhow::Result;
 use enum_map::EnumMap;
+use log::warn;
 use quick_xml::events::{BytesEnd, BytesStart, Event};
 use quick_xml::Writer;
 use std::collections::HashMap;
@@ -16,6 +18,9 @@ pub struct SubMixer {
     volume_table: EnumMap<InputChannels, u8>,
     monitor_tree: MonitorTree,
     linking_tree: LinkingTree,
+
+    // Attributes on the submixerTree which we don't handle..
+    extra_attributes: HashMap<String, String>,
 }
 
 impl Default for SubMixer {
@@ -31,10 +36,11 @@ impl SubMixer {
             volume_table: Default::default(),
             monitor_tree: Default::default(),
             linking_tree: Default::default(),
+            extra_attributes: Default::default(),
         }
     }
 
-    pub fn parse_submixer(&mut self, attributes: &Vec<Attribute>) -> Result<()> {
+    pub fn parse_submixer(&mut self, attributes: &Attributes) -> Result<()> {
         for attr in attributes {
             if attr.name == "submixMode" {
                 self.submix_enabled = matches!(attr.value.as_str(), "1");
@@ -60,16 +66,23 @@ impl SubMixer {
                 }
 
                 if !found {
-                    println!("Unable to find Channel: {channel}");
+                    warn!("Unable to find Channel: {channel}, preserving attribute");
+                    self.extra_attributes
+                        .insert(attr.name.clone(), attr.value.clone());
                 }
                 continue;
             }
+
+            // Newer firmware may add attributes we don't know about yet, keep them so they
+            // survive being written back..
+            self.extra_attributes
+                .insert(attr.name.clone(), attr.value.clone());
         }
         Ok(())
     }
 
     pub fn write_submixer<W: Write>(&self, writer: &mut Writer<W>) -> Result<()> {
-        let mut elem = BytesStart::new("submixerTree");
+        let mut elem = BytesStart::new(SUBMIXER_TREE);
 
         // Create the values..
         let mut attributes: HashMap<String, String> = HashMap::default();
@@ -85,6 +98,10 @@ impl SubMixer {
             attributes.insert(key, value);
         }
 
+        for (key, value) in &self.extra_attributes {
+            attributes.insert(key.clone(), value.clone());
+        }
+
         for (key, value) in &attributes {
             elem.push_attribute((key.as_str(), value.as_str()));
         }
@@ -96,16 +113,16 @@ impl SubMixer {
         self.linking_tree.write_linking_tree(writer)?;
 
         // We're done.
-        writer.write_event(Event::End(BytesEnd::new("submixerTree")))?;
+        writer.write_event(Event::End(BytesEnd::new(SUBMIXER_TREE)))?;
 
         Ok(())
     }
 
-    pub fn parse_monitor(&mut self, attributes: &Vec<Attribute>) -> Result<()> {
+    pub fn parse_monitor(&mut self, attributes: &Attributes) -> Result<()> {
         self.monitor_tree.parse_monitor_tree(attributes)
     }
 
-    pub fn parse_linking(&mut self, attributes: &Vec<Attribute>) -> Result<()> {
+    pub fn parse_linking(&mut self, attributes: &Attributes) -> Result<()> {
         self.linking_tree.parse_links(attributes)
     }
 
@@ -151,4 +168,17 @@ impl SubMixer {
     pub fn monitor_tree_mut(&mut self) -> &mut MonitorTree {
         &mut self.monitor_tree
     }
+
+    pub fn get_link_ratio(&self, channel: InputChannels) -> f64 {
+        self.linking_tree.get_ratio(channel)
+    }
+
+    /// Attributes from the submixerTree, monitorTree and linkingTree which aren't modelled
+    /// here, but will still be written back out.
+    pub fn extra_attributes(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.extra_attributes
+            .iter()
+            .chain(self.monitor_tree.extra_attributes())
+            .chain(self.linking_tree.extra_attributes())
+    }
 }
@@ -0,0 +1,477 @@
+use std::collections::HashMap;
+use std::fs;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{bail, Context, Result};
+use log::warn;
+
+use crate::profile::Profile;
+
+const EXTENSION: &str = "goxlr";
+
+// How often watch() checks the directory for changes..
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/**
+ * A profile found in a directory. The validity check only looks at the archive's directory and
+ * the root of profile.xml, so a valid entry can still fail to load, but anything marked invalid
+ * definitely won't.
+ */
+#[derive(Debug)]
+pub struct ProfileEntry {
+    pub name: String,
+    pub path: PathBuf,
+    pub modified: Option<SystemTime>,
+    pub valid: Result<(), String>,
+}
+
+/// Lists the profiles in a directory (not recursively), sorted by name. Where two files differ
+/// only by case (which can happen on case sensitive filesystems), only the first is returned.
+pub fn list_profiles(dir: &Path) -> Result<Vec<ProfileEntry>> {
+    let profiles = profile_files(dir)?
+        .into_iter()
+        .map(|(name, path)| ProfileEntry {
+            name,
+            modified: fs::metadata(&path).and_then(|meta| meta.modified()).ok(),
+            valid: check_profile(&path),
+            path,
+        })
+        .collect();
+    Ok(profiles)
+}
+
+/// The name and path of each profile in a directory, following the same rules as list_profiles
+/// but without opening any of the files.
+pub(crate) fn profile_files(dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let entries =
+        fs::read_dir(dir).with_context(|| format!("Unable to read directory {:?}", dir))?;
+
+    let mut profiles: Vec<(String, PathBuf)> = vec![];
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        if !path.is_file() || !has_profile_extension(&path) {
+            continue;
+        }
+
+        match path.file_stem().and_then(|stem| stem.to_str()) {
+            Some(name) => profiles.push((name.to_string(), path)),
+            None => warn!("Skipping profile with a non UTF-8 name: {:?}", path),
+        }
+    }
+
+    profiles.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut result: Vec<(String, PathBuf)> = vec![];
+    for (name, path) in profiles {
+        if let Some((_, existing)) = result
+            .iter()
+            .find(|(existing, _)| existing.eq_ignore_ascii_case(&name))
+        {
+            warn!(
+                "Ignoring {:?}, it only differs by case from {:?}",
+                path, existing
+            );
+            continue;
+        }
+        result.push((name, path));
+    }
+    Ok(result)
+}
+
+/// Returns the path a profile with this name would have in the directory. The name may or may
+/// not already have the extension, but anything which could escape the directory is rejected.
+pub fn profile_path(dir: &Path, name: &str) -> Result<PathBuf> {
+    if name.trim().is_empty() {
+        bail!("Profile name is empty");
+    }
+    if name.contains(['/', '\\', '\0']) {
+        bail!("Profile name {:?} contains a path separator", name);
+    }
+
+    // This catches '.', '..', and anything the platform treats as a prefix or root..
+    let mut components = Path::new(name).components();
+    if !matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    ) {
+        bail!("Profile name {:?} isn't a plain file name", name);
+    }
+
+    let file_name = if has_profile_extension(Path::new(name)) {
+        name.to_string()
+    } else {
+        format!("{name}.{EXTENSION}")
+    };
+    Ok(dir.join(file_name))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DirectoryEvent {
+    Created(String),
+    Modified(String),
+    Removed(String),
+}
+
+/// A running watch() on a directory, the watch stops when this is dropped.
+pub struct DirectoryWatcher {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl DirectoryWatcher {
+    /// Asks the watcher to stop, it'll finish after its current poll.
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// Stops the watcher, and waits for it to finish.
+    pub fn join(mut self) {
+        self.stop();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for DirectoryWatcher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/**
+ * Watches a directory for profiles being created, modified or removed by something other than
+ * us (the official app running under Wine, or files being copied in). This polls the directory
+ * rather than relying on platform notifications.
+ *
+ * A change is only reported once the file's size and modification time have been stable for a
+ * full poll, and the archive can be opened, so copying a profile in doesn't produce an event for
+ * a half written file.
+ */
+pub fn watch<F>(dir: &Path, callback: F) -> Result<DirectoryWatcher>
+where
+    F: FnMut(DirectoryEvent) + Send + 'static,
+{
+    watch_every(dir, POLL_INTERVAL, callback)
+}
+
+fn watch_every<F>(dir: &Path, interval: Duration, mut callback: F) -> Result<DirectoryWatcher>
+where
+    F: FnMut(DirectoryEvent) + Send + 'static,
+{
+    let dir = dir.to_path_buf();
+    let mut known = scan(&dir)?;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let stopping = stop.clone();
+    let handle = thread::spawn(move || {
+        let mut pending: HashMap<String, FileStamp> = HashMap::new();
+
+        while !stopping.load(Ordering::Relaxed) {
+            thread::sleep(interval);
+
+            let current = match scan(&dir) {
+                Ok(current) => current,
+                Err(e) => {
+                    warn!("Unable to check {:?} for changes: {}", dir, e);
+                    continue;
+                }
+            };
+
+            known.retain(|name, _| {
+                if !current.contains_key(name) {
+                    callback(DirectoryEvent::Removed(name.clone()));
+                    return false;
+                }
+                true
+            });
+            pending.retain(|name, _| current.contains_key(name));
+
+            for (name, stamp) in current {
+                if known.get(&name) == Some(&stamp) {
+                    pending.remove(&name);
+                    continue;
+                }
+
+                // Wait for it to stop changing, and for the archive to be readable..
+                if pending.get(&name) != Some(&stamp) || check_profile(&stamp.path).is_err() {
+                    pending.insert(name, stamp);
+                    continue;
+                }
+
+                pending.remove(&name);
+                let event = if known.contains_key(&name) {
+                    DirectoryEvent::Modified(name.clone())
+                } else {
+                    DirectoryEvent::Created(name.clone())
+                };
+                known.insert(name, stamp);
+                callback(event);
+            }
+        }
+    });
+
+    Ok(DirectoryWatcher {
+        stop,
+        handle: Some(handle),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FileStamp {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+fn scan(dir: &Path) -> Result<HashMap<String, FileStamp>> {
+    let entries =
+        fs::read_dir(dir).with_context(|| format!("Unable to read directory {:?}", dir))?;
+
+    let mut files = HashMap::new();
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        if !path.is_file() || !has_profile_extension(&path) {
+            continue;
+        }
+
+        let name = match path.file_stem().and_then(|stem| stem.to_str()) {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        let metadata = match entry.metadata() {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+
+        let stamp = FileStamp {
+            path,
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        };
+        files.insert(name, stamp);
+    }
+    Ok(files)
+}
+
+fn has_profile_extension(path: &Path) -> bool {
+    match path.extension() {
+        Some(extension) => extension.eq_ignore_ascii_case(EXTENSION),
+        None => false,
+    }
+}
+
+fn check_profile(path: &Path) -> Result<(), String> {
+    let file = File::open(path).map_err(|e| format!("Unable to open: {e}"))?;
+
+    if !Profile::is_valid_profile(BufReader::new(file)) {
+        return Err(String::from("Not a GoXLR profile"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+    use std::sync::mpsc;
+
+    use super::*;
+
+    const OFFICIAL: &[u8] = include_bytes!("../../defaults/resources/profiles/Default.goxlr");
+
+    fn archive_without_profile() -> Vec<u8> {
+        let mut archive = zip::ZipWriter::new(Cursor::new(vec![]));
+        archive
+            .start_file("scribble1.png", Default::default())
+            .unwrap();
+        archive.write_all(&[1, 2, 3]).unwrap();
+        archive.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn profiles_are_listed_with_their_validity() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("Stream.goxlr"), OFFICIAL).unwrap();
+        fs::write(dir.path().join("Backup.GOXLR"), OFFICIAL).unwrap();
+        fs::write(dir.path().join("Corrupt.goxlr"), b"not a zip").unwrap();
+        fs::write(dir.path().join("Empty.goxlr"), archive_without_profile()).unwrap();
+        fs::write(dir.path().join("notes.txt"), b"hello").unwrap();
+        fs::create_dir(dir.path().join("Folder.goxlr")).unwrap();
+
+        let profiles = list_profiles(dir.path()).unwrap();
+        let names: Vec<_> = profiles.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["Backup", "Corrupt", "Empty", "Stream"]);
+
+        let valid: Vec<_> = profiles.iter().map(|p| p.valid.is_ok()).collect();
+        assert_eq!(valid, [true, false, false, true]);
+        assert!(profiles.iter().all(|p| p.modified.is_some()));
+        assert_eq!(profiles[3].path, dir.path().join("Stream.goxlr"));
+    }
+
+    #[test]
+    fn names_differing_by_case_are_listed_once() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("Stream.goxlr"), OFFICIAL).unwrap();
+        fs::write(dir.path().join("stream.goxlr"), OFFICIAL).unwrap();
+
+        // On a case insensitive filesystem, the second write replaced the first..
+        let profiles = list_profiles(dir.path()).unwrap();
+        assert_eq!(profiles.len(), 1);
+        assert!(profiles[0].name.eq_ignore_ascii_case("stream"));
+    }
+
+    #[test]
+    fn missing_directories_are_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(list_profiles(&dir.path().join("missing")).is_err());
+
+        let loaded = Profile::load_dir(&dir.path().join("missing"));
+        assert_eq!(loaded.len(), 1);
+        assert!(loaded[0].1.is_err());
+    }
+
+    #[test]
+    fn loading_a_directory_follows_the_listing() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("Stream.goxlr"), OFFICIAL).unwrap();
+        fs::write(dir.path().join("Backup.goxlr"), OFFICIAL).unwrap();
+        fs::write(dir.path().join("Corrupt.goxlr"), b"not a zip").unwrap();
+        fs::write(dir.path().join("notes.txt"), b"hello").unwrap();
+
+        let listed: Vec<_> = list_profiles(dir.path())
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.path)
+            .collect();
+        let loaded = Profile::load_dir(dir.path());
+        let paths: Vec<_> = loaded.iter().map(|(path, _)| path.clone()).collect();
+        assert_eq!(paths, listed);
+
+        let ok: Vec<_> = loaded.iter().map(|(_, result)| result.is_ok()).collect();
+        assert_eq!(ok, [true, false, true]);
+    }
+
+    const INTERVAL: Duration = Duration::from_millis(20);
+
+    fn watch_events(dir: &Path) -> (DirectoryWatcher, mpsc::Receiver<DirectoryEvent>) {
+        let (sender, receiver) = mpsc::channel();
+        let watcher = watch_every(dir, INTERVAL, move |event| {
+            let _ = sender.send(event);
+        })
+        .unwrap();
+        (watcher, receiver)
+    }
+
+    fn next_event(receiver: &mpsc::Receiver<DirectoryEvent>) -> DirectoryEvent {
+        receiver.recv_timeout(Duration::from_secs(5)).unwrap()
+    }
+
+    fn assert_quiet(receiver: &mpsc::Receiver<DirectoryEvent>) {
+        thread::sleep(INTERVAL * 5);
+        assert_eq!(receiver.try_recv().ok(), None);
+    }
+
+    #[test]
+    fn watch_reports_created_modified_and_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let existing = dir.path().join("Existing.goxlr");
+        fs::write(&existing, OFFICIAL).unwrap();
+
+        // Profiles already in the directory aren't reported..
+        let (watcher, events) = watch_events(dir.path());
+        assert_quiet(&events);
+
+        let stream = dir.path().join("Stream.goxlr");
+        fs::write(&stream, OFFICIAL).unwrap();
+        assert_eq!(
+            next_event(&events),
+            DirectoryEvent::Created("Stream".to_string())
+        );
+
+        let mut file = fs::OpenOptions::new().append(true).open(&existing).unwrap();
+        file.write_all(&[0]).unwrap();
+        drop(file);
+        assert_eq!(
+            next_event(&events),
+            DirectoryEvent::Modified("Existing".to_string())
+        );
+
+        fs::remove_file(&stream).unwrap();
+        assert_eq!(
+            next_event(&events),
+            DirectoryEvent::Removed("Stream".to_string())
+        );
+
+        // Anything which isn't a profile is ignored..
+        fs::write(dir.path().join("notes.txt"), b"hello").unwrap();
+        assert_quiet(&events);
+        watcher.join();
+    }
+
+    #[test]
+    fn watch_waits_for_a_readable_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        let (watcher, events) = watch_events(dir.path());
+
+        // As if the copy was still in progress..
+        let path = dir.path().join("Copied.goxlr");
+        fs::write(&path, &OFFICIAL[..OFFICIAL.len() / 2]).unwrap();
+        assert_quiet(&events);
+
+        fs::write(&path, OFFICIAL).unwrap();
+        assert_eq!(
+            next_event(&events),
+            DirectoryEvent::Created("Copied".to_string())
+        );
+        watcher.join();
+    }
+
+    #[test]
+    fn watching_stops_when_joined() {
+        let dir = tempfile::tempdir().unwrap();
+        let (watcher, events) = watch_events(dir.path());
+        watcher.join();
+
+        // The callback (and its sender) is dropped along with the thread..
+        fs::write(dir.path().join("Late.goxlr"), OFFICIAL).unwrap();
+        assert_eq!(
+            events.recv_timeout(INTERVAL * 5),
+            Err(mpsc::RecvTimeoutError::Disconnected)
+        );
+    }
+
+    #[test]
+    fn profile_paths_stay_in_the_directory() {
+        let dir = Path::new("profiles");
+        assert_eq!(
+            profile_path(dir, "Stream").unwrap(),
+            dir.join("Stream.goxlr")
+        );
+        assert_eq!(
+            profile_path(dir, "Stream.GOXLR").unwrap(),
+            dir.join("Stream.GOXLR")
+        );
+        assert_eq!(profile_path(dir, "v1.2").unwrap(), dir.join("v1.2.goxlr"));
+
+        for name in [
+            "",
+            " ",
+            ".",
+            "..",
+            "../Stream",
+            "a/b",
+            "a\\b",
+            "/etc/passwd",
+            "a\0",
+        ] {
+            assert!(profile_path(dir, name).is_err(), "{name:?}");
+        }
+    }
+}
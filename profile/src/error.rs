@@ -1,5 +1,29 @@
+use std::path::PathBuf;
+
 #[derive(thiserror::Error, Debug)]
 pub enum ParseError {
+    #[error("Invalid value for '{attr}' in <{tag}>: {source}")]
+    Attribute {
+        tag: String,
+        attr: String,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    #[error("Invalid <{tag}>: {source}")]
+    Tag {
+        tag: String,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    #[error("{name} exceeds the maximum allowed size ({limit})")]
+    LimitExceeded { name: String, limit: u64 },
+
+    #[error("Unsafe document: {0}")]
+    UnsafeDocument(String),
+
+    #[error("Malformed XML at position {position}: {message}")]
+    Malformed { position: usize, message: String },
+
     #[error("Expected int: {0}")]
     ExpectedInt(#[from] std::num::ParseIntError),
 
@@ -63,6 +87,9 @@ pub enum ParseError {
     #[error("Invalid scribble: {0}")]
     InvalidScribble(#[from] crate::components::scribble::ParseError),
 
+    #[error("Invalid shutdown behaviour: {0}")]
+    InvalidShutdown(#[from] crate::components::shutdown::ParseError),
+
     #[error("Invalid simple: {0}")]
     InvalidSimple(#[from] crate::components::simple::ParseError),
 
@@ -101,3 +128,9 @@ pub enum SaveError {
     #[error("Profile zip error: {0}")]
     ZipError(#[from] zip::result::ZipError),
 }
+
+#[derive(thiserror::Error, Debug)]
+pub enum SampleError {
+    #[error("Sample '{track}' not found, tried: {tried:?}")]
+    NotFound { track: String, tried: Vec<PathBuf> },
+}
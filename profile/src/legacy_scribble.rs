@@ -0,0 +1,96 @@
+use std::io::Cursor;
+
+use anyhow::Result;
+use image::{DynamicImage, GrayImage, ImageOutputFormat, Luma};
+use log::warn;
+
+use crate::profile::PNG_SIGNATURE;
+
+const WIDTH: usize = 128;
+const HEIGHT: usize = 64;
+const RAW_BITMAP_SIZE: usize = WIDTH * HEIGHT / 8;
+
+/**
+ * Scribble images are expected to be PNG files, but some older profiles have the image stored
+ * in the format the hardware takes directly. When loading, anything which isn't a PNG is passed
+ * through here, and if we recognise the format it's converted so the rest of the utility only
+ * ever has to deal with PNGs.
+ *
+ * Supported legacy formats:
+ *  - Raw device bitmaps: exactly 1024 bytes, a 128x64 monochrome image stored as 8 pages of 128
+ *    columns, where each byte holds 8 vertical pixels (least significant bit at the top) and a
+ *    set bit is a lit pixel.
+ *
+ * Anything else is logged, and kept exactly as it was read so it's not lost on save.
+ */
+pub(crate) fn convert_scribble(filename: &str, bytes: Vec<u8>) -> Vec<u8> {
+    if bytes.is_empty() || bytes.starts_with(PNG_SIGNATURE) {
+        return bytes;
+    }
+
+    if bytes.len() == RAW_BITMAP_SIZE {
+        warn!("{} is a raw device bitmap, converting to PNG..", filename);
+        return match raw_bitmap_to_png(&bytes) {
+            Ok(png) => png,
+            Err(error) => {
+                warn!("Unable to convert {}, keeping as is: {}", filename, error);
+                bytes
+            }
+        };
+    }
+
+    warn!(
+        "{} isn't a PNG, or a recognised legacy format, keeping as is",
+        filename
+    );
+    bytes
+}
+
+fn raw_bitmap_to_png(bitmap: &[u8]) -> Result<Vec<u8>> {
+    let image = GrayImage::from_fn(WIDTH as u32, HEIGHT as u32, |x, y| {
+        let (x, y) = (x as usize, y as usize);
+        if bitmap[(WIDTH * (y / 8)) + x] & (1 << (y % 8)) != 0 {
+            Luma([255])
+        } else {
+            Luma([0])
+        }
+    });
+
+    let mut output = Cursor::new(Vec::new());
+    DynamicImage::ImageLuma8(image).write_to(&mut output, ImageOutputFormat::Png)?;
+    Ok(output.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_bitmaps_decode_to_the_same_image() {
+        // Light the top left pixel, the bottom right pixel, and a full column in the middle..
+        let mut bitmap = vec![0; RAW_BITMAP_SIZE];
+        bitmap[0] = 0x01;
+        bitmap[RAW_BITMAP_SIZE - 1] = 0x80;
+        for page in 0..HEIGHT / 8 {
+            bitmap[(WIDTH * page) + 64] = 0xff;
+        }
+
+        let png = convert_scribble("scribble1.png", bitmap);
+        assert!(png.starts_with(PNG_SIGNATURE));
+
+        let image = image::load_from_memory(&png).unwrap().to_luma8();
+        assert_eq!(image.dimensions(), (WIDTH as u32, HEIGHT as u32));
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let lit = (x, y) == (0, 0) || (x, y) == (127, 63) || x == 64;
+            assert_eq!(pixel[0] == 255, lit, "({x}, {y})");
+        }
+    }
+
+    #[test]
+    fn other_data_is_kept_as_is() {
+        let png = [PNG_SIGNATURE, &[1, 2, 3]].concat();
+        assert_eq!(convert_scribble("scribble1.png", png.clone()), png);
+        assert_eq!(convert_scribble("scribble1.png", vec![1, 2, 3]), [1, 2, 3]);
+        assert!(convert_scribble("scribble1.png", vec![]).is_empty());
+    }
+}
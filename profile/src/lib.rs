@@ -1,11 +1,18 @@
 use enum_map::Enum;
-use strum::{Display, EnumIter, EnumProperty};
+use strum::{Display, EnumIter, EnumProperty, IntoEnumIterator};
 
+pub mod builder;
+pub mod changes;
 pub mod components;
+pub mod directory;
 pub mod error;
+mod legacy_scribble;
 pub mod mic_profile;
 pub mod microphone;
+pub mod mute_snapshot;
 pub mod profile;
+pub mod quirks;
+pub mod theme;
 
 #[derive(Debug, Display, Enum, EnumIter, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum SampleButtons {
@@ -16,7 +23,7 @@ pub enum SampleButtons {
     Clear,
 }
 
-#[derive(Debug, EnumIter, Enum, EnumProperty, Copy, Clone)]
+#[derive(Debug, EnumIter, Enum, EnumProperty, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Preset {
     #[strum(props(tagSuffix = "preset1", contextTitle = "effects1"))]
     #[strum(to_string = "PRESET_1")]
@@ -50,3 +57,84 @@ pub enum Faders {
     C,
     D,
 }
+
+/**
+ * Mute buttons, faders and scribbles all exist once per fader in the profile, but they don't agree
+ * on how to number them. FaderMeter elements count from 0 (FaderMeter0 - FaderMeter3), while mute
+ * and scribble elements count from 1 (mute1 - mute4, scribble1 - scribble4). All conversions
+ * between tag names and Faders should go through here, rather than doing the maths inline.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum FaderElement {
+    FaderMeter,
+    Mute,
+    Scribble,
+}
+
+impl FaderElement {
+    fn prefix(&self) -> &'static str {
+        match self {
+            FaderElement::FaderMeter => "FaderMeter",
+            FaderElement::Mute => "mute",
+            FaderElement::Scribble => "scribble",
+        }
+    }
+
+    fn base(&self) -> u8 {
+        match self {
+            FaderElement::FaderMeter => 0,
+            FaderElement::Mute | FaderElement::Scribble => 1,
+        }
+    }
+
+    /// The numeric ID used for this fader in the XML
+    pub(crate) fn id(&self, fader: Faders) -> u8 {
+        fader as u8 + self.base()
+    }
+
+    pub(crate) fn tag_name(&self, fader: Faders) -> String {
+        format!("{}{}", self.prefix(), self.id(fader))
+    }
+
+    /// Returns the fader for a tag name, or None if the tag isn't one of ours (for example,
+    /// 'muteChat'), or the ID is out of range.
+    pub(crate) fn fader(&self, tag_name: &str) -> Option<Faders> {
+        let id: u8 = tag_name.strip_prefix(self.prefix())?.parse().ok()?;
+        let index = id.checked_sub(self.base())?;
+        Faders::iter().nth(index.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fader_element_tags_match_the_profile_format() {
+        let expected = [
+            (Faders::A, "FaderMeter0", "mute1", "scribble1"),
+            (Faders::B, "FaderMeter1", "mute2", "scribble2"),
+            (Faders::C, "FaderMeter2", "mute3", "scribble3"),
+            (Faders::D, "FaderMeter3", "mute4", "scribble4"),
+        ];
+
+        for (fader, meter, mute, scribble) in expected {
+            assert_eq!(FaderElement::FaderMeter.tag_name(fader), meter);
+            assert_eq!(FaderElement::Mute.tag_name(fader), mute);
+            assert_eq!(FaderElement::Scribble.tag_name(fader), scribble);
+
+            assert_eq!(FaderElement::FaderMeter.fader(meter), Some(fader));
+            assert_eq!(FaderElement::Mute.fader(mute), Some(fader));
+            assert_eq!(FaderElement::Scribble.fader(scribble), Some(fader));
+        }
+    }
+
+    #[test]
+    fn fader_element_rejects_other_tags() {
+        assert_eq!(FaderElement::Mute.fader("muteChat"), None);
+        assert_eq!(FaderElement::Mute.fader("mute0"), None);
+        assert_eq!(FaderElement::Mute.fader("mute5"), None);
+        assert_eq!(FaderElement::FaderMeter.fader("FaderMeter4"), None);
+        assert_eq!(FaderElement::Scribble.fader("mute1"), None);
+    }
+}
@@ -4,7 +4,7 @@ use crate::microphone::equalizer_mini::EqualizerMini;
 use crate::microphone::gate::Gate;
 use crate::microphone::mic_setup::MicSetup;
 use crate::microphone::ui_setup::UiSetup;
-use crate::profile::wrap_start_event;
+use crate::profile::{parse_with_context, wrap_start_event};
 use anyhow::{anyhow, bail, Result};
 use log::debug;
 use quick_xml::events::{BytesDecl, BytesEnd, BytesStart, Event};
@@ -55,10 +55,12 @@ impl MicProfileSettings {
                     if name == "dspTreeMicProfile" {
                         // Ok, this is an incredibly large tag, with many settings (30 or so), so
                         // we split it into 3 separate elements.
-                        equalizer.parse_equaliser(&attributes)?;
-                        equalizer_mini.parse_equaliser(&attributes)?;
-                        compressor.parse_compressor(&attributes)?;
-                        gate.parse_gate(&attributes)?;
+                        parse_with_context(&name, &attributes, |a| equalizer.parse_equaliser(a))?;
+                        parse_with_context(&name, &attributes, |a| {
+                            equalizer_mini.parse_equaliser(a)
+                        })?;
+                        parse_with_context(&name, &attributes, |a| compressor.parse_compressor(a))?;
+                        parse_with_context(&name, &attributes, |a| gate.parse_gate(a))?;
 
                         // Before we're done here, there's a single attribute that doesn't fit into
                         // any of the above categories, find it and handle it here..
@@ -85,12 +87,12 @@ impl MicProfileSettings {
                     }
 
                     if name == "setupTreeMicProfile" {
-                        mic_setup.parse_config(&attributes)?;
+                        parse_with_context(&name, &attributes, |a| mic_setup.parse_config(a))?;
                         continue;
                     }
 
                     if name == "micProfileUIMicProfile" {
-                        ui_setup.parse_ui(&attributes)?;
+                        parse_with_context(&name, &attributes, |a| ui_setup.parse_ui(a))?;
                         continue;
                     }
 
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use std::os::raw::c_float;
 
-use crate::profile::Attribute;
+use crate::profile::Attributes;
 use anyhow::{anyhow, Result};
 
 #[derive(thiserror::Error, Debug)]
@@ -43,7 +43,7 @@ impl Compressor {
         }
     }
 
-    pub fn parse_compressor(&mut self, attributes: &Vec<Attribute>) -> Result<(), ParseError> {
+    pub fn parse_compressor(&mut self, attributes: &Attributes) -> Result<(), ParseError> {
         for attr in attributes {
             if attr.name == "MIC_COMP_THRESHOLD" {
                 self.set_threshold(attr.value.parse::<c_float>()? as i8)?;
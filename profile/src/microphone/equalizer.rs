@@ -2,7 +2,7 @@ use std::collections::HashMap;
 use std::os::raw::c_float;
 use std::str::FromStr;
 
-use crate::profile::Attribute;
+use crate::profile::Attributes;
 use anyhow::{anyhow, bail, Result};
 
 #[derive(thiserror::Error, Debug)]
@@ -77,7 +77,7 @@ impl Equalizer {
         }
     }
 
-    pub fn parse_equaliser(&mut self, attributes: &Vec<Attribute>) -> Result<(), ParseError> {
+    pub fn parse_equaliser(&mut self, attributes: &Attributes) -> Result<(), ParseError> {
         for attr in attributes {
             if attr.name == "MIC_EQ_31.5HZ_GAIN" {
                 self.set_eq_31h_gain(attr.value.parse::<c_float>()? as i8)?;
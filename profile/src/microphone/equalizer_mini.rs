@@ -1,5 +1,5 @@
 use crate::microphone::equalizer::validate_gain;
-use crate::profile::Attribute;
+use crate::profile::Attributes;
 use anyhow::{anyhow, Result};
 use std::collections::HashMap;
 use std::os::raw::c_float;
@@ -63,7 +63,7 @@ impl EqualizerMini {
     }
 
     // TODO: These may not need to be handled as floats..
-    pub fn parse_equaliser(&mut self, attributes: &Vec<Attribute>) -> Result<(), ParseError> {
+    pub fn parse_equaliser(&mut self, attributes: &Attributes) -> Result<(), ParseError> {
         for attr in attributes {
             if attr.name == "MIC_MINI_EQ_90HZ_GAIN" {
                 self.set_eq_90h_gain(attr.value.parse::<c_float>()? as i8)?;
@@ -1,4 +1,4 @@
-use crate::profile::Attribute;
+use crate::profile::Attributes;
 use anyhow::{anyhow, Result};
 use std::collections::HashMap;
 use std::os::raw::c_float;
@@ -44,7 +44,7 @@ impl Gate {
         }
     }
 
-    pub fn parse_gate(&mut self, attributes: &Vec<Attribute>) -> Result<(), ParseError> {
+    pub fn parse_gate(&mut self, attributes: &Attributes) -> Result<(), ParseError> {
         for attr in attributes {
             if attr.name == "MIC_GATE_MACRO_AMOUNT" {
                 self.amount = attr.value.parse::<c_float>()? as u8;
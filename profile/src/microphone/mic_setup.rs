@@ -1,4 +1,4 @@
-use crate::profile::Attribute;
+use crate::profile::Attributes;
 use anyhow::{anyhow, Result};
 use quick_xml::events::{BytesStart, Event};
 use quick_xml::Writer;
@@ -45,7 +45,7 @@ impl MicSetup {
         }
     }
 
-    pub fn parse_config(&mut self, attributes: &Vec<Attribute>) -> Result<(), ParseError> {
+    pub fn parse_config(&mut self, attributes: &Attributes) -> Result<(), ParseError> {
         for attr in attributes {
             if attr.name == "MIC_TYPE" {
                 self.set_mic_type(attr.value.parse::<c_float>()? as u8)?;
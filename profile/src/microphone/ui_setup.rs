@@ -1,4 +1,4 @@
-use crate::profile::Attribute;
+use crate::profile::Attributes;
 use anyhow::Result;
 use quick_xml::events::{BytesStart, Event};
 use quick_xml::Writer;
@@ -44,7 +44,7 @@ impl UiSetup {
         }
     }
 
-    pub fn parse_ui(&mut self, attributes: &Vec<Attribute>) -> Result<(), ParseError> {
+    pub fn parse_ui(&mut self, attributes: &Attributes) -> Result<(), ParseError> {
         for attr in attributes {
             if attr.name == "eqAdvanced" {
                 self.eq_advanced = matches!(attr.value.as_str(), "1");
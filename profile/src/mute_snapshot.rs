@@ -0,0 +1,179 @@
+use std::io::{BufReader, Read, Write};
+
+use anyhow::{anyhow, bail, Result};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::{Reader, Writer};
+use strum::{EnumProperty, IntoEnumIterator};
+
+use crate::components::mixer::FullChannelList;
+use crate::components::mute::MuteFunction;
+use crate::profile::{wrap_start_event, Attribute, DocumentGuard, LoadOptions};
+use crate::{FaderElement, Faders};
+
+const SNAPSHOT: &str = "muteAllSnapshot";
+const CHAT: &str = "muteChat";
+
+/**
+ * What ProfileSettings::mute_all changed, so ProfileSettings::restore can put it back. Anything
+ * which was already muted isn't changed by mute_all, so isn't recorded here.
+ *
+ * The snapshot can be written out and loaded back in (as a small XML document) so a mute can be
+ * undone after a restart. Each entry records the button's generation after it was muted, which is
+ * how restore spots buttons that have been changed since, within the same session. Generations
+ * don't survive a reload, so for a snapshot from a previous session restore instead checks that
+ * the button is still in the state mute_all left it in.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MuteAllSnapshot {
+    pub(crate) instance: u64,
+    pub(crate) faders: Vec<FaderMuteSnapshot>,
+    pub(crate) chat: Option<ChatMuteSnapshot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FaderMuteSnapshot {
+    pub fader: Faders,
+    pub generation: u64,
+    pub channel: FullChannelList,
+
+    // The button's settings, and the channel's volume, from before it was muted..
+    pub function: MuteFunction,
+    pub previous_volume: u8,
+    pub volume: u8,
+
+    // The function mute_all muted it with..
+    pub applied: MuteFunction,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatMuteSnapshot {
+    pub generation: u64,
+    pub function: MuteFunction,
+    pub applied: MuteFunction,
+}
+
+impl MuteAllSnapshot {
+    pub fn faders(&self) -> &[FaderMuteSnapshot] {
+        &self.faders
+    }
+
+    pub fn chat(&self) -> Option<&ChatMuteSnapshot> {
+        self.chat.as_ref()
+    }
+
+    /// True if mute_all didn't need to change anything..
+    pub fn is_empty(&self) -> bool {
+        self.faders.is_empty() && self.chat.is_none()
+    }
+
+    pub fn write_to<W: Write>(&self, sink: W) -> Result<()> {
+        let mut writer = Writer::new_with_indent(sink, b' ', 2);
+
+        let mut root = BytesStart::new(SNAPSHOT);
+        root.push_attribute(("instance", self.instance.to_string().as_str()));
+        writer.write_event(Event::Start(root.clone()))?;
+
+        for entry in &self.faders {
+            let mut elem = BytesStart::new(FaderElement::Mute.tag_name(entry.fader));
+            elem.push_attribute(("generation", entry.generation.to_string().as_str()));
+            elem.push_attribute(("channel", format!("{:?}", entry.channel).as_str()));
+            elem.push_attribute(("function", function_value(entry.function)));
+            elem.push_attribute(("prevLevel", entry.previous_volume.to_string().as_str()));
+            elem.push_attribute(("volume", entry.volume.to_string().as_str()));
+            elem.push_attribute(("applied", function_value(entry.applied)));
+            writer.write_event(Event::Empty(elem))?;
+        }
+
+        if let Some(chat) = &self.chat {
+            let mut elem = BytesStart::new(CHAT);
+            elem.push_attribute(("generation", chat.generation.to_string().as_str()));
+            elem.push_attribute(("function", function_value(chat.function)));
+            elem.push_attribute(("applied", function_value(chat.applied)));
+            writer.write_event(Event::Empty(elem))?;
+        }
+
+        writer.write_event(Event::End(root.to_end()))?;
+        Ok(())
+    }
+
+    pub fn load<R: Read>(read: R) -> Result<Self> {
+        let mut reader = Reader::from_reader(BufReader::new(read));
+        let mut guard = DocumentGuard::new(&LoadOptions::default());
+        let mut buf = Vec::new();
+
+        let mut snapshot: Option<MuteAllSnapshot> = None;
+        loop {
+            buf.clear();
+            let event = reader.read_event_into(&mut buf)?;
+            guard.check(&event)?;
+            let (name, attributes) = match &event {
+                Event::Start(e) | Event::Empty(e) => wrap_start_event(e)?,
+                Event::Eof => break,
+                _ => continue,
+            };
+
+            if name == SNAPSHOT {
+                snapshot = Some(MuteAllSnapshot {
+                    instance: attribute(&attributes, "instance")?.parse()?,
+                    faders: vec![],
+                    chat: None,
+                });
+                continue;
+            }
+
+            let current = match &mut snapshot {
+                Some(snapshot) => snapshot,
+                None => bail!("Expected {}, found {}", SNAPSHOT, name),
+            };
+
+            if name == CHAT {
+                current.chat = Some(ChatMuteSnapshot {
+                    generation: attribute(&attributes, "generation")?.parse()?,
+                    function: parse_function(attribute(&attributes, "function")?)?,
+                    applied: parse_function(attribute(&attributes, "applied")?)?,
+                });
+                continue;
+            }
+
+            let fader = match FaderElement::Mute.fader(&name) {
+                Some(fader) => fader,
+                None => bail!("Unexpected element in mute snapshot: {}", name),
+            };
+
+            let channel = attribute(&attributes, "channel")?;
+            let channel = FullChannelList::iter()
+                .find(|c| format!("{c:?}") == channel)
+                .ok_or_else(|| anyhow!("Unknown channel {}", channel))?;
+
+            current.faders.push(FaderMuteSnapshot {
+                fader,
+                generation: attribute(&attributes, "generation")?.parse()?,
+                channel,
+                function: parse_function(attribute(&attributes, "function")?)?,
+                previous_volume: attribute(&attributes, "prevLevel")?.parse()?,
+                volume: attribute(&attributes, "volume")?.parse()?,
+                applied: parse_function(attribute(&attributes, "applied")?)?,
+            });
+        }
+
+        snapshot.ok_or_else(|| anyhow!("No {} found", SNAPSHOT))
+    }
+}
+
+fn attribute<'a>(attributes: &'a [Attribute], name: &str) -> Result<&'a str> {
+    attributes
+        .iter()
+        .find(|a| a.name == name)
+        .map(|a| a.value.as_str())
+        .ok_or_else(|| anyhow!("Missing attribute {}", name))
+}
+
+fn function_value(function: MuteFunction) -> &'static str {
+    function.get_str("Value").unwrap()
+}
+
+fn parse_function(value: &str) -> Result<MuteFunction> {
+    MuteFunction::iter()
+        .find(|function| function_value(*function) == value)
+        .ok_or_else(|| anyhow!("Unknown mute function {}", value))
+}
@@ -1,126 +1,715 @@
+use std::cell::Cell;
+use std::collections::HashMap;
 use std::fs;
 use std::fs::File;
-use std::io::{BufReader, Read, Write};
-use std::path::Path;
+use std::io::{BufReader, Read, Seek, Write};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::Duration;
 
 use anyhow::{anyhow, bail, Context as ErrorContext, Result};
 use enum_map::EnumMap;
+use goxlr_types::ramp::ramp_steps;
 use log::{debug, warn};
 use quick_xml::events::{BytesDecl, BytesStart, Event};
 use quick_xml::{Reader, Writer};
 use strum::EnumProperty;
 use strum::IntoEnumIterator;
+use zip::read::ZipFile;
 use zip::write::FileOptions;
+use zip::{CompressionMethod, DateTime};
 
+use crate::changes::{ChangeEvent, ChangeHandler, ChangeSection};
 use crate::components::animation::AnimationTree;
 use crate::components::browser::BrowserPreviewTree;
+use crate::components::colours::{Colour, ColourMap, LightingTarget};
 use crate::components::context::Context;
 use crate::components::echo::EchoEncoderBase;
+use crate::components::effect_base::{
+    next_style, EffectBase, EffectType, EncoderBase, EncoderPressAction, EncoderPressOutcome,
+    EncoderType,
+};
 use crate::components::effects::Effects;
 use crate::components::fader::Fader;
 use crate::components::gender::GenderEncoderBase;
 use crate::components::hardtune::HardtuneEffectBase;
 use crate::components::megaphone::MegaphoneEffectBase;
-use crate::components::mixer::{InputChannels, Mixers, OutputChannels};
-use crate::components::mute::MuteButton;
+use crate::components::mixer::{FullChannelList, InputChannels, Mixers, OutputChannels};
+use crate::components::mute::{MuteAction, MuteButton, MuteFunction};
 use crate::components::mute_chat::MuteChat;
 use crate::components::pitch::PitchEncoderBase;
 use crate::components::preset_writer::PresetWriter;
+use crate::components::registry::{
+    ComponentKind, ANIMATION_TREE, BROWSER_PREVIEW_TREE, MUTE_CHAT, SAMPLER_ROOT, SELECTED_CONTEXT,
+};
 use crate::components::reverb::ReverbEncoderBase;
 use crate::components::robot::RobotEffectBase;
 use crate::components::root::RootElement;
-use crate::components::sample::SampleBase;
+use crate::components::sample::{SampleBase, Track};
 use crate::components::scribble::Scribble;
-use crate::components::simple::{SimpleElement, SimpleElements};
+use crate::components::shutdown::{ShutdownAction, ShutdownBehaviour};
+use crate::components::simple::{BleepBehaviour, SimpleElement, SimpleElements};
 use crate::components::submix::mix_routing_tree::{Mix, MixRoutingTree};
 use crate::components::submix::submixer::SubMixer;
-use crate::SampleButtons::{BottomLeft, BottomRight, Clear, TopLeft, TopRight};
-use crate::{Faders, Preset, SampleButtons};
+use crate::directory;
+use crate::error::{ParseError, SampleError};
+use crate::legacy_scribble::convert_scribble;
+use crate::mute_snapshot::{ChatMuteSnapshot, FaderMuteSnapshot, MuteAllSnapshot};
+use crate::quirks;
+use crate::theme::Theme;
+use crate::{FaderElement, Faders, Preset, SampleButtons};
 
 #[derive(Debug)]
 pub struct Profile {
     settings: ProfileSettings,
     scribbles: [Vec<u8>; 4],
+
+    // How each file was stored in the original archive, so we can write them back the same way
+    entries: HashMap<String, ArchiveEntry>,
+
+    // Files we don't know about, but which need to be preserved when saving
+    extra_files: Vec<(String, Vec<u8>)>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone)]
+struct ArchiveEntry {
+    compression: CompressionMethod,
+    last_modified: DateTime,
+}
+
+impl ArchiveEntry {
+    fn from_file(file: &ZipFile) -> Self {
+        // We can only write back methods which have been compiled in, anything else (bzip2 /
+        // zstd etc) will be deflated instead.
+        let compression = match file.compression() {
+            CompressionMethod::Stored => CompressionMethod::Stored,
+            _ => CompressionMethod::Deflated,
+        };
+
+        Self {
+            compression,
+            last_modified: file.last_modified(),
+        }
+    }
+
+    fn options(&self) -> FileOptions {
+        FileOptions::default()
+            .compression_method(self.compression)
+            .last_modified_time(self.last_modified)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Attribute {
     pub(crate) name: String,
     pub(crate) value: String,
 }
 
+/**
+ * The attributes of a single element, as handed to the component parsers. Iterating over these
+ * keeps track of the attribute currently being parsed, so if a parser fails part way through we
+ * know which attribute it choked on without having to parse the element a second time.
+ */
+#[derive(Debug, Default)]
+pub struct Attributes<'a> {
+    attributes: &'a [Attribute],
+    current: Cell<Option<usize>>,
+}
+
+impl<'a> Attributes<'a> {
+    pub fn new(attributes: &'a [Attribute]) -> Self {
+        Self {
+            attributes,
+            current: Cell::new(None),
+        }
+    }
+
+    pub fn iter(&self) -> AttributeIter<'a, '_> {
+        AttributeIter {
+            attributes: self,
+            index: 0,
+        }
+    }
+
+    /// The attribute handed out by the last iteration, this is cleared once an iteration has
+    /// been exhausted, so errors raised after the parse loop aren't blamed on the last attribute.
+    pub fn current(&self) -> Option<&'a Attribute> {
+        self.current.get().map(|index| &self.attributes[index])
+    }
+}
+
+pub struct AttributeIter<'a, 'b> {
+    attributes: &'b Attributes<'a>,
+    index: usize,
+}
+
+impl<'a> Iterator for AttributeIter<'a, '_> {
+    type Item = &'a Attribute;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let attribute = self.attributes.attributes.get(self.index);
+        self.attributes.current.set(attribute.map(|_| self.index));
+        self.index += 1;
+        attribute
+    }
+}
+
+impl<'a, 'b> IntoIterator for &'b Attributes<'a> {
+    type Item = &'a Attribute;
+    type IntoIter = AttributeIter<'a, 'b>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Leniency {
+    Strict,
+    Lenient,
+}
+
+/**
+ * Profiles are often shared around and loaded without much thought, so when reading an archive we
+ * don't trust the sizes it declares. These are the upper limits for what we're willing to read,
+ * the defaults are several times larger than anything the official app produces.
+ */
+#[derive(Debug, Clone)]
+pub struct LoadOptions {
+    pub max_entries: usize,
+    pub max_profile_size: u64,
+    pub max_scribble_size: u64,
+    pub max_extra_file_size: u64,
+
+    // Limits applied while parsing the XML itself
+    pub max_depth: usize,
+    pub max_attribute_length: usize,
+
+    // Don't read the scribble images at all (they'll be empty on the loaded profile)
+    pub skip_scribbles: bool,
+
+    // Recreate any missing effect banks, faders, mute buttons or scribbles after loading
+    pub repair: bool,
+    // Skip over elements (and XML errors) which can't be parsed rather than failing the load,
+    // anything skipped is available from ProfileSettings::load_errors
+    pub lenient: bool,
+    // Overrides 'lenient' for individual components, anything not listed here (along with XML
+    // errors, which don't belong to a component) uses 'lenient'
+    pub component_leniency: HashMap<ComponentId, Leniency>,
+
+    // What to do with a scribble image which has no matching scribble element
+    pub orphaned_scribbles: OrphanedScribbles,
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        Self {
+            max_entries: 64,
+            max_profile_size: 16 * 1024 * 1024,
+            max_scribble_size: 4 * 1024 * 1024,
+            max_extra_file_size: 16 * 1024 * 1024,
+            max_depth: 32,
+            max_attribute_length: 64 * 1024,
+            skip_scribbles: false,
+            repair: false,
+            lenient: false,
+            component_leniency: HashMap::new(),
+            orphaned_scribbles: OrphanedScribbles::default(),
+        }
+    }
+}
+
+impl LoadOptions {
+    /// Limits for profile bundles (see Profile::export_bundle), which carry every sample the
+    /// profile uses, so need far more (and far larger) files than a regular profile.
+    pub fn bundle() -> Self {
+        Self {
+            max_entries: 1024,
+            max_extra_file_size: 512 * 1024 * 1024,
+            ..Default::default()
+        }
+    }
+
+    /// Whether an error in this component (or outside of any component, for None) can be skipped
+    pub fn is_lenient(&self, component: Option<ComponentId>) -> bool {
+        let leniency = component.and_then(|c| self.component_leniency.get(&c));
+        match leniency {
+            Some(leniency) => *leniency == Leniency::Lenient,
+            None => self.lenient,
+        }
+    }
+}
+
+/**
+ * A scribble is made up of two parts, the scribbleN element in profile.xml and the scribbleN.png
+ * image in the archive, and some tools strip one without the other. A scribble element with no
+ * image is always fine, the image is regenerated from the element's settings, so this only
+ * decides what happens to an image that nothing refers to.
+ */
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum OrphanedScribbles {
+    // Log it and leave the image alone, it'll still be written back out on save..
+    #[default]
+    Warn,
+
+    // Create a default scribble element pointing at the image, so it gets displayed..
+    CreateMetadata,
+}
+
+/**
+ * The official app is fussy about the files it imports, it expects a UTF-8 BOM, CRLF line endings
+ * and a fixed attribute order on the root and mixerTree elements, and will occasionally refuse a
+ * profile without them. Setting official_app_compat writes profiles in that form.
+ */
+#[derive(Debug, Default, Copy, Clone)]
+pub struct WriteOptions {
+    pub official_app_compat: bool,
+
+    // When saving, write straight to the target rather than writing to a temporary file and
+    // renaming it into place, only useful on filesystems where rename isn't atomic.
+    pub direct_write: bool,
+
+    // Leave out effect, encoder and sampler attributes which are at their default values, the
+    // profile will load back identically, but the official app may not accept it.
+    pub minimal: bool,
+}
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+
+// How often a volume fade should update the device..
+const FADE_STEP_INTERVAL: Duration = Duration::from_millis(20);
+
+// Converts the LF line endings produced by quick-xml into CRLF..
+struct CrlfWriter<W: Write>(W);
+
+impl<W: Write> Write for CrlfWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut lines = buf.split(|b| *b == b'\n');
+        if let Some(first) = lines.next() {
+            self.0.write_all(first)?;
+        }
+        for line in lines {
+            self.0.write_all(b"\r\n")?;
+            self.0.write_all(line)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
 impl Profile {
     pub fn load<R: Read + std::io::Seek>(read: R) -> Result<Self> {
+        Profile::load_with_options(read, &LoadOptions::default())
+    }
+
+    pub fn load_with_options<R: Read + std::io::Seek>(
+        read: R,
+        options: &LoadOptions,
+    ) -> Result<Self> {
         debug!("Loading Profile Archive..");
 
         let mut archive = zip::ZipArchive::new(read)?;
+        if archive.len() > options.max_entries {
+            return Err(ParseError::LimitExceeded {
+                name: String::from("archive entries"),
+                limit: options.max_entries as u64,
+            }
+            .into());
+        }
 
         let mut scribbles: [Vec<u8>; 4] = Default::default();
 
         // Load the scribbles if they exist, store them in memory for later fuckery.
-        for (i, scribble) in scribbles.iter_mut().enumerate() {
-            let filename = format!("scribble{}.png", i + 1);
-            if let Ok(mut file) = archive.by_name(filename.as_str()) {
-                *scribble = vec![0; file.size() as usize];
-                file.read_exact(scribble)?;
+        if !options.skip_scribbles {
+            for (i, scribble) in scribbles.iter_mut().enumerate() {
+                let filename = format!("scribble{}.png", i + 1);
+                if let Ok(file) = archive.by_name(filename.as_str()) {
+                    let contents = read_limited(file, &filename, options.max_scribble_size)?;
+                    *scribble = convert_scribble(&filename, contents);
+                }
+            }
+        }
+
+        // Record how everything was stored, and grab anything we don't directly handle..
+        let mut entries = HashMap::new();
+        let mut extra_files = Vec::new();
+        for index in 0..archive.len() {
+            let mut file = archive.by_index(index)?;
+            if !file.is_file() {
+                continue;
+            }
+
+            let name = file.name().to_string();
+            entries.insert(name.clone(), ArchiveEntry::from_file(&file));
+
+            if !Profile::is_known_file(&name) {
+                debug!("Preserving Unknown File: {}", name);
+                let contents = read_limited(&mut file, &name, options.max_extra_file_size)?;
+                extra_files.push((name, contents));
             }
         }
 
         debug!("Attempting to read profile.xml..");
-        let result = ProfileSettings::load(archive.by_name("profile.xml")?);
+        let file = archive.by_name("profile.xml")?;
+        let profile = read_limited(file, "profile.xml", options.max_profile_size)?;
+        let result = ProfileSettings::load_with_options(profile.as_slice(), options);
         match result {
-            Ok(settings) => Ok(Profile {
-                settings,
-                scribbles,
-            }),
-            Err(e) => {
-                warn!("Unable to Load Profile: {}", e);
-                bail!("Unable to Load Profile");
+            Ok(settings) => {
+                let mut profile = Profile {
+                    settings,
+                    scribbles,
+                    entries,
+                    extra_files,
+                };
+                if !options.skip_scribbles {
+                    profile.reconcile_scribbles(options.orphaned_scribbles);
+                }
+                Ok(profile)
+            }
+            Err(e) => Err(e.context("Unable to Load Profile")),
+        }
+    }
+
+    fn reconcile_scribbles(&mut self, policy: OrphanedScribbles) {
+        for fader in Faders::iter() {
+            let id = FaderElement::Scribble.id(fader);
+            let filename = format!("scribble{id}.png");
+            let has_image = !self.scribbles[id as usize - 1].is_empty();
+
+            if let Some(scribble) = &self.settings.scribbles[fader] {
+                if !has_image && !scribble.bitmap_file().is_empty() {
+                    debug!("{} is missing, it will be regenerated", filename);
+                }
+                continue;
+            }
+
+            if !has_image {
+                continue;
+            }
+
+            match policy {
+                OrphanedScribbles::Warn => {
+                    warn!(
+                        "{} has no scribble settings, it won't be displayed",
+                        filename
+                    );
+                }
+                OrphanedScribbles::CreateMetadata => {
+                    warn!("{} has no scribble settings, creating defaults", filename);
+                    let mut scribble = Scribble::new(id);
+                    scribble.set_bitmap_file(filename);
+                    self.settings.scribbles[fader] = Some(scribble);
+                }
+            }
+        }
+    }
+
+    /// Loads the profile settings without reading the scribble images, useful when only the
+    /// settings are needed. Note that saving a profile loaded this way will drop the images.
+    pub fn load_without_scribbles<R: Read + std::io::Seek>(read: R) -> Result<Self> {
+        let options = LoadOptions {
+            skip_scribbles: true,
+            ..Default::default()
+        };
+        Profile::load_with_options(read, &options)
+    }
+
+    pub fn load_from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path).with_context(|| format!("Unable to open {:?}", path))?;
+        Profile::load(BufReader::new(file))
+    }
+
+    /// Loads every .goxlr file in a directory (not recursively). Each file gets its own result,
+    /// so a single broken profile won't prevent the rest from loading. Files are picked out the
+    /// same way as directory::list_profiles, so come back in name order.
+    pub fn load_dir(path: &Path) -> Vec<(PathBuf, Result<Profile>)> {
+        let files = match directory::profile_files(path) {
+            Ok(files) => files,
+            Err(e) => return vec![(path.to_path_buf(), Err(e))],
+        };
+
+        files
+            .into_iter()
+            .map(|(_, path)| {
+                let result = Profile::load_from_path(&path);
+                if let Err(e) = &result {
+                    warn!("Unable to load {:?}: {}", path, e);
+                }
+                (path, result)
+            })
+            .collect()
+    }
+
+    /// A lightweight check to see whether something looks like a GoXLR profile, this only reads
+    /// the zip's central directory, and enough of profile.xml to find the root element.
+    pub fn is_valid_profile<R: Read + std::io::Seek>(read: R) -> bool {
+        let mut archive = match zip::ZipArchive::new(read) {
+            Ok(archive) => archive,
+            Err(_) => return false,
+        };
+
+        let file = match archive.by_name("profile.xml") {
+            Ok(file) => file,
+            Err(_) => return false,
+        };
+
+        let mut reader = Reader::from_reader(BufReader::new(file));
+        let mut buf = Vec::new();
+        loop {
+            match reader.read_event_into(&mut buf) {
+                Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => {
+                    return e.local_name().as_ref() == b"ValueTreeRoot";
+                }
+                Ok(Event::Eof) | Err(_) => return false,
+
+                // Declarations, comments, whitespace etc..
+                Ok(_) => {}
             }
+            buf.clear();
         }
     }
 
-    // Ok, this is better.
     pub fn save(&mut self, path: impl AsRef<Path>) -> Result<()> {
-        let temp_file = tempfile::NamedTempFile::new()?;
+        self.save_with_options(path, &WriteOptions::default())
+    }
+
+    /// Saves the profile, by default this writes to a temporary file in the same directory and
+    /// renames it over the target, so an interrupted save can't leave a truncated profile behind.
+    pub fn save_with_options(
+        &mut self,
+        path: impl AsRef<Path>,
+        options: &WriteOptions,
+    ) -> Result<()> {
+        let path = path.as_ref();
+        if options.direct_write {
+            debug!("Writing Profile directly to {:?}", path);
+            let file = File::create(path)?;
+            self.write_archive(&file, options)?;
+            file.sync_all()?;
+            self.settings.clear_dirty();
+            return Ok(());
+        }
 
+        // The temporary file needs to be on the same filesystem for the rename to be atomic..
+        let directory = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let temp_file = tempfile::NamedTempFile::new_in(directory)?;
         debug!("Creating Temporary Save File: {:?}", temp_file.path());
 
+        // The temporary file is only readable by us, keep the permissions of the file it replaces..
+        if let Ok(metadata) = fs::metadata(path) {
+            temp_file
+                .as_file()
+                .set_permissions(metadata.permissions())?;
+        }
+
+        self.write_archive(temp_file.as_file(), options)?;
+
+        // Syncing Write..
+        temp_file.as_file().sync_all()?;
+
+        debug!("Save Complete, moving to {:?}", path);
+        temp_file.persist(path)?;
+        self.settings.clear_dirty();
+        Ok(())
+    }
+
+    fn write_archive(&mut self, file: &File, options: &WriteOptions) -> Result<()> {
         // Create a new ZipFile at the requested location
-        let mut archive = zip::ZipWriter::new(temp_file.as_file());
+        let mut archive = zip::ZipWriter::new(file);
+        self.write_entries(&mut archive, options, true)?;
+        archive.finish()?;
+        Ok(())
+    }
 
+    fn write_entries<W: Write + Seek>(
+        &mut self,
+        archive: &mut zip::ZipWriter<W>,
+        options: &WriteOptions,
+        include_assets: bool,
+    ) -> Result<()> {
         // Store the profile..
-        archive.start_file("profile.xml", FileOptions::default())?;
-        self.settings.write_to(&mut archive)?;
+        archive.start_file("profile.xml", self.file_options("profile.xml"))?;
+        self.settings
+            .write_to_with_options(&mut *archive, options)?;
 
         // Write the scribbles..
         for (i, scribble) in self.scribbles.iter().enumerate() {
             // Only write if there's actually data stored..
             if !self.scribbles[i].is_empty() {
                 let filename = format!("scribble{}.png", i + 1);
-                archive.start_file(filename, FileOptions::default())?;
+                let options = self.file_options(&filename);
+                archive.start_file(filename, options)?;
                 archive.write_all(scribble)?;
             }
         }
-        archive.finish()?;
 
-        // The archive has finished writing, we don't need it anymore (keeping it live prevents
-        // us from removing the temporary file).
-        drop(archive);
+        // Anything else that was in the original archive gets passed straight through..
+        for (name, contents) in &self.extra_files {
+            // When bundling, any previously bundled assets are replaced..
+            if !include_assets && name.starts_with(BUNDLE_ASSETS) {
+                continue;
+            }
+            archive.start_file(name, self.file_options(name))?;
+            archive.write_all(contents)?;
+        }
+        Ok(())
+    }
 
-        // Syncing Write..
-        temp_file.as_file().sync_all()?;
+    /**
+     * Writes the profile along with every sample and scribble icon it references, so it can be
+     * moved to another machine. The files are stored under assets/, with a manifest mapping each
+     * one back to the reference used in the profile. Files which share a name (ignoring case)
+     * are numbered (name (2).wav) in the order they're found. Fails if any referenced file can't
+     * be found, or its name can't be safely extracted.
+     */
+    pub fn export_bundle<W: Write + Seek>(&mut self, out: W, assets: &AssetResolver) -> Result<()> {
+        // Work out what we need, and where it's coming from..
+        let mut bundled: Vec<(&str, String, PathBuf)> = vec![];
+        for (_button, sampler) in &self.settings.sampler_map {
+            if let Some(sampler) = sampler {
+                for track in sampler.tracks() {
+                    if bundled
+                        .iter()
+                        .any(|(_, reference, _)| reference == track.track())
+                    {
+                        continue;
+                    }
+                    let source = self
+                        .settings
+                        .resolve_sample_path(track, &assets.samples_dir)?;
+                    bundled.push((BUNDLE_SAMPLES, track.track().to_string(), source));
+                }
+            }
+        }
+
+        for (_fader, scribble) in &self.settings.scribbles {
+            if let Some(icon) = scribble.as_ref().and_then(|scribble| scribble.icon_file()) {
+                if bundled.iter().any(|(_, reference, _)| reference == &icon) {
+                    continue;
+                }
+                let source = assets.icons_dir.join(&icon);
+                if !source.is_file() {
+                    bail!("Scribble icon {:?} not found", source);
+                }
+                bundled.push((BUNDLE_ICONS, icon, source));
+            }
+        }
+
+        let mut archive = zip::ZipWriter::new(out);
+        self.write_entries(&mut archive, &WriteOptions::default(), false)?;
 
-        // Once complete, we simply move the file over the existing file..
-        debug!("Save Complete, copying to {:?}", path.as_ref());
-        fs::copy(temp_file.as_ref(), path)?;
+        let mut used: Vec<String> = vec![];
+        let mut manifest = String::new();
+        for (kind, reference, source) in bundled {
+            let name = unique_asset_name(kind, &source, &used)?;
+            archive.start_file(name.as_str(), FileOptions::default())?;
+            archive.write_all(&fs::read(&source)?)?;
 
-        debug!("Removing Temporary File: {:?}", temp_file);
-        fs::remove_file(temp_file)?;
+            manifest.push_str(&format!("{kind}\t{name}\t{reference}\n"));
+            used.push(name);
+        }
+
+        archive.start_file(BUNDLE_MANIFEST, FileOptions::default())?;
+        archive.write_all(manifest.as_bytes())?;
+        archive.finish()?;
         Ok(())
     }
 
+    /// Loads a profile bundle created by export_bundle, extracting its assets into the given
+    /// directory, and pointing the profile's samples and scribble icons at the extracted files.
+    /// Existing files are never overwritten, an identical file is reused and anything else is
+    /// extracted under a numbered name (name (2).wav). The bundle is loaded with
+    /// LoadOptions::bundle.
+    pub fn import_bundle<R: Read + Seek>(read: R, extract_assets_to: &Path) -> Result<Profile> {
+        Profile::import_bundle_with_options(read, extract_assets_to, &LoadOptions::bundle())
+    }
+
+    pub fn import_bundle_with_options<R: Read + Seek>(
+        read: R,
+        extract_assets_to: &Path,
+        options: &LoadOptions,
+    ) -> Result<Profile> {
+        let mut profile = Profile::load_with_options(read, options)?;
+
+        let manifest = match profile.take_extra_file(BUNDLE_MANIFEST) {
+            Some(manifest) => String::from_utf8(manifest)?,
+            None => bail!("Not a profile bundle, {} is missing", BUNDLE_MANIFEST),
+        };
+
+        for line in manifest.lines().filter(|line| !line.is_empty()) {
+            let mut parts = line.splitn(3, '\t');
+            let (kind, name, reference) = match (parts.next(), parts.next(), parts.next()) {
+                (Some(kind), Some(name), Some(reference)) => (kind, name, reference),
+                _ => bail!("Invalid manifest entry: {}", line),
+            };
+
+            // Don't let a crafted manifest write outside of the target directory..
+            let file = match bundled_file_name(kind, name) {
+                Some(file) => file,
+                None => bail!("Invalid asset path: {}", name),
+            };
+
+            let contents = match profile.take_extra_file(name) {
+                Some(contents) => contents,
+                None => bail!("Asset {} is missing from the bundle", name),
+            };
+
+            let directory = extract_assets_to.join(kind);
+            fs::create_dir_all(&directory)?;
+            let target = free_asset_path(&directory, file, &contents)?;
+            if !target.exists() {
+                fs::write(&target, contents)?;
+            }
+
+            let location = target.to_string_lossy().to_string();
+            if kind == BUNDLE_SAMPLES {
+                for (_button, sampler) in profile.settings.sampler_map.iter_mut() {
+                    if let Some(sampler) = sampler {
+                        for track in sampler.tracks_mut() {
+                            if track.track == reference {
+                                track.track = location.clone();
+                            }
+                        }
+                    }
+                }
+            } else if kind == BUNDLE_ICONS {
+                for (_fader, scribble) in profile.settings.scribbles.iter_mut() {
+                    if let Some(scribble) = scribble {
+                        if scribble.icon_file().as_deref() == Some(reference) {
+                            scribble.set_icon_file(Some(location.clone()));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(profile)
+    }
+
+    fn take_extra_file(&mut self, name: &str) -> Option<Vec<u8>> {
+        let index = self.extra_files.iter().position(|(file, _)| file == name)?;
+        self.entries.remove(name);
+        Some(self.extra_files.remove(index).1)
+    }
+
+    fn is_known_file(name: &str) -> bool {
+        name == "profile.xml" || (1..=4).any(|i| name == format!("scribble{i}.png"))
+    }
+
+    fn file_options(&self, name: &str) -> FileOptions {
+        // New files (for example, a freshly added scribble) just use the defaults.
+        self.entries
+            .get(name)
+            .map(ArchiveEntry::options)
+            .unwrap_or_default()
+    }
+
     pub fn save_preset(&self, path: impl AsRef<Path>) -> Result<()> {
         self.settings.write_preset(path)?;
         Ok(())
@@ -137,358 +726,628 @@ impl Profile {
     pub fn get_scribble(&self, id: usize) -> &Vec<u8> {
         &self.scribbles[id]
     }
-}
 
-#[derive(Debug)]
-pub struct ProfileSettings {
-    root: RootElement,
-    browser: BrowserPreviewTree,
-    animation_tree: AnimationTree,
-    mix_routing: MixRoutingTree,
-    submix_tree: SubMixer,
-    mixer: Mixers,
-    context: Context,
-    mute_chat: MuteChat,
-    mute_buttons: EnumMap<Faders, Option<MuteButton>>,
-    faders: EnumMap<Faders, Option<Fader>>,
-    effects: EnumMap<Preset, Option<Effects>>,
-    scribbles: EnumMap<Faders, Option<Scribble>>,
-    sampler_map: EnumMap<SampleButtons, Option<SampleBase>>,
-    simple_elements: EnumMap<SimpleElements, Option<SimpleElement>>,
-    megaphone_effect: MegaphoneEffectBase,
-    robot_effect: RobotEffectBase,
-    hardtune_effect: HardtuneEffectBase,
-    reverb_encoder: ReverbEncoderBase,
-    echo_encoder: EchoEncoderBase,
-    pitch_encoder: PitchEncoderBase,
-    gender_encoder: GenderEncoderBase,
-}
+    pub fn export_scribble(&self, id: usize, path: &Path) -> Result<()> {
+        let scribble = match self.scribbles.get(id) {
+            Some(scribble) => scribble,
+            None => bail!("Invalid Scribble ID: {}", id),
+        };
 
-impl ProfileSettings {
-    pub fn load<R: Read>(read: R) -> Result<Self> {
-        // Wrap our reader into a Buffered Reader for parsing..
-        let buf_reader = BufReader::new(read);
-        let mut reader = Reader::from_reader(buf_reader);
+        if scribble.is_empty() {
+            bail!("Scribble {} has no image", id);
+        }
 
-        debug!("Preparing Structure..");
+        // Make sure we're actually writing out a PNG, and not something that happened to be
+        // named like one inside the archive..
+        if !scribble.starts_with(PNG_SIGNATURE) {
+            bail!("Scribble {} is not a valid PNG", id);
+        }
 
-        let mut root = RootElement::new();
-        let mut browser = BrowserPreviewTree::new("browserPreviewTree".to_string());
+        fs::write(path, scribble).with_context(|| format!("Unable to write {:?}", path))
+    }
 
-        let mut animation_tree = AnimationTree::new("animationTree".to_string());
+    /// Writes every scribble which has an image to the directory as scribbleX.png (matching the
+    /// names used inside the profile), returning the paths of the files written.
+    pub fn export_all_scribbles(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        let mut written = vec![];
+        for (id, scribble) in self.scribbles.iter().enumerate() {
+            if scribble.is_empty() {
+                continue;
+            }
 
-        let mut mix_routing = MixRoutingTree::new();
-        let mut submix_tree = SubMixer::new();
+            let path = dir.join(format!("scribble{}.png", id + 1));
+            self.export_scribble(id, &path)?;
+            written.push(path);
+        }
+        Ok(written)
+    }
+}
 
-        let mut mixer = Mixers::new();
-        let mut context = Context::new("selectedContext".to_string());
-        let mut mute_chat = MuteChat::new("muteChat".to_string());
+pub(crate) const PNG_SIGNATURE: &[u8] = &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
 
-        let mut mute_buttons: EnumMap<Faders, Option<MuteButton>> = EnumMap::default();
-        let mut faders: EnumMap<Faders, Option<Fader>> = EnumMap::default();
-        let mut scribbles: EnumMap<Faders, Option<Scribble>> = EnumMap::default();
+/**
+ * The contents of a preset file, parsed but not yet applied to a profile. Presets are a single
+ * top level element holding the name, with one child element per effect or encoder.
+ */
+#[derive(Debug)]
+pub struct ParsedPreset {
+    name: String,
+    elements: Vec<(String, Vec<Attribute>)>,
+}
 
-        let mut effects: EnumMap<Preset, Option<Effects>> = EnumMap::default();
+impl ParsedPreset {
+    pub fn load<R: Read>(read: R) -> Result<Self> {
+        ParsedPreset::load_with_options(read, &LoadOptions::default())
+    }
 
-        let mut simple_elements: EnumMap<SimpleElements, Option<SimpleElement>> =
-            Default::default();
+    pub fn load_with_options<R: Read>(read: R, options: &LoadOptions) -> Result<Self> {
+        let buf_reader = BufReader::new(read);
+        let mut reader = Reader::from_reader(buf_reader);
+        let mut guard = DocumentGuard::new(options);
 
-        let mut megaphone_effect = MegaphoneEffectBase::new("megaphoneEffect".to_string());
-        let mut robot_effect = RobotEffectBase::new("robotEffect".to_string());
-        let mut hardtune_effect = HardtuneEffectBase::new("hardtuneEffect".to_string());
-        let mut reverb_encoder = ReverbEncoderBase::new("reverbEncoder".to_string());
-        let mut echo_encoder = EchoEncoderBase::new("echoEncoder".to_string());
-        let mut pitch_encoder = PitchEncoderBase::new("pitchEncoder".to_string());
-        let mut gender_encoder = GenderEncoderBase::new("genderEncoder".to_string());
+        let mut name = None;
+        let mut elements = vec![];
 
-        let mut sampler_map: EnumMap<SampleButtons, Option<SampleBase>> = EnumMap::default();
-        let mut active_sample_button: Option<&mut SampleBase> = None;
+        // How deep we are in the document, the top level element holds the name, and each of its
+        // children is an effect. Hand edited files may write an effect as <tag></tag> rather than
+        // <tag/>, or put the name anywhere in the root's attributes, so don't assume either..
+        let mut depth: usize = 0;
 
         let mut buf = Vec::new();
         loop {
-            match reader.read_event_into(&mut buf) {
-                // Applies to most tags, represents a tag with no child
-                Ok(Event::Empty(ref e)) => {
-                    let (name, attributes) = wrap_start_event(e)?;
-                    if name == "browserPreviewTree" {
-                        browser.parse_browser(&attributes)?;
-                        continue;
-                    }
+            let event = reader.read_event_into(&mut buf);
+            if let Ok(event) = &event {
+                guard.check(event)?;
+            }
 
-                    if name == "animationTree" {
-                        animation_tree.parse_animation(&attributes)?;
-                        continue;
+            match event {
+                Ok(Event::Empty(ref e)) | Ok(Event::Start(ref e)) => {
+                    let (tag, attributes) = wrap_start_event(e)?;
+                    match depth {
+                        0 => name = Self::find_name(attributes).or(name),
+                        1 => elements.push((tag, attributes)),
+                        _ => {}
                     }
-
-                    if name == "mixRoutingTree" {
-                        mix_routing.parse_mix_tree(&attributes)?;
-                        continue;
+                    if matches!(event, Ok(Event::Start(_))) {
+                        depth += 1;
                     }
+                }
 
-                    if name == "monitorTree" {
-                        submix_tree.parse_monitor(&attributes)?;
-                        continue;
-                    }
+                // Ends a tag with children
+                Ok(Event::End(_)) => depth = depth.saturating_sub(1),
+                Ok(Event::Eof) => {
+                    break;
+                }
 
-                    if name == "linkingTree" {
-                        submix_tree.parse_linking(&attributes)?;
-                        continue;
-                    }
+                Ok(_) => {}
+                Err(_) => {}
+            }
+            buf.clear();
+        }
 
-                    if name == "mixerTree" {
-                        mixer.parse_mixers(&attributes)?;
-                        continue;
-                    }
+        match name {
+            Some(name) => Ok(Self { name, elements }),
+            None => bail!("Preset Name not found, cannot proceed."),
+        }
+    }
 
-                    if name == "selectedContext" {
-                        context.parse_context(&attributes)?;
-                        continue;
-                    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
 
-                    if name == "muteChat" {
-                        mute_chat.parse_mute_chat(&attributes)?;
-                        continue;
-                    }
+    fn find_name(attributes: Vec<Attribute>) -> Option<String> {
+        attributes
+            .into_iter()
+            .find(|a| a.name == "name")
+            .map(|a| a.value)
+    }
+}
 
-                    // Might need to pattern match this..
-                    if name.starts_with("mute") && name != "muteChat" {
-                        // In the XML, the count starts as 1, here, we're gonna store as 0.
-                        if let Some(id) = name
-                            .chars()
-                            .last()
-                            .map(|s| u8::from_str(&s.to_string()))
-                            .transpose()?
-                        {
-                            let mut mute_button = MuteButton::new(id);
-                            mute_button.parse_button(&attributes)?;
-                            mute_buttons[Faders::iter().nth((id - 1).into()).unwrap()] =
-                                Some(mute_button);
-                            continue;
-                        }
-                    }
+/// A quick overview of a profile, see ProfileSettings::summary
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ProfileSummary {
+    // How many of the four faders, and six effect banks, are present in the profile..
+    pub faders: usize,
+    pub effect_banks: usize,
 
-                    if name.starts_with("FaderMeter") {
-                        // In the XML, the count starts at 0, and we have different capitalisation :D
-                        if let Some(id) = name
-                            .chars()
-                            .last()
-                            .map(|s| u8::from_str(&s.to_string()))
-                            .transpose()?
-                        {
-                            let mut fader = Fader::new(id);
-                            fader.parse_fader(&attributes)?;
-                            faders[Faders::iter().nth(id.into()).unwrap()] = Some(fader);
-                            continue;
-                        }
-                    }
+    // The number of distinct sample files used across every button and bank..
+    pub samples: usize,
 
-                    if name.starts_with("effects") {
-                        let mut found = false;
-
-                        // Version 2, now with more enum, search for the prefix..
-                        for preset in Preset::iter() {
-                            if preset.get_str("contextTitle").unwrap() == name {
-                                let mut effect = Effects::new(preset);
-                                effect.parse_effect(&attributes)?;
-                                effects[preset] = Some(effect);
-                                found = true;
-                                break;
-                            }
-                        }
-                        if found {
-                            continue;
-                        }
-                    }
+    // The 'device' attribute from the root element, as written by the official app..
+    pub device: u64,
+}
 
-                    if name.starts_with("scribble") {
-                        if let Some(id) = name
-                            .chars()
-                            .last()
-                            .map(|s| u8::from_str(&s.to_string()))
-                            .transpose()?
-                        {
-                            let mut scribble = Scribble::new(id);
-                            scribble.parse_scribble(&attributes)?;
-                            scribbles[Faders::iter().nth((id - 1).into()).unwrap()] =
-                                Some(scribble);
-                            continue;
-                        }
-                    }
+/// Something which suggests a fader's components have ended up in the wrong slot, see
+/// ProfileSettings::audit_fader_assignments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FaderAnomaly {
+    // A component's colour prefix doesn't match the element it should've been loaded from..
+    MismatchedElement {
+        fader: Faders,
+        element: String,
+        expected: String,
+    },
+
+    // The same channel is assigned to more than one fader..
+    DuplicateChannel {
+        channel: FullChannelList,
+        faders: Vec<Faders>,
+    },
+
+    // The scribble's bottom text is the name of the channel on a different fader..
+    ScribbleMismatch {
+        fader: Faders,
+        text: String,
+        matches: Faders,
+    },
+}
 
-                    if name.starts_with("megaphoneEffectpreset") {
-                        if let Ok(preset) = ProfileSettings::parse_preset(name.clone()) {
-                            megaphone_effect.parse_megaphone_preset(preset, &attributes)?;
-                            continue;
-                        }
-                    }
+/// The components which were created by ProfileSettings::repair
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RepairReport {
+    pub created: Vec<ComponentId>,
+}
 
-                    if name.starts_with("robotEffectpreset") {
-                        if let Ok(preset) = ProfileSettings::parse_preset(name.clone()) {
-                            robot_effect.parse_robot_preset(preset, &attributes)?;
-                            continue;
-                        }
-                    }
+impl RepairReport {
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty()
+    }
+}
 
-                    if name.starts_with("hardtuneEffectpreset") {
-                        if let Ok(preset) = ProfileSettings::parse_preset(name.clone()) {
-                            hardtune_effect.parse_hardtune_preset(preset, &attributes)?;
-                            continue;
-                        }
-                    }
+/// The top level sections of a profile which can be individually reset to their defaults.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ComponentId {
+    Mixer,
+    MuteChat,
+    Fader(Faders),
+    MuteButton(Faders),
+    Scribble(Faders),
+    Effects(Preset),
+    Megaphone,
+    Robot,
+    HardTune,
+    Reverb,
+    Echo,
+    Pitch,
+    Gender,
+    Animation,
+    Context,
+    Submixes,
+    MixRouting,
+    Sampler(SampleButtons),
+    SimpleElement(SimpleElements),
+    ShutdownBehaviour,
+    SamplerRoot,
+    Root,
+}
 
-                    if name.starts_with("reverbEncoderpreset") {
-                        if let Ok(preset) = ProfileSettings::parse_preset(name.clone()) {
-                            reverb_encoder.parse_reverb_preset(preset, &attributes)?;
-                            continue;
-                        }
-                    }
+impl From<LightingTarget> for ComponentId {
+    fn from(target: LightingTarget) -> Self {
+        match target {
+            LightingTarget::MuteChat => ComponentId::MuteChat,
+            LightingTarget::Fader(fader) => ComponentId::Fader(fader),
+            LightingTarget::MuteButton(fader) => ComponentId::MuteButton(fader),
+            LightingTarget::Scribble(fader) => ComponentId::Scribble(fader),
+            LightingTarget::EffectButton(preset) => ComponentId::Effects(preset),
+            LightingTarget::Effect(EffectType::Megaphone) => ComponentId::Megaphone,
+            LightingTarget::Effect(EffectType::Robot) => ComponentId::Robot,
+            LightingTarget::Effect(EffectType::HardTune) => ComponentId::HardTune,
+            LightingTarget::Encoder(EncoderType::Reverb) => ComponentId::Reverb,
+            LightingTarget::Encoder(EncoderType::Echo) => ComponentId::Echo,
+            LightingTarget::Encoder(EncoderType::Pitch) => ComponentId::Pitch,
+            LightingTarget::Encoder(EncoderType::Gender) => ComponentId::Gender,
+            LightingTarget::Sampler(button) => ComponentId::Sampler(button),
+            LightingTarget::Simple(element) => ComponentId::SimpleElement(element),
+        }
+    }
+}
 
-                    if name.starts_with("echoEncoderpreset") {
-                        if let Ok(preset) = ProfileSettings::parse_preset(name.clone()) {
-                            echo_encoder.parse_echo_preset(preset, &attributes)?;
-                            continue;
-                        }
-                    }
+impl ComponentId {
+    // The component a parsed element is loaded into. Sample stacks belong to whichever sampler
+    // they're nested in, and the browser tree isn't a component.
+    fn from_kind(kind: ComponentKind, sampler: Option<SampleButtons>) -> Option<ComponentId> {
+        let component = match kind {
+            ComponentKind::Root | ComponentKind::AppTree => ComponentId::Root,
+            ComponentKind::Browser => return None,
+            ComponentKind::SamplerRoot => ComponentId::SamplerRoot,
+            ComponentKind::Animation => ComponentId::Animation,
+            ComponentKind::MixRouting => ComponentId::MixRouting,
+            ComponentKind::Submixer | ComponentKind::Monitor | ComponentKind::Linking => {
+                ComponentId::Submixes
+            }
+            ComponentKind::Mixer => ComponentId::Mixer,
+            ComponentKind::Context => ComponentId::Context,
+            ComponentKind::MuteChat => ComponentId::MuteChat,
+            ComponentKind::ShutdownBehaviour => ComponentId::ShutdownBehaviour,
+            ComponentKind::MuteButton(fader) => ComponentId::MuteButton(fader),
+            ComponentKind::Fader(fader) => ComponentId::Fader(fader),
+            ComponentKind::Scribble(fader) => ComponentId::Scribble(fader),
+            ComponentKind::Effects(preset) => ComponentId::Effects(preset),
+            ComponentKind::EffectRoot(effect) | ComponentKind::EffectPreset(effect, _) => {
+                ComponentId::from(LightingTarget::Effect(effect))
+            }
+            ComponentKind::EncoderRoot(encoder) | ComponentKind::EncoderPreset(encoder, _) => {
+                ComponentId::from(LightingTarget::Encoder(encoder))
+            }
+            ComponentKind::Sampler(button) => ComponentId::Sampler(button),
+            ComponentKind::SampleStack(_) => ComponentId::Sampler(sampler?),
+            ComponentKind::Simple(element) => ComponentId::SimpleElement(element),
+        };
+        Some(component)
+    }
+}
 
-                    if name.starts_with("pitchEncoderpreset") {
-                        if let Ok(preset) = ProfileSettings::parse_preset(name.clone()) {
-                            pitch_encoder.parse_pitch_preset(preset, &attributes)?;
-                            continue;
-                        }
-                    }
+/**
+ * The parts of the device state which can be read back from the hardware, mirrors goxlr-usb's
+ * HardwareState using the profile's types. The device can't report fader assignments, routing
+ * or lighting, so those aren't present here.
+ */
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct HardwareState {
+    pub fader_volumes: EnumMap<Faders, u8>,
+}
 
-                    if name.starts_with("genderEncoderpreset") {
-                        if let Ok(preset) = ProfileSettings::parse_preset(name.clone()) {
-                            gender_encoder.parse_gender_preset(preset, &attributes)?;
-                            continue;
-                        }
-                    }
+impl HardwareState {
+    /// The device can't report which profile it's running, so this picks out the first of the
+    /// named profiles which matches the state read back from it (see
+    /// ProfileSettings::matches_hardware_state). Returns None if none of them match.
+    pub fn matching_profile<'a, T>(
+        &self,
+        profiles: impl IntoIterator<Item = (T, &'a ProfileSettings)>,
+    ) -> Option<T> {
+        profiles
+            .into_iter()
+            .find(|(_, settings)| settings.matches_hardware_state(self))
+            .map(|(name, _)| name)
+    }
+}
 
-                    if name.starts_with("sampleStack") {
-                        if let Some(id) = name.chars().last() {
-                            if let Some(button) = &mut active_sample_button {
-                                button.parse_sample_stack(id, &attributes)?;
-                                continue;
-                            }
-                        }
-                    }
+/**
+ * The positions of the four effect encoders for a single bank, as the hardware reports (and
+ * expects) them. Reverb and Echo go from 0 to 24, Gender from -24 to 24, and Pitch depends on the
+ * pitch style and whether HardTune is enabled on the bank (-24 to 24 without HardTune, -1 to 1 or
+ * -2 to 2 with it for Narrow and Wide respectively).
+ */
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct EncoderValues {
+    pub reverb: u8,
+    pub echo: u8,
+    pub pitch: i8,
+    pub gender: i8,
+}
 
-                    if name.starts_with("sampleBank")
-                        || name == "fxClear"
-                        || name == "swear"
-                        || name == "globalColour"
-                        || name == "logoX"
-                    {
-                        // In this case, the tag name, and attribute prefixes are the same..
-                        let mut simple_element = SimpleElement::new(name.clone());
-                        simple_element.parse_simple(&attributes)?;
-                        simple_elements[SimpleElements::from_str(&name)?] = Some(simple_element);
+/// A set of components, used to report which components have been modified since load.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ComponentSet(Vec<ComponentId>);
 
-                        continue;
-                    }
+impl ComponentSet {
+    pub fn insert(&mut self, component: ComponentId) {
+        if !self.contains(component) {
+            self.0.push(component);
+        }
+    }
 
-                    if name == "AppTree" {
-                        // This is handled by ValueTreeRoot
-                        continue;
-                    }
+    pub fn contains(&self, component: ComponentId) -> bool {
+        self.0.contains(&component)
+    }
 
-                    warn!("Unhandled Tag: {}", name);
-                }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
 
-                // Represents a tag which has children
-                Ok(Event::Start(ref e)) => {
-                    let (name, attributes) = wrap_start_event(e)?;
+    pub fn iter(&self) -> impl Iterator<Item = ComponentId> + '_ {
+        self.0.iter().copied()
+    }
 
-                    if name == "ValueTreeRoot" {
-                        // This also handles <AppTree, due to a single shared value.
-                        root.parse_root(&attributes)?;
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+}
 
-                        // This code was made for XML version 2, v1 not currently supported.
-                        if root.get_version() > 3 {
-                            bail!("Unsupported Profile Version {}", root.get_version());
-                        }
-                        continue;
-                    }
+#[derive(Debug)]
+pub struct ProfileSettings {
+    root: RootElement,
+    browser: BrowserPreviewTree,
+    animation_tree: AnimationTree,
+    mix_routing: MixRoutingTree,
+    submix_tree: SubMixer,
+    mixer: Mixers,
+    context: Context,
+    mute_chat: MuteChat,
+    mute_buttons: EnumMap<Faders, Option<MuteButton>>,
+    faders: EnumMap<Faders, Option<Fader>>,
+    effects: EnumMap<Preset, Option<Effects>>,
+    scribbles: EnumMap<Faders, Option<Scribble>>,
+    sampler_map: EnumMap<SampleButtons, Option<SampleBase>>,
+    simple_elements: EnumMap<SimpleElements, Option<SimpleElement>>,
+    megaphone_effect: MegaphoneEffectBase,
+    robot_effect: RobotEffectBase,
+    hardtune_effect: HardtuneEffectBase,
+    reverb_encoder: ReverbEncoderBase,
+    echo_encoder: EchoEncoderBase,
+    pitch_encoder: PitchEncoderBase,
+    gender_encoder: GenderEncoderBase,
 
-                    if name == "submixerTree" {
-                        submix_tree.parse_submixer(&attributes)?;
-                        continue;
-                    }
+    // Not part of the official format, only present if it's been explicitly set
+    shutdown_behaviour: Option<ShutdownBehaviour>,
 
-                    if name == "megaphoneEffect" {
-                        megaphone_effect.parse_megaphone_root(&attributes)?;
-                        continue;
-                    }
+    // Also not part of the official format, where this profile's samples live (either absolute,
+    // or relative to the samples directory)
+    sampler_root: Option<String>,
 
-                    if name == "robotEffect" {
-                        robot_effect.parse_robot_root(&attributes)?;
-                        continue;
-                    }
+    // Anything skipped while loading in lenient mode
+    load_errors: Vec<ParseError>,
 
-                    if name == "hardtuneEffect" {
-                        hardtune_effect.parse_hardtune_root(&attributes)?;
-                        continue;
-                    }
+    change_handler: Option<ChangeHandler>,
 
-                    if name == "reverbEncoder" {
-                        reverb_encoder.parse_reverb_root(&attributes)?;
-                        continue;
-                    }
+    // Components which have been mutably borrowed since load (or the last clear_dirty)
+    dirty: ComponentSet,
 
-                    if name == "echoEncoder" {
-                        echo_encoder.parse_echo_root(&attributes)?;
-                        continue;
-                    }
+    // Bumped whenever the state, function or previous volume of a mute button (or the chat mute)
+    // changes, along with a value unique to this load, so a MuteAllSnapshot can tell if they've
+    // been changed since. Changes are spotted by comparing against the state last seen, which is
+    // checked before every mutable borrow, and whenever the generation is read.
+    instance: u64,
+    mute_generations: EnumMap<Faders, u64>,
+    mute_seen: EnumMap<Faders, Option<MuteButtonState>>,
+    chat_mute_generation: u64,
+    chat_mute_seen: Option<MuteChatState>,
+}
 
-                    if name == "pitchEncoder" {
-                        pitch_encoder.parse_pitch_root(&attributes)?;
-                        continue;
-                    }
+// Everything a preset can change, see import_preset_bundle..
+struct EffectBanks {
+    effects: EnumMap<Preset, Option<Effects>>,
+    megaphone: MegaphoneEffectBase,
+    robot: RobotEffectBase,
+    hardtune: HardtuneEffectBase,
+    reverb: ReverbEncoderBase,
+    echo: EchoEncoderBase,
+    pitch: PitchEncoderBase,
+    gender: GenderEncoderBase,
+}
 
-                    if name == "genderEncoder" {
-                        gender_encoder.parse_gender_root(&attributes)?;
-                        continue;
-                    }
+// The parts of a mute button a MuteAllSnapshot cares about: muted, blinking (muted to all), the
+// mute function, and the previous volume..
+type MuteButtonState = (bool, bool, MuteFunction, u8);
+type MuteChatState = (bool, bool, MuteFunction);
 
-                    // These can probably be a little cleaner..
-                    if name == "sampleTopLeft" {
-                        let mut sampler = SampleBase::new("sampleTopLeft".to_string());
-                        sampler.parse_sample_root(&attributes)?;
-                        sampler_map[TopLeft] = Some(sampler);
-                        active_sample_button = sampler_map[TopLeft].as_mut();
-                        continue;
-                    }
+impl ProfileSettings {
+    pub fn load<R: Read>(read: R) -> Result<Self> {
+        ProfileSettings::load_with_options(read, &LoadOptions::default())
+    }
 
-                    if name == "sampleTopRight" {
-                        let mut sampler = SampleBase::new("sampleTopRight".to_string());
-                        sampler.parse_sample_root(&attributes)?;
-                        sampler_map[TopRight] = Some(sampler);
-                        active_sample_button = sampler_map[TopRight].as_mut();
-                        continue;
-                    }
+    pub fn load_with_options<R: Read>(read: R, options: &LoadOptions) -> Result<Self> {
+        // Wrap our reader into a Buffered Reader for parsing..
+        let buf_reader = BufReader::new(read);
+        let mut reader = Reader::from_reader(buf_reader);
+        let mut guard = DocumentGuard::new(options);
 
-                    if name == "sampleBottomLeft" {
-                        let mut sampler = SampleBase::new("sampleBottomLeft".to_string());
-                        sampler.parse_sample_root(&attributes)?;
-                        sampler_map[BottomLeft] = Some(sampler);
-                        active_sample_button = sampler_map[BottomLeft].as_mut();
-                        continue;
-                    }
+        debug!("Preparing Structure..");
 
-                    if name == "sampleBottomRight" {
-                        let mut sampler = SampleBase::new("sampleBottomRight".to_string());
-                        sampler.parse_sample_root(&attributes)?;
-                        sampler_map[BottomRight] = Some(sampler);
-                        active_sample_button = sampler_map[BottomRight].as_mut();
-                        continue;
-                    }
+        let mut root = RootElement::new();
+        let mut browser = BrowserPreviewTree::new(BROWSER_PREVIEW_TREE.to_string());
 
-                    if name == "sampleClear" {
-                        let mut sampler = SampleBase::new("sampleClear".to_string());
-                        sampler.parse_sample_root(&attributes)?;
-                        sampler_map[Clear] = Some(sampler);
-                        active_sample_button = sampler_map[Clear].as_mut();
-                        continue;
+        let mut animation_tree = AnimationTree::new(ANIMATION_TREE.to_string());
+
+        let mut mix_routing = MixRoutingTree::new();
+        let mut submix_tree = SubMixer::new();
+
+        let mut mixer = Mixers::new();
+        let mut context = Context::new(SELECTED_CONTEXT.to_string());
+        let mut mute_chat = MuteChat::new(MUTE_CHAT.to_string());
+
+        let mut mute_buttons: EnumMap<Faders, Option<MuteButton>> = EnumMap::default();
+        let mut faders: EnumMap<Faders, Option<Fader>> = EnumMap::default();
+        let mut scribbles: EnumMap<Faders, Option<Scribble>> = EnumMap::default();
+
+        let mut effects: EnumMap<Preset, Option<Effects>> = EnumMap::default();
+
+        let mut simple_elements: EnumMap<SimpleElements, Option<SimpleElement>> =
+            Default::default();
+
+        let mut megaphone_effect = MegaphoneEffectBase::new("megaphoneEffect".to_string());
+        let mut robot_effect = RobotEffectBase::new("robotEffect".to_string());
+        let mut hardtune_effect = HardtuneEffectBase::new("hardtuneEffect".to_string());
+        let mut reverb_encoder = ReverbEncoderBase::new("reverbEncoder".to_string());
+        let mut echo_encoder = EchoEncoderBase::new("echoEncoder".to_string());
+        let mut pitch_encoder = PitchEncoderBase::new("pitchEncoder".to_string());
+        let mut gender_encoder = GenderEncoderBase::new("genderEncoder".to_string());
+
+        let mut shutdown_behaviour: Option<ShutdownBehaviour> = None;
+        let mut sampler_root: Option<String> = None;
+
+        let mut sampler_map: EnumMap<SampleButtons, Option<SampleBase>> = EnumMap::default();
+        let mut active_sample_button: Option<&mut SampleBase> = None;
+        let mut active_sampler: Option<SampleButtons> = None;
+
+        let mut load_errors: Vec<ParseError> = Vec::new();
+        let mut last_error_position = usize::MAX;
+
+        let mut buf = Vec::new();
+        loop {
+            let event = reader.read_event_into(&mut buf);
+            if let Ok(event) = &event {
+                guard.check(event)?;
+            }
+
+            match event {
+                // Most tags have no children (Empty), the effect roots and samplers do (Start),
+                // the registry tells us which component owns the tag either way..
+                Ok(Event::Empty(ref e)) | Ok(Event::Start(ref e)) => {
+                    let (name, attributes) = match wrap_start_event(e) {
+                        Ok(wrapped) => wrapped,
+                        Err(error) => {
+                            let position = reader.buffer_position();
+                            let error = ParseError::Malformed {
+                                position,
+                                message: error.to_string(),
+                            };
+                            recover(options, None, &mut load_errors, error)?;
+                            continue;
+                        }
+                    };
+                    let kind = match ComponentKind::from_tag(&name) {
+                        Some(kind) => kind,
+                        None => {
+                            warn!("Unhandled Tag: {}", name);
+                            continue;
+                        }
+                    };
+
+                    // Components are only stored if they parsed successfully, so in lenient mode
+                    // a broken element is left at its default (or missing, for repair to handle)
+                    let result = match kind {
+                        ComponentKind::Root => {
+                            // This also handles <AppTree, due to a single shared value.
+                            let result =
+                                parse_with_context(&name, &attributes, |a| root.parse_root(a));
+
+                            // This code was made for XML version 2, v1 not currently supported.
+                            if root.get_version() > 3 {
+                                bail!("Unsupported Profile Version {}", root.get_version());
+                            }
+                            result
+                        }
+                        ComponentKind::AppTree => {
+                            // This is handled by ValueTreeRoot
+                            Ok(())
+                        }
+                        ComponentKind::Browser => {
+                            parse_with_context(&name, &attributes, |a| browser.parse_browser(a))
+                        }
+                        ComponentKind::Animation => parse_with_context(&name, &attributes, |a| {
+                            animation_tree.parse_animation(a)
+                        }),
+                        ComponentKind::MixRouting => parse_with_context(&name, &attributes, |a| {
+                            mix_routing.parse_mix_tree(a)
+                        }),
+                        ComponentKind::Submixer => parse_with_context(&name, &attributes, |a| {
+                            submix_tree.parse_submixer(a)
+                        }),
+                        ComponentKind::Monitor => {
+                            parse_with_context(&name, &attributes, |a| submix_tree.parse_monitor(a))
+                        }
+                        ComponentKind::Linking => {
+                            parse_with_context(&name, &attributes, |a| submix_tree.parse_linking(a))
+                        }
+                        ComponentKind::Mixer => {
+                            parse_with_context(&name, &attributes, |a| mixer.parse_mixers(a))
+                        }
+                        ComponentKind::Context => {
+                            parse_with_context(&name, &attributes, |a| context.parse_context(a))
+                        }
+                        ComponentKind::MuteChat => {
+                            parse_with_context(&name, &attributes, |a| mute_chat.parse_mute_chat(a))
+                        }
+                        ComponentKind::ShutdownBehaviour => {
+                            let mut behaviour = ShutdownBehaviour::new(ShutdownAction::KeepState);
+                            parse_with_context(&name, &attributes, |a| behaviour.parse_shutdown(a))
+                                .map(|_| shutdown_behaviour = Some(behaviour))
+                        }
+                        ComponentKind::SamplerRoot => {
+                            for attribute in &attributes {
+                                if attribute.name == "path" && !attribute.value.is_empty() {
+                                    sampler_root = Some(attribute.value.clone());
+                                }
+                            }
+                            Ok(())
+                        }
+                        ComponentKind::MuteButton(fader) => {
+                            let mut mute_button = MuteButton::new(FaderElement::Mute.id(fader));
+                            parse_with_context(&name, &attributes, |a| mute_button.parse_button(a))
+                                .map(|_| mute_buttons[fader] = Some(mute_button))
+                        }
+                        ComponentKind::Fader(fader) => {
+                            let mut value = Fader::new(FaderElement::FaderMeter.id(fader));
+                            parse_with_context(&name, &attributes, |a| value.parse_fader(a))
+                                .map(|_| faders[fader] = Some(value))
+                        }
+                        ComponentKind::Scribble(fader) => {
+                            let mut scribble = Scribble::new(FaderElement::Scribble.id(fader));
+                            parse_with_context(&name, &attributes, |a| scribble.parse_scribble(a))
+                                .map(|_| scribbles[fader] = Some(scribble))
+                        }
+                        ComponentKind::Effects(preset) => {
+                            let mut effect = Effects::new(preset);
+                            parse_with_context(&name, &attributes, |a| effect.parse_effect(a))
+                                .map(|_| effects[preset] = Some(effect))
+                        }
+                        ComponentKind::EffectRoot(effect)
+                        | ComponentKind::EffectPreset(effect, _) => {
+                            let base: &mut dyn EffectBase = match effect {
+                                EffectType::Megaphone => &mut megaphone_effect,
+                                EffectType::Robot => &mut robot_effect,
+                                EffectType::HardTune => &mut hardtune_effect,
+                            };
+                            match kind {
+                                ComponentKind::EffectPreset(_, preset) => {
+                                    parse_with_context(&name, &attributes, |a| {
+                                        base.parse_preset(preset, a)
+                                    })
+                                }
+                                _ => parse_with_context(&name, &attributes, |a| base.parse_root(a)),
+                            }
+                        }
+                        ComponentKind::EncoderRoot(encoder)
+                        | ComponentKind::EncoderPreset(encoder, _) => {
+                            let base: &mut dyn EncoderBase = match encoder {
+                                EncoderType::Reverb => &mut reverb_encoder,
+                                EncoderType::Echo => &mut echo_encoder,
+                                EncoderType::Pitch => &mut pitch_encoder,
+                                EncoderType::Gender => &mut gender_encoder,
+                            };
+                            match kind {
+                                ComponentKind::EncoderPreset(_, preset) => {
+                                    parse_with_context(&name, &attributes, |a| {
+                                        base.parse_preset(preset, a)
+                                    })
+                                }
+                                _ => parse_with_context(&name, &attributes, |a| base.parse_root(a)),
+                            }
+                        }
+                        ComponentKind::Sampler(button) => {
+                            let mut sampler = SampleBase::new(name.clone());
+                            let result = parse_with_context(&name, &attributes, |a| {
+                                sampler.parse_sample_root(a)
+                            });
+
+                            // If the sampler failed, make sure its stacks don't end up attached
+                            // to the previous one..
+                            active_sample_button = None;
+                            active_sampler = Some(button);
+                            if result.is_ok() {
+                                sampler_map[button] = Some(sampler);
+                                active_sample_button = sampler_map[button].as_mut();
+                            }
+                            result
+                        }
+                        ComponentKind::SampleStack(_) => {
+                            let id = name.chars().last();
+                            match (id, &mut active_sample_button) {
+                                (Some(id), Some(button)) => {
+                                    parse_with_context(&name, &attributes, |a| {
+                                        button.parse_sample_stack(id, a)
+                                    })
+                                }
+                                _ => {
+                                    warn!("Unhandled Tag: {} (outside of a sampler)", name);
+                                    Ok(())
+                                }
+                            }
+                        }
+                        ComponentKind::Simple(element) => {
+                            // In this case, the tag name, and attribute prefixes are the same..
+                            let mut simple_element = SimpleElement::new(name.clone());
+                            parse_with_context(&name, &attributes, |a| {
+                                simple_element.parse_simple(a)
+                            })
+                            .map(|_| simple_elements[element] = Some(simple_element))
+                        }
+                    };
+
+                    if let Err(error) = result {
+                        let component = ComponentId::from_kind(kind, active_sampler);
+                        recover(options, component, &mut load_errors, error)?;
                     }
                 }
 
@@ -499,7 +1358,18 @@ impl ProfileSettings {
                 }
                 Ok(_) => {}
                 Err(e) => {
-                    bail!("Error Parsing Profile: {}", e);
+                    let position = reader.buffer_position();
+                    let error = ParseError::Malformed {
+                        position,
+                        message: e.to_string(),
+                    };
+                    recover(options, None, &mut load_errors, error)?;
+
+                    // If the reader didn't move past the problem, it'll just hit it again..
+                    if position == last_error_position {
+                        bail!("Unable to recover from XML error at position {}", position);
+                    }
+                    last_error_position = position;
                 }
             }
         }
@@ -507,7 +1377,7 @@ impl ProfileSettings {
         debug!("{:?}", mix_routing);
         debug!("{:?}", submix_tree);
 
-        Ok(Self {
+        let mut settings = Self {
             root,
             browser,
             animation_tree,
@@ -529,81 +1399,155 @@ impl ProfileSettings {
             echo_encoder,
             pitch_encoder,
             gender_encoder,
-        })
+            shutdown_behaviour,
+            sampler_root,
+            load_errors,
+            change_handler: None,
+            dirty: ComponentSet::default(),
+            instance: rand::random(),
+            mute_generations: EnumMap::default(),
+            mute_seen: EnumMap::default(),
+            chat_mute_generation: 0,
+            chat_mute_seen: None,
+        };
+
+        if options.repair {
+            let report = settings.repair()?;
+            if !report.is_empty() {
+                warn!(
+                    "Profile was missing components, recreated: {:?}",
+                    report.created
+                );
+            }
+        }
+
+        Ok(settings)
     }
 
     pub fn load_preset<R: Read>(&mut self, read: R) -> Result<()> {
-        let buf_reader = BufReader::new(read);
-        let mut reader = Reader::from_reader(buf_reader);
+        self.load_preset_with_options(read, &LoadOptions::default())
+    }
 
-        // So, in principle here, all we need to do is loop over the tags, check on the
-        // tag name, and load it directly into the relevant effect. This should force a
-        // replace of the current effect, and bam, done.
+    pub fn load_preset_with_options<R: Read>(
+        &mut self,
+        read: R,
+        options: &LoadOptions,
+    ) -> Result<()> {
+        let parsed = ParsedPreset::load_with_options(read, options)?;
 
-        // Firstly, we need the current preset to overwrite.
+        // Replace the currently selected bank with the contents of the preset..
         let current = self.context().selected_effects();
-        let mut buf = Vec::new();
-        loop {
-            match reader.read_event_into(&mut buf) {
-                Ok(Event::Empty(ref e)) => {
-                    let (name, attributes) = wrap_start_event(e)?;
-
-                    match name.as_str() {
-                        "reverbEncoder" => self
-                            .reverb_encoder
-                            .parse_reverb_preset(current, &attributes)?,
-                        "echoEncoder" => {
-                            self.echo_encoder.parse_echo_preset(current, &attributes)?
-                        }
-                        "pitchEncoder" => self
-                            .pitch_encoder
-                            .parse_pitch_preset(current, &attributes)?,
-                        "genderEncoder" => self
-                            .gender_encoder
-                            .parse_gender_preset(current, &attributes)?,
-                        "megaphoneEffect" => self
-                            .megaphone_effect
-                            .parse_megaphone_preset(current, &attributes)?,
-                        "robotEffect" => {
-                            self.robot_effect.parse_robot_preset(current, &attributes)?
-                        }
-                        "hardtuneEffect" => self
-                            .hardtune_effect
-                            .parse_hardtune_preset(current, &attributes)?,
-                        _ => warn!("Unexpected Start Tag {}", name),
-                    }
-                }
+        self.apply_preset(current, &parsed)
+    }
 
-                Ok(Event::Start(ref e)) => {
-                    let (_name, attributes) = wrap_start_event(e)?;
-                    let mut found = false;
+    pub fn apply_preset(&mut self, preset: Preset, parsed: &ParsedPreset) -> Result<()> {
+        self.effects_mut(preset).set_name(parsed.name.clone())?;
+        for (name, attributes) in &parsed.elements {
+            let attributes = &Attributes::new(attributes);
+            match name.as_str() {
+                "reverbEncoder" => self
+                    .reverb_encoder_mut()
+                    .parse_reverb_preset(preset, attributes)?,
+                "echoEncoder" => self
+                    .echo_encoder_mut()
+                    .parse_echo_preset(preset, attributes)?,
+                "pitchEncoder" => self
+                    .pitch_encoder_mut()
+                    .parse_pitch_preset(preset, attributes)?,
+                "genderEncoder" => self
+                    .gender_encoder_mut()
+                    .parse_gender_preset(preset, attributes)?,
+                "megaphoneEffect" => self
+                    .megaphone_effect_mut()
+                    .parse_megaphone_preset(preset, attributes)?,
+                "robotEffect" => self
+                    .robot_effect_mut()
+                    .parse_robot_preset(preset, attributes)?,
+                "hardtuneEffect" => self
+                    .hardtune_effect_mut()
+                    .parse_hardtune_preset(preset, attributes)?,
+                _ => warn!("Unexpected Start Tag {}", name),
+            }
+        }
+        Ok(())
+    }
 
-                    // We can cheese this a little, there's only one tag in a preset that has
-                    // children, and that's the top level element. So if this is going, we
-                    // already know what to do.
-                    for attribute in attributes {
-                        if attribute.name == "name" {
-                            found = true;
-                            self.effects_mut(current).set_name(attribute.value)?;
-                            break;
-                        }
-                    }
-                    if !found {
-                        bail!("Preset Name not found, cannot proceed.");
-                    }
-                }
+    /// Returns true if the effect bank is identical to the contents of a preset file, useful for
+    /// showing whether a bank has been modified since the preset was loaded.
+    pub fn bank_matches_preset(&self, preset: Preset, preset_xml: impl Read) -> Result<bool> {
+        let parsed = ParsedPreset::load(preset_xml)?;
+        if self.effects(preset).name() != parsed.name {
+            return Ok(false);
+        }
 
-                // Ends a tag with children
-                Ok(Event::End(_)) => {}
-                Ok(Event::Eof) => {
-                    break;
+        // Parse each element into a fresh base, then compare the result with the current bank..
+        for (name, attributes) in &parsed.elements {
+            let attributes = &Attributes::new(attributes);
+            let (from_preset, current) = match name.as_str() {
+                "reverbEncoder" => {
+                    let mut base = ReverbEncoderBase::new(name.clone());
+                    base.parse_reverb_preset(preset, attributes)?;
+                    (
+                        base.get_preset_attributes(preset),
+                        self.reverb_encoder.get_preset_attributes(preset),
+                    )
                 }
+                "echoEncoder" => {
+                    let mut base = EchoEncoderBase::new(name.clone());
+                    base.parse_echo_preset(preset, attributes)?;
+                    (
+                        base.get_preset_attributes(preset),
+                        self.echo_encoder.get_preset_attributes(preset),
+                    )
+                }
+                "pitchEncoder" => {
+                    let mut base = PitchEncoderBase::new(name.clone());
+                    base.parse_pitch_preset(preset, attributes)?;
+                    (
+                        base.get_preset_attributes(preset),
+                        self.pitch_encoder.get_preset_attributes(preset),
+                    )
+                }
+                "genderEncoder" => {
+                    let mut base = GenderEncoderBase::new(name.clone());
+                    base.parse_gender_preset(preset, attributes)?;
+                    (
+                        base.get_preset_attributes(preset),
+                        self.gender_encoder.get_preset_attributes(preset),
+                    )
+                }
+                "megaphoneEffect" => {
+                    let mut base = MegaphoneEffectBase::new(name.clone());
+                    base.parse_megaphone_preset(preset, attributes)?;
+                    (
+                        base.get_preset_attributes(preset),
+                        self.megaphone_effect.get_preset_attributes(preset),
+                    )
+                }
+                "robotEffect" => {
+                    let mut base = RobotEffectBase::new(name.clone());
+                    base.parse_robot_preset(preset, attributes)?;
+                    (
+                        base.get_preset_attributes(preset),
+                        self.robot_effect.get_preset_attributes(preset),
+                    )
+                }
+                "hardtuneEffect" => {
+                    let mut base = HardtuneEffectBase::new(name.clone());
+                    base.parse_hardtune_preset(preset, attributes)?;
+                    (
+                        base.get_preset_attributes(preset),
+                        self.hardtune_effect.get_preset_attributes(preset),
+                    )
+                }
+                _ => continue,
+            };
 
-                Ok(_) => {}
-                Err(_) => {}
+            if from_preset != current {
+                return Ok(false);
             }
         }
-        Ok(())
+        Ok(true)
     }
 
     pub fn write<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
@@ -612,6 +1556,25 @@ impl ProfileSettings {
     }
 
     pub fn write_to<W: Write>(&mut self, sink: W) -> Result<()> {
+        self.write_to_with_options(sink, &WriteOptions::default())
+    }
+
+    pub fn write_to_with_options<W: Write>(
+        &mut self,
+        mut sink: W,
+        options: &WriteOptions,
+    ) -> Result<()> {
+        let compat = options.official_app_compat;
+        let mut crlf_sink;
+
+        // Encoders and Effects are written as trait objects, so the writer needs a dyn sink..
+        let sink: &mut dyn Write = if compat {
+            sink.write_all(UTF8_BOM)?;
+            crlf_sink = CrlfWriter(&mut sink);
+            &mut crlf_sink
+        } else {
+            &mut sink
+        };
         let mut writer = Writer::new_with_indent(sink, u8::try_from('\t')?, 1);
         writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("utf-8"), None)))?;
 
@@ -636,28 +1599,28 @@ impl ProfileSettings {
                 .set_headphone_mix(Mix::A);
         }
 
-        self.root.write_initial(&mut writer)?;
+        self.root.write_initial(&mut writer, compat)?;
         self.browser.write_browser(&mut writer)?;
         self.animation_tree.write_animation(&mut writer)?;
 
         self.mix_routing.write_mix_tree(&mut writer)?;
         self.submix_tree.write_submixer(&mut writer)?;
 
-        self.mixer.write_mixers(&mut writer)?;
+        self.mixer.write_mixers(&mut writer, compat)?;
         self.context.write_context(&mut writer)?;
 
         self.mute_chat.write_mute_chat(&mut writer)?;
 
         for (faders, mute_button) in self.mute_buttons.iter() {
             if let Some(mute_button) = mute_button {
-                let name = format!("mute{}", (faders as u8) + 1);
+                let name = FaderElement::Mute.tag_name(faders);
                 mute_button.write_button(name, &mut writer)?;
             }
         }
 
         for (faders, fader) in self.faders.iter() {
             if let Some(fader) = fader {
-                let name = format!("FaderMeter{}", faders as u8);
+                let name = FaderElement::FaderMeter.tag_name(faders);
                 fader.write_fader(name, &mut writer)?;
             }
         }
@@ -674,317 +1637,3951 @@ impl ProfileSettings {
             }
         }
 
-        self.megaphone_effect.write_megaphone(&mut writer)?;
-        self.robot_effect.write_robot(&mut writer)?;
-        self.hardtune_effect.write_hardtune(&mut writer)?;
+        for (_effect, base) in self.effect_bases() {
+            base.write(&mut writer, options.minimal)?;
+        }
+
+        for (_encoder, base) in self.encoders() {
+            base.write(&mut writer, options.minimal)?;
+        }
+
+        for (_key, value) in &self.sampler_map {
+            if let Some(value) = value {
+                value.write_sample(&mut writer, options.minimal)?;
+            }
+        }
+
+        for (_name, simple_element) in self.simple_elements() {
+            simple_element.write_simple(&mut writer)?;
+        }
+
+        if let Some(shutdown_behaviour) = &self.shutdown_behaviour {
+            shutdown_behaviour.write_shutdown(&mut writer)?;
+        }
+
+        if let Some(sampler_root) = &self.sampler_root {
+            let mut elem = BytesStart::new(SAMPLER_ROOT);
+            elem.push_attribute(("path", sampler_root.as_str()));
+            writer.write_event(Event::Empty(elem))?;
+        }
+
+        // Finalise the XML..
+        self.root.write_final(&mut writer)?;
+
+        let routing = self.mixer.mixer_table_mut();
+        // Everything's written, restore the original monitor settings..
+        if monitored_output != OutputChannels::Headphones {
+            for input in InputChannels::iter() {
+                routing[input][OutputChannels::Headphones] = routing[input][monitored_output];
+            }
+            self.submix_tree
+                .monitor_tree_mut()
+                .set_monitored_output(monitored_output);
+            self.submix_tree
+                .monitor_tree_mut()
+                .set_headphone_mix(headphone_mix);
+        }
+
+        Ok(())
+    }
+
+    pub fn write_preset<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let out_file = File::create(path)?;
+        self.write_preset_to(&out_file)?;
+        out_file.sync_all().context("Unable to Sync File")
+    }
+
+    pub fn write_preset_to<W: Write>(&self, sink: W) -> Result<()> {
+        self.write_preset_bank_to(self.context().selected_effects(), sink)
+    }
+
+    pub fn write_preset_bank_to<W: Write>(&self, current: Preset, sink: W) -> Result<()> {
+        let mut writer = Writer::new_with_indent(sink, u8::try_from('\t')?, 1);
+        writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("utf-8"), None)))?;
+
+        let preset_writer = PresetWriter::new(String::from(self.effects(current).name()));
+        preset_writer.write_initial(&mut writer)?;
+        preset_writer.write_tag(
+            &mut writer,
+            "reverbEncoder",
+            self.reverb_encoder.get_preset_attributes(current),
+        )?;
+
+        preset_writer.write_tag(
+            &mut writer,
+            "echoEncoder",
+            self.echo_encoder.get_preset_attributes(current),
+        )?;
+
+        preset_writer.write_tag(
+            &mut writer,
+            "pitchEncoder",
+            self.pitch_encoder.get_preset_attributes(current),
+        )?;
+
+        preset_writer.write_tag(
+            &mut writer,
+            "genderEncoder",
+            self.gender_encoder.get_preset_attributes(current),
+        )?;
+
+        preset_writer.write_tag(
+            &mut writer,
+            "megaphoneEffect",
+            self.megaphone_effect.get_preset_attributes(current),
+        )?;
+
+        preset_writer.write_tag(
+            &mut writer,
+            "robotEffect",
+            self.robot_effect.get_preset_attributes(current),
+        )?;
+
+        preset_writer.write_tag(
+            &mut writer,
+            "hardtuneEffect",
+            self.hardtune_effect.get_preset_attributes(current),
+        )?;
+
+        preset_writer.write_final(&mut writer)?;
+        Ok(())
+    }
+
+    /// Writes all six effect banks into a single zip, each stored as a regular preset file
+    /// (preset1.preset through preset6.preset), so they can also be extracted and used alone.
+    pub fn export_preset_bundle<W: Write + Seek>(&self, sink: W) -> Result<()> {
+        let mut archive = zip::ZipWriter::new(sink);
+        for preset in Preset::iter() {
+            archive.start_file(preset_bundle_entry(preset), FileOptions::default())?;
+            self.write_preset_bank_to(preset, &mut archive)?;
+        }
+        archive.finish()?;
+        Ok(())
+    }
+
+    /// Loads a bundle created by export_preset_bundle, replacing each bank present in the bundle.
+    /// Banks missing from the bundle are left as they are.
+    pub fn import_preset_bundle<R: Read + Seek>(&mut self, read: R) -> Result<()> {
+        let mut archive = zip::ZipArchive::new(read)?;
+
+        // Parse everything first, so a broken entry doesn't leave us with a partial import..
+        let mut parsed = vec![];
+        for preset in Preset::iter() {
+            if let Ok(file) = archive.by_name(&preset_bundle_entry(preset)) {
+                parsed.push((preset, ParsedPreset::load(file)?));
+            }
+        }
+
+        // A preset can still be rejected while it's applied (a value out of range, say), so keep
+        // a copy of the banks to put back, rather than leaving a partial import..
+        let original = self.effect_banks();
+        let dirty = self.dirty.clone();
+        for (preset, parsed) in parsed {
+            if let Err(error) = self.apply_preset(preset, &parsed) {
+                self.set_effect_banks(original);
+                self.dirty = dirty;
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+
+    fn effect_banks(&self) -> EffectBanks {
+        EffectBanks {
+            effects: self.effects.clone(),
+            megaphone: self.megaphone_effect.clone(),
+            robot: self.robot_effect.clone(),
+            hardtune: self.hardtune_effect.clone(),
+            reverb: self.reverb_encoder.clone(),
+            echo: self.echo_encoder.clone(),
+            pitch: self.pitch_encoder.clone(),
+            gender: self.gender_encoder.clone(),
+        }
+    }
+
+    fn set_effect_banks(&mut self, banks: EffectBanks) {
+        self.effects = banks.effects;
+        self.megaphone_effect = banks.megaphone;
+        self.robot_effect = banks.robot;
+        self.hardtune_effect = banks.hardtune;
+        self.reverb_encoder = banks.reverb;
+        self.echo_encoder = banks.echo;
+        self.pitch_encoder = banks.pitch;
+        self.gender_encoder = banks.gender;
+    }
+
+    pub fn parse_preset(key: String) -> Result<Preset> {
+        if let Some(id) = key
+            .chars()
+            .last()
+            .map(|s| u8::from_str(&s.to_string()))
+            .transpose()?
+        {
+            if let Some(preset) = Preset::iter().nth((id - 1) as usize) {
+                return Ok(preset);
+            }
+        }
+        Err(anyhow!("Unable to Parse Preset from Number"))
+    }
+
+    /// The profile's root element, which holds the format version, the device it was created
+    /// for, and any metadata attributes added by other tools.
+    pub fn root(&self) -> &RootElement {
+        &self.root
+    }
+
+    pub fn root_mut(&mut self) -> &mut RootElement {
+        self.dirty.insert(ComponentId::Root);
+        &mut self.root
+    }
+
+    pub fn shutdown_behaviour(&self) -> Option<&ShutdownBehaviour> {
+        self.shutdown_behaviour.as_ref()
+    }
+
+    pub fn shutdown_behaviour_mut(&mut self) -> Option<&mut ShutdownBehaviour> {
+        self.dirty.insert(ComponentId::ShutdownBehaviour);
+        self.shutdown_behaviour.as_mut()
+    }
+
+    pub fn set_shutdown_behaviour(&mut self, behaviour: Option<ShutdownBehaviour>) {
+        self.dirty.insert(ComponentId::ShutdownBehaviour);
+        self.shutdown_behaviour = behaviour;
+    }
+
+    /// The errors skipped over while loading with LoadOptions::lenient, always empty otherwise.
+    pub fn load_errors(&self) -> &[ParseError] {
+        &self.load_errors
+    }
+
+    pub fn sampler_root(&self) -> Option<&str> {
+        self.sampler_root.as_deref()
+    }
+
+    pub fn set_sampler_root(&mut self, root: Option<String>) {
+        self.dirty.insert(ComponentId::SamplerRoot);
+        self.sampler_root = root;
+    }
+
+    /// Builds a summary of the profile, suitable for listing profiles without loading each of
+    /// them into a device. This is cheap, but callers rendering often should hold onto it.
+    pub fn summary(&self) -> ProfileSummary {
+        ProfileSummary {
+            faders: self.faders.values().flatten().count(),
+            effect_banks: self.effects.values().flatten().count(),
+            samples: self.referenced_samples().len(),
+            device: self.root.device(),
+        }
+    }
+
+    /// Every sample file referenced by the profile, across all buttons and banks, without
+    /// duplicates. These are the names as stored, see resolve_sample_path to locate them.
+    pub fn referenced_samples(&self) -> Vec<String> {
+        let mut samples: Vec<String> = vec![];
+        for (_button, sampler) in &self.sampler_map {
+            if let Some(sampler) = sampler {
+                for track in sampler.tracks() {
+                    if !samples.iter().any(|sample| sample == track.track()) {
+                        samples.push(track.track().to_string());
+                    }
+                }
+            }
+        }
+        samples
+    }
+
+    /**
+     * Locates the file for a track. Profiles imported from Windows may contain absolute paths
+     * (C:\Users\..\sample.wav), which won't exist here, so as well as the path as stored, the
+     * file is looked for by its relative path and then by name under the sampler root (if set,
+     * relative roots are under samples_dir) and samples_dir. If nothing matches, the error
+     * lists every path which was tried.
+     */
+    pub fn resolve_sample_path(
+        &self,
+        track: &Track,
+        samples_dir: &Path,
+    ) -> Result<PathBuf, SampleError> {
+        let stored = track.track();
+
+        // Normalise Windows separators, and check whether this is a Windows absolute path..
+        let normalised = stored.replace('\\', "/");
+        let windows_absolute = normalised.starts_with("//")
+            || (normalised.len() > 2
+                && normalised.as_bytes()[1] == b':'
+                && normalised.as_bytes()[0].is_ascii_alphabetic());
+        let file_name = normalised.rsplit('/').next().unwrap_or(&normalised);
+
+        let mut candidates = vec![];
+        if Path::new(stored).is_absolute() {
+            candidates.push(PathBuf::from(stored));
+        }
+
+        let mut roots = vec![];
+        if let Some(root) = &self.sampler_root {
+            roots.push(samples_dir.join(root));
+        }
+        roots.push(samples_dir.to_path_buf());
+
+        for root in roots {
+            if !windows_absolute && !Path::new(&normalised).is_absolute() {
+                candidates.push(root.join(&normalised));
+            }
+            candidates.push(root.join(file_name));
+        }
+        candidates.dedup();
+
+        for candidate in &candidates {
+            if candidate.is_file() {
+                return Ok(candidate.clone());
+            }
+        }
+
+        Err(SampleError::NotFound {
+            track: stored.to_string(),
+            tried: candidates,
+        })
+    }
+
+    pub fn animation(&self) -> &AnimationTree {
+        &self.animation_tree
+    }
+
+    pub fn animation_mut(&mut self) -> &mut AnimationTree {
+        self.dirty.insert(ComponentId::Animation);
+        &mut self.animation_tree
+    }
+
+    pub fn mixer_mut(&mut self) -> &mut Mixers {
+        self.dirty.insert(ComponentId::Mixer);
+        &mut self.mixer
+    }
+
+    pub fn mixer(&self) -> &Mixers {
+        &self.mixer
+    }
+
+    /// The output currently being monitored through the headphones, see MonitorTree for how the
+    /// headphone routing is stored while another output is being monitored.
+    pub fn monitored_output(&self) -> OutputChannels {
+        self.submix_tree.monitor_tree().monitored_output()
+    }
+
+    /// Syncs volumes reported by the hardware (for example, when a fader has been moved by hand)
+    /// back into the mixer, so that saving doesn't revert them. Returns true if anything changed.
+    pub fn apply_hardware_volumes(&mut self, volumes: &[(FullChannelList, u8)]) -> Result<bool> {
+        let mut changed = false;
+        for (channel, volume) in volumes {
+            if self.mixer.channel_volume(*channel) != *volume {
+                self.mixer_mut().set_channel_volume(*channel, *volume)?;
+                changed = true;
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Syncs the state read back from the device into the profile, currently this is just the
+    /// fader positions, which are applied to whichever channel is assigned to each fader.
+    /// Returns true if anything changed.
+    pub fn apply_hardware_state(&mut self, state: &HardwareState) -> Result<bool> {
+        let volumes: Vec<(FullChannelList, u8)> = state
+            .fader_volumes
+            .iter()
+            .map(|(fader, volume)| (self.fader(fader).channel(), *volume))
+            .collect();
+        self.apply_hardware_volumes(&volumes)
+    }
+
+    /// Checks whether the state read back from the device is what this profile would set, that is
+    /// each fader's position is the volume of the channel assigned to it. This is the inverse of
+    /// apply_hardware_state, which would leave a matching profile unchanged.
+    pub fn matches_hardware_state(&self, state: &HardwareState) -> bool {
+        state.fader_volumes.iter().all(|(fader, volume)| {
+            self.mixer.channel_volume(self.fader(fader).channel()) == *volume
+        })
+    }
+
+    /// Plans a fade of a channel from its current volume to the target, returning the volume to
+    /// set at each offset from the start. This is the same plan goxlr-usb's ramp_volume follows,
+    /// for callers which schedule the updates themselves.
+    pub fn fade_plan(
+        &self,
+        channel: FullChannelList,
+        target: u8,
+        duration: Duration,
+    ) -> Vec<(Duration, u8)> {
+        let from = self.mixer.channel_volume(channel);
+        ramp_steps(from, target, duration, FADE_STEP_INTERVAL)
+    }
+
+    pub fn faders(&self) -> &EnumMap<Faders, Option<Fader>> {
+        &self.faders
+    }
+
+    pub fn faders_mut(&mut self) -> &mut EnumMap<Faders, Option<Fader>> {
+        Faders::iter().for_each(|fader| self.dirty.insert(ComponentId::Fader(fader)));
+        &mut self.faders
+    }
+
+    pub fn fader_mut(&mut self, fader: Faders) -> &mut Fader {
+        self.dirty.insert(ComponentId::Fader(fader));
+        self.faders[fader].as_mut().unwrap()
+    }
+
+    pub fn fader(&self, fader: Faders) -> &Fader {
+        self.faders[fader].as_ref().unwrap()
+    }
+
+    /**
+     * Assigns a channel to a fader. If the fader's scribble is set to follow the channel, and its
+     * bottom text is still the previous channel's name, the text is changed to the new channel's
+     * name. Text which doesn't match (because the user has changed it) is never touched.
+     */
+    pub fn assign_fader_channel(&mut self, fader: Faders, channel: FullChannelList) {
+        let previous = self.fader(fader).channel();
+        self.fader_mut(fader).set_channel(channel);
+
+        let follows = match &self.scribbles[fader] {
+            Some(scribble) => {
+                scribble.follows_channel()
+                    && scribble.text_bottom_middle().as_deref() == Some(previous.display_name())
+            }
+            None => false,
+        };
+
+        if follows {
+            let text = channel.display_name().to_string();
+            self.scribble_mut(fader).set_text_bottom_middle(text);
+        }
+    }
+
+    /**
+     * Links (or unlinks) a fader's colours to its scribble's backlight. While linked, changing
+     * either side through set_fader_colours or set_scribble_colour also changes the other, with
+     * the top of the fader matching the scribble. Linking copies the scribble's current colour
+     * across to the fader.
+     */
+    pub fn link_fader_colours(&mut self, fader: Faders, linked: bool) -> Result<()> {
+        self.scribble_mut(fader).set_fader_colours_linked(linked);
+        if linked {
+            self.sync_linked_fader(fader)?;
+        }
+        Ok(())
+    }
+
+    pub fn fader_colours_linked(&self, fader: Faders) -> bool {
+        match &self.scribbles[fader] {
+            Some(scribble) => scribble.fader_colours_linked(),
+            None => false,
+        }
+    }
+
+    pub fn set_fader_colours(&mut self, fader: Faders, top: Colour, bottom: Colour) -> Result<()> {
+        let colours = self.fader_mut(fader).colour_map_mut();
+        colours.set_colour(0, top)?;
+        colours.set_colour(1, bottom)?;
+
+        if self.fader_colours_linked(fader) {
+            self.scribble_mut(fader)
+                .colour_map_mut()
+                .set_colour(0, top)?;
+        }
+        Ok(())
+    }
+
+    pub fn set_scribble_colour(&mut self, fader: Faders, colour: Colour) -> Result<()> {
+        self.scribble_mut(fader)
+            .colour_map_mut()
+            .set_colour(0, colour)?;
+
+        if self.fader_colours_linked(fader) {
+            self.fader_mut(fader)
+                .colour_map_mut()
+                .set_colour(0, colour)?;
+        }
+        Ok(())
+    }
+
+    /// For when colours have been changed directly on the colour maps (such as applying a global
+    /// colour), brings every linked fader back in line with its scribble..
+    pub fn sync_linked_fader_colours(&mut self) -> Result<()> {
+        for fader in Faders::iter() {
+            if self.fader_colours_linked(fader) {
+                self.sync_linked_fader(fader)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn sync_linked_fader(&mut self, fader: Faders) -> Result<()> {
+        let colour = *self.scribble(fader).colour_map().colour_or_default(0);
+        self.fader_mut(fader).colour_map_mut().set_colour(0, colour)
+    }
+
+    /**
+     * Looks for signs that faders, mute buttons or scribbles were saved into the wrong slot, which
+     * could happen with older versions that didn't agree on whether fader IDs started at 0 or 1.
+     * This only reports what it finds, nothing in the profile is changed.
+     */
+    pub fn audit_fader_assignments(&self) -> Vec<FaderAnomaly> {
+        let mut anomalies = vec![];
+
+        for fader in Faders::iter() {
+            let prefixes = [
+                (
+                    FaderElement::FaderMeter,
+                    self.faders[fader].as_ref().map(|f| f.colour_map().prefix()),
+                ),
+                (
+                    FaderElement::Mute,
+                    self.mute_buttons[fader]
+                        .as_ref()
+                        .map(|m| m.colour_map().prefix()),
+                ),
+                (
+                    FaderElement::Scribble,
+                    self.scribbles[fader]
+                        .as_ref()
+                        .map(|s| s.colour_map().prefix()),
+                ),
+            ];
+
+            for (element, prefix) in prefixes {
+                let expected = element.tag_name(fader);
+                if let Some(prefix) = prefix {
+                    if prefix != expected {
+                        anomalies.push(FaderAnomaly::MismatchedElement {
+                            fader,
+                            element: prefix.to_string(),
+                            expected,
+                        });
+                    }
+                }
+            }
+        }
+
+        for channel in FullChannelList::iter() {
+            let faders: Vec<Faders> = Faders::iter()
+                .filter(|fader| match &self.faders[*fader] {
+                    Some(value) => value.channel() == channel,
+                    None => false,
+                })
+                .collect();
+            if faders.len() > 1 {
+                anomalies.push(FaderAnomaly::DuplicateChannel { channel, faders });
+            }
+        }
+
+        // Text the user has changed won't match any channel, so this only flags channel names..
+        for fader in Faders::iter() {
+            let text = match self.scribbles[fader]
+                .as_ref()
+                .and_then(|s| s.text_bottom_middle())
+            {
+                Some(text) => text,
+                None => continue,
+            };
+            let own = self.faders[fader]
+                .as_ref()
+                .map(|f| f.channel().display_name());
+            if own == Some(text.as_str()) {
+                continue;
+            }
+
+            let matches = Faders::iter().find(|other| match &self.faders[*other] {
+                Some(value) => *other != fader && value.channel().display_name() == text,
+                None => false,
+            });
+            if let Some(matches) = matches {
+                anomalies.push(FaderAnomaly::ScribbleMismatch {
+                    fader,
+                    text,
+                    matches,
+                });
+            }
+        }
+
+        anomalies
+    }
+
+    /**
+     * Mutes every fader (and the chat mute) with the given function, returning what was changed
+     * so it can be undone with restore. Anything which is already muted is left alone, so keeps
+     * its own function and previous volume. As with the official app, muting to all stores the
+     * channel's volume as the button's previous volume and drops the channel to 0.
+     *
+     * This only changes the profile, applying it to the device is up to the caller.
+     */
+    pub fn mute_all(&mut self, function: MuteFunction) -> Result<MuteAllSnapshot> {
+        let mut faders = vec![];
+        for fader in Faders::iter() {
+            if self.mute_buttons[fader].is_none() || self.faders[fader].is_none() {
+                continue;
+            }
+
+            let button = self.mute_button(fader);
+            if button.colour_map().get_state() || button.colour_map().is_blink() {
+                continue;
+            }
+
+            let channel = self.fader(fader).channel();
+            let volume = self.mixer.channel_volume(channel);
+            let entry = FaderMuteSnapshot {
+                fader,
+                generation: 0,
+                channel,
+                function: *button.mute_function(),
+                previous_volume: button.previous_volume(),
+                volume,
+                applied: function,
+            };
+
+            let button = self.mute_button_mut(fader);
+            button.set_mute_function(function);
+            button.colour_map_mut().set_state_on(true)?;
+            if function == MuteFunction::All {
+                button.set_previous_volume(volume)?;
+                self.mixer_mut().set_channel_volume(channel, 0)?;
+            }
+
+            faders.push(FaderMuteSnapshot {
+                generation: self.mute_generation(fader),
+                ..entry
+            });
+        }
+
+        let mut chat = None;
+        if !self.mute_chat.cough_button_on() && !self.mute_chat.get_blink_on() {
+            let previous = *self.mute_chat.cough_mute_source();
+
+            let mute_chat = self.mute_chat_mut();
+            mute_chat.set_cough_mute_source(function);
+            mute_chat.set_cough_button_on(true);
+
+            chat = Some(ChatMuteSnapshot {
+                generation: self.chat_mute_generation(),
+                function: previous,
+                applied: function,
+            });
+        }
+
+        Ok(MuteAllSnapshot {
+            instance: self.instance,
+            faders,
+            chat,
+        })
+    }
+
+    /**
+     * Undoes a mute_all. Anything which has been changed since (for example, unmuted and muted
+     * again by hand, or had its channel reassigned) is left as it is.
+     */
+    pub fn restore(&mut self, snapshot: &MuteAllSnapshot) -> Result<()> {
+        let same_instance = snapshot.instance == self.instance;
+
+        for entry in &snapshot.faders {
+            let fader = entry.fader;
+            if self.mute_buttons[fader].is_none() || self.faders[fader].is_none() {
+                continue;
+            }
+
+            let untouched = if same_instance {
+                self.mute_generation(fader) == entry.generation
+            } else {
+                let button = self.mute_button(fader);
+                button.colour_map().get_state()
+                    && !button.colour_map().is_blink()
+                    && *button.mute_function() == entry.applied
+            };
+            if !untouched || self.fader(fader).channel() != entry.channel {
+                debug!("{:?} has changed since it was muted, leaving it", fader);
+                continue;
+            }
+
+            let button = self.mute_button_mut(fader);
+            button.colour_map_mut().set_state_on(false)?;
+            button.colour_map_mut().set_blink_on(false)?;
+            button.set_mute_function(entry.function);
+            button.set_previous_volume(entry.previous_volume)?;
+            if entry.applied == MuteFunction::All {
+                self.mixer_mut()
+                    .set_channel_volume(entry.channel, entry.volume)?;
+            }
+        }
+
+        if let Some(entry) = &snapshot.chat {
+            let untouched = if same_instance {
+                self.chat_mute_generation() == entry.generation
+            } else {
+                self.mute_chat.cough_button_on()
+                    && !self.mute_chat.get_blink_on()
+                    && *self.mute_chat.cough_mute_source() == entry.applied
+            };
+
+            if untouched {
+                let mute_chat = self.mute_chat_mut();
+                mute_chat.set_cough_button_on(false);
+                mute_chat.set_cough_mute_source(entry.function);
+            } else {
+                debug!("The chat mute has changed since it was muted, leaving it");
+            }
+        }
+        Ok(())
+    }
+
+    pub fn mute_buttons(&self) -> &EnumMap<Faders, Option<MuteButton>> {
+        &self.mute_buttons
+    }
+
+    pub fn mute_buttons_mut(&mut self) -> &mut EnumMap<Faders, Option<MuteButton>> {
+        Faders::iter().for_each(|fader| {
+            self.dirty.insert(ComponentId::MuteButton(fader));
+            self.mute_generation(fader);
+        });
+        &mut self.mute_buttons
+    }
+
+    pub fn mute_button_mut(&mut self, fader: Faders) -> &mut MuteButton {
+        self.dirty.insert(ComponentId::MuteButton(fader));
+        self.mute_generation(fader);
+        self.mute_buttons[fader].as_mut().unwrap()
+    }
+
+    // Catches up with any change since the button was last seen, and returns the generation..
+    fn mute_generation(&mut self, fader: Faders) -> u64 {
+        let state = self.mute_buttons[fader].as_ref().map(|button| {
+            let colour_map = button.colour_map();
+            (
+                colour_map.get_state(),
+                colour_map.is_blink(),
+                *button.mute_function(),
+                button.previous_volume(),
+            )
+        });
+
+        if self.mute_seen[fader] != state {
+            self.mute_seen[fader] = state;
+            self.mute_generations[fader] += 1;
+        }
+        self.mute_generations[fader]
+    }
+
+    pub fn mute_button(&self, fader: Faders) -> &MuteButton {
+        self.mute_buttons[fader].as_ref().unwrap()
+    }
+
+    /// Resolves what the mute button on a fader does to the channel currently assigned to it..
+    pub fn mute_action_for_fader(&self, fader: Faders) -> MuteAction {
+        let channel = self.fader(fader).channel();
+        let function = *self.mute_button(fader).mute_function();
+        MuteAction::new(channel, function)
+    }
+
+    pub fn scribbles(&self) -> &EnumMap<Faders, Option<Scribble>> {
+        &self.scribbles
+    }
+
+    pub fn scribbles_mut(&mut self) -> &mut EnumMap<Faders, Option<Scribble>> {
+        Faders::iter().for_each(|fader| self.dirty.insert(ComponentId::Scribble(fader)));
+        &mut self.scribbles
+    }
+
+    pub fn scribble(&self, fader: Faders) -> &Scribble {
+        self.scribbles[fader].as_ref().unwrap()
+    }
+
+    pub fn scribble_mut(&mut self, fader: Faders) -> &mut Scribble {
+        self.dirty.insert(ComponentId::Scribble(fader));
+        self.scribbles[fader].as_mut().unwrap()
+    }
+
+    pub fn effects(&self, effect: Preset) -> &Effects {
+        self.effects[effect].as_ref().unwrap()
+    }
+
+    pub fn effects_mut(&mut self, effect: Preset) -> &mut Effects {
+        self.dirty.insert(ComponentId::Effects(effect));
+        self.effects[effect].as_mut().unwrap()
+    }
+
+    pub fn mute_chat_mut(&mut self) -> &mut MuteChat {
+        self.dirty.insert(ComponentId::MuteChat);
+        self.chat_mute_generation();
+        &mut self.mute_chat
+    }
+
+    fn chat_mute_generation(&mut self) -> u64 {
+        let state = Some((
+            self.mute_chat.cough_button_on(),
+            self.mute_chat.get_blink_on(),
+            *self.mute_chat.cough_mute_source(),
+        ));
+
+        if self.chat_mute_seen != state {
+            self.chat_mute_seen = state;
+            self.chat_mute_generation += 1;
+        }
+        self.chat_mute_generation
+    }
+
+    pub fn mute_chat(&self) -> &MuteChat {
+        &self.mute_chat
+    }
+
+    pub fn megaphone_effect(&self) -> &MegaphoneEffectBase {
+        &self.megaphone_effect
+    }
+
+    pub fn megaphone_effect_mut(&mut self) -> &mut MegaphoneEffectBase {
+        self.dirty.insert(ComponentId::Megaphone);
+        &mut self.megaphone_effect
+    }
+
+    pub fn robot_effect(&self) -> &RobotEffectBase {
+        &self.robot_effect
+    }
+
+    pub fn robot_effect_mut(&mut self) -> &mut RobotEffectBase {
+        self.dirty.insert(ComponentId::Robot);
+        &mut self.robot_effect
+    }
+
+    pub fn hardtune_effect(&self) -> &HardtuneEffectBase {
+        &self.hardtune_effect
+    }
+
+    pub fn hardtune_effect_mut(&mut self) -> &mut HardtuneEffectBase {
+        self.dirty.insert(ComponentId::HardTune);
+        &mut self.hardtune_effect
+    }
+
+    pub fn sample_button(&self, button: SampleButtons) -> &SampleBase {
+        self.sampler_map[button].as_ref().unwrap()
+    }
+
+    pub fn sample_button_mut(&mut self, button: SampleButtons) -> &mut SampleBase {
+        self.dirty.insert(ComponentId::Sampler(button));
+        self.sampler_map[button].as_mut().unwrap()
+    }
+
+    /// As with sample_button, but returns None if the profile doesn't have this button (for
+    /// example, profiles created on a Mini).
+    pub fn try_sample_button(&self, button: SampleButtons) -> Option<&SampleBase> {
+        self.sampler_map[button].as_ref()
+    }
+
+    pub fn try_sample_button_mut(&mut self, button: SampleButtons) -> Option<&mut SampleBase> {
+        if self.sampler_map[button].is_some() {
+            self.dirty.insert(ComponentId::Sampler(button));
+        }
+        self.sampler_map[button].as_mut()
+    }
+
+    pub fn pitch_encoder(&self) -> &PitchEncoderBase {
+        &self.pitch_encoder
+    }
+
+    pub fn pitch_encoder_mut(&mut self) -> &mut PitchEncoderBase {
+        self.dirty.insert(ComponentId::Pitch);
+        &mut self.pitch_encoder
+    }
+
+    pub fn echo_encoder(&self) -> &EchoEncoderBase {
+        &self.echo_encoder
+    }
+
+    pub fn echo_encoder_mut(&mut self) -> &mut EchoEncoderBase {
+        self.dirty.insert(ComponentId::Echo);
+        &mut self.echo_encoder
+    }
+
+    pub fn gender_encoder(&self) -> &GenderEncoderBase {
+        &self.gender_encoder
+    }
+
+    pub fn gender_encoder_mut(&mut self) -> &mut GenderEncoderBase {
+        self.dirty.insert(ComponentId::Gender);
+        &mut self.gender_encoder
+    }
+
+    pub fn reverb_encoder(&self) -> &ReverbEncoderBase {
+        &self.reverb_encoder
+    }
+
+    pub fn reverb_encoder_mut(&mut self) -> &mut ReverbEncoderBase {
+        self.dirty.insert(ComponentId::Reverb);
+        &mut self.reverb_encoder
+    }
+
+    /**
+     * Some third party tools produce profiles which are missing elements that the official app
+     * always writes (for example, only three of the six effect banks). This recreates any missing
+     * effect banks, faders, mute buttons and scribbles from their defaults, after which their
+     * accessors are safe to call.
+     */
+    pub fn repair(&mut self) -> Result<RepairReport> {
+        let mut missing = vec![];
+        for preset in Preset::iter() {
+            if self.effects[preset].is_none() {
+                missing.push(ComponentId::Effects(preset));
+            }
+        }
+        for fader in Faders::iter() {
+            if self.faders[fader].is_none() {
+                missing.push(ComponentId::Fader(fader));
+            }
+            if self.mute_buttons[fader].is_none() {
+                missing.push(ComponentId::MuteButton(fader));
+            }
+            if self.scribbles[fader].is_none() {
+                missing.push(ComponentId::Scribble(fader));
+            }
+        }
+
+        for component in &missing {
+            self.reset_component(*component)?;
+        }
+
+        Ok(RepairReport { created: missing })
+    }
+
+    /**
+     * Resets a single component back to the state it would have if it were absent from the
+     * profile, for the effects this also re-applies the factory values for each preset's style.
+     */
+    pub fn reset_component(&mut self, component: ComponentId) -> Result<()> {
+        match component {
+            ComponentId::Mixer => self.mixer = Mixers::new(),
+            ComponentId::MuteChat => self.mute_chat = MuteChat::new(MUTE_CHAT.to_string()),
+
+            ComponentId::Fader(fader) => {
+                let id = FaderElement::FaderMeter.id(fader);
+                self.faders[fader] = Some(Fader::new(id))
+            }
+            ComponentId::MuteButton(fader) => {
+                let id = FaderElement::Mute.id(fader);
+                self.mute_buttons[fader] = Some(MuteButton::new(id))
+            }
+            ComponentId::Scribble(fader) => {
+                let id = FaderElement::Scribble.id(fader);
+                self.scribbles[fader] = Some(Scribble::new(id))
+            }
+            ComponentId::Effects(preset) => self.effects[preset] = Some(Effects::new(preset)),
+
+            ComponentId::Megaphone => {
+                let mut base = MegaphoneEffectBase::new("megaphoneEffect".to_string());
+                for preset in Preset::iter() {
+                    let effect = base.get_preset_mut(preset);
+                    effect.set_style(*effect.style())?;
+                }
+                self.megaphone_effect = base;
+            }
+            ComponentId::Robot => {
+                let mut base = RobotEffectBase::new("robotEffect".to_string());
+                for preset in Preset::iter() {
+                    let effect = base.get_preset_mut(preset);
+                    effect.set_style(*effect.style())?;
+                }
+                self.robot_effect = base;
+            }
+            ComponentId::HardTune => {
+                let mut base = HardtuneEffectBase::new("hardtuneEffect".to_string());
+                for preset in Preset::iter() {
+                    let effect = base.get_preset_mut(preset);
+                    effect.set_style(*effect.style())?;
+                }
+                self.hardtune_effect = base;
+            }
+            ComponentId::Reverb => {
+                let mut base = ReverbEncoderBase::new("reverbEncoder".to_string());
+                for preset in Preset::iter() {
+                    let encoder = base.get_preset_mut(preset);
+                    encoder.set_style(*encoder.style())?;
+                }
+                self.reverb_encoder = base;
+            }
+            ComponentId::Echo => {
+                let mut base = EchoEncoderBase::new("echoEncoder".to_string());
+                for preset in Preset::iter() {
+                    let encoder = base.get_preset_mut(preset);
+                    encoder.set_style(*encoder.style())?;
+                }
+                self.echo_encoder = base;
+            }
+
+            // Pitch and Gender don't have factory style parameters..
+            ComponentId::Pitch => {
+                self.pitch_encoder = PitchEncoderBase::new("pitchEncoder".to_string())
+            }
+            ComponentId::Gender => {
+                self.gender_encoder = GenderEncoderBase::new("genderEncoder".to_string())
+            }
+
+            ComponentId::Animation => {
+                self.animation_tree = AnimationTree::new(ANIMATION_TREE.to_string())
+            }
+            ComponentId::Context => self.context = Context::new(SELECTED_CONTEXT.to_string()),
+            ComponentId::Submixes => self.submix_tree = SubMixer::new(),
+            ComponentId::MixRouting => self.mix_routing = MixRoutingTree::new(),
+            ComponentId::Sampler(button) => {
+                let name = ComponentKind::Sampler(button).tag_name();
+                self.sampler_map[button] = Some(SampleBase::new(name))
+            }
+            ComponentId::SimpleElement(element) => {
+                self.simple_elements[element] = Some(SimpleElement::new(element.to_string()))
+            }
+            ComponentId::ShutdownBehaviour => self.shutdown_behaviour = None,
+            ComponentId::SamplerRoot => self.sampler_root = None,
+            ComponentId::Root => self.root = RootElement::new(),
+        }
+        self.dirty.insert(component);
+        Ok(())
+    }
+
+    /**
+     * The Pitch encoder and HardTune are linked, when HardTune is enabled the pitch encoder
+     * switches from semitones to whole octaves, which changes how the stored value is
+     * interpreted. The stored value itself isn't touched when HardTune is toggled, so the pitch
+     * amount should be accessed via pitch_amount / set_pitch_amount, which handle the conversion,
+     * rather than passing the HardTune state into the PitchEncoder manually.
+     *
+     * This is the state of the selected bank (see effect_enabled), anything working on a given
+     * bank should check that bank's HardTune state instead.
+     */
+    pub fn is_pitch_hardtune_linked(&self) -> bool {
+        self.hardtune_effect.colour_map().get_state()
+    }
+
+    pub fn set_hardtune_enabled(&mut self, enabled: bool) -> Result<()> {
+        self.hardtune_effect_mut()
+            .colour_map_mut()
+            .set_state_on(enabled)
+    }
+
+    /**
+     * Megaphone, Robot and HardTune each store an enabled state per preset, alongside a colour
+     * map state which drives the button light. The per-preset state is authoritative, the
+     * colour map only reflects the currently selected preset, so it's only updated here when
+     * the preset being changed is the active one (and is refreshed from the preset when the
+     * bank changes). Changing a preset other than the active one has no effect on the others.
+     */
+    pub fn effect_enabled(&self, preset: Preset, effect: EffectType) -> bool {
+        match effect {
+            EffectType::Megaphone => self.megaphone_effect.get_preset(preset).state(),
+            EffectType::Robot => self.robot_effect.get_preset(preset).state(),
+            EffectType::HardTune => self.hardtune_effect.get_preset(preset).state(),
+        }
+    }
+
+    /// The encoder positions for a bank, with pitch converted based on the bank's HardTune state.
+    pub fn encoder_values(&self, preset: Preset) -> EncoderValues {
+        let hardtune_enabled = self.effect_enabled(preset, EffectType::HardTune);
+        EncoderValues {
+            reverb: encoder_amount(self.reverb_encoder.get_preset(preset).knob_position()),
+            echo: encoder_amount(self.echo_encoder.get_preset(preset).knob_position()),
+            pitch: self
+                .pitch_encoder
+                .get_preset(preset)
+                .get_encoder_position(hardtune_enabled),
+            gender: self.gender_encoder.get_preset(preset).knob_position(),
+        }
+    }
+
+    /// Stores encoder positions read from the hardware against a bank. Narrow pitch values are
+    /// rounded down to the nearest position the profile can hold..
+    pub fn apply_encoder_values(&mut self, preset: Preset, values: EncoderValues) -> Result<()> {
+        let hardtune_enabled = self.effect_enabled(preset, EffectType::HardTune);
+
+        if values.reverb > 24 || values.echo > 24 {
+            bail!(
+                "Reverb and Echo should be between 0 and 24, got {} and {}",
+                values.reverb,
+                values.echo
+            );
+        }
+
+        let pitch = self.pitch_encoder.get_preset(preset);
+        let pitch_value = pitch.calculate_encoder_value(values.pitch, hardtune_enabled);
+        if pitch_value != pitch.knob_position(hardtune_enabled) {
+            self.pitch_encoder_mut()
+                .get_preset_mut(preset)
+                .set_knob_position(pitch_value, hardtune_enabled)?;
+        }
+        if values.gender != self.gender_encoder.get_preset(preset).knob_position() {
+            self.gender_encoder_mut()
+                .get_preset_mut(preset)
+                .set_knob_position(values.gender)?;
+        }
+        if values.reverb as i8 != self.reverb_encoder.get_preset(preset).knob_position() {
+            self.reverb_encoder_mut()
+                .get_preset_mut(preset)
+                .set_knob_position(values.reverb as i8)?;
+        }
+        if values.echo as i8 != self.echo_encoder.get_preset(preset).knob_position() {
+            self.echo_encoder_mut()
+                .get_preset_mut(preset)
+                .set_knob_position(values.echo as i8)?;
+        }
+        Ok(())
+    }
+
+    pub fn set_effect_enabled(
+        &mut self,
+        preset: Preset,
+        effect: EffectType,
+        enabled: bool,
+    ) -> Result<()> {
+        let active = self.context.selected_effects() == preset;
+        match effect {
+            EffectType::Megaphone => {
+                let base = self.megaphone_effect_mut();
+                base.get_preset_mut(preset).set_state(enabled);
+                if active {
+                    base.colour_map_mut().set_state_on(enabled)?;
+                }
+            }
+            EffectType::Robot => {
+                let base = self.robot_effect_mut();
+                base.get_preset_mut(preset).set_state(enabled);
+                if active {
+                    base.colour_map_mut().set_state_on(enabled)?;
+                }
+            }
+            EffectType::HardTune => {
+                self.hardtune_effect_mut()
+                    .get_preset_mut(preset)
+                    .set_state(enabled);
+
+                if active {
+                    self.set_hardtune_enabled(enabled)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets the enabled state of Megaphone, Robot and HardTune across all six presets, not just
+    /// the selected one, so switching banks afterwards doesn't re-enable anything. The encoders
+    /// have no enabled state of their own, see set_fx_enabled to bypass those as well.
+    pub fn set_all_effects_state(&mut self, on: bool) -> Result<()> {
+        for preset in Preset::iter() {
+            for effect in EffectType::iter() {
+                self.set_effect_enabled(preset, effect, on)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn fx_enabled(&self) -> bool {
+        self.simple_element(SimpleElements::FxClear)
+            .colour_map()
+            .get_state()
+    }
+
+    /**
+     * The FX button gates every effect and encoder, while it's off the hardware should treat them
+     * all as disabled regardless of their own state. This returns the components whose enabled
+     * values need to be re-sent to the device, which will be empty if the state didn't change.
+     */
+    pub fn set_fx_enabled(&mut self, enabled: bool) -> Result<ComponentSet> {
+        let mut changed = ComponentSet::default();
+        if self.fx_enabled() == enabled {
+            return Ok(changed);
+        }
+
+        self.simple_element_mut(SimpleElements::FxClear)
+            .colour_map_mut()
+            .set_state_on(enabled)?;
+
+        for component in [
+            ComponentId::Megaphone,
+            ComponentId::Robot,
+            ComponentId::HardTune,
+            ComponentId::Reverb,
+            ComponentId::Echo,
+            ComponentId::Pitch,
+            ComponentId::Gender,
+        ] {
+            changed.insert(component);
+        }
+        Ok(changed)
+    }
+
+    pub fn pitch_amount(&self, preset: Preset) -> i8 {
+        let linked = self.effect_enabled(preset, EffectType::HardTune);
+        self.pitch_encoder.get_preset(preset).knob_position(linked)
+    }
+
+    pub fn set_pitch_amount(&mut self, preset: Preset, amount: i8) -> Result<()> {
+        let linked = self.effect_enabled(preset, EffectType::HardTune);
+        self.pitch_encoder_mut()
+            .get_preset_mut(preset)
+            .set_knob_position(amount, linked)
+    }
+
+    /// Applies the encoder's configured press action to the preset, and reports the value the
+    /// encoder should now be showing.
+    pub fn encoder_press(
+        &mut self,
+        preset: Preset,
+        encoder: EncoderType,
+    ) -> Result<EncoderPressOutcome> {
+        let linked = self.effect_enabled(preset, EffectType::HardTune);
+        let action = match encoder {
+            EncoderType::Reverb => self.reverb_encoder.press_action(),
+            EncoderType::Echo => self.echo_encoder.press_action(),
+            EncoderType::Pitch => self.pitch_encoder.press_action(),
+            EncoderType::Gender => self.gender_encoder.press_action(),
+        };
+
+        match (encoder, action) {
+            (_, EncoderPressAction::None) => {}
+            (EncoderType::Reverb, EncoderPressAction::ResetValue) => {
+                self.reverb_encoder_mut()
+                    .get_preset_mut(preset)
+                    .set_knob_position(0)?;
+            }
+            (EncoderType::Reverb, EncoderPressAction::CycleStyle) => {
+                let reverb = self.reverb_encoder_mut().get_preset_mut(preset);
+                reverb.set_style(next_style(*reverb.style()))?;
+            }
+            (EncoderType::Echo, EncoderPressAction::ResetValue) => {
+                self.echo_encoder_mut()
+                    .get_preset_mut(preset)
+                    .set_knob_position(0)?;
+            }
+            (EncoderType::Echo, EncoderPressAction::CycleStyle) => {
+                let echo = self.echo_encoder_mut().get_preset_mut(preset);
+                echo.set_style(next_style(*echo.style()))?;
+            }
+            (EncoderType::Pitch, EncoderPressAction::ResetValue) => {
+                self.pitch_encoder_mut()
+                    .get_preset_mut(preset)
+                    .set_knob_position(0, linked)?;
+            }
+            (EncoderType::Pitch, EncoderPressAction::CycleStyle) => {
+                let pitch = self.pitch_encoder_mut().get_preset_mut(preset);
+                pitch.set_style(next_style(*pitch.style()));
+            }
+            (EncoderType::Gender, EncoderPressAction::ResetValue) => {
+                self.gender_encoder_mut()
+                    .get_preset_mut(preset)
+                    .set_knob_position(0)?;
+            }
+            (EncoderType::Gender, EncoderPressAction::CycleStyle) => {
+                let gender = self.gender_encoder_mut().get_preset_mut(preset);
+                gender.set_style(next_style(*gender.style()));
+            }
+        }
+
+        let encoder_value = match encoder {
+            EncoderType::Reverb => self.reverb_encoder.get_preset(preset).knob_position(),
+            EncoderType::Echo => self.echo_encoder.get_preset(preset).knob_position(),
+            EncoderType::Pitch => self
+                .pitch_encoder
+                .get_preset(preset)
+                .get_encoder_position(linked),
+            EncoderType::Gender => self.gender_encoder.get_preset(preset).knob_position(),
+        };
+
+        Ok(EncoderPressOutcome {
+            action,
+            encoder_value,
+        })
+    }
+
+    pub fn encoders(&self) -> impl Iterator<Item = (EncoderType, &dyn EncoderBase)> {
+        let encoders: [(EncoderType, &dyn EncoderBase); 4] = [
+            (EncoderType::Reverb, &self.reverb_encoder),
+            (EncoderType::Echo, &self.echo_encoder),
+            (EncoderType::Pitch, &self.pitch_encoder),
+            (EncoderType::Gender, &self.gender_encoder),
+        ];
+        encoders.into_iter()
+    }
+
+    pub fn encoders_mut(&mut self) -> impl Iterator<Item = (EncoderType, &mut dyn EncoderBase)> {
+        for component in [
+            ComponentId::Reverb,
+            ComponentId::Echo,
+            ComponentId::Pitch,
+            ComponentId::Gender,
+        ] {
+            self.dirty.insert(component);
+        }
+        let encoders: [(EncoderType, &mut dyn EncoderBase); 4] = [
+            (EncoderType::Reverb, &mut self.reverb_encoder),
+            (EncoderType::Echo, &mut self.echo_encoder),
+            (EncoderType::Pitch, &mut self.pitch_encoder),
+            (EncoderType::Gender, &mut self.gender_encoder),
+        ];
+        encoders.into_iter()
+    }
+
+    pub fn effect_bases(&self) -> impl Iterator<Item = (EffectType, &dyn EffectBase)> {
+        let effects: [(EffectType, &dyn EffectBase); 3] = [
+            (EffectType::Megaphone, &self.megaphone_effect),
+            (EffectType::Robot, &self.robot_effect),
+            (EffectType::HardTune, &self.hardtune_effect),
+        ];
+        effects.into_iter()
+    }
+
+    pub fn effect_bases_mut(&mut self) -> impl Iterator<Item = (EffectType, &mut dyn EffectBase)> {
+        for component in [
+            ComponentId::Megaphone,
+            ComponentId::Robot,
+            ComponentId::HardTune,
+        ] {
+            self.dirty.insert(component);
+        }
+        let effects: [(EffectType, &mut dyn EffectBase); 3] = [
+            (EffectType::Megaphone, &mut self.megaphone_effect),
+            (EffectType::Robot, &mut self.robot_effect),
+            (EffectType::HardTune, &mut self.hardtune_effect),
+        ];
+        effects.into_iter()
+    }
+
+    /**
+     * Sets the global colour, creating the globalColour element if it's missing. This only
+     * changes the global colour itself, but note that any simple element missing from the
+     * profile falls back to the global colour (in both simple_element and simple_element_mut),
+     * so those will also show the new colour, and edits made through simple_element_mut for a
+     * missing element will change the global colour.
+     */
+    pub fn set_global_colour(&mut self, colour: Colour) -> Result<()> {
+        let global = SimpleElements::GlobalColour;
+        self.dirty.insert(ComponentId::SimpleElement(global));
+        self.simple_elements[global]
+            .get_or_insert_with(|| SimpleElement::new(global.to_string()))
+            .colour_map_mut()
+            .set_colour(0, colour)
+    }
+
+    pub fn simple_element_mut(&mut self, name: SimpleElements) -> &mut SimpleElement {
+        if self.simple_elements[name].is_some() {
+            self.dirty.insert(ComponentId::SimpleElement(name));
+            return self.simple_elements[name].as_mut().unwrap();
+        }
+
+        // If for whatever reason, this is missing, we'll use the global colour.
+        let global = SimpleElements::GlobalColour;
+        self.dirty.insert(ComponentId::SimpleElement(global));
+        self.simple_elements[global].as_mut().unwrap()
+    }
+
+    pub fn simple_element(&self, name: SimpleElements) -> &SimpleElement {
+        if self.simple_elements[name].is_some() {
+            return self.simple_elements[name].as_ref().unwrap();
+        }
+
+        // If for whatever reason, this is missing, we'll use the global colour.
+        return self.simple_elements[SimpleElements::GlobalColour]
+            .as_ref()
+            .unwrap();
+    }
+
+    // These go straight to the swear element, falling back to the global colour here would
+    // attach the bleep settings to the wrong element..
+    pub fn bleep_behaviour(&self) -> Option<BleepBehaviour> {
+        self.simple_elements[SimpleElements::Swear]
+            .as_ref()
+            .and_then(|swear| swear.bleep_behaviour())
+    }
+
+    pub fn set_bleep_behaviour(&mut self, behaviour: Option<BleepBehaviour>) {
+        self.swear_element_mut().set_bleep_behaviour(behaviour);
+    }
+
+    pub fn bleep_volume(&self) -> Option<i8> {
+        self.simple_elements[SimpleElements::Swear]
+            .as_ref()
+            .and_then(|swear| swear.bleep_volume())
+    }
+
+    pub fn set_bleep_volume(&mut self, volume: Option<i8>) -> Result<()> {
+        self.swear_element_mut().set_bleep_volume(volume)
+    }
+
+    fn swear_element_mut(&mut self) -> &mut SimpleElement {
+        let swear = SimpleElements::Swear;
+        self.dirty.insert(ComponentId::SimpleElement(swear));
+        self.simple_elements[swear].get_or_insert_with(|| SimpleElement::new(swear.to_string()))
+    }
+
+    /// Iterates the simple elements present in the profile, alongside their current values..
+    pub fn simple_elements(&self) -> impl Iterator<Item = (SimpleElements, &SimpleElement)> {
+        self.simple_elements
+            .iter()
+            .filter_map(|(name, element)| element.as_ref().map(|element| (name, element)))
+    }
+
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    pub fn context_mut(&mut self) -> &mut Context {
+        self.dirty.insert(ComponentId::Context);
+        &mut self.context
+    }
+
+    pub fn submixes(&self) -> &SubMixer {
+        &self.submix_tree
+    }
+    pub fn submixes_mut(&mut self) -> &mut SubMixer {
+        self.dirty.insert(ComponentId::Submixes);
+        &mut self.submix_tree
+    }
+
+    pub fn mix_routing(&self) -> &MixRoutingTree {
+        &self.mix_routing
+    }
+    pub fn mix_routing_mut(&mut self) -> &mut MixRoutingTree {
+        self.dirty.insert(ComponentId::MixRouting);
+        &mut self.mix_routing
+    }
+
+    /// Components which have been mutably accessed since the profile was loaded (or the flags
+    /// were last cleared), this is conservative, a component may be reported without a change.
+    pub fn dirty_components(&self) -> &ComponentSet {
+        &self.dirty
+    }
+
+    pub fn clear_dirty(&mut self) {
+        self.dirty.clear();
+    }
+
+    pub fn set_change_handler(&mut self, handler: Box<dyn Fn(ChangeEvent) + Send>) {
+        self.change_handler = Some(ChangeHandler::new(handler));
+    }
+
+    pub fn clear_change_handler(&mut self) {
+        self.change_handler = None;
+    }
+
+    // These are 'Scoped' versions of the _mut accessors, once the closure returns anything that
+    // has been changed is sent to the change handler.
+    pub fn with_mixer<R>(&mut self, f: impl FnOnce(&mut Mixers) -> R) -> R {
+        self.with_section(ChangeSection::Mixer, |settings| f(settings.mixer_mut()))
+    }
+
+    pub fn with_fader<R>(&mut self, fader: Faders, f: impl FnOnce(&mut Fader) -> R) -> R {
+        self.with_section(
+            ChangeSection::Fader,
+            |settings| f(settings.fader_mut(fader)),
+        )
+    }
+
+    pub fn with_effects<R>(&mut self, f: impl FnOnce(&mut ProfileSettings) -> R) -> R {
+        self.with_section(ChangeSection::Effects, f)
+    }
+
+    pub fn with_colours<R>(&mut self, f: impl FnOnce(&mut ProfileSettings) -> R) -> R {
+        self.with_section(ChangeSection::Colours, f)
+    }
+
+    fn with_section<R>(
+        &mut self,
+        section: ChangeSection,
+        f: impl FnOnce(&mut ProfileSettings) -> R,
+    ) -> R {
+        // If nobody is listening, there's no point building the snapshots..
+        if self.change_handler.is_none() {
+            return f(self);
+        }
+
+        let before = self.snapshot(section);
+        let result = f(self);
+        let after = self.snapshot(section);
+
+        if let Some(handler) = &mut self.change_handler {
+            handler.emit_changes(section, &before, after);
+        }
+        result
+    }
+
+    fn snapshot(&self, section: ChangeSection) -> HashMap<String, String> {
+        let mut snapshot = HashMap::new();
+
+        match section {
+            ChangeSection::Mixer => snapshot.extend(self.mixer.get_attributes()),
+            ChangeSection::Fader => {
+                for (fader, value) in self.faders.iter() {
+                    if let Some(value) = value {
+                        let name = FaderElement::FaderMeter.tag_name(fader);
+                        snapshot.extend(value.get_attributes(&name));
+                    }
+                }
+            }
+            ChangeSection::Effects => {
+                for preset in Preset::iter() {
+                    let suffix = preset.get_str("tagSuffix").unwrap();
+                    let mut attributes = vec![];
+                    for (encoder, base) in self.encoders() {
+                        let tag = encoder.get_str("tagName").unwrap();
+                        attributes.push((tag, base.get_preset_attributes(preset)));
+                    }
+                    for (effect, base) in self.effect_bases() {
+                        let tag = effect.get_str("tagName").unwrap();
+                        attributes.push((tag, base.get_preset_attributes(preset)));
+                    }
+
+                    for (tag, values) in attributes {
+                        for (key, value) in values {
+                            snapshot.insert(format!("{tag}{suffix}.{key}"), value);
+                        }
+                    }
+                }
+            }
+            ChangeSection::Colours => {
+                for (_target, colour_map) in self.colour_maps() {
+                    let mut attributes = HashMap::new();
+                    colour_map.write_colours(&mut attributes);
+
+                    // Most attributes are prefixed, but a couple (colorGroup) aren't..
+                    let prefix = colour_map.prefix();
+                    for (key, value) in attributes {
+                        if key.starts_with(prefix) {
+                            snapshot.insert(key, value);
+                        } else {
+                            snapshot.insert(format!("{prefix}{key}"), value);
+                        }
+                    }
+                }
+            }
+        }
+        snapshot
+    }
+
+    /// The look of the profile (colours and animation) with none of its audio settings..
+    pub fn theme(&self) -> Theme {
+        let mut animation = AnimationTree::new(ANIMATION_TREE.to_string());
+        animation.replace(&self.animation_tree);
+
+        let mut theme = Theme::new(Some(animation));
+        for (_, colour_map) in self.colour_maps() {
+            theme.add_colour_map(colour_map);
+        }
+        theme
+    }
+
+    pub fn export_theme<W: Write>(&self, sink: W) -> Result<Theme> {
+        let theme = self.theme();
+        theme.write_to(sink)?;
+        Ok(theme)
+    }
+
+    /// Applies a theme, anything in the profile the theme doesn't mention is left as it is.
+    pub fn apply_theme(&mut self, theme: &Theme) {
+        for (_, colour_map) in self.colour_maps_mut() {
+            if let Some(themed) = theme.colour_map(colour_map.prefix()) {
+                colour_map.replace(themed);
+            }
+        }
+        if let Some(animation) = theme.animation() {
+            self.animation_mut().replace(animation);
+        }
+    }
+
+    pub fn import_theme<R: Read>(&mut self, read: R) -> Result<Theme> {
+        let theme = Theme::load(read)?;
+        self.apply_theme(&theme);
+        Ok(theme)
+    }
+
+    pub fn colour_maps(&self) -> impl Iterator<Item = (LightingTarget, &ColourMap)> {
+        let mut maps = vec![(LightingTarget::MuteChat, self.mute_chat.colour_map())];
+
+        for (fader, button) in self.mute_buttons.iter() {
+            if let Some(button) = button {
+                maps.push((LightingTarget::MuteButton(fader), button.colour_map()));
+            }
+        }
+        for (fader, value) in self.faders.iter() {
+            if let Some(value) = value {
+                maps.push((LightingTarget::Fader(fader), value.colour_map()));
+            }
+        }
+        for (preset, effect) in self.effects.iter() {
+            if let Some(effect) = effect {
+                maps.push((LightingTarget::EffectButton(preset), effect.colour_map()));
+            }
+        }
+        for (fader, scribble) in self.scribbles.iter() {
+            if let Some(scribble) = scribble {
+                maps.push((LightingTarget::Scribble(fader), scribble.colour_map()));
+            }
+        }
+        for (button, sampler) in self.sampler_map.iter() {
+            if let Some(sampler) = sampler {
+                maps.push((LightingTarget::Sampler(button), sampler.colour_map()));
+            }
+        }
+        for (element, simple) in self.simple_elements.iter() {
+            if let Some(simple) = simple {
+                maps.push((LightingTarget::Simple(element), simple.colour_map()));
+            }
+        }
+
+        for (effect, base) in self.effect_bases() {
+            maps.push((LightingTarget::Effect(effect), base.colour_map()));
+        }
+        for (encoder, base) in self.encoders() {
+            maps.push((LightingTarget::Encoder(encoder), base.colour_map()));
+        }
+        maps.into_iter()
+    }
+
+    pub fn colour_maps_mut(&mut self) -> impl Iterator<Item = (LightingTarget, &mut ColourMap)> {
+        let targets: Vec<LightingTarget> = self.colour_maps().map(|(target, _)| target).collect();
+        for target in targets {
+            self.dirty.insert(ComponentId::from(target));
+        }
+
+        // The mute state lives in these colour maps..
+        Faders::iter().for_each(|fader| {
+            self.mute_generation(fader);
+        });
+        self.chat_mute_generation();
+
+        let mut maps = vec![(LightingTarget::MuteChat, self.mute_chat.colour_map_mut())];
+
+        for (fader, button) in self.mute_buttons.iter_mut() {
+            if let Some(button) = button {
+                maps.push((LightingTarget::MuteButton(fader), button.colour_map_mut()));
+            }
+        }
+        for (fader, value) in self.faders.iter_mut() {
+            if let Some(value) = value {
+                maps.push((LightingTarget::Fader(fader), value.colour_map_mut()));
+            }
+        }
+        for (preset, effect) in self.effects.iter_mut() {
+            if let Some(effect) = effect {
+                maps.push((
+                    LightingTarget::EffectButton(preset),
+                    effect.colour_map_mut(),
+                ));
+            }
+        }
+        for (fader, scribble) in self.scribbles.iter_mut() {
+            if let Some(scribble) = scribble {
+                maps.push((LightingTarget::Scribble(fader), scribble.colour_map_mut()));
+            }
+        }
+        for (button, sampler) in self.sampler_map.iter_mut() {
+            if let Some(sampler) = sampler {
+                maps.push((LightingTarget::Sampler(button), sampler.colour_map_mut()));
+            }
+        }
+        for (element, simple) in self.simple_elements.iter_mut() {
+            if let Some(simple) = simple {
+                maps.push((LightingTarget::Simple(element), simple.colour_map_mut()));
+            }
+        }
+
+        // We can't use effect_bases_mut() / encoders_mut() here, as they borrow all of self..
+        let effects: [(EffectType, &mut dyn EffectBase); 3] = [
+            (EffectType::Megaphone, &mut self.megaphone_effect),
+            (EffectType::Robot, &mut self.robot_effect),
+            (EffectType::HardTune, &mut self.hardtune_effect),
+        ];
+        for (effect, base) in effects {
+            maps.push((LightingTarget::Effect(effect), base.colour_map_mut()));
+        }
+
+        let encoders: [(EncoderType, &mut dyn EncoderBase); 4] = [
+            (EncoderType::Reverb, &mut self.reverb_encoder),
+            (EncoderType::Echo, &mut self.echo_encoder),
+            (EncoderType::Pitch, &mut self.pitch_encoder),
+            (EncoderType::Gender, &mut self.gender_encoder),
+        ];
+        for (encoder, base) in encoders {
+            maps.push((LightingTarget::Encoder(encoder), base.colour_map_mut()));
+        }
+        maps.into_iter()
+    }
+}
+
+/**
+ * quick-xml doesn't expand entities beyond the predefined XML ones (anything else fails to
+ * unescape), but we still don't want to be handed a DTD, a document nested deep enough to cause
+ * problems, or attributes large enough to exhaust memory. This is checked against every event
+ * before it's handled.
+ */
+pub(crate) struct DocumentGuard {
+    depth: usize,
+    max_depth: usize,
+    max_attribute_length: usize,
+}
+
+impl DocumentGuard {
+    pub(crate) fn new(options: &LoadOptions) -> Self {
+        Self {
+            depth: 0,
+            max_depth: options.max_depth,
+            max_attribute_length: options.max_attribute_length,
+        }
+    }
+
+    pub(crate) fn check(&mut self, event: &Event) -> Result<(), ParseError> {
+        match event {
+            Event::DocType(_) => Err(ParseError::UnsafeDocument(String::from(
+                "Document Type Definitions are not permitted",
+            ))),
+            Event::Start(e) => {
+                self.depth += 1;
+                self.check_element(e, self.depth)
+            }
+            Event::Empty(e) => self.check_element(e, self.depth + 1),
+            Event::End(_) => {
+                self.depth = self.depth.saturating_sub(1);
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn check_element(&self, element: &BytesStart, depth: usize) -> Result<(), ParseError> {
+        if depth > self.max_depth {
+            return Err(ParseError::UnsafeDocument(format!(
+                "Maximum element depth of {} exceeded",
+                self.max_depth
+            )));
+        }
+
+        for attribute in element.attributes().flatten() {
+            if attribute.value.len() > self.max_attribute_length {
+                return Err(ParseError::UnsafeDocument(format!(
+                    "Attribute '{}' exceeds the maximum length of {}",
+                    String::from_utf8_lossy(attribute.key.local_name().as_ref()),
+                    self.max_attribute_length
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads a file from an archive, failing if it turns out to be larger than the limit. The size
+/// declared in the archive is only used as a hint, as it can't be trusted.
+fn read_limited<R: Read>(file: R, name: &str, limit: u64) -> Result<Vec<u8>> {
+    let mut contents = Vec::new();
+
+    // Read one byte over the limit, so we can tell the difference between 'at' and 'over'..
+    file.take(limit.saturating_add(1))
+        .read_to_end(&mut contents)?;
+    if contents.len() as u64 > limit {
+        return Err(ParseError::LimitExceeded {
+            name: name.to_string(),
+            limit,
+        }
+        .into());
+    }
+    Ok(contents)
+}
+
+// In strict mode an error fails the load, in lenient mode it's logged and kept for the caller..
+fn recover(
+    options: &LoadOptions,
+    component: Option<ComponentId>,
+    errors: &mut Vec<ParseError>,
+    error: ParseError,
+) -> Result<(), ParseError> {
+    if !options.is_lenient(component) {
+        return Err(error);
+    }
+    warn!("Skipping part of the profile: {}", error);
+    errors.push(error);
+    Ok(())
+}
+
+/// Runs a component parser against a tag's attributes. If parsing fails, the error names the
+/// attribute the parser was reading at the time, so it can say where in the profile the bad
+/// value lives (or just the tag, if it failed after reading all the attributes).
+pub(crate) fn parse_with_context<E, F>(
+    tag: &str,
+    attributes: &[Attribute],
+    parse: F,
+) -> Result<(), ParseError>
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    F: FnOnce(&Attributes) -> Result<(), E>,
+{
+    let attributes = Attributes::new(attributes);
+    parse(&attributes).map_err(|error| match attributes.current() {
+        Some(attribute) => ParseError::Attribute {
+            tag: tag.to_string(),
+            attr: attribute.name.clone(),
+            source: error.into(),
+        },
+        None => ParseError::Tag {
+            tag: tag.to_string(),
+            source: error.into(),
+        },
+    })
+}
+
+// Where assets live inside a profile bundle, and the manifest mapping them to the profile..
+const BUNDLE_ASSETS: &str = "assets/";
+const BUNDLE_SAMPLES: &str = "samples";
+const BUNDLE_ICONS: &str = "icons";
+const BUNDLE_MANIFEST: &str = "assets/manifest.txt";
+
+/// The locations of the files a profile references, but doesn't contain.
+#[derive(Debug, Clone)]
+pub struct AssetResolver {
+    pub samples_dir: PathBuf,
+    pub icons_dir: PathBuf,
+}
+
+fn unique_asset_name(kind: &str, source: &Path, used: &[String]) -> Result<String> {
+    let file = source
+        .file_name()
+        .map(|file| file.to_string_lossy().to_string())
+        .unwrap_or_else(|| String::from("asset"));
+    if !is_plain_file_name(&file) {
+        bail!("Unable to bundle {:?}, invalid file name", source);
+    }
+
+    // Names are compared ignoring case, so they stay distinct on Windows and macOS..
+    let (stem, extension) = split_file_name(&file);
+    let mut name = format!("{BUNDLE_ASSETS}{kind}/{file}");
+    let mut count = 2;
+    while used.iter().any(|used| used.eq_ignore_ascii_case(&name)) {
+        name = format!("{BUNDLE_ASSETS}{kind}/{stem} ({count}){extension}");
+        count += 1;
+    }
+    Ok(name)
+}
+
+// Bundled assets are always assets/<kind>/<file>, returns the file if the name matches that.
+fn bundled_file_name<'a>(kind: &str, name: &'a str) -> Option<&'a str> {
+    if kind != BUNDLE_SAMPLES && kind != BUNDLE_ICONS {
+        return None;
+    }
+    let file = name.strip_prefix(BUNDLE_ASSETS)?.strip_prefix(kind)?;
+    let file = file.strip_prefix('/')?;
+    is_plain_file_name(file).then_some(file)
+}
+
+// A single file name, with nothing that could be read as a path on any platform.
+fn is_plain_file_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\', ':', '\0'])
+}
+
+fn split_file_name(file: &str) -> (&str, &str) {
+    match file.rfind('.') {
+        Some(index) if index > 0 => file.split_at(index),
+        _ => (file, ""),
+    }
+}
+
+// Finds where an extracted asset should go without replacing anything already there. A file
+// with the same contents is reused, otherwise the name is numbered until it's free.
+fn free_asset_path(directory: &Path, file: &str, contents: &[u8]) -> Result<PathBuf> {
+    let (stem, extension) = split_file_name(file);
+    let mut target = directory.join(file);
+    let mut count = 2;
+    while target.exists() {
+        if target.is_file() && fs::read(&target)? == contents {
+            break;
+        }
+        target = directory.join(format!("{stem} ({count}){extension}"));
+        count += 1;
+    }
+    Ok(target)
+}
+
+fn preset_bundle_entry(preset: Preset) -> String {
+    format!("{}.preset", preset.get_str("tagSuffix").unwrap())
+}
+
+// Reverb and Echo amounts go from 0 to 24, anything outside of that (from a hand edited profile)
+// is clamped, rather than wrapping around when cast for the hardware.
+fn encoder_amount(position: i8) -> u8 {
+    position.clamp(0, 24) as u8
+}
+
+/// This will wrap a 'Start' XML event into a name, and attribute Vec. We're using
+/// our own Attribute Struct here to allow easy moving between XML libraries in future.
+/// TODO: If we're doing this, we might as well make the attributes a HashMap
+///
+/// Tag and attribute names are matched on their local name, so a hand-edited profile which has
+/// picked up a namespace prefix (<ns:mixerTree>) still matches. Namespace declarations (xmlns and
+/// xmlns:ns) aren't settings, so they're dropped rather than passed on to the components.
+pub(crate) fn wrap_start_event(event: &BytesStart) -> Result<(String, Vec<Attribute>)> {
+    let mut attributes = Vec::new();
+
+    let name: String = String::from_utf8_lossy(event.local_name().as_ref()).parse()?;
+    for attribute in event.attributes() {
+        match attribute {
+            Ok(a) => {
+                if a.key.as_namespace_binding().is_some() {
+                    continue;
+                }
+
+                let key: String = String::from_utf8_lossy(a.key.local_name().as_ref()).parse()?;
+                let value = a.unescape_value()?;
+                let value = quirks::normalise(&name, &key, &value).into_owned();
+                attributes.push(Attribute { name: key, value });
+            }
+            Err(e) => {
+                bail!("Error Processing Attribute: {}", e);
+            }
+        }
+    }
+    Ok((name, attributes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::pitch::PitchStyle;
+    use crate::components::sample::SampleBank;
+    use std::collections::BTreeMap;
+
+    const PROFILE: &str = include_str!("../test-data/profile.xml");
+
+    fn load_error(xml: &str) -> ParseError {
+        let error = ProfileSettings::load(xml.as_bytes()).unwrap_err();
+        error.downcast::<ParseError>().unwrap()
+    }
+
+    // Puts every element (and one of the attributes) of the test profile into a namespace, the
+    // way some XML editors do..
+    fn namespaced_profile() -> String {
+        let mut xml = String::new();
+        let mut rest = PROFILE;
+        while let Some(index) = rest.find('<') {
+            xml.push_str(&rest[..=index]);
+            rest = &rest[index + 1..];
+            if rest.starts_with('/') {
+                xml.push('/');
+                rest = &rest[1..];
+            }
+            if rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                xml.push_str("gx:");
+            }
+        }
+        xml.push_str(rest);
+
+        let root = "<gx:ValueTreeRoot ";
+        let declarations = "xmlns=\"urn:example\" xmlns:gx=\"urn:goxlr\" ";
+        xml.replacen(root, &format!("{root}{declarations}"), 1)
+            .replacen("micLevel=", "gx:micLevel=", 1)
+    }
+
+    #[test]
+    fn namespaced_documents_load() {
+        let xml = namespaced_profile();
+        assert!(xml.contains("<gx:mixerTree "));
+        assert!(xml.contains(" gx:micLevel=\"230\""));
+        assert!(xml.contains("</gx:ValueTreeRoot>"));
+
+        let mut namespaced = ProfileSettings::load(xml.as_bytes()).unwrap();
+        let mut plain = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        assert!(namespaced.load_errors().is_empty());
+        assert_eq!(namespaced.mixer().channel_volume(FullChannelList::Mic), 230);
+
+        // Everything matches the plain profile, and the declarations aren't carried through..
+        let mut written = vec![];
+        namespaced.write_to(&mut written).unwrap();
+        let mut expected = vec![];
+        plain.write_to(&mut expected).unwrap();
+        assert_eq!(elements(&written), elements(&expected));
+    }
+
+    // Each element's name and attributes, ignoring the order the attributes were written in..
+    fn elements(xml: &[u8]) -> Vec<(String, BTreeMap<String, String>)> {
+        let mut reader = Reader::from_reader(xml);
+        let mut buf = vec![];
+        let mut elements = vec![];
+        loop {
+            match reader.read_event_into(&mut buf).unwrap() {
+                Event::Start(e) | Event::Empty(e) => {
+                    let attributes = e
+                        .attributes()
+                        .map(|a| a.unwrap())
+                        .map(|a| {
+                            let key = String::from_utf8_lossy(a.key.as_ref()).to_string();
+                            (key, a.unescape_value().unwrap().to_string())
+                        })
+                        .collect();
+                    let name = String::from_utf8_lossy(e.name().as_ref()).to_string();
+                    elements.push((name, attributes));
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+            buf.clear();
+        }
+        elements
+    }
+
+    #[test]
+    fn attribute_errors_name_the_attribute() {
+        let xml = PROFILE.replace("micLevel=\"230\"", "micLevel=\"loud\"");
+        match load_error(&xml) {
+            ParseError::Attribute { tag, attr, .. } => {
+                assert_eq!(tag, "mixerTree");
+                assert_eq!(attr, "micLevel");
+            }
+            error => panic!("Unexpected error: {error}"),
+        }
+    }
+
+    #[test]
+    fn errors_after_the_attributes_name_the_tag() {
+        // The sample stack only reads its values once it's seen every attribute..
+        let xml = PROFILE.replace("playbackMode=\"1\"", "playbackMode=\"first\"");
+        match load_error(&xml) {
+            ParseError::Tag { tag, .. } => assert_eq!(tag, "sampleStackA"),
+            error => panic!("Unexpected error: {error}"),
+        }
+    }
+
+    #[test]
+    fn archive_errors_name_the_attribute() {
+        let xml = PROFILE.replace("micLevel=\"230\"", "micLevel=\"loud\"");
+        let archive = archive(&[("profile.xml", xml.as_bytes())]);
+        let error = Profile::load(std::io::Cursor::new(archive)).unwrap_err();
+        assert!(error.to_string().contains("Unable to Load Profile"));
+        match error.downcast::<ParseError>().unwrap() {
+            ParseError::Attribute { tag, attr, .. } => {
+                assert_eq!(tag, "mixerTree");
+                assert_eq!(attr, "micLevel");
+            }
+            error => panic!("Unexpected error: {error}"),
+        }
+    }
+
+    fn lenient() -> LoadOptions {
+        LoadOptions {
+            lenient: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn lenient_loads_skip_elements_which_fail_to_parse() {
+        let xml = PROFILE.replace("micLevel=\"230\"", "micLevel=\"loud\"");
+        let settings = ProfileSettings::load_with_options(xml.as_bytes(), &lenient()).unwrap();
+
+        // The mixer is left at its defaults, everything else loads as normal..
+        let strict = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        assert_eq!(strict.mixer().channel_volume(FullChannelList::Mic), 230);
+        assert_eq!(settings.mixer().channel_volume(FullChannelList::Mic), 0);
+        assert_eq!(
+            settings.fader(Faders::A).channel(),
+            strict.fader(Faders::A).channel()
+        );
+
+        match settings.load_errors() {
+            [ParseError::Attribute { tag, attr, .. }] => {
+                assert_eq!(tag, "mixerTree");
+                assert_eq!(attr, "micLevel");
+            }
+            errors => panic!("Unexpected errors: {errors:?}"),
+        }
+    }
+
+    #[test]
+    fn lenient_loads_skip_malformed_xml() {
+        // A repeated attribute is a well-formedness error, which doesn't belong to a component..
+        let xml = PROFILE.replacen(
+            "<mixerTree ",
+            "<brokenTag a=\"1\" a=\"2\"/>\n<mixerTree ",
+            1,
+        );
+        assert!(matches!(load_error(&xml), ParseError::Malformed { .. }));
+
+        let settings = ProfileSettings::load_with_options(xml.as_bytes(), &lenient()).unwrap();
+        assert_eq!(settings.mixer().channel_volume(FullChannelList::Mic), 230);
+        match settings.load_errors() {
+            [ParseError::Malformed { message, .. }] => assert!(message.contains("Attribute")),
+            errors => panic!("Unexpected errors: {errors:?}"),
+        }
+    }
+
+    fn with_leniency(lenient: bool, component: ComponentId, leniency: Leniency) -> LoadOptions {
+        LoadOptions {
+            lenient,
+            component_leniency: HashMap::from([(component, leniency)]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn lenient_components_tolerate_bad_values_in_a_strict_load() {
+        let xml = PROFILE.replace("micLevel=\"230\"", "micLevel=\"loud\"");
+        let options = with_leniency(false, ComponentId::Mixer, Leniency::Lenient);
+
+        let settings = ProfileSettings::load_with_options(xml.as_bytes(), &options).unwrap();
+        assert_eq!(settings.mixer().channel_volume(FullChannelList::Mic), 0);
+        assert_eq!(settings.load_errors().len(), 1);
+
+        // Other components are still strict..
+        let xml = xml.replace("playbackMode=\"1\"", "playbackMode=\"first\"");
+        let error = ProfileSettings::load_with_options(xml.as_bytes(), &options).unwrap_err();
+        match error.downcast::<ParseError>().unwrap() {
+            ParseError::Tag { tag, .. } => assert_eq!(tag, "sampleStackA"),
+            error => panic!("Unexpected error: {error}"),
+        }
+    }
+
+    #[test]
+    fn strict_components_fail_a_lenient_load() {
+        let xml = PROFILE.replace("micLevel=\"230\"", "micLevel=\"loud\"");
+        let options = with_leniency(true, ComponentId::Mixer, Leniency::Strict);
+
+        let error = ProfileSettings::load_with_options(xml.as_bytes(), &options).unwrap_err();
+        match error.downcast::<ParseError>().unwrap() {
+            ParseError::Attribute { tag, attr, .. } => {
+                assert_eq!(tag, "mixerTree");
+                assert_eq!(attr, "micLevel");
+            }
+            error => panic!("Unexpected error: {error}"),
+        }
+
+        // Leaving the mixer alone, the rest of the profile is still lenient..
+        let xml = PROFILE.replace("playbackMode=\"1\"", "playbackMode=\"first\"");
+        let settings = ProfileSettings::load_with_options(xml.as_bytes(), &options).unwrap();
+        assert_eq!(settings.load_errors().len(), 1);
+    }
+
+    #[test]
+    fn clean_profiles_have_no_load_errors() {
+        let settings = ProfileSettings::load_with_options(PROFILE.as_bytes(), &lenient()).unwrap();
+        assert!(settings.load_errors().is_empty());
+
+        let settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        assert!(settings.load_errors().is_empty());
+    }
+
+    #[test]
+    fn hold_durations_round_trip() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        let button = settings.mute_button_mut(Faders::A);
+        button.set_hold_duration(Some(750)).unwrap();
+        assert!(button.set_hold_duration(Some(50)).is_err());
+        settings
+            .mute_chat_mut()
+            .set_hold_duration(Some(2000))
+            .unwrap();
+
+        let mut written = vec![];
+        settings.write_to(&mut written).unwrap();
+        let xml = String::from_utf8(written.clone()).unwrap();
+        assert!(xml.contains("mute1holdDuration=\"750\""));
+        assert!(!xml.contains("mute2holdDuration"));
+
+        let settings = ProfileSettings::load(written.as_slice()).unwrap();
+        assert_eq!(settings.mute_button(Faders::A).hold_duration(), Some(750));
+        assert_eq!(settings.mute_button(Faders::B).hold_duration(), None);
+        assert_eq!(settings.mute_chat().hold_duration(), Some(2000));
+    }
+
+    #[test]
+    fn out_of_range_hold_durations_are_rejected() {
+        let xml = PROFILE.replace("mute1prevLevel=", "mute1holdDuration=\"0\" mute1prevLevel=");
+        match load_error(&xml) {
+            ParseError::Attribute { tag, attr, .. } => {
+                assert_eq!(tag, "mute1");
+                assert_eq!(attr, "mute1holdDuration");
+            }
+            error => panic!("Unexpected error: {error}"),
+        }
+
+        let xml = PROFILE.replace("<muteChat ", "<muteChat holdDuration=\"6000\" ");
+        match load_error(&xml) {
+            ParseError::Attribute { tag, attr, .. } => {
+                assert_eq!(tag, "muteChat");
+                assert_eq!(attr, "holdDuration");
+            }
+            error => panic!("Unexpected error: {error}"),
+        }
+    }
+
+    #[test]
+    fn bleep_settings_round_trip() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        assert_eq!(settings.bleep_behaviour(), None);
+        assert_eq!(settings.bleep_volume(), None);
+
+        // Unset values aren't written, and stay unset..
+        let mut written = vec![];
+        settings.write_to(&mut written).unwrap();
+        assert!(!String::from_utf8(written.clone())
+            .unwrap()
+            .contains("bleep"));
+        let reloaded = ProfileSettings::load(written.as_slice()).unwrap();
+        assert_eq!(reloaded.bleep_behaviour(), None);
+        assert_eq!(reloaded.bleep_volume(), None);
+
+        assert!(settings.set_bleep_volume(Some(-37)).is_err());
+        assert!(settings.set_bleep_volume(Some(1)).is_err());
+        settings.set_bleep_behaviour(Some(BleepBehaviour::Toggle));
+        settings.set_bleep_volume(Some(-36)).unwrap();
+
+        let mut written = vec![];
+        settings.write_to(&mut written).unwrap();
+        let reloaded = ProfileSettings::load(written.as_slice()).unwrap();
+        assert_eq!(reloaded.bleep_behaviour(), Some(BleepBehaviour::Toggle));
+        assert_eq!(reloaded.bleep_volume(), Some(-36));
+    }
+
+    #[test]
+    fn out_of_range_bleep_volumes_are_rejected() {
+        for volume in ["-40", "5", "-20.5"] {
+            let xml = PROFILE.replace(
+                "swearvelocity=",
+                &format!("swearbleepVolume=\"{volume}\" swearvelocity="),
+            );
+            match load_error(&xml) {
+                ParseError::Attribute { tag, attr, .. } => {
+                    assert_eq!(tag, "swear");
+                    assert_eq!(attr, "swearbleepVolume");
+                }
+                error => panic!("Unexpected error: {error}"),
+            }
+        }
+
+        // Whole numbers written as floats are still fine..
+        let xml = PROFILE.replace(
+            "swearvelocity=",
+            "swearbleepVolume=\"-20.0\" swearvelocity=",
+        );
+        let settings = ProfileSettings::load(xml.as_bytes()).unwrap();
+        assert_eq!(settings.bleep_volume(), Some(-20));
+    }
+
+    #[test]
+    fn screen_brightness_round_trips() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        assert_eq!(settings.root().screen_brightness(), 100);
+
+        // Nothing is written until it's been set..
+        let mut written = vec![];
+        settings.write_to(&mut written).unwrap();
+        assert!(!String::from_utf8(written)
+            .unwrap()
+            .contains("screenBrightness"));
+
+        assert!(settings.root_mut().set_screen_brightness(101).is_err());
+        settings.root_mut().set_screen_brightness(40).unwrap();
+
+        let mut written = vec![];
+        settings.write_to(&mut written).unwrap();
+        let xml = String::from_utf8(written.clone()).unwrap();
+        assert!(xml.contains("screenBrightness=\"40\""));
+
+        let settings = ProfileSettings::load(written.as_slice()).unwrap();
+        assert_eq!(settings.root().screen_brightness(), 40);
+
+        // Whole numbers written as floats still load..
+        let xml = xml.replace("screenBrightness=\"40\"", "screenBrightness=\"40.000000\"");
+        let settings = ProfileSettings::load(xml.as_bytes()).unwrap();
+        assert_eq!(settings.root().screen_brightness(), 40);
+    }
+
+    #[test]
+    fn out_of_range_screen_brightness_is_rejected() {
+        for value in ["101", "300", "-5", "50.5"] {
+            let brightness = format!("<ValueTreeRoot screenBrightness=\"{value}\" ");
+            let xml = PROFILE.replace("<ValueTreeRoot ", &brightness);
+            match load_error(&xml) {
+                ParseError::Attribute { tag, attr, .. } => {
+                    assert_eq!(tag, "ValueTreeRoot");
+                    assert_eq!(attr, "screenBrightness");
+                }
+                error => panic!("Unexpected error for {value}: {error}"),
+            }
+        }
+    }
+
+    #[test]
+    fn attributes_track_the_current_attribute() {
+        let list = vec![
+            Attribute {
+                name: String::from("first"),
+                value: String::from("1"),
+            },
+            Attribute {
+                name: String::from("second"),
+                value: String::from("2"),
+            },
+        ];
+        let attributes = Attributes::new(&list);
+        assert!(attributes.current().is_none());
+
+        let mut iter = attributes.iter();
+        iter.next();
+        assert_eq!(attributes.current().unwrap().name, "first");
+        iter.next();
+        assert_eq!(attributes.current().unwrap().name, "second");
+        assert!(iter.next().is_none());
+        assert!(attributes.current().is_none());
+    }
+
+    #[test]
+    fn toggling_hardtune_keeps_the_pitch_value() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        settings.set_hardtune_enabled(false).unwrap();
+        settings.set_pitch_amount(Preset::Preset1, 10).unwrap();
+
+        let stored = |settings: &ProfileSettings| {
+            let pitch = settings.pitch_encoder().get_preset(Preset::Preset1);
+            pitch.get_pitch_value()
+        };
+        let before = stored(&settings);
+
+        // A value between octaves isn't rounded away when HardTune is enabled..
+        settings.set_hardtune_enabled(true).unwrap();
+        assert_eq!(stored(&settings), before);
+
+        settings.set_hardtune_enabled(false).unwrap();
+        assert_eq!(settings.pitch_amount(Preset::Preset1), 10);
+    }
+
+    fn archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut archive = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (name, contents) in files {
+            archive.start_file(*name, FileOptions::default()).unwrap();
+            archive.write_all(contents).unwrap();
+        }
+        archive.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn entries_keep_their_compression_through_a_save() {
+        let modified = DateTime::from_date_and_time(2021, 6, 1, 12, 30, 0).unwrap();
+        let entries = [
+            ("profile.xml", CompressionMethod::Deflated),
+            ("scribble1.png", CompressionMethod::Stored),
+            ("metadata.bin", CompressionMethod::Stored),
+            ("notes.txt", CompressionMethod::Deflated),
+        ];
+
+        let mut source = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (name, method) in entries {
+            let options = FileOptions::default()
+                .compression_method(method)
+                .last_modified_time(modified);
+            source.start_file(name, options).unwrap();
+            match name {
+                "profile.xml" => source.write_all(PROFILE.as_bytes()).unwrap(),
+                _ => source.write_all(&[name.len() as u8; 64]).unwrap(),
+            }
+        }
+        let source = source.finish().unwrap();
+
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("saved.goxlr");
+        let mut profile = Profile::load(source).unwrap();
+        profile.save(&path).unwrap();
+
+        // DateTime doesn't implement PartialEq..
+        let times = |time: DateTime| (time.datepart(), time.timepart());
+        let mut saved = zip::ZipArchive::new(File::open(&path).unwrap()).unwrap();
+        for (name, method) in entries {
+            let mut entry = saved.by_name(name).unwrap();
+            assert_eq!(entry.compression(), method, "{name}");
+            assert_eq!(times(entry.last_modified()), times(modified), "{name}");
+
+            // The files we don't know about are copied through untouched..
+            if name != "profile.xml" {
+                let mut contents = vec![];
+                entry.read_to_end(&mut contents).unwrap();
+                assert_eq!(contents, [name.len() as u8; 64], "{name}");
+            }
+        }
+    }
+
+    // The profile with the first scribble's element removed, leaving scribble1.png unowned..
+    fn without_scribble1() -> String {
+        let start = PROFILE.find("  <scribble1 ").unwrap();
+        let end = PROFILE
+            .find("scribble1bitmap=\"scribble1.png\"/>\n")
+            .unwrap();
+        let end = end + "scribble1bitmap=\"scribble1.png\"/>\n".len();
+        format!("{}{}", &PROFILE[..start], &PROFILE[end..])
+    }
+
+    #[test]
+    fn orphaned_scribble_images_are_kept() {
+        let xml = without_scribble1();
+        let data = archive(&[("profile.xml", xml.as_bytes()), ("scribble1.png", b"image")]);
+        let mut profile = Profile::load(std::io::Cursor::new(data)).unwrap();
+        assert!(profile.settings().scribbles()[Faders::A].is_none());
+        assert_eq!(profile.get_scribble(0), b"image");
+
+        // Nothing refers to it, but it's still written back out..
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("saved.goxlr");
+        profile.save(&path).unwrap();
+        let mut saved = zip::ZipArchive::new(File::open(&path).unwrap()).unwrap();
+        let mut contents = vec![];
+        let mut image = saved.by_name("scribble1.png").unwrap();
+        image.read_to_end(&mut contents).unwrap();
+        assert_eq!(contents, b"image");
+    }
+
+    #[test]
+    fn orphaned_scribble_images_can_be_given_defaults() {
+        let xml = without_scribble1();
+        let data = archive(&[("profile.xml", xml.as_bytes()), ("scribble1.png", b"image")]);
+        let options = LoadOptions {
+            orphaned_scribbles: OrphanedScribbles::CreateMetadata,
+            ..Default::default()
+        };
+        let profile = Profile::load_with_options(std::io::Cursor::new(data), &options).unwrap();
+
+        let scribble = profile.settings().scribble(Faders::A);
+        assert_eq!(scribble.bitmap_file(), "scribble1.png");
+        assert_eq!(scribble.icon_file(), None);
+
+        // The other faders have their own elements, and no images, so they're left alone..
+        let scribble = profile.settings().scribble(Faders::B);
+        assert_eq!(scribble.icon_file().as_deref(), Some("music.png"));
+        assert!(profile.get_scribble(1).is_empty());
+    }
+
+    #[test]
+    fn scribble_elements_without_images_load() {
+        let data = archive(&[("profile.xml", PROFILE.as_bytes())]);
+        let options = LoadOptions {
+            orphaned_scribbles: OrphanedScribbles::CreateMetadata,
+            ..Default::default()
+        };
+        let profile = Profile::load_with_options(std::io::Cursor::new(data), &options).unwrap();
+        for fader in Faders::iter() {
+            let scribble = profile.settings().scribble(fader);
+            assert_eq!(
+                scribble.bitmap_file(),
+                format!("scribble{}.png", fader as u8 + 1)
+            );
+        }
+        assert!((0..4).all(|id| profile.get_scribble(id).is_empty()));
+
+        // With no element or image, there's nothing to reconcile..
+        let xml = without_scribble1();
+        let data = archive(&[("profile.xml", xml.as_bytes())]);
+        let profile = Profile::load_with_options(std::io::Cursor::new(data), &options).unwrap();
+        assert!(profile.settings().scribbles()[Faders::A].is_none());
+    }
+
+    fn limit_exceeded(result: Result<Profile>) -> (String, u64) {
+        match result.unwrap_err().downcast::<ParseError>().unwrap() {
+            ParseError::LimitExceeded { name, limit } => (name, limit),
+            error => panic!("Unexpected error: {error}"),
+        }
+    }
+
+    #[test]
+    fn compressed_scribbles_are_limited_by_their_real_size() {
+        // Compresses down to a few KB, but declares and inflates to 8MB..
+        let bomb = vec![0; 8 * 1024 * 1024];
+        let data = archive(&[
+            ("profile.xml", PROFILE.as_bytes()),
+            ("scribble1.png", &bomb),
+        ]);
+        assert!(data.len() < 64 * 1024);
+
+        let result = Profile::load(std::io::Cursor::new(data));
+        let (name, limit) = limit_exceeded(result);
+        assert_eq!(name, "scribble1.png");
+        assert_eq!(limit, LoadOptions::default().max_scribble_size);
+    }
+
+    #[test]
+    fn oversized_profiles_are_rejected() {
+        let data = archive(&[("profile.xml", PROFILE.as_bytes())]);
+        let options = LoadOptions {
+            max_profile_size: 1024,
+            ..Default::default()
+        };
+
+        let result = Profile::load_with_options(std::io::Cursor::new(data), &options);
+        assert_eq!(limit_exceeded(result), (String::from("profile.xml"), 1024));
+    }
+
+    #[test]
+    fn archives_with_too_many_entries_are_rejected() {
+        let names: Vec<String> = (0..5).map(|i| format!("extra{i}.txt")).collect();
+        let mut files: Vec<(&str, &[u8])> = vec![("profile.xml", PROFILE.as_bytes())];
+        files.extend(names.iter().map(|name| (name.as_str(), &b"extra"[..])));
+
+        let options = LoadOptions {
+            max_entries: 4,
+            ..Default::default()
+        };
+        let result = Profile::load_with_options(std::io::Cursor::new(archive(&files)), &options);
+        assert_eq!(limit_exceeded(result).1, 4);
+    }
+
+    #[test]
+    fn archives_within_the_limits_load() {
+        let data = archive(&[("profile.xml", PROFILE.as_bytes())]);
+        assert!(Profile::load(std::io::Cursor::new(data)).is_ok());
+    }
+
+    #[test]
+    fn limits_can_be_lifted_entirely() {
+        let contents = read_limited(PROFILE.as_bytes(), "profile.xml", u64::MAX).unwrap();
+        assert_eq!(contents, PROFILE.as_bytes());
+
+        let options = LoadOptions {
+            max_profile_size: u64::MAX,
+            ..Default::default()
+        };
+        let data = archive(&[("profile.xml", PROFILE.as_bytes())]);
+        assert!(Profile::load_with_options(std::io::Cursor::new(data), &options).is_ok());
+    }
+
+    #[test]
+    fn files_at_the_limit_are_read() {
+        let limit = PROFILE.len() as u64;
+        assert!(read_limited(PROFILE.as_bytes(), "profile.xml", limit).is_ok());
+
+        let result = read_limited(PROFILE.as_bytes(), "profile.xml", limit - 1);
+        match result.unwrap_err().downcast::<ParseError>().unwrap() {
+            ParseError::LimitExceeded {
+                limit: reported, ..
+            } => assert_eq!(reported, limit - 1),
+            error => panic!("Unexpected error: {error}"),
+        }
+    }
+
+    const BILLION_LAUGHS: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE lolz [
+  <!ENTITY lol "lol">
+  <!ENTITY lol1 "&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;">
+  <!ENTITY lol2 "&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;">
+  <!ENTITY lol3 "&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;">
+  <!ENTITY lol4 "&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;">
+  <!ENTITY lol5 "&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;">
+  <!ENTITY lol6 "&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;">
+  <!ENTITY lol7 "&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;">
+  <!ENTITY lol8 "&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;">
+  <!ENTITY lol9 "&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;">
+]>
+<ValueTreeRoot version="2" loudness="&lol9;" device="0"/>"#;
+
+    fn is_unsafe_document(error: anyhow::Error) -> bool {
+        matches!(
+            error.downcast::<ParseError>(),
+            Ok(ParseError::UnsafeDocument(_))
+        )
+    }
+
+    #[test]
+    fn billion_laughs_profiles_are_rejected() {
+        let error = ProfileSettings::load(BILLION_LAUGHS.as_bytes()).unwrap_err();
+        assert!(is_unsafe_document(error));
+    }
+
+    #[test]
+    fn billion_laughs_presets_are_rejected() {
+        let error = ParsedPreset::load(BILLION_LAUGHS.as_bytes()).unwrap_err();
+        assert!(is_unsafe_document(error));
+    }
+
+    #[test]
+    fn deeply_nested_documents_are_rejected() {
+        let depth = LoadOptions::default().max_depth + 1;
+        let xml = format!(
+            "<ValueTreeRoot>{}{}</ValueTreeRoot>",
+            "<nested>".repeat(depth),
+            "</nested>".repeat(depth)
+        );
+        let error = ProfileSettings::load(xml.as_bytes()).unwrap_err();
+        assert!(is_unsafe_document(error));
+    }
+
+    #[test]
+    fn long_attributes_are_rejected() {
+        let options = LoadOptions {
+            max_attribute_length: 16,
+            ..Default::default()
+        };
+        let xml = PROFILE.replace("file=\"\"", &format!("file=\"{}\"", "a".repeat(17)));
+        let error = ProfileSettings::load_with_options(xml.as_bytes(), &options).unwrap_err();
+        assert!(is_unsafe_document(error));
+    }
+
+    // A profile saved by the official app, which writes the root and mixerTree attributes in a
+    // fixed order..
+    const OFFICIAL: &[u8] = include_bytes!("../../defaults/resources/profiles/Default.goxlr");
+
+    // Pulls out an element's opening tag, with the official app's line wrapping (and CRLFs)
+    // collapsed down to single spaces..
+    fn element(xml: &str, tag: &str) -> String {
+        let start = xml.find(&format!("<{tag} ")).unwrap();
+        let end = start + xml[start..].find('>').unwrap() + 1;
+        xml[start..end]
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn ordered_attributes_match_the_official_app() {
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(OFFICIAL)).unwrap();
+        let mut official = String::new();
+        archive
+            .by_name("profile.xml")
+            .unwrap()
+            .read_to_string(&mut official)
+            .unwrap();
+
+        let mut settings = ProfileSettings::load(official.as_bytes()).unwrap();
+        let options = WriteOptions {
+            official_app_compat: true,
+            ..Default::default()
+        };
+        let mut written = vec![];
+        settings
+            .write_to_with_options(&mut written, &options)
+            .unwrap();
+        let written = String::from_utf8(written).unwrap();
+
+        // This capture predates the Line Out volume, which the current app writes straight
+        // after sampleLevel (see the test profile below)..
+        let written = written.replace(" lineOutLevel=\"255\"", "");
+        for tag in ["ValueTreeRoot", "mixerTree"] {
+            assert_eq!(element(&written, tag), element(&official, tag), "<{tag}>");
+        }
+
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        let mut written = vec![];
+        settings
+            .write_to_with_options(&mut written, &options)
+            .unwrap();
+        let written = String::from_utf8(written).unwrap();
+        assert_eq!(
+            element(&written, "mixerTree"),
+            element(PROFILE, "mixerTree")
+        );
+    }
+
+    // Some third party tools only write the first three effect banks..
+    fn without_last_banks(xml: &str) -> String {
+        let start = xml.find("  <effects4 ").unwrap();
+        let end = xml.find("  <robotEffect ").unwrap();
+        format!("{}{}", &xml[..start], &xml[end..])
+    }
+
+    #[test]
+    fn repair_recreates_missing_effect_banks() {
+        let mut settings = ProfileSettings::load(without_last_banks(PROFILE).as_bytes()).unwrap();
+        assert!(settings.effects[Preset::Preset4].is_none());
+
+        let report = settings.repair().unwrap();
+        let expected =
+            [Preset::Preset4, Preset::Preset5, Preset::Preset6].map(ComponentId::Effects);
+        assert_eq!(report.created, expected);
+
+        // The existing banks are untouched, and the new ones are usable..
+        assert_eq!(settings.effects(Preset::Preset1).name(), "Big Verb");
+        assert_eq!(
+            settings.effects(Preset::Preset6).name(),
+            "Effects Group effects6"
+        );
+
+        // Once written, the banks are part of the profile and nothing needs repairing..
+        let mut written = vec![];
+        settings.write_to(&mut written).unwrap();
+        let mut settings = ProfileSettings::load(written.as_slice()).unwrap();
+        assert!(settings.repair().unwrap().is_empty());
+    }
+
+    #[test]
+    fn repair_leaves_complete_profiles_alone() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        assert!(settings.repair().unwrap().is_empty());
+    }
+
+    const BIG_ECHO: &[u8] = include_bytes!("../../defaults/resources/presets/Big Echo.preset");
+    const TANK: &[u8] = include_bytes!("../../defaults/resources/presets/Tank.preset");
+
+    #[test]
+    fn banks_match_the_preset_they_were_loaded_from() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        let parsed = ParsedPreset::load(BIG_ECHO).unwrap();
+        settings.apply_preset(Preset::Preset1, &parsed).unwrap();
+
+        assert!(settings
+            .bank_matches_preset(Preset::Preset1, BIG_ECHO)
+            .unwrap());
+        assert!(!settings.bank_matches_preset(Preset::Preset1, TANK).unwrap());
+        assert!(!settings
+            .bank_matches_preset(Preset::Preset2, BIG_ECHO)
+            .unwrap());
+    }
+
+    #[test]
+    fn edited_banks_no_longer_match_their_preset() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        let parsed = ParsedPreset::load(BIG_ECHO).unwrap();
+        settings.apply_preset(Preset::Preset1, &parsed).unwrap();
+
+        let echo = settings.echo_encoder_mut().get_preset_mut(Preset::Preset1);
+        echo.set_feedback_left(echo.feedback_left() / 2 + 1)
+            .unwrap();
+        assert!(!settings
+            .bank_matches_preset(Preset::Preset1, BIG_ECHO)
+            .unwrap());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn saving_keeps_the_existing_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("Default.goxlr");
+        fs::write(&path, OFFICIAL).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        let mut profile = Profile::load(std::io::Cursor::new(OFFICIAL)).unwrap();
+        profile.save(&path).unwrap();
+
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o644);
+        assert!(Profile::load(File::open(&path).unwrap()).is_ok());
+    }
+
+    #[test]
+    fn edits_mark_their_component_dirty() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        assert!(settings.dirty_components().is_empty());
+
+        settings
+            .mixer_mut()
+            .set_channel_volume(FullChannelList::Mic, 10)
+            .unwrap();
+        settings.fader_mut(Faders::A);
+        settings.set_sampler_root(Some(String::from("Samples")));
+
+        let dirty = settings.dirty_components();
+        assert!(dirty.contains(ComponentId::Mixer));
+        assert!(dirty.contains(ComponentId::Fader(Faders::A)));
+        assert!(dirty.contains(ComponentId::SamplerRoot));
+        assert!(!dirty.contains(ComponentId::Fader(Faders::B)));
+        assert_eq!(dirty.iter().count(), 3);
+    }
 
-        self.reverb_encoder.write_reverb(&mut writer)?;
-        self.echo_encoder.write_echo(&mut writer)?;
-        self.pitch_encoder.write_pitch(&mut writer)?;
-        self.gender_encoder.write_gender(&mut writer)?;
+    #[test]
+    fn resetting_a_component_marks_it_dirty() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        settings.set_sampler_root(Some(String::from("Samples")));
+        settings.clear_dirty();
+
+        settings.reset_component(ComponentId::SamplerRoot).unwrap();
+        assert_eq!(settings.sampler_root(), None);
+        assert!(settings
+            .dirty_components()
+            .contains(ComponentId::SamplerRoot));
+    }
 
-        for (_key, value) in &self.sampler_map {
-            if let Some(value) = value {
-                value.write_sample(&mut writer)?;
-            }
+    #[test]
+    fn saving_clears_the_dirty_components() {
+        let directory = tempfile::tempdir().unwrap();
+        for direct_write in [false, true] {
+            let path = directory.path().join(format!("Dirty{direct_write}.goxlr"));
+            let options = WriteOptions {
+                direct_write,
+                ..Default::default()
+            };
+
+            let mut profile = Profile::load(std::io::Cursor::new(OFFICIAL)).unwrap();
+            let settings = profile.settings_mut();
+            settings.set_sampler_root(Some(String::from("Samples")));
+            assert!(!settings.dirty_components().is_empty());
+
+            profile.save_with_options(&path, &options).unwrap();
+            assert!(profile.settings().dirty_components().is_empty());
+
+            let reloaded = Profile::load(File::open(&path).unwrap()).unwrap();
+            assert_eq!(reloaded.settings().sampler_root(), Some("Samples"));
+            assert!(reloaded.settings().dirty_components().is_empty());
         }
+    }
 
-        for simple_element in SimpleElements::iter() {
-            self.simple_elements[simple_element]
-                .as_ref()
-                .unwrap()
-                .write_simple(&mut writer)?;
+    #[test]
+    fn sample_paths_of_every_style_resolve() {
+        let directory = tempfile::tempdir().unwrap();
+        let samples = directory.path().join("samples");
+        let elsewhere = directory.path().join("elsewhere/clap.wav");
+        for path in [
+            samples.join("Samples/kick.wav"),
+            samples.join("Sub/snare.wav"),
+            samples.join("hat.wav"),
+            elsewhere.clone(),
+        ] {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, []).unwrap();
         }
 
-        // Finalise the XML..
-        self.root.write_final(&mut writer)?;
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        settings.set_sampler_root(Some(String::from("Samples")));
+
+        let elsewhere_name = elsewhere.to_string_lossy().to_string();
+        for (stored, expected) in [
+            // Windows absolute and UNC paths are looked up by name..
+            (
+                "C:\\Users\\Me\\GoXLR\\Samples\\kick.wav",
+                samples.join("Samples/kick.wav"),
+            ),
+            ("\\\\server\\share\\hat.wav", samples.join("hat.wav")),
+            // Relative paths keep their directories, whichever separator they use..
+            ("Sub/snare.wav", samples.join("Sub/snare.wav")),
+            ("Sub\\snare.wav", samples.join("Sub/snare.wav")),
+            // Bare names prefer the sampler root..
+            ("kick.wav", samples.join("Samples/kick.wav")),
+            ("hat.wav", samples.join("hat.wav")),
+            // And native absolute paths are used as they are..
+            (elsewhere_name.as_str(), elsewhere.clone()),
+        ] {
+            let track = Track::new(stored.to_string(), 0., 100., 1.);
+            let resolved = settings.resolve_sample_path(&track, &samples).unwrap();
+            assert_eq!(resolved, expected, "{stored}");
+        }
+    }
 
-        let routing = self.mixer.mixer_table_mut();
-        // Everything's written, restore the original monitor settings..
-        if monitored_output != OutputChannels::Headphones {
-            for input in InputChannels::iter() {
-                routing[input][OutputChannels::Headphones] = routing[input][monitored_output];
+    #[test]
+    fn missing_samples_list_every_path_tried() {
+        let samples = Path::new("/nonexistent/samples");
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        settings.set_sampler_root(Some(String::from("Samples")));
+
+        let track = Track::new(String::from("C:\\Samples\\gone.wav"), 0., 100., 1.);
+        match settings.resolve_sample_path(&track, samples) {
+            Err(SampleError::NotFound { track, tried }) => {
+                assert_eq!(track, "C:\\Samples\\gone.wav");
+                assert_eq!(
+                    tried,
+                    [samples.join("Samples/gone.wav"), samples.join("gone.wav")]
+                );
             }
-            self.submix_tree
-                .monitor_tree_mut()
-                .set_monitored_output(monitored_output);
-            self.submix_tree
-                .monitor_tree_mut()
-                .set_headphone_mix(headphone_mix);
+            result => panic!("Unexpected result: {result:?}"),
         }
 
-        Ok(())
+        let track = Track::new(String::from("Sub\\gone.wav"), 0., 100., 1.);
+        match settings.resolve_sample_path(&track, samples) {
+            Err(SampleError::NotFound { tried, .. }) => assert_eq!(
+                tried,
+                [
+                    samples.join("Samples/Sub/gone.wav"),
+                    samples.join("Samples/gone.wav"),
+                    samples.join("Sub/gone.wav"),
+                    samples.join("gone.wav"),
+                ]
+            ),
+            result => panic!("Unexpected result: {result:?}"),
+        }
     }
 
-    pub fn write_preset<P: AsRef<Path>>(&self, path: P) -> Result<()> {
-        let out_file = File::create(path)?;
-        self.write_preset_to(&out_file)?;
-        out_file.sync_all().context("Unable to Sync File")
+    #[test]
+    fn referenced_samples_are_listed_once_as_stored() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        let before = settings.referenced_samples();
+        for (button, bank, track) in [
+            (
+                SampleButtons::TopLeft,
+                SampleBank::A,
+                "C:\\Samples\\kick.wav",
+            ),
+            (SampleButtons::TopRight, SampleBank::B, "Sub/snare.wav"),
+            (
+                SampleButtons::BottomLeft,
+                SampleBank::C,
+                "C:\\Samples\\kick.wav",
+            ),
+        ] {
+            let stack = settings.sample_button_mut(button).get_stack_mut(bank);
+            stack.add_track(Track::new(track.to_string(), 0., 100., 1.));
+        }
+
+        // Each file is listed once, in the form it was stored..
+        let referenced = settings.referenced_samples();
+        assert_eq!(referenced.len(), before.len() + 2);
+        for track in ["C:\\Samples\\kick.wav", "Sub/snare.wav"] {
+            let count = referenced.iter().filter(|sample| *sample == track).count();
+            assert_eq!(count, 1, "{track}");
+        }
+        assert!(before.iter().all(|sample| referenced.contains(sample)));
     }
 
-    pub fn write_preset_to<W: Write>(&self, sink: W) -> Result<()> {
-        let mut writer = Writer::new_with_indent(sink, u8::try_from('\t')?, 1);
-        writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("utf-8"), None)))?;
+    #[test]
+    fn faders_and_effects_can_be_read_together() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        settings.clear_dirty();
+
+        // All of these are held at once, which the mutable accessors wouldn't allow..
+        let faders = settings.faders();
+        let mute_buttons = settings.mute_buttons();
+        let scribbles = settings.scribbles();
+        let effects = settings.effects(Preset::Preset1);
+
+        for fader in Faders::iter() {
+            assert!(faders[fader].is_some());
+            assert!(mute_buttons[fader].is_some());
+            assert!(scribbles[fader].is_some());
+        }
+        assert!(!effects.name().is_empty());
 
-        let current = self.context().selected_effects();
-        let preset_writer = PresetWriter::new(String::from(self.effects(current).name()));
-        preset_writer.write_initial(&mut writer)?;
-        preset_writer.write_tag(
-            &mut writer,
-            "reverbEncoder",
-            self.reverb_encoder.get_preset_attributes(current),
-        )?;
+        // ..and reading them doesn't mark anything as changed.
+        assert!(settings.dirty_components().is_empty());
+    }
 
-        preset_writer.write_tag(
-            &mut writer,
-            "echoEncoder",
-            self.echo_encoder.get_preset_attributes(current),
-        )?;
+    #[test]
+    fn fade_plans_start_from_the_channel_volume() {
+        let settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        let from = settings.mixer().channel_volume(FullChannelList::Mic);
 
-        preset_writer.write_tag(
-            &mut writer,
-            "pitchEncoder",
-            self.pitch_encoder.get_preset_attributes(current),
-        )?;
+        let plan = settings.fade_plan(FullChannelList::Mic, 0, Duration::from_millis(100));
+        assert_eq!(plan.len(), 5);
+        assert!(plan[0].1 < from);
+        assert_eq!(plan.last(), Some(&(Duration::from_millis(100), 0)));
 
-        preset_writer.write_tag(
-            &mut writer,
-            "genderEncoder",
-            self.gender_encoder.get_preset_attributes(current),
-        )?;
+        let plan = settings.fade_plan(FullChannelList::Mic, from, Duration::from_secs(1));
+        assert_eq!(plan, vec![(Duration::ZERO, from)]);
 
-        preset_writer.write_tag(
-            &mut writer,
-            "megaphoneEffect",
-            self.megaphone_effect.get_preset_attributes(current),
-        )?;
+        let plan = settings.fade_plan(FullChannelList::Mic, 0, Duration::from_millis(5));
+        assert_eq!(plan, vec![(Duration::from_millis(5), 0)]);
+    }
 
-        preset_writer.write_tag(
-            &mut writer,
-            "robotEffect",
-            self.robot_effect.get_preset_attributes(current),
-        )?;
+    fn hardware_state(settings: &ProfileSettings) -> HardwareState {
+        let mut state = HardwareState::default();
+        for fader in Faders::iter() {
+            state.fader_volumes[fader] = fader_volume(settings, fader);
+        }
+        state
+    }
 
-        preset_writer.write_tag(
-            &mut writer,
-            "hardtuneEffect",
-            self.hardtune_effect.get_preset_attributes(current),
-        )?;
+    #[test]
+    fn hardware_state_finds_the_matching_profile() {
+        let loaded = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        let mut edited = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        let channel = edited.fader(Faders::A).channel();
+        let volume = edited.mixer().channel_volume(channel);
+        edited
+            .mixer_mut()
+            .set_channel_volume(channel, volume.wrapping_add(10))
+            .unwrap();
 
-        preset_writer.write_final(&mut writer)?;
-        Ok(())
+        let state = hardware_state(&loaded);
+        assert!(loaded.matches_hardware_state(&state));
+        assert!(!edited.matches_hardware_state(&state));
+
+        let profiles = [("Edited", &edited), ("Loaded", &loaded)];
+        assert_eq!(state.matching_profile(profiles), Some("Loaded"));
+        assert_eq!(
+            hardware_state(&edited).matching_profile(profiles),
+            Some("Edited")
+        );
+
+        // Nothing matches a state which no profile would set..
+        let mut unknown = state.clone();
+        unknown.fader_volumes[Faders::B] = unknown.fader_volumes[Faders::B].wrapping_add(1);
+        assert_eq!(unknown.matching_profile(profiles), None);
+
+        // Applying a matching state leaves the profile alone..
+        let mut applied = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        assert!(!applied.apply_hardware_state(&state).unwrap());
     }
 
-    pub fn parse_preset(key: String) -> Result<Preset> {
-        if let Some(id) = key
-            .chars()
-            .last()
-            .map(|s| u8::from_str(&s.to_string()))
-            .transpose()?
-        {
-            if let Some(preset) = Preset::iter().nth((id - 1) as usize) {
-                return Ok(preset);
-            }
+    #[test]
+    fn preset_bundles_round_trip() {
+        let mut source = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        source
+            .effects_mut(Preset::Preset3)
+            .set_name("Shared".to_string())
+            .unwrap();
+        let echo = source.echo_encoder_mut().get_preset_mut(Preset::Preset3);
+        echo.set_feedback_left(echo.feedback_left() / 2 + 1)
+            .unwrap();
+
+        let mut bundle = std::io::Cursor::new(vec![]);
+        source.export_preset_bundle(&mut bundle).unwrap();
+
+        // Each bank is a regular preset file, which matches the bank it came from..
+        for preset in Preset::iter() {
+            let file = bundle_entry(&bundle, preset);
+            assert!(source.bank_matches_preset(preset, file).unwrap());
+        }
+
+        let mut target = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        assert!(!target
+            .bank_matches_preset(Preset::Preset3, bundle_entry(&bundle, Preset::Preset3))
+            .unwrap());
+
+        target
+            .import_preset_bundle(std::io::Cursor::new(bundle.get_ref()))
+            .unwrap();
+        for preset in Preset::iter() {
+            assert_eq!(target.effects(preset).name(), source.effects(preset).name());
+            assert!(target
+                .bank_matches_preset(preset, bundle_entry(&bundle, preset))
+                .unwrap());
         }
-        Err(anyhow!("Unable to Parse Preset from Number"))
     }
 
-    pub fn animation(&self) -> &AnimationTree {
-        &self.animation_tree
+    #[test]
+    fn failed_bundle_imports_leave_every_bank_alone() {
+        let mut source = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        source
+            .effects_mut(Preset::Preset1)
+            .set_name("Shared".to_string())
+            .unwrap();
+        let mut bundle = std::io::Cursor::new(vec![]);
+        source.export_preset_bundle(&mut bundle).unwrap();
+
+        // The first bank is fine, the second parses but is rejected while being applied..
+        let first = bundle_entry(&bundle, Preset::Preset1).into_inner();
+        let second = String::from_utf8(bundle_entry(&bundle, Preset::Preset2).into_inner());
+        let second = second.unwrap();
+        let start = second.find("DELAY_KNOB_POSITION=\"").unwrap() + 21;
+        let end = start + second[start..].find('"').unwrap();
+        let second = format!("{}30{}", &second[..start], &second[end..]);
+        let broken = archive(&[
+            (&preset_bundle_entry(Preset::Preset1), &first),
+            (&preset_bundle_entry(Preset::Preset2), second.as_bytes()),
+        ]);
+
+        let mut target = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        let result = target.import_preset_bundle(std::io::Cursor::new(broken));
+        assert!(result.is_err());
+
+        let untouched = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        assert_ne!(target.effects(Preset::Preset1).name(), "Shared");
+        assert_eq!(
+            target.echo_encoder().get_preset(Preset::Preset2),
+            untouched.echo_encoder().get_preset(Preset::Preset2)
+        );
+        assert!(target.dirty_components().is_empty());
     }
 
-    pub fn animation_mut(&mut self) -> &mut AnimationTree {
-        &mut self.animation_tree
+    fn bundle_entry(bundle: &std::io::Cursor<Vec<u8>>, preset: Preset) -> std::io::Cursor<Vec<u8>> {
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bundle.get_ref())).unwrap();
+        let mut contents = vec![];
+        let mut file = archive.by_name(&preset_bundle_entry(preset)).unwrap();
+        file.read_to_end(&mut contents).unwrap();
+        std::io::Cursor::new(contents)
     }
 
-    pub fn mixer_mut(&mut self) -> &mut Mixers {
-        &mut self.mixer
+    #[test]
+    fn bundles_round_trip_their_assets() {
+        let directory = tempfile::tempdir().unwrap();
+        let samples = directory.path().join("samples");
+        let icons = directory.path().join("icons");
+        for (path, contents) in [
+            (samples.join("One/kick.wav"), vec![1]),
+            // Larger than a regular profile allows for extra files..
+            (samples.join("Two/kick.wav"), vec![2; 17 * 1024 * 1024]),
+            (icons.join("icon.png"), vec![3]),
+        ] {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+
+        let mut profile = Profile::load(std::io::Cursor::new(OFFICIAL)).unwrap();
+        let stack = profile
+            .settings_mut()
+            .sample_button_mut(SampleButtons::TopLeft)
+            .get_stack_mut(SampleBank::A);
+        for track in ["One/kick.wav", "Two/kick.wav"] {
+            stack.add_track(Track::new(track.to_string(), 0., 100., 1.));
+        }
+        for fader in Faders::iter() {
+            let icon = (fader == Faders::A).then(|| String::from("icon.png"));
+            profile
+                .settings_mut()
+                .scribble_mut(fader)
+                .set_icon_file(icon);
+        }
+
+        let assets = AssetResolver {
+            samples_dir: samples,
+            icons_dir: icons,
+        };
+        let mut bundle = std::io::Cursor::new(vec![]);
+        profile.export_bundle(&mut bundle, &assets).unwrap();
+
+        let extracted = directory.path().join("extracted");
+        bundle.set_position(0);
+        let imported = Profile::import_bundle(bundle, &extracted).unwrap();
+
+        // Matching file names are numbered in the order they were found..
+        let tracks: Vec<String> = imported
+            .settings()
+            .sample_button(SampleButtons::TopLeft)
+            .get_stack(SampleBank::A)
+            .get_tracks()
+            .iter()
+            .map(|track| track.track().to_string())
+            .collect();
+        let expected = [
+            extracted.join("samples/kick.wav"),
+            extracted.join("samples/kick (2).wav"),
+        ];
+        assert_eq!(
+            tracks,
+            expected
+                .clone()
+                .map(|path| path.to_string_lossy().to_string())
+        );
+        assert_eq!(fs::read(&expected[0]).unwrap(), [1]);
+        assert_eq!(fs::read(&expected[1]).unwrap().len(), 17 * 1024 * 1024);
+
+        let icon = extracted.join("icons/icon.png");
+        let scribble = imported.settings().scribble(Faders::A);
+        assert_eq!(
+            scribble.icon_file(),
+            Some(icon.to_string_lossy().to_string())
+        );
+        assert_eq!(fs::read(icon).unwrap(), [3]);
+
+        // The manifest and assets are consumed by the import..
+        assert!(imported.extra_files.is_empty());
     }
 
-    pub fn mixer(&self) -> &Mixers {
-        &self.mixer
+    fn asset_bundle(name: &str, manifest: &str) -> std::io::Cursor<Vec<u8>> {
+        std::io::Cursor::new(archive(&[
+            ("profile.xml", PROFILE.as_bytes()),
+            (name, b"asset"),
+            (BUNDLE_MANIFEST, manifest.as_bytes()),
+        ]))
     }
 
-    pub fn faders_mut(&mut self) -> &mut EnumMap<Faders, Option<Fader>> {
-        &mut self.faders
+    #[test]
+    fn bundle_imports_never_overwrite_existing_files() {
+        let directory = tempfile::tempdir().unwrap();
+        let samples = directory.path().join("samples");
+        fs::create_dir_all(&samples).unwrap();
+        fs::write(samples.join("kick.wav"), b"existing").unwrap();
+
+        let manifest = "samples\tassets/samples/kick.wav\tkick.wav\n";
+        let bundle = asset_bundle("assets/samples/kick.wav", manifest);
+        Profile::import_bundle(bundle, directory.path()).unwrap();
+        assert_eq!(fs::read(samples.join("kick.wav")).unwrap(), b"existing");
+        assert_eq!(fs::read(samples.join("kick (2).wav")).unwrap(), b"asset");
+
+        // Importing the same bundle again reuses the matching file, rather than adding another..
+        let bundle = asset_bundle("assets/samples/kick.wav", manifest);
+        Profile::import_bundle(bundle, directory.path()).unwrap();
+        assert!(!samples.join("kick (3).wav").exists());
     }
 
-    pub fn fader_mut(&mut self, fader: Faders) -> &mut Fader {
-        self.faders[fader].as_mut().unwrap()
+    #[test]
+    fn bundle_imports_reject_unsafe_asset_names() {
+        let directory = tempfile::tempdir().unwrap();
+        for (kind, name) in [
+            ("samples", "assets/samples/../escaped.wav"),
+            ("samples", "assets/samples/.."),
+            ("samples", "assets/samples/sub/kick.wav"),
+            ("samples", "assets/samples/sub\\kick.wav"),
+            ("samples", "assets/samples/C:kick.wav"),
+            ("samples", "assets/samples/"),
+            ("samples", "assets/icons/icon.png"),
+            ("scripts", "assets/scripts/run.sh"),
+            ("samples", "samples/kick.wav"),
+        ] {
+            let manifest = format!("{kind}\t{name}\tkick.wav\n");
+            let result = Profile::import_bundle(asset_bundle(name, &manifest), directory.path());
+            let error = result.unwrap_err().to_string();
+            assert!(error.contains("Invalid asset path"), "{name}: {error}");
+        }
+        assert!(fs::read_dir(directory.path()).unwrap().next().is_none());
     }
 
-    pub fn fader(&self, fader: Faders) -> &Fader {
-        self.faders[fader].as_ref().unwrap()
+    #[test]
+    fn bundled_names_differ_by_more_than_case() {
+        let used = vec![format!("{BUNDLE_ASSETS}samples/Kick.wav")];
+        let name = unique_asset_name("samples", Path::new("/a/kick.wav"), &used).unwrap();
+        assert_eq!(name, format!("{BUNDLE_ASSETS}samples/kick (2).wav"));
+
+        let source = Path::new("/a/sub\\kick.wav");
+        assert!(unique_asset_name("samples", source, &[]).is_err());
     }
 
-    pub fn mute_buttons(&mut self) -> &mut EnumMap<Faders, Option<MuteButton>> {
-        &mut self.mute_buttons
+    #[test]
+    fn every_colour_map_is_listed_once() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        let targets: Vec<_> = settings.colour_maps().map(|(target, _)| target).collect();
+
+        // A full profile has the mute chat button, the mute buttons, faders and scribbles, six
+        // effect buttons, five sampler buttons, seven simple elements, three effects and four
+        // encoders..
+        assert_eq!(targets.len(), 1 + 4 + 4 + 4 + 6 + 5 + 7 + 3 + 4);
+        for (index, target) in targets.iter().enumerate() {
+            assert!(!targets[..index].contains(target), "{target:?}");
+        }
+
+        let mutable: Vec<_> = settings
+            .colour_maps_mut()
+            .map(|(target, _)| target)
+            .collect();
+        assert_eq!(mutable, targets);
     }
 
-    pub fn mute_button_mut(&mut self, fader: Faders) -> &mut MuteButton {
-        self.mute_buttons[fader].as_mut().unwrap()
+    #[test]
+    fn missing_components_have_no_colour_map() {
+        let xml = without_scribble1();
+        let settings = ProfileSettings::load(xml.as_bytes()).unwrap();
+        let targets: Vec<_> = settings.colour_maps().map(|(target, _)| target).collect();
+
+        assert_eq!(targets.len(), 37);
+        assert!(!targets.contains(&LightingTarget::Scribble(Faders::A)));
+        assert!(targets.contains(&LightingTarget::Scribble(Faders::B)));
     }
 
-    pub fn mute_button(&self, fader: Faders) -> &MuteButton {
-        self.mute_buttons[fader].as_ref().unwrap()
+    #[test]
+    fn minimal_profiles_reload_identically() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+
+        let mut full = vec![];
+        settings.write_to(&mut full).unwrap();
+
+        let options = WriteOptions {
+            minimal: true,
+            ..Default::default()
+        };
+        let mut minimal = vec![];
+        settings
+            .write_to_with_options(&mut minimal, &options)
+            .unwrap();
+        assert!(minimal.len() < full.len());
+
+        // The sample track covers the whole sample, so its positions are left out..
+        let xml = String::from_utf8(minimal.clone()).unwrap();
+        assert!(!xml.contains("track_0StartPosition"));
+
+        let reloaded = ProfileSettings::load(minimal.as_slice()).unwrap();
+        assert_eq!(reloaded.reverb_encoder(), settings.reverb_encoder());
+        assert_eq!(reloaded.echo_encoder(), settings.echo_encoder());
+        assert_eq!(reloaded.pitch_encoder(), settings.pitch_encoder());
+        assert_eq!(reloaded.gender_encoder(), settings.gender_encoder());
+        assert_eq!(reloaded.megaphone_effect(), settings.megaphone_effect());
+        assert_eq!(reloaded.robot_effect(), settings.robot_effect());
+        assert_eq!(reloaded.hardtune_effect(), settings.hardtune_effect());
+        for button in SampleButtons::iter() {
+            assert_eq!(
+                reloaded.sample_button(button),
+                settings.sample_button(button)
+            );
+        }
     }
 
-    pub fn scribbles_mut(&mut self) -> &mut EnumMap<Faders, Option<Scribble>> {
-        &mut self.scribbles
+    #[test]
+    fn scribble_text_follows_the_channel() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        settings.assign_fader_channel(Faders::A, FullChannelList::Mic);
+        let scribble = settings.scribble_mut(Faders::A);
+        scribble.set_follows_channel(true);
+        scribble.set_text_bottom_middle("Mic".to_string());
+
+        settings.assign_fader_channel(Faders::A, FullChannelList::LineIn);
+        assert_eq!(settings.fader(Faders::A).channel(), FullChannelList::LineIn);
+        assert_eq!(
+            settings.scribble(Faders::A).text_bottom_middle().as_deref(),
+            Some("Line In")
+        );
     }
 
-    pub fn scribble(&self, fader: Faders) -> &Scribble {
-        self.scribbles[fader].as_ref().unwrap()
+    #[test]
+    fn customised_scribble_text_is_preserved() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        settings.assign_fader_channel(Faders::A, FullChannelList::Mic);
+        let scribble = settings.scribble_mut(Faders::A);
+        scribble.set_follows_channel(true);
+        scribble.set_text_bottom_middle("Podcast".to_string());
+
+        settings.assign_fader_channel(Faders::A, FullChannelList::LineIn);
+        assert_eq!(
+            settings.scribble(Faders::A).text_bottom_middle().as_deref(),
+            Some("Podcast")
+        );
+
+        // Without the flag, even the channel's own name is left alone..
+        let scribble = settings.scribble_mut(Faders::A);
+        scribble.set_follows_channel(false);
+        scribble.set_text_bottom_middle("Line In".to_string());
+
+        settings.assign_fader_channel(Faders::A, FullChannelList::Music);
+        assert_eq!(
+            settings.scribble(Faders::A).text_bottom_middle().as_deref(),
+            Some("Line In")
+        );
     }
 
-    pub fn scribble_mut(&mut self, fader: Faders) -> &mut Scribble {
-        self.scribbles[fader].as_mut().unwrap()
+    #[test]
+    fn follows_channel_is_only_written_when_set() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        let mut written = vec![];
+        settings.write_to(&mut written).unwrap();
+        assert!(!String::from_utf8(written)
+            .unwrap()
+            .contains("followsChannel"));
+
+        settings.scribble_mut(Faders::B).set_follows_channel(true);
+        let mut written = vec![];
+        settings.write_to(&mut written).unwrap();
+        assert!(String::from_utf8(written.clone())
+            .unwrap()
+            .contains("scribble2followsChannel=\"1\""));
+
+        let reloaded = ProfileSettings::load(written.as_slice()).unwrap();
+        assert!(!reloaded.scribble(Faders::A).follows_channel());
+        assert!(reloaded.scribble(Faders::B).follows_channel());
     }
 
-    pub fn effects(&self, effect: Preset) -> &Effects {
-        self.effects[effect].as_ref().unwrap()
+    fn fader_top(settings: &ProfileSettings, fader: Faders) -> Colour {
+        *settings.fader(fader).colour_map().colour_or_default(0)
     }
 
-    pub fn effects_mut(&mut self, effect: Preset) -> &mut Effects {
-        self.effects[effect].as_mut().unwrap()
+    fn scribble_colour(settings: &ProfileSettings, fader: Faders) -> Colour {
+        *settings.scribble(fader).colour_map().colour_or_default(0)
     }
 
-    pub fn mute_chat_mut(&mut self) -> &mut MuteChat {
-        &mut self.mute_chat
+    #[test]
+    fn linked_fader_colours_propagate_both_ways() {
+        let red = Colour::fromrgb("FF0000").unwrap();
+        let green = Colour::fromrgb("00FF00").unwrap();
+        let blue = Colour::fromrgb("0000FF").unwrap();
+
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        settings.set_scribble_colour(Faders::A, red).unwrap();
+        settings.link_fader_colours(Faders::A, true).unwrap();
+        assert_eq!(fader_top(&settings, Faders::A), red);
+
+        settings.set_scribble_colour(Faders::A, green).unwrap();
+        assert_eq!(fader_top(&settings, Faders::A), green);
+
+        settings.set_fader_colours(Faders::A, blue, red).unwrap();
+        assert_eq!(scribble_colour(&settings, Faders::A), blue);
+        assert_eq!(
+            *settings.fader(Faders::A).colour_map().colour_or_default(1),
+            red
+        );
     }
 
-    pub fn mute_chat(&self) -> &MuteChat {
-        &self.mute_chat
+    #[test]
+    fn unlinked_fader_colours_are_independent() {
+        let red = Colour::fromrgb("FF0000").unwrap();
+        let green = Colour::fromrgb("00FF00").unwrap();
+
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        settings.set_fader_colours(Faders::B, red, red).unwrap();
+        settings.set_scribble_colour(Faders::B, green).unwrap();
+        assert_eq!(fader_top(&settings, Faders::B), red);
+
+        settings.set_fader_colours(Faders::B, green, green).unwrap();
+        settings.set_scribble_colour(Faders::B, red).unwrap();
+        assert_eq!(fader_top(&settings, Faders::B), green);
+
+        // Unlinking stops the propagation..
+        settings.link_fader_colours(Faders::B, true).unwrap();
+        settings.link_fader_colours(Faders::B, false).unwrap();
+        settings.set_scribble_colour(Faders::B, green).unwrap();
+        assert_eq!(fader_top(&settings, Faders::B), red);
     }
 
-    pub fn megaphone_effect(&self) -> &MegaphoneEffectBase {
-        &self.megaphone_effect
+    #[test]
+    fn direct_colour_changes_sync_only_linked_faders() {
+        let red = Colour::fromrgb("FF0000").unwrap();
+        let green = Colour::fromrgb("00FF00").unwrap();
+
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        settings.set_fader_colours(Faders::C, green, green).unwrap();
+        settings.set_fader_colours(Faders::D, green, green).unwrap();
+        settings.link_fader_colours(Faders::C, true).unwrap();
+
+        // As a global colour would, bypassing the linked setters..
+        for fader in [Faders::C, Faders::D] {
+            let colours = settings.scribble_mut(fader).colour_map_mut();
+            colours.set_colour(0, red).unwrap();
+        }
+        settings.sync_linked_fader_colours().unwrap();
+
+        assert_eq!(fader_top(&settings, Faders::C), red);
+        assert_eq!(fader_top(&settings, Faders::D), green);
     }
 
-    pub fn megaphone_effect_mut(&mut self) -> &mut MegaphoneEffectBase {
-        &mut self.megaphone_effect
+    #[test]
+    fn fader_colour_links_are_only_written_when_set() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        let mut written = vec![];
+        settings.write_to(&mut written).unwrap();
+        assert!(!String::from_utf8(written)
+            .unwrap()
+            .contains("linkFaderColours"));
+
+        settings.link_fader_colours(Faders::C, true).unwrap();
+        let mut written = vec![];
+        settings.write_to(&mut written).unwrap();
+
+        let reloaded = ProfileSettings::load(written.as_slice()).unwrap();
+        assert!(reloaded.fader_colours_linked(Faders::C));
+        assert!(!reloaded.fader_colours_linked(Faders::A));
     }
 
-    pub fn robot_effect(&self) -> &RobotEffectBase {
-        &self.robot_effect
+    // The lines written for the root, the samplers (with their stacks), and the simple elements..
+    fn stable_lines(xml: &[u8]) -> Vec<String> {
+        let xml = String::from_utf8(xml.to_vec()).unwrap();
+        xml.lines()
+            .filter(|line| {
+                let tag = line.trim_start().trim_start_matches('<');
+                let end = tag.find([' ', '/', '>']).unwrap_or(tag.len());
+                matches!(
+                    ComponentKind::from_tag(&tag[..end]),
+                    Some(
+                        ComponentKind::Root
+                            | ComponentKind::Sampler(_)
+                            | ComponentKind::SampleStack(_)
+                            | ComponentKind::Simple(_)
+                    )
+                )
+            })
+            .map(String::from)
+            .collect()
     }
 
-    pub fn robot_effect_mut(&mut self) -> &mut RobotEffectBase {
-        &mut self.robot_effect
+    #[test]
+    fn sampler_and_simple_elements_are_byte_stable() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        let root = settings.root_mut();
+        root.set_attribute("exportedBy", Some("test".to_string()))
+            .unwrap();
+        root.set_attribute("exportedAt", Some("2024-01-01".to_string()))
+            .unwrap();
+        let mut first = vec![];
+        settings.write_to(&mut first).unwrap();
+
+        let expected = stable_lines(&first);
+        assert!(expected[0].starts_with("<ValueTreeRoot "));
+        assert!(expected.iter().any(|line| line.contains("<sampleStackA ")));
+        assert!(expected.iter().any(|line| line.contains("<globalColour ")));
+
+        // Each load builds new HashMaps, with their own iteration order..
+        for _ in 0..8 {
+            let mut settings = ProfileSettings::load(first.as_slice()).unwrap();
+            let mut written = vec![];
+            settings.write_to(&mut written).unwrap();
+            assert_eq!(stable_lines(&written), expected);
+        }
     }
 
-    pub fn hardtune_effect(&self) -> &HardtuneEffectBase {
-        &self.hardtune_effect
+    fn unmuted_profile() -> ProfileSettings {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        for fader in Faders::iter() {
+            let colours = settings.mute_button_mut(fader).colour_map_mut();
+            colours.set_state_on(false).unwrap();
+            colours.set_blink_on(false).unwrap();
+        }
+        let mute_chat = settings.mute_chat_mut();
+        mute_chat.set_cough_button_on(false);
+        mute_chat.set_blink_on(false);
+        settings
     }
 
-    pub fn hardtune_effect_mut(&mut self) -> &mut HardtuneEffectBase {
-        &mut self.hardtune_effect
+    fn is_muted(settings: &ProfileSettings, fader: Faders) -> bool {
+        settings.mute_button(fader).colour_map().get_state()
     }
 
-    pub fn sample_button(&self, button: SampleButtons) -> &SampleBase {
-        self.sampler_map[button].as_ref().unwrap()
+    fn fader_volume(settings: &ProfileSettings, fader: Faders) -> u8 {
+        settings
+            .mixer()
+            .channel_volume(settings.fader(fader).channel())
     }
 
-    pub fn sample_button_mut(&mut self, button: SampleButtons) -> &mut SampleBase {
-        self.sampler_map[button].as_mut().unwrap()
+    #[test]
+    fn mute_all_is_restored() {
+        let mut settings = unmuted_profile();
+        let volumes = Faders::iter().map(|fader| fader_volume(&settings, fader));
+        let volumes: Vec<_> = volumes.collect();
+
+        let snapshot = settings.mute_all(MuteFunction::All).unwrap();
+        assert_eq!(snapshot.faders().len(), 4);
+        for fader in Faders::iter() {
+            assert!(is_muted(&settings, fader));
+            assert_eq!(fader_volume(&settings, fader), 0);
+        }
+        assert!(settings.mute_chat().cough_button_on());
+
+        settings.restore(&snapshot).unwrap();
+        for (fader, volume) in Faders::iter().zip(volumes) {
+            assert!(!is_muted(&settings, fader));
+            assert_eq!(fader_volume(&settings, fader), volume);
+        }
+        assert!(!settings.mute_chat().cough_button_on());
     }
 
-    pub fn pitch_encoder(&self) -> &PitchEncoderBase {
-        &self.pitch_encoder
+    #[test]
+    fn already_muted_buttons_are_left_alone() {
+        let mut settings = unmuted_profile();
+        let button = settings.mute_button_mut(Faders::A);
+        button.set_mute_function(MuteFunction::ToStream);
+        button.set_previous_volume(123).unwrap();
+        button.colour_map_mut().set_state_on(true).unwrap();
+
+        let snapshot = settings.mute_all(MuteFunction::All).unwrap();
+        assert!(snapshot
+            .faders()
+            .iter()
+            .all(|entry| entry.fader != Faders::A));
+        assert_eq!(settings.mute_button(Faders::A).previous_volume(), 123);
+
+        settings.restore(&snapshot).unwrap();
+        let button = settings.mute_button(Faders::A);
+        assert!(is_muted(&settings, Faders::A));
+        assert_eq!(*button.mute_function(), MuteFunction::ToStream);
+        assert_eq!(button.previous_volume(), 123);
     }
 
-    pub fn pitch_encoder_mut(&mut self) -> &mut PitchEncoderBase {
-        &mut self.pitch_encoder
+    #[test]
+    fn restore_skips_buttons_muted_again_in_between() {
+        let mut settings = unmuted_profile();
+        let snapshot = settings.mute_all(MuteFunction::All).unwrap();
+
+        // The user unmutes B, then mutes it again by hand, ending up as mute_all left it..
+        let colours = settings.mute_button_mut(Faders::B).colour_map_mut();
+        colours.set_state_on(false).unwrap();
+        let colours = settings.mute_button_mut(Faders::B).colour_map_mut();
+        colours.set_state_on(true).unwrap();
+
+        // Changing the colour of a mute button isn't a change to whether it's muted..
+        let red = Colour::fromrgb("FF0000").unwrap();
+        let colours = settings.mute_button_mut(Faders::C).colour_map_mut();
+        colours.set_colour(0, red).unwrap();
+
+        // And neither is borrowing the chat mute without changing it..
+        settings.mute_chat_mut().set_cough_button_on(true);
+
+        settings.restore(&snapshot).unwrap();
+        assert!(!is_muted(&settings, Faders::A));
+        assert!(is_muted(&settings, Faders::B));
+        assert!(!is_muted(&settings, Faders::C));
+        assert!(!is_muted(&settings, Faders::D));
+        assert!(!settings.mute_chat().cough_button_on());
     }
 
-    pub fn echo_encoder(&self) -> &EchoEncoderBase {
-        &self.echo_encoder
+    #[test]
+    fn snapshots_restore_after_a_reload() {
+        let mut settings = unmuted_profile();
+        let volume = fader_volume(&settings, Faders::D);
+        let snapshot = settings.mute_all(MuteFunction::All).unwrap();
+
+        let mut stored = vec![];
+        snapshot.write_to(&mut stored).unwrap();
+        let mut written = vec![];
+        settings.write_to(&mut written).unwrap();
+
+        // Without the generations, a button only counts as changed if it's no longer muted the
+        // way mute_all left it..
+        let mut settings = ProfileSettings::load(written.as_slice()).unwrap();
+        let button = settings.mute_button_mut(Faders::A);
+        button.set_mute_function(MuteFunction::ToPhones);
+
+        let snapshot = MuteAllSnapshot::load(stored.as_slice()).unwrap();
+        settings.restore(&snapshot).unwrap();
+        assert!(is_muted(&settings, Faders::A));
+        assert!(!is_muted(&settings, Faders::D));
+        assert_eq!(fader_volume(&settings, Faders::D), volume);
     }
 
-    pub fn echo_encoder_mut(&mut self) -> &mut EchoEncoderBase {
-        &mut self.echo_encoder
+    #[test]
+    fn unsafe_snapshots_are_rejected() {
+        let depth = LoadOptions::default().max_depth + 1;
+        let nested = format!(
+            "{}{}",
+            "<muteAllSnapshot instance=\"1\">".repeat(depth),
+            "</muteAllSnapshot>".repeat(depth)
+        );
+        let error = MuteAllSnapshot::load(nested.as_bytes()).unwrap_err();
+        assert!(is_unsafe_document(error));
+
+        let length = LoadOptions::default().max_attribute_length + 1;
+        let oversized = format!("<muteAllSnapshot instance=\"{}\"/>", "1".repeat(length));
+        let error = MuteAllSnapshot::load(oversized.as_bytes()).unwrap_err();
+        assert!(is_unsafe_document(error));
+
+        let error = MuteAllSnapshot::load(BILLION_LAUGHS.as_bytes()).unwrap_err();
+        assert!(is_unsafe_document(error));
     }
 
-    pub fn gender_encoder(&self) -> &GenderEncoderBase {
-        &self.gender_encoder
+    fn with_pitch(settings: &mut ProfileSettings, preset: Preset, pitch: i8) -> Result<i8> {
+        let mut values = settings.encoder_values(preset);
+        values.pitch = pitch;
+        settings.apply_encoder_values(preset, values)?;
+        Ok(settings.encoder_values(preset).pitch)
     }
 
-    pub fn gender_encoder_mut(&mut self) -> &mut GenderEncoderBase {
-        &mut self.gender_encoder
+    #[test]
+    fn encoder_values_across_the_pitch_boundary() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        let preset = Preset::Preset1;
+        settings
+            .set_effect_enabled(preset, EffectType::HardTune, false)
+            .unwrap();
+
+        // Narrow stores half the encoder value, so odd values round down..
+        let pitch = settings.pitch_encoder_mut().get_preset_mut(preset);
+        pitch.set_style(PitchStyle::Narrow);
+        assert_eq!(with_pitch(&mut settings, preset, 24).unwrap(), 24);
+        assert_eq!(
+            settings
+                .pitch_encoder()
+                .get_preset(preset)
+                .get_pitch_value(),
+            12
+        );
+        assert_eq!(with_pitch(&mut settings, preset, 23).unwrap(), 22);
+        assert_eq!(with_pitch(&mut settings, preset, -24).unwrap(), -24);
+        assert!(with_pitch(&mut settings, preset, 26).is_err());
+
+        // Switching to Wide keeps the encoder position the same..
+        let pitch = settings.pitch_encoder_mut().get_preset_mut(preset);
+        pitch.set_style(PitchStyle::Wide);
+        assert_eq!(settings.encoder_values(preset).pitch, -24);
+        assert_eq!(with_pitch(&mut settings, preset, 23).unwrap(), 23);
+        assert_eq!(with_pitch(&mut settings, preset, -24).unwrap(), -24);
+        assert!(with_pitch(&mut settings, preset, 25).is_err());
+
+        // And back to Narrow, which halves the stored value..
+        with_pitch(&mut settings, preset, 12).unwrap();
+        let pitch = settings.pitch_encoder_mut().get_preset_mut(preset);
+        pitch.set_style(PitchStyle::Narrow);
+        assert_eq!(settings.encoder_values(preset).pitch, 12);
+        assert_eq!(
+            settings
+                .pitch_encoder()
+                .get_preset(preset)
+                .get_pitch_value(),
+            6
+        );
     }
 
-    pub fn reverb_encoder(&self) -> &ReverbEncoderBase {
-        &self.reverb_encoder
+    #[test]
+    fn encoder_values_with_hardtune_enabled() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        let preset = Preset::Preset1;
+        settings
+            .set_effect_enabled(preset, EffectType::HardTune, true)
+            .unwrap();
+
+        let pitch = settings.pitch_encoder_mut().get_preset_mut(preset);
+        pitch.set_style(PitchStyle::Narrow);
+        assert_eq!(with_pitch(&mut settings, preset, 1).unwrap(), 1);
+        assert_eq!(
+            settings
+                .pitch_encoder()
+                .get_preset(preset)
+                .get_pitch_value(),
+            12
+        );
+        assert_eq!(with_pitch(&mut settings, preset, -1).unwrap(), -1);
+        assert!(with_pitch(&mut settings, preset, 2).is_err());
+
+        let pitch = settings.pitch_encoder_mut().get_preset_mut(preset);
+        pitch.set_style(PitchStyle::Wide);
+        assert_eq!(with_pitch(&mut settings, preset, 2).unwrap(), 2);
+        assert_eq!(
+            settings
+                .pitch_encoder()
+                .get_preset(preset)
+                .get_pitch_value(),
+            24
+        );
+        assert_eq!(with_pitch(&mut settings, preset, -2).unwrap(), -2);
+        assert!(with_pitch(&mut settings, preset, 3).is_err());
+
+        // Out of range reverb and echo are rejected without touching the pitch..
+        let mut values = settings.encoder_values(preset);
+        values.pitch = 1;
+        values.reverb = 25;
+        assert!(settings.apply_encoder_values(preset, values).is_err());
+        values.reverb = 24;
+        values.echo = 25;
+        assert!(settings.apply_encoder_values(preset, values).is_err());
+        assert_eq!(settings.encoder_values(preset).pitch, -2);
     }
 
-    pub fn reverb_encoder_mut(&mut self) -> &mut ReverbEncoderBase {
-        &mut self.reverb_encoder
+    fn reloaded(settings: &mut ProfileSettings) -> ProfileSettings {
+        let mut written = vec![];
+        settings.write_to(&mut written).unwrap();
+        ProfileSettings::load(written.as_slice()).unwrap()
     }
 
-    pub fn simple_element_mut(&mut self, name: SimpleElements) -> &mut SimpleElement {
-        if self.simple_elements[name].is_some() {
-            return self.simple_elements[name].as_mut().unwrap();
+    // Whether the effect's button is lit..
+    fn effect_button(settings: &ProfileSettings, effect: EffectType) -> bool {
+        match effect {
+            EffectType::Megaphone => settings.megaphone_effect().colour_map().get_state(),
+            EffectType::Robot => settings.robot_effect().colour_map().get_state(),
+            EffectType::HardTune => settings.hardtune_effect().colour_map().get_state(),
         }
-
-        // If for whatever reason, this is missing, we'll use the global colour.
-        return self.simple_elements[SimpleElements::GlobalColour]
-            .as_mut()
-            .unwrap();
     }
 
-    pub fn simple_element(&self, name: SimpleElements) -> &SimpleElement {
-        if self.simple_elements[name].is_some() {
-            return self.simple_elements[name].as_ref().unwrap();
+    #[test]
+    fn effects_are_flipped_one_bank_at_a_time() {
+        for effect in EffectType::iter() {
+            for flipped in Preset::iter() {
+                let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+                let before: Vec<bool> = Preset::iter()
+                    .map(|preset| settings.effect_enabled(preset, effect))
+                    .collect();
+                let button = effect_button(&settings, effect);
+
+                let enabled = !settings.effect_enabled(flipped, effect);
+                settings
+                    .set_effect_enabled(flipped, effect, enabled)
+                    .unwrap();
+
+                // The flip survives a save, and none of the other banks have moved..
+                let settings = reloaded(&mut settings);
+                for (preset, before) in Preset::iter().zip(before) {
+                    let expected = if preset == flipped { enabled } else { before };
+                    let state = settings.effect_enabled(preset, effect);
+                    assert_eq!(state, expected, "{effect:?} {flipped:?} {preset:?}");
+                }
+
+                // The button only follows the selected bank..
+                let expected = match settings.context().selected_effects() == flipped {
+                    true => enabled,
+                    false => button,
+                };
+                assert_eq!(effect_button(&settings, effect), expected);
+            }
         }
+    }
 
-        // If for whatever reason, this is missing, we'll use the global colour.
-        return self.simple_elements[SimpleElements::GlobalColour]
-            .as_ref()
+    #[test]
+    fn fx_enabled_is_written_and_reloaded() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        assert!(!settings.fx_enabled());
+
+        let changed = settings.set_fx_enabled(true).unwrap();
+        assert!(changed.contains(ComponentId::HardTune));
+        assert!(changed.contains(ComponentId::Gender));
+
+        // The state is held by the fxClear element..
+        let mut written = vec![];
+        settings.write_to(&mut written).unwrap();
+        let written = String::from_utf8(written).unwrap();
+        assert!(written.contains("fxClearstate=\"1\""));
+
+        let mut settings = ProfileSettings::load(written.as_bytes()).unwrap();
+        assert!(settings.fx_enabled());
+
+        // Setting the same state again has nothing to re-send..
+        assert!(settings.set_fx_enabled(true).unwrap().is_empty());
+        settings.set_fx_enabled(false).unwrap();
+        assert!(!reloaded(&mut settings).fx_enabled());
+    }
+
+    #[test]
+    fn pitch_follows_the_hardtune_state_of_its_own_bank() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        let selected = settings.context().selected_effects();
+        let other = Preset::iter().find(|preset| *preset != selected).unwrap();
+        settings
+            .set_effect_enabled(selected, EffectType::HardTune, false)
+            .unwrap();
+        settings
+            .set_effect_enabled(other, EffectType::HardTune, true)
             .unwrap();
+        assert!(!settings.is_pitch_hardtune_linked());
+
+        // The other bank has HardTune on, so its pitch is in whole octaves..
+        let pitch = settings.pitch_encoder_mut().get_preset_mut(other);
+        pitch.set_style(PitchStyle::Wide);
+        settings.set_pitch_amount(other, 2).unwrap();
+        assert_eq!(settings.pitch_amount(other), 2);
+        assert_eq!(settings.encoder_values(other).pitch, 2);
+        assert_eq!(
+            settings.pitch_encoder().get_preset(other).get_pitch_value(),
+            24
+        );
+
+        // While the selected bank is still in semitones..
+        let pitch = settings.pitch_encoder_mut().get_preset_mut(selected);
+        pitch.set_style(PitchStyle::Wide);
+        settings.set_pitch_amount(selected, 2).unwrap();
+        assert_eq!(settings.encoder_values(selected).pitch, 2);
+        assert_eq!(
+            settings
+                .pitch_encoder()
+                .get_preset(selected)
+                .get_pitch_value(),
+            2
+        );
     }
 
-    pub fn context(&self) -> &Context {
-        &self.context
+    fn set_press_action(settings: &mut ProfileSettings, action: EncoderPressAction) {
+        settings.reverb_encoder_mut().set_press_action(action);
+        settings.echo_encoder_mut().set_press_action(action);
+        settings.pitch_encoder_mut().set_press_action(action);
+        settings.gender_encoder_mut().set_press_action(action);
     }
 
-    pub fn context_mut(&mut self) -> &mut Context {
-        &mut self.context
+    // Loads the test profile with every encoder part way along, and HardTune off on the bank..
+    fn pressable_settings(preset: Preset) -> ProfileSettings {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        settings
+            .set_effect_enabled(preset, EffectType::HardTune, false)
+            .unwrap();
+        let reverb = settings.reverb_encoder_mut().get_preset_mut(preset);
+        reverb.set_knob_position(12).unwrap();
+        let echo = settings.echo_encoder_mut().get_preset_mut(preset);
+        echo.set_knob_position(6).unwrap();
+        let gender = settings.gender_encoder_mut().get_preset_mut(preset);
+        gender.set_knob_position(-4).unwrap();
+        let pitch = settings.pitch_encoder_mut().get_preset_mut(preset);
+        pitch.set_style(PitchStyle::Wide);
+        settings.set_pitch_amount(preset, 5).unwrap();
+        settings
     }
 
-    pub fn submixes(&self) -> &SubMixer {
-        &self.submix_tree
+    #[test]
+    fn pressing_an_encoder_without_an_action_changes_nothing() {
+        let preset = Preset::Preset1;
+        let mut settings = pressable_settings(preset);
+        set_press_action(&mut settings, EncoderPressAction::None);
+
+        let values = settings.encoder_values(preset);
+        for (encoder, expected) in [
+            (EncoderType::Reverb, 12),
+            (EncoderType::Echo, 6),
+            (EncoderType::Pitch, 5),
+            (EncoderType::Gender, -4),
+        ] {
+            let outcome = settings.encoder_press(preset, encoder).unwrap();
+            assert_eq!(outcome.action, EncoderPressAction::None);
+            assert_eq!(outcome.encoder_value, expected);
+        }
+        assert_eq!(settings.encoder_values(preset), values);
     }
-    pub fn submixes_mut(&mut self) -> &mut SubMixer {
-        &mut self.submix_tree
+
+    #[test]
+    fn pressing_an_encoder_can_reset_its_value() {
+        let preset = Preset::Preset1;
+        let mut settings = pressable_settings(preset);
+        set_press_action(&mut settings, EncoderPressAction::ResetValue);
+
+        for encoder in EncoderType::iter() {
+            let outcome = settings.encoder_press(preset, encoder).unwrap();
+            assert_eq!(outcome.action, EncoderPressAction::ResetValue);
+            assert_eq!(outcome.encoder_value, 0);
+        }
+        assert_eq!(settings.encoder_values(preset), EncoderValues::default());
+
+        // Only the pressed bank is reset..
+        let other = settings.encoder_values(Preset::Preset2);
+        let original = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        assert_eq!(other, original.encoder_values(Preset::Preset2));
     }
 
-    pub fn mix_routing(&self) -> &MixRoutingTree {
-        &self.mix_routing
+    #[test]
+    fn pressing_an_encoder_can_cycle_its_style() {
+        let preset = Preset::Preset1;
+        let mut settings = pressable_settings(preset);
+        set_press_action(&mut settings, EncoderPressAction::CycleStyle);
+
+        let reverb = *settings.reverb_encoder().get_preset(preset).style();
+        let echo = *settings.echo_encoder().get_preset(preset).style();
+        let gender = *settings.gender_encoder().get_preset(preset).style();
+        for encoder in [EncoderType::Reverb, EncoderType::Echo, EncoderType::Gender] {
+            let outcome = settings.encoder_press(preset, encoder).unwrap();
+            assert_eq!(outcome.action, EncoderPressAction::CycleStyle);
+        }
+        assert_eq!(
+            *settings.reverb_encoder().get_preset(preset).style(),
+            next_style(reverb)
+        );
+        assert_eq!(
+            *settings.echo_encoder().get_preset(preset).style(),
+            next_style(echo)
+        );
+        assert_eq!(
+            *settings.gender_encoder().get_preset(preset).style(),
+            next_style(gender)
+        );
+
+        // Pitch is on the last style, so wraps back around to the first..
+        let outcome = settings.encoder_press(preset, EncoderType::Pitch).unwrap();
+        let pitch = settings.pitch_encoder().get_preset(preset);
+        assert_eq!(*pitch.style(), PitchStyle::Narrow);
+        assert_eq!(outcome.encoder_value, pitch.get_encoder_position(false));
     }
-    pub fn mix_routing_mut(&mut self) -> &mut MixRoutingTree {
-        &mut self.mix_routing
+
+    #[test]
+    fn pressing_pitch_reports_hardtune_linked_values() {
+        let preset = Preset::Preset1;
+        let mut settings = pressable_settings(preset);
+        settings
+            .set_effect_enabled(preset, EffectType::HardTune, true)
+            .unwrap();
+        settings.set_pitch_amount(preset, 2).unwrap();
+
+        // With HardTune on, Wide is in whole octaves, so the encoder shows 2 rather than 24..
+        set_press_action(&mut settings, EncoderPressAction::None);
+        let outcome = settings.encoder_press(preset, EncoderType::Pitch).unwrap();
+        assert_eq!(outcome.encoder_value, 2);
+
+        set_press_action(&mut settings, EncoderPressAction::CycleStyle);
+        let outcome = settings.encoder_press(preset, EncoderType::Pitch).unwrap();
+        let pitch = settings.pitch_encoder().get_preset(preset);
+        assert_eq!(*pitch.style(), PitchStyle::Narrow);
+        assert_eq!(outcome.encoder_value, pitch.get_encoder_position(true));
+
+        set_press_action(&mut settings, EncoderPressAction::ResetValue);
+        let outcome = settings.encoder_press(preset, EncoderType::Pitch).unwrap();
+        assert_eq!(outcome.encoder_value, 0);
+        let pitch = settings.pitch_encoder().get_preset(preset);
+        assert_eq!(pitch.get_pitch_value(), 0);
     }
-}
 
-/// This will wrap a 'Start' XML event into a name, and attribute Vec. We're using
-/// our own Attribute Struct here to allow easy moving between XML libraries in future.
-/// TODO: If we're doing this, we might as well make the attributes a HashMap
-pub(crate) fn wrap_start_event(event: &BytesStart) -> Result<(String, Vec<Attribute>)> {
-    let mut attributes = Vec::new();
+    #[test]
+    fn press_actions_are_saved_when_set() {
+        let mut settings = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        let mut written = vec![];
+        settings.write_to(&mut written).unwrap();
+        assert!(!String::from_utf8(written).unwrap().contains("pressAction"));
+
+        settings
+            .reverb_encoder_mut()
+            .set_press_action(EncoderPressAction::CycleStyle);
+        settings
+            .pitch_encoder_mut()
+            .set_press_action(EncoderPressAction::ResetValue);
+        let mut written = vec![];
+        settings.write_to(&mut written).unwrap();
+
+        let reloaded = ProfileSettings::load(written.as_slice()).unwrap();
+        let actions = [
+            reloaded.reverb_encoder().press_action(),
+            reloaded.echo_encoder().press_action(),
+            reloaded.pitch_encoder().press_action(),
+            reloaded.gender_encoder().press_action(),
+        ];
+        assert_eq!(
+            actions,
+            [
+                EncoderPressAction::CycleStyle,
+                EncoderPressAction::None,
+                EncoderPressAction::ResetValue,
+                EncoderPressAction::None,
+            ]
+        );
+    }
 
-    let name = String::from_utf8_lossy(event.local_name().as_ref()).parse()?;
-    for attribute in event.attributes() {
-        match attribute {
-            Ok(a) => {
-                attributes.push(Attribute {
-                    name: String::from_utf8_lossy(a.key.local_name().as_ref()).parse()?,
-                    value: String::from(a.unescape_value()?.as_ref()),
-                });
-            }
-            Err(e) => {
-                bail!("Error Processing Attribute: {}", e);
-            }
-        }
+    #[test]
+    fn encoder_amounts_are_kept_in_range() {
+        assert_eq!(encoder_amount(-1), 0);
+        assert_eq!(encoder_amount(12), 12);
+        assert_eq!(encoder_amount(25), 24);
     }
-    Ok((name, attributes))
 }
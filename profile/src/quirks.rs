@@ -0,0 +1,263 @@
+use std::borrow::Cow;
+
+use log::debug;
+
+/**
+ * Various versions of the official app have written attribute values which don't quite match
+ * what they're supposed to be (floats where ints are expected, stray whitespace in enums, and the
+ * odd legacy spelling). Rather than having every component handle these individually, all
+ * attributes pass through here before they reach a parser.
+ *
+ * To add a new quirk, simply add a new entry to the QUIRKS table below.
+ */
+pub struct Quirk {
+    name: &'static str,
+    description: &'static str,
+    applies_to: fn(&str, &str) -> bool,
+    fix: fn(&str) -> Option<String>,
+}
+
+impl Quirk {
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn description(&self) -> &'static str {
+        self.description
+    }
+
+    pub fn applies_to(&self, element: &str, attribute: &str) -> bool {
+        (self.applies_to)(element, attribute)
+    }
+
+    /// Returns the corrected value, or None if the value isn't affected by this quirk.
+    pub fn fix(&self, value: &str) -> Option<String> {
+        (self.fix)(value)
+    }
+}
+
+const QUIRKS: &[Quirk] = &[
+    Quirk {
+        name: "trim_whitespace",
+        description: "Enum and numeric values with leading or trailing whitespace",
+        applies_to: |_, attribute| !is_free_text(attribute),
+        fix: trim_whitespace,
+    },
+    Quirk {
+        name: "float_formatted_int",
+        description: "Whole numbers written as floats (for example, '128.000000')",
+        applies_to: |_, attribute| !is_free_text(attribute),
+        fix: float_formatted_int,
+    },
+    Quirk {
+        name: "fractional_hardtune_int",
+        description: "HardTune amounts written with a fraction (for example, '50.5'), these are \
+            truncated to a whole number",
+        applies_to: |element, attribute| {
+            element.starts_with("hardtuneEffect") && HARDTUNE_INTS.contains(&attribute)
+        },
+        fix: truncate_fraction,
+    },
+    Quirk {
+        name: "legacy_mute_all",
+        description: "Mute Button functions written as 'All' rather than 'Mute All'",
+        applies_to: |element, attribute| {
+            element.starts_with("mute") && attribute.ends_with("Function")
+        },
+        fix: |value| (value == "All").then(|| String::from("Mute All")),
+    },
+];
+
+pub fn quirks() -> &'static [Quirk] {
+    QUIRKS
+}
+
+pub fn normalise<'a>(element: &str, attribute: &str, value: &'a str) -> Cow<'a, str> {
+    let mut value = Cow::Borrowed(value);
+
+    for quirk in QUIRKS {
+        if !quirk.applies_to(element, attribute) {
+            continue;
+        }
+
+        if let Some(fixed) = quirk.fix(&value) {
+            debug!(
+                "Applied Quirk {} to {}.{}: '{}' -> '{}'",
+                quirk.name, element, attribute, value, fixed
+            );
+            value = Cow::Owned(fixed);
+        }
+    }
+    value
+}
+
+// The HardTune preset attributes which hold whole numbers. These used to be parsed as floats and
+// cast, so a value with a fraction still needs to load as it did then..
+const HARDTUNE_INTS: [&str; 6] = [
+    "HARDTUNE_KEYSOURCE",
+    "HARDTUNE_AMOUNT",
+    "HARDTUNE_WINDOW",
+    "HARDTUNE_RATE",
+    "HARDTUNE_SCALE",
+    "HARDTUNE_PITCH_AMT",
+];
+
+// These are user provided values (names, file names, text), so we shouldn't be touching them.
+fn is_free_text(attribute: &str) -> bool {
+    if let Some(index) = attribute.strip_prefix("track_") {
+        // track_X is the file name, track_XStartPosition etc are numbers.
+        return index.chars().all(|c| c.is_ascii_digit());
+    }
+
+    attribute == "file"
+        || attribute.ends_with("Name")
+        || attribute.ends_with("string0")
+        || attribute.ends_with("string1")
+        || attribute.ends_with("iconFile")
+        || attribute.ends_with("bitmap")
+}
+
+fn trim_whitespace(value: &str) -> Option<String> {
+    let trimmed = value.trim();
+    (trimmed.len() != value.len()).then(|| trimmed.to_string())
+}
+
+fn float_formatted_int(value: &str) -> Option<String> {
+    let (whole, fraction) = value.split_once('.')?;
+    if fraction.is_empty() || !fraction.chars().all(|c| c == '0') {
+        return None;
+    }
+
+    let digits = whole.strip_prefix('-').unwrap_or(whole);
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    // Avoid producing '-0'..
+    if digits.chars().all(|c| c == '0') {
+        return Some(String::from("0"));
+    }
+    Some(whole.to_string())
+}
+
+fn truncate_fraction(value: &str) -> Option<String> {
+    let (whole, fraction) = value.split_once('.')?;
+    let is_number = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+    if !is_number(whole) || !is_number(fraction) {
+        return None;
+    }
+    Some(whole.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trims_whitespace_from_values() {
+        assert_eq!(normalise("mixerTree", "volume0", " 128 "), "128");
+        assert_eq!(normalise("fxTree", "reverbStyle", "Library "), "Library");
+    }
+
+    #[test]
+    fn converts_float_formatted_ints() {
+        assert_eq!(normalise("mixerTree", "volume0", "128.000000"), "128");
+        assert_eq!(normalise("mixerTree", "volume0", "-12.0"), "-12");
+        assert_eq!(normalise("mixerTree", "volume0", "-0.000"), "0");
+
+        // Real fractions, and things which aren't numbers, are left alone..
+        assert_eq!(normalise("mixerTree", "volume0", "0.5"), "0.5");
+        assert_eq!(normalise("mixerTree", "volume0", "1."), "1.");
+        assert_eq!(normalise("mixerTree", "volume0", "a.00"), "a.00");
+    }
+
+    #[test]
+    fn truncates_fractional_hardtune_ints() {
+        let element = "hardtuneEffectpreset1";
+        assert_eq!(normalise(element, "HARDTUNE_AMOUNT", "50.5"), "50");
+        assert_eq!(normalise(element, "HARDTUNE_WINDOW", "20.99"), "20");
+        assert_eq!(normalise(element, "HARDTUNE_AMOUNT", "50.000"), "50");
+        assert_eq!(normalise(element, "HARDTUNE_KEYSOURCE", "2.5"), "2");
+
+        // Anything which isn't a positive number is left for the parser to reject..
+        assert_eq!(normalise(element, "HARDTUNE_RATE", "-1.5"), "-1.5");
+        assert_eq!(normalise(element, "HARDTUNE_SCALE", "a.5"), "a.5");
+
+        // And other attributes keep their fractions..
+        assert_eq!(normalise(element, "HARDTUNE_STYLE", "1.5"), "1.5");
+        assert_eq!(normalise("mixerTree", "HARDTUNE_AMOUNT", "50.5"), "50.5");
+    }
+
+    #[test]
+    fn fixes_legacy_mute_all() {
+        assert_eq!(normalise("mute1", "muteFunction", "All"), "Mute All");
+        assert_eq!(normalise("mute1", "muteFunction", "Mute All"), "Mute All");
+
+        // Only applies to the mute buttons..
+        assert_eq!(normalise("mixerTree", "muteFunction", "All"), "All");
+    }
+
+    #[test]
+    fn leaves_free_text_alone() {
+        assert_eq!(normalise("scribble1", "string0", " 1.000 "), " 1.000 ");
+        assert_eq!(
+            normalise("sampleStack", "track_0", " file.wav "),
+            " file.wav "
+        );
+        assert_eq!(normalise("scribble1", "iconFile", "icon.0"), "icon.0");
+
+        // But track positions are numbers..
+        assert_eq!(
+            normalise("sampleStack", "track_0StartPosition", "1.00"),
+            "1"
+        );
+    }
+
+    #[test]
+    fn unaffected_values_are_borrowed() {
+        assert!(matches!(
+            normalise("mixerTree", "volume0", "128"),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn quirks_are_applied_on_load() {
+        use crate::components::mixer::FullChannelList;
+        use crate::profile::ProfileSettings;
+
+        let xml = include_str!("../test-data/profile.xml")
+            .replace("micLevel=\"230\"", "micLevel=\"230.000000 \"");
+        let settings = ProfileSettings::load(xml.as_bytes()).unwrap();
+        assert_eq!(settings.mixer().channel_volume(FullChannelList::Mic), 230);
+    }
+
+    #[test]
+    fn fractional_hardtune_values_load_truncated() {
+        use crate::profile::ProfileSettings;
+        use crate::Preset;
+
+        // The first preset in the file is Preset1..
+        let xml = include_str!("../test-data/profile.xml").replacen(
+            "HARDTUNE_AMOUNT=\"70\" HARDTUNE_WINDOW=\"20\"",
+            "HARDTUNE_AMOUNT=\"50.5\" HARDTUNE_WINDOW=\"20.9\"",
+            1,
+        );
+        let settings = ProfileSettings::load(xml.as_bytes()).unwrap();
+        let preset = settings.hardtune_effect().get_preset(Preset::Preset1);
+        assert_eq!(preset.amount(), 50);
+        assert_eq!(preset.window(), 20);
+
+        // Values which still aren't numbers fail to load..
+        let xml = xml.replace("HARDTUNE_AMOUNT=\"50.5\"", "HARDTUNE_AMOUNT=\"50.5.5\"");
+        assert!(ProfileSettings::load(xml.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn quirks_are_named() {
+        for quirk in quirks() {
+            assert!(!quirk.name().is_empty());
+            assert!(!quirk.description().is_empty());
+        }
+    }
+}
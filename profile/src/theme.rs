@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+use std::io::{BufReader, Read, Write};
+
+use anyhow::{bail, Result};
+use log::warn;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::{Reader, Writer};
+
+use crate::components::animation::AnimationTree;
+use crate::components::colours::ColourMap;
+use crate::components::registry::ANIMATION_TREE;
+use crate::profile::{parse_with_context, wrap_start_event, DocumentGuard, LoadOptions};
+
+const THEME: &str = "goxlrTheme";
+const THEME_VERSION: &str = "1";
+
+/**
+ * The look of a profile without any of its audio settings, the colours, off styles and fader
+ * displays of every lit element, along with the animation settings. Button states (whether
+ * something is currently on, or blinking) aren't part of a theme.
+ *
+ * Each colour map is stored under its prefix (FaderMeter0, mute1, effects1 etc) with the same
+ * attributes the profile uses, so a theme can be applied to any profile that has those elements.
+ */
+#[derive(Debug, Default)]
+pub struct Theme {
+    colour_maps: Vec<ColourMap>,
+    animation: Option<AnimationTree>,
+}
+
+impl Theme {
+    pub(crate) fn new(animation: Option<AnimationTree>) -> Self {
+        Self {
+            colour_maps: vec![],
+            animation,
+        }
+    }
+
+    /// Adds a copy of the look (but not the state) of a colour map..
+    pub(crate) fn add_colour_map(&mut self, source: &ColourMap) {
+        let mut colour_map = ColourMap::new(source.prefix().to_string());
+        colour_map.replace(source);
+        self.colour_maps.push(colour_map);
+    }
+
+    pub fn colour_map(&self, prefix: &str) -> Option<&ColourMap> {
+        self.colour_maps.iter().find(|map| map.prefix() == prefix)
+    }
+
+    pub fn colour_maps(&self) -> impl Iterator<Item = &ColourMap> {
+        self.colour_maps.iter()
+    }
+
+    pub fn animation(&self) -> Option<&AnimationTree> {
+        self.animation.as_ref()
+    }
+
+    pub fn write_to<W: Write>(&self, sink: W) -> Result<()> {
+        let mut writer = Writer::new_with_indent(sink, b' ', 2);
+
+        let mut root = BytesStart::new(THEME);
+        root.push_attribute(("version", THEME_VERSION));
+        writer.write_event(Event::Start(root.clone()))?;
+
+        if let Some(animation) = &self.animation {
+            animation.write_animation(&mut writer)?;
+        }
+
+        for colour_map in &self.colour_maps {
+            let mut attributes = HashMap::new();
+            colour_map.write_colours(&mut attributes);
+
+            // Sorted, so exporting the same theme twice produces the same file..
+            let mut attributes: Vec<_> = attributes.into_iter().collect();
+            attributes.sort();
+
+            let mut elem = BytesStart::new(colour_map.prefix());
+            for (key, value) in &attributes {
+                elem.push_attribute((key.as_str(), value.as_str()));
+            }
+            writer.write_event(Event::Empty(elem))?;
+        }
+
+        writer.write_event(Event::End(root.to_end()))?;
+        Ok(())
+    }
+
+    pub fn load<R: Read>(read: R) -> Result<Self> {
+        let mut reader = Reader::from_reader(BufReader::new(read));
+        let mut guard = DocumentGuard::new(&LoadOptions::default());
+        let mut buf = Vec::new();
+
+        let mut theme: Option<Theme> = None;
+        loop {
+            buf.clear();
+            let event = reader.read_event_into(&mut buf)?;
+            guard.check(&event)?;
+            let (name, attributes) = match &event {
+                Event::Start(e) | Event::Empty(e) => wrap_start_event(e)?,
+                Event::Eof => break,
+                _ => continue,
+            };
+
+            if name == THEME {
+                theme = Some(Theme::default());
+                continue;
+            }
+
+            let current = match &mut theme {
+                Some(theme) => theme,
+                None => bail!("Expected {}, found {}", THEME, name),
+            };
+
+            if name == ANIMATION_TREE {
+                let mut animation = AnimationTree::new(ANIMATION_TREE.to_string());
+                parse_with_context(&name, &attributes, |a| animation.parse_animation(a))?;
+                current.animation = Some(animation);
+                continue;
+            }
+
+            let mut colour_map = ColourMap::new(name.clone());
+            for attribute in &attributes {
+                if !colour_map.read_colours(attribute)? {
+                    warn!("[THEME] Unparsed Attribute: {}", attribute.name);
+                }
+            }
+            current.colour_maps.push(colour_map);
+        }
+
+        match theme {
+            Some(theme) => Ok(theme),
+            None => bail!("No {} found", THEME),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::animation::AnimationMode;
+    use crate::components::colours::{Colour, ColourOffStyle};
+    use crate::error::ParseError;
+    use crate::profile::ProfileSettings;
+    use crate::Faders;
+
+    const PROFILE: &str = include_str!("../test-data/profile.xml");
+
+    fn export(settings: &ProfileSettings) -> Vec<u8> {
+        let mut theme = vec![];
+        settings.export_theme(&mut theme).unwrap();
+        theme
+    }
+
+    fn colour_lines(theme: &[u8]) -> Vec<String> {
+        let theme = String::from_utf8(theme.to_vec()).unwrap();
+        theme
+            .lines()
+            .filter(|line| !line.contains(ANIMATION_TREE))
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn themes_round_trip() {
+        let mut source = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        let red = Colour::fromrgb("FF0000").unwrap();
+        let colours = source.fader_mut(Faders::A).colour_map_mut();
+        colours.set_colour(0, red).unwrap();
+        colours.set_off_style(ColourOffStyle::Colour2).unwrap();
+        source
+            .animation_mut()
+            .set_mode(AnimationMode::Ripple)
+            .unwrap();
+        source.animation_mut().set_mod1(42).unwrap();
+
+        let mut target = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        target.import_theme(export(&source).as_slice()).unwrap();
+
+        let colours = target.fader(Faders::A).colour_map();
+        assert_eq!(colours.colour(0), &red);
+        assert_eq!(colours.get_off_style(), &ColourOffStyle::Colour2);
+        assert_eq!(target.animation().mode(), AnimationMode::Ripple);
+        assert_eq!(target.animation().mod1(), 42);
+
+        // Exporting the imported theme gives the same colour maps (the animation writer doesn't
+        // have a fixed attribute order)..
+        assert_eq!(
+            colour_lines(&export(&target)),
+            colour_lines(&export(&source))
+        );
+    }
+
+    #[test]
+    fn themes_leave_button_states_alone() {
+        let mut source = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        let colours = source.mute_button_mut(Faders::A).colour_map_mut();
+        colours.set_state_on(true).unwrap();
+        colours.set_blink_on(true).unwrap();
+
+        let theme = export(&source);
+        let xml = String::from_utf8(theme.clone()).unwrap();
+        assert!(!xml.contains("state="));
+        assert!(!xml.contains("blink="));
+
+        let mut target = ProfileSettings::load(PROFILE.as_bytes()).unwrap();
+        let colours = target.mute_button_mut(Faders::A).colour_map_mut();
+        colours.set_state_on(false).unwrap();
+        colours.set_blink_on(false).unwrap();
+        target.import_theme(theme.as_slice()).unwrap();
+
+        let colours = target.mute_button(Faders::A).colour_map();
+        assert!(!colours.get_state());
+        assert!(!colours.is_blink());
+    }
+
+    #[test]
+    fn unsafe_themes_are_rejected() {
+        let theme = r#"<?xml version="1.0"?>
+<!DOCTYPE lolz [<!ENTITY lol "lol">]>
+<goxlrTheme version="1"/>"#;
+        let error = Theme::load(theme.as_bytes()).unwrap_err();
+        assert!(matches!(
+            error.downcast::<ParseError>(),
+            Ok(ParseError::UnsafeDocument(_))
+        ));
+    }
+}
@@ -1,10 +1,14 @@
 use anyhow::{bail, Result};
-use image::imageops::{dither, overlay, BiLevel, FilterType};
-use image::{ColorType, DynamicImage, GenericImage, GenericImageView, GrayImage, Luma, Rgba};
+use image::imageops::{dither, invert, overlay, BiLevel, FilterType};
+use image::{
+    ColorType, DynamicImage, GenericImage, GenericImageView, GrayImage, ImageOutputFormat, Luma,
+    Rgba,
+};
 use imageproc::drawing::{draw_text_mut, text_size};
 use log::warn;
 use rusttype::{Font, Scale};
 use std::borrow::BorrowMut;
+use std::io::Cursor;
 use std::path::PathBuf;
 
 static FONT: &[u8] = include_bytes!("../fonts/Play-Bold.ttf");
@@ -46,6 +50,79 @@ pub fn get_scribble(
     }
 }
 
+/// The area the icon will be drawn into on the scribble screen
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum IconArea {
+    // The icon is the only thing on screen..
+    Full,
+
+    // The icon shares the screen with bottom text..
+    WithText,
+}
+
+impl IconArea {
+    fn size(&self) -> (u32, u32) {
+        match self {
+            IconArea::Full => (128, 64),
+            IconArea::WithText => (80, 41),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct IconOptions {
+    pub area: IconArea,
+
+    // Floyd-Steinberg dithering if true, otherwise a straight threshold..
+    pub dither: bool,
+    pub threshold: u8,
+    pub invert: bool,
+}
+
+impl Default for IconOptions {
+    fn default() -> Self {
+        Self {
+            area: IconArea::Full,
+            dither: true,
+            threshold: 128,
+            invert: false,
+        }
+    }
+}
+
+/// Takes an arbitrary image, and produces a black and white PNG sized for the scribble screen,
+/// which can be stored in a profile and used as a scribble icon.
+pub fn prepare_icon(bytes: &[u8], options: &IconOptions) -> Result<Vec<u8>> {
+    let icon = flatten_grayscale(image::load_from_memory(bytes)?.grayscale());
+
+    // Scale the icon to fit, and center it on a white background..
+    let (width, height) = options.area.size();
+    let icon = icon.resize(width, height, FilterType::Gaussian);
+
+    let mut image = DynamicImage::ImageLuma8(GrayImage::from_pixel(width, height, Luma([255])));
+
+    let x = (width - icon.width()) / 2;
+    let y = (height - icon.height()) / 2;
+    overlay(&mut image, &icon, x as i64, y as i64);
+
+    let mut image = image.to_luma8();
+    if options.dither {
+        dither(&mut image, &BiLevel);
+    } else {
+        for pixel in image.pixels_mut() {
+            pixel[0] = if pixel[0] < options.threshold { 0 } else { 255 };
+        }
+    }
+
+    if options.invert {
+        invert(&mut image);
+    }
+
+    let mut output = Cursor::new(Vec::new());
+    DynamicImage::ImageLuma8(image).write_to(&mut output, ImageOutputFormat::Png)?;
+    Ok(output.into_inner())
+}
+
 fn load_grayscale_image(path: PathBuf) -> Result<DynamicImage> {
     if !path.exists() {
         warn!("Unable to Load {}, file not found", path.to_string_lossy());
@@ -53,8 +130,10 @@ fn load_grayscale_image(path: PathBuf) -> Result<DynamicImage> {
     }
 
     let img = image::open(path)?;
-    let mut img = img.grayscale();
+    Ok(flatten_grayscale(img.grayscale()))
+}
 
+fn flatten_grayscale(mut img: DynamicImage) -> DynamicImage {
     if img.color() == ColorType::La8 || img.color() == ColorType::L16 {
         // Ensure any fully transparent pixels are white..
         for (x, y, pixel) in img.clone().pixels() {
@@ -64,7 +143,7 @@ fn load_grayscale_image(path: PathBuf) -> Result<DynamicImage> {
         }
     }
 
-    Ok(img)
+    img
 }
 
 fn create_text_image(text: &str) -> Result<DynamicImage> {
@@ -177,3 +256,116 @@ fn to_goxlr(img: GrayImage, invert: bool) -> Result<[u8; 1024]> {
     }
     Ok(bytes)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: Luma<u8> = Luma([255]);
+    const BLACK: Luma<u8> = Luma([0]);
+
+    // A 10x10 square of a single shade, which is scaled up to fill the height of the area..
+    fn square(shade: u8) -> Vec<u8> {
+        let image = GrayImage::from_pixel(10, 10, Luma([shade]));
+        let mut output = Cursor::new(Vec::new());
+        DynamicImage::ImageLuma8(image)
+            .write_to(&mut output, ImageOutputFormat::Png)
+            .unwrap();
+        output.into_inner()
+    }
+
+    // Left to right, black to white, with a fully transparent band across the middle..
+    fn gradient() -> Vec<u8> {
+        let image = image::RgbaImage::from_fn(64, 32, |x, y| {
+            let shade = (x * 4) as u8;
+            let alpha = if (12..20).contains(&y) { 0 } else { 255 };
+            Rgba([shade, shade, shade, alpha])
+        });
+        let mut output = Cursor::new(Vec::new());
+        DynamicImage::ImageRgba8(image)
+            .write_to(&mut output, ImageOutputFormat::Png)
+            .unwrap();
+        output.into_inner()
+    }
+
+    fn prepare(bytes: &[u8], options: IconOptions) -> GrayImage {
+        let icon = prepare_icon(bytes, &options).unwrap();
+        image::load_from_memory(&icon).unwrap().to_luma8()
+    }
+
+    fn threshold(value: u8) -> IconOptions {
+        IconOptions {
+            dither: false,
+            threshold: value,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn icons_are_sized_for_their_area() {
+        for (area, size) in [(IconArea::Full, (128, 64)), (IconArea::WithText, (80, 41))] {
+            let options = IconOptions {
+                area,
+                ..Default::default()
+            };
+            assert_eq!(prepare(&square(0), options).dimensions(), size);
+        }
+    }
+
+    #[test]
+    fn icons_are_centered_on_a_white_background() {
+        let image = prepare(&square(0), threshold(128));
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let expected = if (32..96).contains(&x) { BLACK } else { WHITE };
+            assert_eq!(*pixel, expected, "({x}, {y})");
+        }
+    }
+
+    #[test]
+    fn thresholds_split_shades_into_black_and_white() {
+        let image = prepare(&square(100), threshold(101));
+        assert_eq!(*image.get_pixel(64, 32), BLACK);
+
+        let image = prepare(&square(100), threshold(100));
+        assert!(image.pixels().all(|pixel| *pixel == WHITE));
+    }
+
+    #[test]
+    fn dithering_mixes_black_and_white() {
+        let image = prepare(&square(128), IconOptions::default());
+        assert!(image
+            .pixels()
+            .all(|pixel| *pixel == WHITE || *pixel == BLACK));
+
+        // Roughly half of the square should end up black..
+        let black = (32..96)
+            .flat_map(|x| (0..64).map(move |y| (x, y)))
+            .filter(|(x, y)| *image.get_pixel(*x, *y) == BLACK)
+            .count();
+        assert!((1700..2400).contains(&black), "{black} black pixels");
+    }
+
+    #[test]
+    fn inverting_swaps_black_and_white() {
+        for options in [threshold(128), IconOptions::default()] {
+            let plain = prepare(&gradient(), options);
+            let inverted = prepare(
+                &gradient(),
+                IconOptions {
+                    invert: true,
+                    ..options
+                },
+            );
+            for (plain, inverted) in plain.pixels().zip(inverted.pixels()) {
+                assert_eq!(plain[0], 255 - inverted[0]);
+            }
+        }
+    }
+
+    #[test]
+    fn icons_match_the_golden_image() {
+        let golden = include_bytes!("../test-data/icon.png");
+        let golden = image::load_from_memory(golden).unwrap().to_luma8();
+        assert_eq!(prepare(&gradient(), IconOptions::default()), golden);
+    }
+}
@@ -0,0 +1,110 @@
+use std::time::Duration;
+
+/// Calculates the (offset, volume) steps for a volume ramp. Volumes only ever move towards the
+/// target and the final step is always exactly the target, steps which wouldn't change the volume
+/// are skipped.
+pub fn ramp_steps(
+    from: u8,
+    to: u8,
+    duration: Duration,
+    step_interval: Duration,
+) -> Vec<(Duration, u8)> {
+    if from == to || duration.is_zero() || step_interval.is_zero() {
+        return vec![(Duration::ZERO, to)];
+    }
+
+    // There's never any point in more steps than there are volumes between the two, which also
+    // keeps the maths below well away from overflowing, however long the ramp is..
+    let distance = to as i32 - from as i32;
+    let steps = (duration.as_millis() / step_interval.as_millis().max(1))
+        .clamp(1, distance.unsigned_abs() as u128) as u32;
+
+    let mut result = vec![];
+    let mut last = from;
+    for step in 1..=steps {
+        let volume = (from as i32 + (distance * step as i32) / steps as i32) as u8;
+        if volume == last && step != steps {
+            continue;
+        }
+        let offset = match duration.checked_mul(step) {
+            Some(total) => total / steps,
+            None if step == steps => duration,
+            None => duration / steps * step,
+        };
+        result.push((offset, volume));
+        last = volume;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL: Duration = Duration::from_millis(20);
+
+    #[test]
+    fn equal_endpoints_are_a_single_step() {
+        let steps = ramp_steps(128, 128, Duration::from_secs(1), INTERVAL);
+        assert_eq!(steps, vec![(Duration::ZERO, 128)]);
+    }
+
+    #[test]
+    fn zero_durations_jump_straight_to_the_target() {
+        assert_eq!(
+            ramp_steps(0, 255, Duration::ZERO, INTERVAL),
+            vec![(Duration::ZERO, 255)]
+        );
+        assert_eq!(
+            ramp_steps(0, 255, INTERVAL, Duration::ZERO),
+            vec![(Duration::ZERO, 255)]
+        );
+    }
+
+    #[test]
+    fn ramps_shorter_than_a_step_take_one_step() {
+        let duration = Duration::from_millis(5);
+        assert_eq!(ramp_steps(255, 0, duration, INTERVAL), vec![(duration, 0)]);
+    }
+
+    #[test]
+    fn small_changes_skip_repeated_volumes() {
+        let steps = ramp_steps(10, 12, Duration::from_secs(1), INTERVAL);
+        assert_eq!(
+            steps.iter().map(|(_, volume)| *volume).collect::<Vec<_>>(),
+            [11, 12]
+        );
+        assert_eq!(steps.last().unwrap().0, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn long_ramps_take_at_most_one_step_per_volume() {
+        let steps = ramp_steps(0, 255, Duration::from_secs(60), INTERVAL);
+        assert_eq!(steps.len(), 255);
+        assert_eq!(steps[0], (Duration::from_secs(60) / 255, 1));
+        assert_eq!(steps.last(), Some(&(Duration::from_secs(60), 255)));
+    }
+
+    #[test]
+    fn huge_durations_dont_overflow() {
+        for interval in [INTERVAL, Duration::from_nanos(1)] {
+            let steps = ramp_steps(255, 0, Duration::MAX, interval);
+            assert_eq!(steps.len(), 255);
+            assert_eq!(steps.last(), Some(&(Duration::MAX, 0)));
+            assert!(steps.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+    }
+
+    #[test]
+    fn ramps_never_overshoot() {
+        for (from, to) in [(0, 255), (255, 0), (200, 17), (3, 250)] {
+            let steps = ramp_steps(from, to, Duration::from_millis(330), INTERVAL);
+            let mut last = from;
+            for (_, volume) in &steps {
+                assert!(volume.abs_diff(to) <= last.abs_diff(to), "{from} -> {to}");
+                last = *volume;
+            }
+            assert_eq!(last, to);
+        }
+    }
+}
@@ -0,0 +1,68 @@
+//! Reads the firmware version using the raw control transfers rather than GoXLRCommands, and
+//! decodes the response with the frame helpers. This runs against the mock device (which answers
+//! with a canned version), so it's run as part of the tests:
+//!
+//!     cargo test -p goxlr-usb --features unsafe-raw --example raw_firmware_version
+//!
+//! To read a real GoXLR instead, attach with device::from_device and use its raw() access, but
+//! don't do that while the daemon is attached to the device.
+
+use anyhow::{bail, Result};
+use byteorder::{ByteOrder, LittleEndian};
+
+use goxlr_usb::commands::{Command, HardwareInfoCommand};
+use goxlr_usb::device::base::RawGoXLR;
+use goxlr_usb::device::mock::MockDevice;
+use goxlr_usb::frame;
+
+fn main() -> Result<()> {
+    let command = Command::GetHardwareInfo(HardwareInfoCommand::FirmwareVersion);
+
+    // Firmware 1.4.2, build 107..
+    let mut response = [0; 8];
+    LittleEndian::write_u32(&mut response[0..4], (1 << 12) | (4 << 8) | 2);
+    LittleEndian::write_u32(&mut response[4..8], 107);
+
+    let mut mock = MockDevice::new();
+    mock.respond_with(command, &response);
+    let version = read_firmware_version(&mut mock)?;
+
+    println!("Firmware: {version}");
+    assert_eq!(version, "1.4.2.107");
+    Ok(())
+}
+
+fn read_firmware_version(raw: &mut dyn RawGoXLR) -> Result<String> {
+    // A freshly attached device has just had its command index reset, so 1 is the next index..
+    let command = Command::GetHardwareInfo(HardwareInfoCommand::FirmwareVersion);
+    let request = frame::build_request(command, &[], 1);
+    raw.write_control(frame::REQUEST_SEND_COMMAND, 0, 0, &request)?;
+
+    let response = raw.read_control(
+        frame::REQUEST_READ_RESPONSE,
+        0,
+        0,
+        frame::MAX_RESPONSE_LENGTH,
+    )?;
+
+    let header = frame::parse_header(&response)?;
+    let body = &response[frame::HEADER_LENGTH..];
+    println!(
+        "Command {:#x} (index {}) returned {} bytes",
+        header.command_id, header.command_index, header.body_length
+    );
+
+    // The version is packed into the first u32 of the body, followed by the build..
+    if header.command_id != command.command_id() || body.len() < 8 {
+        bail!("Unexpected response to {:?}", command);
+    }
+    let version = LittleEndian::read_u32(&body[0..4]);
+    let build = LittleEndian::read_u32(&body[4..8]);
+    Ok(format!(
+        "{}.{}.{}.{}",
+        version >> 12,
+        (version >> 8) & 0xF,
+        version & 0xFF,
+        build
+    ))
+}
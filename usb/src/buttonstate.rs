@@ -1,5 +1,6 @@
 use enum_map::Enum;
 use enumset::{EnumSet, EnumSetType};
+use goxlr_types::{EncoderName, FaderName};
 use strum::EnumIter;
 
 #[derive(Debug, Copy, Clone)]
@@ -18,6 +19,17 @@ pub struct CurrentButtonStates {
     pub encoders: [i8; 4],
 }
 
+/**
+ * Everything the GoXLR is able to report back about its current state. Fader assignments,
+ * routing and lighting are write-only on the device, so these have to come from a profile.
+ */
+#[derive(Debug, Copy, Clone)]
+pub struct HardwareState {
+    pub fader_volumes: [(FaderName, u8); 4],
+    pub encoders: [(EncoderName, i8); 4],
+    pub pressed: EnumSet<Buttons>,
+}
+
 #[derive(EnumSetType, Enum, EnumIter, Debug)]
 pub enum Buttons {
     // These are all the buttons from the GoXLR Mini.
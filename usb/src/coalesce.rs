@@ -0,0 +1,215 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use enum_map::{EnumArray, EnumMap};
+use goxlr_types::ChannelName;
+
+use crate::device::base::GoXLRCommands;
+
+/**
+ * Keeps track of the latest pending value for each key, and only hands them back out once a
+ * flush interval has passed (or a flush is forced). Any updates that arrive between flushes simply
+ * replace the previous pending value, so a burst of changes to the same key results in a single
+ * command being sent, containing the final value.
+ *
+ * This isn't tied to volumes, anything keyed by an enum (such as colour targets) can be coalesced
+ * in the same way.
+ */
+pub struct Coalescer<K: EnumArray<Option<V>>, V> {
+    pending: EnumMap<K, Option<V>>,
+    interval: Duration,
+    last_flush: Instant,
+}
+
+impl<K: EnumArray<Option<V>> + Copy, V> Coalescer<K, V> {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            pending: EnumMap::default(),
+            interval,
+            last_flush: Instant::now(),
+        }
+    }
+
+    pub fn update(&mut self, key: K, value: V) {
+        self.pending[key] = Some(value);
+    }
+
+    pub fn has_pending(&self) -> bool {
+        self.pending.values().any(|value| value.is_some())
+    }
+
+    pub fn is_due(&self) -> bool {
+        self.has_pending() && self.last_flush.elapsed() >= self.interval
+    }
+
+    /// Returns the pending values if the interval has passed, otherwise nothing..
+    pub fn take_due(&mut self) -> Vec<(K, V)> {
+        if !self.is_due() {
+            return vec![];
+        }
+        self.take()
+    }
+
+    /// Returns all pending values, regardless of the interval.
+    pub fn take(&mut self) -> Vec<(K, V)> {
+        self.last_flush = Instant::now();
+
+        let mut values = vec![];
+        for (key, value) in self.pending.iter_mut() {
+            if let Some(value) = value.take() {
+                values.push((key, value));
+            }
+        }
+        values
+    }
+
+    /// Puts a value back if it failed to send, unless a newer value has arrived since.
+    pub fn restore(&mut self, key: K, value: V) {
+        if self.pending[key].is_none() {
+            self.pending[key] = Some(value);
+        }
+    }
+}
+
+/**
+ * Sits in front of a device's set_volume, dragging a fader in a UI can produce dozens of volume
+ * changes a second, and each one is a full USB round trip (which the Mini especially struggles
+ * with). Only the latest volume for each channel is kept, and at most one command per channel is
+ * sent on each flush.
+ */
+pub struct VolumeUpdater {
+    volumes: Coalescer<ChannelName, u8>,
+}
+
+impl VolumeUpdater {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            volumes: Coalescer::new(interval),
+        }
+    }
+
+    pub fn set_volume(&mut self, channel: ChannelName, volume: u8) {
+        self.volumes.update(channel, volume);
+    }
+
+    pub fn has_pending(&self) -> bool {
+        self.volumes.has_pending()
+    }
+
+    /// Sends any pending volumes if the flush interval has passed, should be called regularly.
+    pub fn poll<D: GoXLRCommands + ?Sized>(&mut self, device: &mut D) -> Result<()> {
+        let volumes = self.volumes.take_due();
+        self.send(device, volumes)
+    }
+
+    /// Immediately sends all pending volumes.
+    pub fn flush<D: GoXLRCommands + ?Sized>(&mut self, device: &mut D) -> Result<()> {
+        let volumes = self.volumes.take();
+        self.send(device, volumes)
+    }
+
+    fn send<D: GoXLRCommands + ?Sized>(
+        &mut self,
+        device: &mut D,
+        volumes: Vec<(ChannelName, u8)>,
+    ) -> Result<()> {
+        for (index, (channel, volume)) in volumes.iter().enumerate() {
+            if let Err(error) = device.set_volume(*channel, *volume) {
+                // Keep anything we didn't manage to send, so the next flush can try again..
+                for (channel, volume) in &volumes[index..] {
+                    self.volumes.restore(*channel, *volume);
+                }
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::Command;
+    use crate::device::mock::MockDevice;
+
+    const NEVER: Duration = Duration::from_secs(3600);
+
+    #[test]
+    fn rapid_updates_send_the_final_value() {
+        let mut device = MockDevice::new();
+        let mut updater = VolumeUpdater::new(NEVER);
+
+        for volume in 0..100 {
+            updater.set_volume(ChannelName::Mic, volume);
+            updater.poll(&mut device).unwrap();
+        }
+        assert!(device.requests().is_empty());
+
+        updater.flush(&mut device).unwrap();
+        assert_eq!(
+            device.requests(),
+            [(Command::SetChannelVolume(ChannelName::Mic), vec![99])]
+        );
+        assert!(!updater.has_pending());
+    }
+
+    #[test]
+    fn each_flush_sends_at_most_one_command_per_channel() {
+        let mut device = MockDevice::new();
+        let mut updater = VolumeUpdater::new(Duration::ZERO);
+
+        for volume in 0..100 {
+            updater.set_volume(ChannelName::Mic, volume);
+            updater.set_volume(ChannelName::Chat, 255 - volume);
+            if volume % 10 == 9 {
+                updater.poll(&mut device).unwrap();
+            }
+        }
+
+        let requests = device.requests();
+        assert_eq!(requests.len(), 20);
+        assert!(requests.ends_with(&[
+            (Command::SetChannelVolume(ChannelName::Mic), vec![99]),
+            (Command::SetChannelVolume(ChannelName::Chat), vec![156]),
+        ]));
+    }
+
+    #[test]
+    fn failed_volumes_are_sent_on_the_next_flush() {
+        let mut device = MockDevice::new();
+        let mut updater = VolumeUpdater::new(NEVER);
+        updater.set_volume(ChannelName::Mic, 10);
+        updater.set_volume(ChannelName::Chat, 20);
+
+        device.fail_next(1);
+        assert!(updater.flush(&mut device).is_err());
+        assert!(updater.has_pending());
+
+        // A newer value replaces the one that failed, the other is retried as it was..
+        updater.set_volume(ChannelName::Mic, 30);
+        updater.flush(&mut device).unwrap();
+        assert_eq!(
+            device.requests(),
+            [
+                (Command::SetChannelVolume(ChannelName::Mic), vec![30]),
+                (Command::SetChannelVolume(ChannelName::Chat), vec![20]),
+            ]
+        );
+    }
+
+    #[test]
+    fn restore_keeps_newer_values() {
+        let mut coalescer: Coalescer<ChannelName, u8> = Coalescer::new(NEVER);
+        coalescer.update(ChannelName::Mic, 10);
+        let taken = coalescer.take();
+        assert_eq!(taken, vec![(ChannelName::Mic, 10)]);
+
+        coalescer.update(ChannelName::Mic, 30);
+        coalescer.restore(ChannelName::Mic, 10);
+        coalescer.restore(ChannelName::Chat, 20);
+        assert_eq!(
+            coalescer.take(),
+            vec![(ChannelName::Mic, 30), (ChannelName::Chat, 20)]
+        );
+    }
+}
@@ -1,7 +1,9 @@
+use crate::frame;
 use crate::routing::InputDevice;
 use goxlr_types::{ChannelName, EncoderName, FaderName, SubMixChannelName};
+use strum::{Display, EnumIter, IntoEnumIterator};
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, Display, PartialEq, Eq)]
 pub enum Command {
     ResetCommandIndex,
     SystemInfo(SystemInfoCommand),
@@ -66,9 +68,68 @@ impl Command {
             Command::ExecuteFirmwareUpdateAction(sub) => 0x004 << 12 | sub.id(),
         }
     }
+
+    /// Whether the command only reads from the device, without changing any of its state.
+    pub fn is_read_only(&self) -> bool {
+        matches!(
+            self,
+            Command::SystemInfo(_)
+                | Command::GetButtonStates
+                | Command::GetHardwareInfo(_)
+                | Command::GetMicrophoneLevel
+        )
+    }
+
+    /// The longest body the device will accept for this command.
+    pub fn max_body_len(&self) -> usize {
+        match self {
+            // These are fixed size, the colour map is 520 bytes on firmware 1.3.40 and later..
+            Command::SetScribble(_) => 1024,
+            Command::SetColourMap() => 520,
+            _ => frame::MAX_BODY_LENGTH,
+        }
+    }
+
+    /// The name of the command, without any parameter (for example, 'SetChannelVolume')
+    pub fn name(&self) -> String {
+        self.to_string()
+    }
+
+    /// Looks up the command for an ID, mostly useful for labelling captured USB traffic.
+    pub fn from_command_id(id: u32) -> Option<Command> {
+        Command::iter().find(|command| command.command_id() == id)
+    }
+
+    /// Every possible command, including one for each value of any parameter.
+    pub fn iter() -> impl Iterator<Item = Command> {
+        let mut commands = vec![Command::ResetCommandIndex];
+        commands.extend(SystemInfoCommand::iter().map(Command::SystemInfo));
+        commands.extend(ChannelName::iter().map(Command::SetChannelState));
+        commands.extend(ChannelName::iter().map(Command::SetChannelVolume));
+        commands.extend(EncoderName::iter().map(Command::SetEncoderValue));
+        commands.extend(EncoderName::iter().map(Command::SetEncoderMode));
+        commands.extend(FaderName::iter().map(Command::SetFader));
+        commands.extend(InputDevice::iter().map(Command::SetRouting));
+        commands.push(Command::SetButtonStates());
+        commands.push(Command::SetEffectParameters);
+        commands.push(Command::SetMicrophoneParameters);
+        commands.push(Command::GetMicrophoneLevel);
+        commands.push(Command::SetColourMap());
+        commands.extend(FaderName::iter().map(Command::SetFaderDisplayMode));
+        commands.extend(FaderName::iter().map(Command::SetScribble));
+        commands.push(Command::GetButtonStates);
+        commands.extend(HardwareInfoCommand::iter().map(Command::GetHardwareInfo));
+        commands.push(Command::SetAnimationMode);
+        commands.extend(SubMixChannelName::iter().map(Command::SetSubChannelVolume));
+        commands.push(Command::SetChannelMixes);
+        commands.push(Command::SetMonitoredMix);
+        commands.extend(FirmwareCommand::iter().map(Command::ExecuteFirmwareUpdateCommand));
+        commands.extend(FirmwareAction::iter().map(Command::ExecuteFirmwareUpdateAction));
+        commands.into_iter()
+    }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, EnumIter, PartialEq, Eq)]
 pub enum SystemInfoCommand {
     FirmwareVersion,
     SupportsDCPCategory,
@@ -83,13 +144,13 @@ impl SystemInfoCommand {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, EnumIter, PartialEq, Eq)]
 pub enum HardwareInfoCommand {
     FirmwareVersion = 0,
     SerialNumber = 1,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, EnumIter, PartialEq, Eq)]
 pub enum FirmwareCommand {
     // Start the update (Makes GoXLR go green, we should lock the util here.)
     START,
@@ -111,7 +172,7 @@ pub enum FirmwareCommand {
 }
 
 // DCP Commands for managing a firmware update (0x004)
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, EnumIter, PartialEq, Eq)]
 pub enum FirmwareAction {
     // Formats and erases the update partition
     ERASE,
@@ -136,3 +197,91 @@ impl FirmwareAction {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    // This doesn't compile if a command is added without being listed here, as a reminder that
+    // it also needs adding to Command::iter()..
+    fn variant(command: Command) -> &'static str {
+        match command {
+            Command::ResetCommandIndex => "ResetCommandIndex",
+            Command::SystemInfo(_) => "SystemInfo",
+            Command::SetChannelState(_) => "SetChannelState",
+            Command::SetChannelVolume(_) => "SetChannelVolume",
+            Command::SetEncoderValue(_) => "SetEncoderValue",
+            Command::SetEncoderMode(_) => "SetEncoderMode",
+            Command::SetFader(_) => "SetFader",
+            Command::SetRouting(_) => "SetRouting",
+            Command::SetButtonStates() => "SetButtonStates",
+            Command::SetEffectParameters => "SetEffectParameters",
+            Command::SetMicrophoneParameters => "SetMicrophoneParameters",
+            Command::GetMicrophoneLevel => "GetMicrophoneLevel",
+            Command::SetColourMap() => "SetColourMap",
+            Command::SetFaderDisplayMode(_) => "SetFaderDisplayMode",
+            Command::SetScribble(_) => "SetScribble",
+            Command::GetButtonStates => "GetButtonStates",
+            Command::GetHardwareInfo(_) => "GetHardwareInfo",
+            Command::SetAnimationMode => "SetAnimationMode",
+            Command::SetSubChannelVolume(_) => "SetSubChannelVolume",
+            Command::SetChannelMixes => "SetChannelMixes",
+            Command::SetMonitoredMix => "SetMonitoredMix",
+            Command::ExecuteFirmwareUpdateCommand(_) => "ExecuteFirmwareUpdateCommand",
+            Command::ExecuteFirmwareUpdateAction(_) => "ExecuteFirmwareUpdateAction",
+        }
+    }
+
+    #[test]
+    fn iter_covers_every_variant() {
+        let listed: HashSet<_> = Command::iter().map(variant).collect();
+        assert_eq!(listed.len(), 23);
+    }
+
+    #[test]
+    fn command_ids_are_unique() {
+        let mut seen = HashSet::new();
+        for command in Command::iter() {
+            assert!(
+                seen.insert(command.command_id()),
+                "{:?} shares the id {:#x}",
+                command,
+                command.command_id()
+            );
+        }
+    }
+
+    #[test]
+    fn command_ids_round_trip() {
+        for command in Command::iter() {
+            assert_eq!(
+                Command::from_command_id(command.command_id()),
+                Some(command)
+            );
+        }
+    }
+
+    #[test]
+    fn names_match_the_variant() {
+        for command in Command::iter() {
+            assert_eq!(command.name(), variant(command));
+        }
+    }
+
+    #[test]
+    fn only_queries_are_read_only() {
+        let read_only: Vec<_> = Command::iter()
+            .filter(Command::is_read_only)
+            .map(variant)
+            .collect();
+
+        // Resetting the command index changes the device's state, so it must still invalidate
+        // anything cached..
+        assert!(!read_only.contains(&"ResetCommandIndex"));
+        assert!(read_only
+            .iter()
+            .all(|name| name.starts_with("Get") || *name == "SystemInfo"));
+    }
+}
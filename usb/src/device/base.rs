@@ -1,5 +1,5 @@
 use crate::animation::{AnimationMode, WaterFallDir};
-use crate::buttonstate::{ButtonStates, Buttons, CurrentButtonStates};
+use crate::buttonstate::{ButtonStates, Buttons, CurrentButtonStates, HardwareState};
 use crate::channelstate::ChannelState;
 use crate::commands::Command::ExecuteFirmwareUpdateAction;
 use crate::commands::SystemInfoCommand::SupportsDCPCategory;
@@ -7,20 +7,35 @@ use crate::commands::{
     Command, FirmwareAction, FirmwareCommand, HardwareInfoCommand, SystemInfoCommand,
 };
 use crate::dcp::DCPCategory;
+use crate::device::find_devices_with_options;
+use crate::frame::FIRMWARE_PACKET_HEADER_LENGTH;
+use crate::levels::Levels;
+use crate::requests;
 use crate::routing::InputDevice;
-use anyhow::{bail, Result};
+use crate::{PID_GOXLR_FULL, PID_GOXLR_MINI, PID_GOXLR_UPDATE_MODE, VID_GOXLR};
+use anyhow::{bail, Context, Result};
 use byteorder::{ByteOrder, LittleEndian, ReadBytesExt, WriteBytesExt};
 use enumset::EnumSet;
 use goxlr_types::{
     ChannelName, EffectKey, EncoderName, FaderName, FirmwareVersions, MicrophoneParamKey,
-    MicrophoneType, Mix, SubMixChannelName, VersionNumber,
+    MicrophoneType, Mix, OutputDevice, SubMixChannelName, VersionNumber,
 };
 use log::debug;
+use std::fmt::{Display, Formatter};
 use std::io::{Cursor, Write};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 use tokio::sync::mpsc::Sender;
 
 // This is a basic SuperTrait which defines all the 'Parts' of the GoXLR for use.
-pub trait FullGoXLRDevice: AttachGoXLR + GoXLRCommands + Sync + Send {}
+pub trait FullGoXLRDevice: AttachGoXLR + GoXLRCommands + Sync + Send {
+    /// Direct access to the control transfers (see RawGoXLR), None if the backend can't offer it.
+    #[cfg(feature = "unsafe-raw")]
+    fn raw(&mut self) -> Option<&mut dyn RawGoXLR> {
+        None
+    }
+}
 
 pub trait AttachGoXLR {
     fn from_device(
@@ -33,17 +48,68 @@ pub trait AttachGoXLR {
         Self: Sized;
 
     fn set_unique_identifier(&mut self, identifier: String);
+    fn device_identifier(&self) -> DeviceIdentifier;
     fn is_connected(&mut self) -> bool;
     fn stop_polling(&mut self);
+
+    /// Redoes the configuration check and re-establishes the command pipe, for use when the
+    /// device has been through a suspend / resume and stopped responding. This is never called
+    /// automatically, backends without anything to redo leave it as a no-op.
+    fn reinitialise(&mut self) -> Result<()> {
+        Ok(())
+    }
 }
 
 pub trait ExecutableGoXLR {
     fn request_data(&mut self, command: Command, body: &[u8]) -> Result<Vec<u8>> {
-        self.perform_request(command, body, false)
+        // The underlying error is usually just 'Pipe', so note which command (and which device,
+        // when there's more than one attached) it came from..
+        self.perform_request(command, body, false).with_context(|| {
+            format!(
+                "{}: command {:?} ({:#x}) failed",
+                self.describe(),
+                command,
+                command.command_id()
+            )
+        })
     }
 
     fn perform_request(&mut self, command: Command, body: &[u8], retry: bool) -> Result<Vec<u8>>;
     fn get_descriptor(&self) -> Result<UsbData>;
+
+    /// A short description of the device for logs and errors, such as 'GoXLR serial/S210...'.
+    fn describe(&self) -> String;
+
+    /// The number of times a request has had to be retried since connecting (or the counters
+    /// were last reset), a steady climb here usually points to a bad cable or port.
+    fn retry_count(&self) -> u64 {
+        0
+    }
+
+    /// The number of times the command index has had to be resynced with the device.
+    fn resync_count(&self) -> u64 {
+        0
+    }
+
+    fn reset_request_counters(&mut self) {}
+}
+
+/**
+ * Direct access to the vendor control transfers used to talk to the GoXLR, for diagnostic tools
+ * which need to send or inspect frames themselves. Nothing here tracks the command index or
+ * pauses polling, so mixing these with regular commands can desync the device. See the frame
+ * module for the request numbers and header helpers.
+ */
+#[cfg(feature = "unsafe-raw")]
+pub trait RawGoXLR {
+    fn write_control(&mut self, request: u8, value: u16, index: u16, data: &[u8]) -> Result<()>;
+    fn read_control(
+        &mut self,
+        request: u8,
+        value: u16,
+        index: u16,
+        length: usize,
+    ) -> Result<Vec<u8>>;
 }
 
 // These are commands that can be executed, but perform_request must be implemented..
@@ -121,16 +187,24 @@ pub trait GoXLRCommands: ExecutableGoXLR {
     }
 
     fn set_fader(&mut self, fader: FaderName, channel: ChannelName) -> Result<()> {
-        // Channel ID, unknown, unknown, unknown
-        self.request_data(Command::SetFader(fader), &[channel as u8, 0x00, 0x00, 0x00])?;
+        let (command, body) = requests::set_fader(fader, channel);
+        self.request_data(command, &body)?;
         Ok(())
     }
 
     fn set_volume(&mut self, channel: ChannelName, volume: u8) -> Result<()> {
-        self.request_data(Command::SetChannelVolume(channel), &[volume])?;
+        let (command, body) = requests::set_volume(channel, volume);
+        self.request_data(command, &body)?;
         Ok(())
     }
 
+    // The Mic Monitor (hardware sidetone) is just another channel volume to the firmware, and
+    // takes the same 0-255 scale stored in the profile, so no conversion is needed. This is the
+    // same on both the Full and Mini.
+    fn set_microphone_monitor(&mut self, volume: u8) -> Result<()> {
+        self.set_volume(ChannelName::MicMonitor, volume)
+    }
+
     fn set_encoder_value(&mut self, encoder: EncoderName, value: i8) -> Result<()> {
         self.request_data(Command::SetEncoderValue(encoder), &[value as u8])?;
         Ok(())
@@ -142,12 +216,14 @@ pub trait GoXLRCommands: ExecutableGoXLR {
     }
 
     fn set_channel_state(&mut self, channel: ChannelName, state: ChannelState) -> Result<()> {
-        self.request_data(Command::SetChannelState(channel), &[state.id()])?;
+        let (command, body) = requests::set_channel_state(channel, state);
+        self.request_data(command, &body)?;
         Ok(())
     }
 
     fn set_button_states(&mut self, data: [ButtonStates; 24]) -> Result<()> {
-        self.request_data(Command::SetButtonStates(), &data.map(|state| state as u8))?;
+        let (command, body) = requests::set_button_states(data);
+        self.request_data(command, &body)?;
         Ok(())
     }
 
@@ -161,21 +237,22 @@ pub trait GoXLRCommands: ExecutableGoXLR {
         Ok(())
     }
 
+    /// Sends a full (1.3.40 format) colour map, cut down to the older format if needed.
+    fn set_colour_map(&mut self, map: &[u8; 520], format_1_3_40: bool) -> Result<()> {
+        let (command, body) = requests::set_colour_map(map, format_1_3_40);
+        self.request_data(command, &body)?;
+        Ok(())
+    }
+
     fn set_fader_display_mode(
         &mut self,
         fader: FaderName,
         gradient: bool,
         meter: bool,
     ) -> Result<()> {
-        // This one really doesn't need anything fancy..
-        let gradient_byte = u8::from(gradient);
-        let meter_byte = u8::from(meter);
-
         // TODO: Seemingly broken?
-        self.request_data(
-            Command::SetFaderDisplayMode(fader),
-            &[gradient_byte, meter_byte],
-        )?;
+        let (command, body) = requests::set_fader_display_mode(fader, gradient, meter);
+        self.request_data(command, &body)?;
         Ok(())
     }
 
@@ -192,18 +269,20 @@ pub trait GoXLRCommands: ExecutableGoXLR {
 
     // Submix Stuff
     fn set_sub_volume(&mut self, channel: SubMixChannelName, volume: u8) -> Result<()> {
-        self.request_data(Command::SetSubChannelVolume(channel), &[volume])?;
+        let (command, body) = requests::set_sub_volume(channel, volume);
+        self.request_data(command, &body)?;
         Ok(())
     }
 
-    // TODO: Potentially for later, abstract out the 'data' section into a couple of Vec<>s
-    fn set_channel_mixes(&mut self, data: [u8; 8]) -> Result<()> {
-        self.request_data(Command::SetChannelMixes, &data)?;
+    fn set_channel_mixes(&mut self, outputs: &[(OutputDevice, Mix)]) -> Result<()> {
+        let (command, body) = requests::set_channel_mixes(outputs);
+        self.request_data(command, &body)?;
         Ok(())
     }
 
     fn set_monitored_mix(&mut self, mix: Mix) -> Result<()> {
-        self.request_data(Command::SetMonitoredMix, &[mix as u8])?;
+        let (command, body) = requests::set_monitored_mix(mix);
+        self.request_data(command, &body)?;
         Ok(())
     }
 
@@ -224,8 +303,12 @@ pub trait GoXLRCommands: ExecutableGoXLR {
     }
 
     fn get_microphone_level(&mut self) -> Result<u16> {
+        Ok(self.get_levels()?.microphone_raw)
+    }
+
+    fn get_levels(&mut self) -> Result<Levels> {
         let result = self.request_data(Command::GetMicrophoneLevel, &[])?;
-        Ok(LittleEndian::read_u16(&result))
+        Ok(Levels::from_response(&result)?)
     }
 
     fn set_effect_values(&mut self, effects: &[(EffectKey, i32)]) -> Result<()> {
@@ -254,6 +337,10 @@ pub trait GoXLRCommands: ExecutableGoXLR {
 
     fn get_button_states(&mut self) -> Result<CurrentButtonStates> {
         let result = self.request_data(Command::GetButtonStates, &[])?;
+        if result.len() < 12 {
+            bail!("Button state response too short ({} bytes)", result.len());
+        }
+
         let mut pressed = EnumSet::empty();
         let mut mixers = [0; 4];
         let mut encoders = [0; 4];
@@ -283,6 +370,39 @@ pub trait GoXLRCommands: ExecutableGoXLR {
         })
     }
 
+    /// Returns the current physical position of each fader, in order A - D.
+    fn get_fader_positions(&mut self) -> Result<[(FaderName, u8); 4]> {
+        let volumes = self.get_button_states()?.volumes;
+        Ok([
+            (FaderName::A, volumes[0]),
+            (FaderName::B, volumes[1]),
+            (FaderName::C, volumes[2]),
+            (FaderName::D, volumes[3]),
+        ])
+    }
+
+    /// Reads back the fader positions, encoder values and held buttons from the device. The
+    /// GoXLR doesn't store a profile name, so to work out which profile is active, compare this
+    /// against the known profiles (see the profile crate's HardwareState::matching_profile).
+    fn get_hardware_state(&mut self) -> Result<HardwareState> {
+        let state = self.get_button_states()?;
+        Ok(HardwareState {
+            fader_volumes: [
+                (FaderName::A, state.volumes[0]),
+                (FaderName::B, state.volumes[1]),
+                (FaderName::C, state.volumes[2]),
+                (FaderName::D, state.volumes[3]),
+            ],
+            encoders: [
+                (EncoderName::Pitch, state.encoders[0]),
+                (EncoderName::Gender, state.encoders[1]),
+                (EncoderName::Reverb, state.encoders[2]),
+                (EncoderName::Echo, state.encoders[3]),
+            ],
+            pressed: state.pressed,
+        })
+    }
+
     fn set_animation_mode(
         &mut self,
         enabled: bool,
@@ -291,19 +411,33 @@ pub trait GoXLRCommands: ExecutableGoXLR {
         modifier2: u8,
         waterfall: WaterFallDir,
     ) -> Result<()> {
-        if modifier1 > 100 || modifier2 > 100 {
-            bail!("Modifiers should be < 100");
-        }
+        let (command, body) =
+            requests::set_animation_mode(enabled, mode, modifier1, modifier2, waterfall)?;
 
-        // Build the command..
-        let mut command = [0; 5];
-        command[0] = enabled as u8;
-        command[1] = mode as u8;
-        command[2] = modifier1;
-        command[3] = modifier2;
-        command[4] = waterfall as u8;
+        let _ = self.request_data(command, &body);
+        Ok(())
+    }
+
+    /// Sends a pre-built list of commands in order, reporting progress after each one. The cancel
+    /// flag is checked between commands, so a cancelled run may leave the device part-applied.
+    ///
+    /// This crate doesn't know about profiles, so building the list (for example, from a
+    /// ProfileSettings) is left to the caller, as is setting the flag from another thread.
+    fn execute_commands(
+        &mut self,
+        commands: &[(Command, Vec<u8>)],
+        progress: &mut dyn FnMut(usize, usize),
+        cancel: &AtomicBool,
+    ) -> Result<()> {
+        let total = commands.len();
+        for (index, (command, body)) in commands.iter().enumerate() {
+            if cancel.load(Ordering::Relaxed) {
+                bail!("Command Execution Cancelled after {} of {}", index, total);
+            }
 
-        let _ = self.request_data(Command::SetAnimationMode, &command);
+            self.request_data(*command, body)?;
+            progress(index + 1, total);
+        }
         Ok(())
     }
 
@@ -344,8 +478,10 @@ pub trait GoXLRCommands: ExecutableGoXLR {
         Ok(result[0])
     }
 
+    /// Sends a piece of the firmware, frame::chunk_body with FIRMWARE_PACKET_HEADER_LENGTH will
+    /// split an image into pieces which fit.
     fn send_firmware_packet(&mut self, bytes_sent: u64, data: &[u8]) -> Result<()> {
-        let mut header = [0; 12];
+        let mut header = [0; FIRMWARE_PACKET_HEADER_LENGTH];
         LittleEndian::write_u32(&mut header[0..4], 7);
         LittleEndian::write_u64(&mut header[4..], bytes_sent);
 
@@ -542,15 +678,180 @@ pub trait GoXLRCommands: ExecutableGoXLR {
     }
 }
 
+/// Controls how find_devices_with_options scans for devices.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ScanOptions {
+    /// Stop scanning once this has elapsed, returning whatever has been found so far.
+    pub timeout: Option<Duration>,
+
+    /// Opening a device to read its serial is the slowest part of a scan, so this is off by
+    /// default. Without the serial, device_identifier falls back to the port path, so
+    /// from_stable_id and from_identifier turn this on when resolving an ID.
+    pub read_serials: bool,
+}
+
+/// Whether a device is able to be attached normally, or is stuck in firmware update mode.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DeviceState {
+    Normal,
+    UpdateMode,
+}
+
+impl DeviceState {
+    /// Works out the state from a vendor and product ID, returning None if the device isn't a
+    /// GoXLR. A device in update mode reports the PID_GOXLR_UPDATE_MODE product ID.
+    pub fn from_ids(vendor_id: u16, product_id: u16) -> Option<DeviceState> {
+        Self::detect(vendor_id, product_id, PID_GOXLR_UPDATE_MODE)
+    }
+
+    fn detect(vendor_id: u16, product_id: u16, update_mode: Option<u16>) -> Option<DeviceState> {
+        if vendor_id != VID_GOXLR {
+            return None;
+        }
+
+        match product_id {
+            PID_GOXLR_FULL | PID_GOXLR_MINI => Some(DeviceState::Normal),
+            id if update_mode == Some(id) => Some(DeviceState::UpdateMode),
+            _ => None,
+        }
+    }
+}
+
+// Picks the GoXLRs out of a scan, given each device's vendor and product ID, and pairs them
+// with the state they're in. This is kept apart from libusb so it can be fed a fake device list..
+pub(crate) fn detect_goxlrs<T>(
+    devices: impl IntoIterator<Item = (T, u16, u16)>,
+    update_mode: Option<u16>,
+) -> Vec<(T, DeviceState)> {
+    devices
+        .into_iter()
+        .filter_map(|(device, vendor_id, product_id)| {
+            DeviceState::detect(vendor_id, product_id, update_mode).map(|state| (device, state))
+        })
+        .collect()
+}
+
+/// An identifier for a GoXLR which survives it being unplugged and plugged back in, unlike the
+/// bus number and address. Where the serial can't be read, we fall back to a hash of the port path,
+/// which is stable as long as the device is plugged back into the same port.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DeviceIdentifier {
+    Serial(String),
+    PortPath(u64),
+}
+
+impl DeviceIdentifier {
+    pub fn new(serial: Option<&str>, port_path: &[u8]) -> Self {
+        match serial {
+            Some(serial) if !serial.is_empty() => DeviceIdentifier::Serial(serial.to_string()),
+            _ => DeviceIdentifier::PortPath(Self::hash_path(port_path)),
+        }
+    }
+
+    // This needs to be stable between runs (it may be stored in settings), so we use FNV-1a
+    // rather than the std hasher, which makes no such guarantee..
+    fn hash_path(port_path: &[u8]) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for byte in port_path {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        hash
+    }
+}
+
+impl Display for DeviceIdentifier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeviceIdentifier::Serial(serial) => write!(f, "serial/{}", serial),
+            DeviceIdentifier::PortPath(hash) => write!(f, "port/{:016x}", hash),
+        }
+    }
+}
+
+impl FromStr for DeviceIdentifier {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.split_once('/') {
+            Some(("serial", serial)) if !serial.is_empty() => {
+                Ok(DeviceIdentifier::Serial(serial.to_string()))
+            }
+            Some(("port", hash)) => Ok(DeviceIdentifier::PortPath(u64::from_str_radix(hash, 16)?)),
+            _ => bail!("Invalid Device Identifier: {}", s),
+        }
+    }
+}
+
+// Resolving a stored ID needs the serial, which isn't read during a normal scan..
+fn find_devices_with_serials() -> Vec<GoXLRDevice> {
+    find_devices_with_options(&ScanOptions {
+        read_serials: true,
+        ..Default::default()
+    })
+}
+
 // We primarily need the bus number, and address for comparison..
 #[derive(Debug, Clone)]
 pub struct GoXLRDevice {
     pub(crate) bus_number: u8,
     pub(crate) address: u8,
     pub(crate) identifier: Option<String>,
+
+    // The physical location of the device (bus, then the port numbers through any hubs), this
+    // is empty where the platform doesn't expose it..
+    pub(crate) port_path: Vec<u8>,
+
+    // Only available if the platform was able to read them during the scan..
+    pub(crate) product_id: Option<u16>,
+    pub(crate) serial: Option<String>,
+    pub(crate) state: DeviceState,
 }
 
 impl GoXLRDevice {
+    /// Returns an identifier for this device which can be stored (for example, in a config file)
+    /// and resolved later with from_stable_id. This is the device_identifier prefixed with the
+    /// model, for example 'goxlr-full/serial/<serial>'.
+    pub fn stable_id(&self) -> String {
+        let model = match self.product_id {
+            Some(PID_GOXLR_MINI) => "goxlr-mini",
+            Some(PID_GOXLR_FULL) => "goxlr-full",
+            _ => "goxlr",
+        };
+        format!("{}/{}", model, self.device_identifier())
+    }
+
+    /// Scans for devices, and returns the one matching the stable ID (if it's connected).
+    pub fn from_stable_id(id: &str) -> Option<GoXLRDevice> {
+        let (_, identifier) = id.split_once('/')?;
+        let identifier = DeviceIdentifier::from_str(identifier).ok()?;
+        GoXLRDevice::from_identifier(&identifier).filter(|device| device.stable_id() == id)
+    }
+
+    /// Scans for devices, and returns the one matching the identifier (if it's connected).
+    pub fn from_identifier(id: &DeviceIdentifier) -> Option<GoXLRDevice> {
+        find_devices_with_serials()
+            .into_iter()
+            .find(|device| &device.device_identifier() == id)
+    }
+
+    pub fn device_identifier(&self) -> DeviceIdentifier {
+        if self.port_path.is_empty() {
+            // No port path, so the platform identifier is the best location we have..
+            let location = self.identifier.clone().unwrap_or_default();
+            return DeviceIdentifier::new(self.serial(), location.as_bytes());
+        }
+        DeviceIdentifier::new(self.serial(), &self.port_path)
+    }
+
+    pub fn serial(&self) -> Option<&str> {
+        self.serial.as_deref()
+    }
+
+    pub fn state(&self) -> DeviceState {
+        self.state
+    }
+
     pub fn bus_number(&self) -> u8 {
         self.bus_number
     }
@@ -588,3 +889,293 @@ impl UsbData {
         self.product_name.clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::cache::CachedCommands;
+    use crate::device::mock::MockDevice;
+    use crate::frame;
+    use goxlr_profile_loader::profile::ProfileSettings;
+    use goxlr_profile_loader::Faders;
+
+    fn device(serial: Option<&str>, port_path: &[u8]) -> GoXLRDevice {
+        GoXLRDevice {
+            bus_number: 1,
+            address: 4,
+            identifier: None,
+            port_path: port_path.to_vec(),
+            product_id: Some(PID_GOXLR_FULL),
+            serial: serial.map(String::from),
+            state: DeviceState::Normal,
+        }
+    }
+
+    #[test]
+    fn identifier_prefers_the_serial() {
+        let device = device(Some("S210600000AB"), &[1, 2]);
+        assert_eq!(
+            device.device_identifier(),
+            DeviceIdentifier::Serial("S210600000AB".to_string())
+        );
+    }
+
+    #[test]
+    fn identifier_falls_back_to_the_port_path() {
+        let unread = device(None, &[1, 2]);
+        let empty = device(Some(""), &[1, 2]);
+        let other_port = device(None, &[1, 3]);
+
+        assert!(matches!(
+            unread.device_identifier(),
+            DeviceIdentifier::PortPath(_)
+        ));
+        assert_eq!(unread.device_identifier(), empty.device_identifier());
+        assert_ne!(unread.device_identifier(), other_port.device_identifier());
+
+        // The hash is stored in settings, so it must not change between releases..
+        assert_eq!(
+            unread.device_identifier().to_string(),
+            "port/082f2407b4e8902a"
+        );
+    }
+
+    #[test]
+    fn identifier_without_port_path_uses_platform_identifier() {
+        let mut first = device(None, &[]);
+        let mut second = device(None, &[]);
+        first.identifier = Some("\\\\?\\usb#vid_1220&pid_8fe0#1".to_string());
+        second.identifier = Some("\\\\?\\usb#vid_1220&pid_8fe0#2".to_string());
+
+        assert_ne!(first.device_identifier(), second.device_identifier());
+    }
+
+    #[test]
+    fn identifier_round_trips_through_a_string() {
+        for device in [device(Some("S210600000AB"), &[1, 2]), device(None, &[1, 2])] {
+            let identifier = device.device_identifier();
+            let parsed = DeviceIdentifier::from_str(&identifier.to_string()).unwrap();
+            assert_eq!(parsed, identifier);
+        }
+
+        assert!(DeviceIdentifier::from_str("serial/").is_err());
+        assert!(DeviceIdentifier::from_str("port/not-hex").is_err());
+        assert!(DeviceIdentifier::from_str("usb/1-4").is_err());
+    }
+
+    #[test]
+    fn stable_id_is_built_from_the_identifier() {
+        let full = device(Some("S210600000AB"), &[1, 2]);
+        assert_eq!(full.stable_id(), "goxlr-full/serial/S210600000AB");
+
+        let mut mini = device(None, &[1, 2]);
+        mini.product_id = Some(PID_GOXLR_MINI);
+        assert_eq!(
+            mini.stable_id(),
+            format!("goxlr-mini/{}", mini.device_identifier())
+        );
+    }
+
+    // The real update mode product ID isn't known, so the scan is given a stand-in..
+    const UPDATE_MODE: u16 = 0x8fff;
+
+    #[test]
+    fn scan_reports_update_mode_devices() {
+        let descriptors = [
+            ("full", VID_GOXLR, PID_GOXLR_FULL),
+            ("keyboard", 0x046d, 0xc31c),
+            ("updating", VID_GOXLR, UPDATE_MODE),
+            ("mini", VID_GOXLR, PID_GOXLR_MINI),
+            ("other vendor", 0x046d, UPDATE_MODE),
+        ];
+
+        assert_eq!(
+            detect_goxlrs(descriptors, Some(UPDATE_MODE)),
+            vec![
+                ("full", DeviceState::Normal),
+                ("updating", DeviceState::UpdateMode),
+                ("mini", DeviceState::Normal),
+            ]
+        );
+
+        // Without a known update mode ID, those devices are left out..
+        assert_eq!(
+            detect_goxlrs(descriptors, None),
+            vec![("full", DeviceState::Normal), ("mini", DeviceState::Normal)]
+        );
+    }
+
+    fn apply_commands() -> Vec<(Command, Vec<u8>)> {
+        vec![
+            (Command::SetColourMap(), vec![0; 328]),
+            (Command::SetAnimationMode, vec![0; 5]),
+            (Command::SetButtonStates(), vec![0; 24]),
+        ]
+    }
+
+    #[test]
+    fn executed_commands_report_progress() {
+        let mut device = MockDevice::new();
+        let mut reported = vec![];
+        let mut progress = |done, total| reported.push((done, total));
+
+        let commands = apply_commands();
+        let cancel = AtomicBool::new(false);
+        device
+            .execute_commands(&commands, &mut progress, &cancel)
+            .unwrap();
+
+        assert_eq!(reported, vec![(1, 3), (2, 3), (3, 3)]);
+        assert_eq!(device.requests(), commands.as_slice());
+    }
+
+    #[test]
+    fn cancelling_stops_between_commands() {
+        let mut device = MockDevice::new();
+        let cancel = AtomicBool::new(false);
+
+        // Cancel once the first command has gone out, as a UI would from another thread..
+        let mut progress = |done, _| {
+            if done == 1 {
+                cancel.store(true, Ordering::Relaxed);
+            }
+        };
+
+        let commands = apply_commands();
+        let result = device.execute_commands(&commands, &mut progress, &cancel);
+        assert!(result.is_err());
+        assert_eq!(device.requests(), &commands[..1]);
+    }
+
+    // The sidetone at 0, 128 and 255, then Mix B monitored, as the packets go out on the wire:
+    // the command ID, body length and command index (all little endian), eight reserved bytes,
+    // then the body.
+    const SIDETONE_PACKETS: [[u8; 17]; 4] = [
+        [
+            0x09, 0x60, 0x80, 0x00, 0x01, 0x00, 0x01, 0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0x00,
+        ],
+        [
+            0x09, 0x60, 0x80, 0x00, 0x01, 0x00, 0x02, 0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0x80,
+        ],
+        [
+            0x09, 0x60, 0x80, 0x00, 0x01, 0x00, 0x03, 0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0xff,
+        ],
+        [
+            0x00, 0x80, 0x81, 0x00, 0x01, 0x00, 0x04, 0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0x01,
+        ],
+    ];
+
+    #[test]
+    fn sidetone_packets_match_on_both_devices() {
+        for product_id in [PID_GOXLR_FULL, PID_GOXLR_MINI] {
+            let mut device = MockDevice::with_product_id(product_id);
+            for volume in [0, 128, 255] {
+                device.set_microphone_monitor(volume).unwrap();
+            }
+            device.set_monitored_mix(Mix::B).unwrap();
+
+            let packets: Vec<_> = device
+                .requests()
+                .iter()
+                .zip(device.indexes())
+                .map(|((command, body), index)| frame::build_request(*command, body, *index))
+                .collect();
+            assert_eq!(packets, SIDETONE_PACKETS, "{product_id:#x}");
+        }
+    }
+
+    // A GetButtonStates response: the held buttons as a bitmap, the four encoders, then the four
+    // faders. Fader 1's mute and the Bleep button are held here.
+    const BUTTON_STATES_RESPONSE: [u8; 12] = [
+        0x10, 0x00, 0x40, 0x00, // Buttons
+        0xfd, 0x0c, 0x00, 0x05, // Pitch, Gender, Reverb, Echo
+        0xff, 0x80, 0x00, 0x3c, // Faders A - D
+    ];
+
+    #[test]
+    fn hardware_state_is_decoded_from_the_button_states() {
+        let mut device = MockDevice::new();
+        device.respond_with(Command::GetButtonStates, &BUTTON_STATES_RESPONSE);
+
+        let state = device.get_hardware_state().unwrap();
+        assert_eq!(
+            state.fader_volumes,
+            [
+                (FaderName::A, 0xff),
+                (FaderName::B, 0x80),
+                (FaderName::C, 0x00),
+                (FaderName::D, 0x3c),
+            ]
+        );
+        assert_eq!(
+            state.encoders,
+            [
+                (EncoderName::Pitch, -3),
+                (EncoderName::Gender, 12),
+                (EncoderName::Reverb, 0),
+                (EncoderName::Echo, 5),
+            ]
+        );
+        assert_eq!(state.pressed, Buttons::Fader1Mute | Buttons::Bleep);
+        assert_eq!(device.requests(), &[(Command::GetButtonStates, vec![])]);
+    }
+
+    #[test]
+    fn fader_positions_sync_into_a_loaded_profile() {
+        let xml = include_str!("../../../profile/test-data/profile.xml");
+        let mut profile = ProfileSettings::load(xml.as_bytes()).unwrap();
+
+        let mut device = MockDevice::new();
+        device.respond_with(Command::GetButtonStates, &BUTTON_STATES_RESPONSE);
+        let positions = device.get_fader_positions().unwrap();
+
+        // Each fader's position is the volume of whichever channel is assigned to it..
+        let volumes: Vec<_> = positions
+            .iter()
+            .map(|(fader, volume)| (profile.fader(profile_fader(*fader)).channel(), *volume))
+            .collect();
+        assert!(profile.apply_hardware_volumes(&volumes).unwrap());
+        for (fader, volume) in positions {
+            let channel = profile.fader(profile_fader(fader)).channel();
+            assert_eq!(profile.mixer().channel_volume(channel), volume);
+        }
+
+        // Reading the same positions again leaves the profile alone..
+        assert!(!profile.apply_hardware_volumes(&volumes).unwrap());
+    }
+
+    fn profile_fader(fader: FaderName) -> Faders {
+        match fader {
+            FaderName::A => Faders::A,
+            FaderName::B => Faders::B,
+            FaderName::C => Faders::C,
+            FaderName::D => Faders::D,
+        }
+    }
+
+    #[test]
+    fn request_errors_name_the_device() {
+        let mut device = MockDevice::new();
+        device.fail_next(1);
+        let error = device.get_hardware_state().unwrap_err();
+
+        // Both the device and the command are named, ahead of the underlying error..
+        let message = format!("{error:#}");
+        assert!(message.contains("serial/S210600000AB"), "{message}");
+        assert!(message.contains("GetButtonStates"), "{message}");
+        assert!(message.ends_with("Pipe"), "{message}");
+
+        // Wrapping the device in the cache doesn't lose it..
+        let mut cached = CachedCommands::new(MockDevice::new());
+        cached.inner_mut().fail_next(1);
+        let error = cached.get_hardware_state().unwrap_err();
+        assert!(format!("{error:#}").contains("serial/S210600000AB"));
+    }
+
+    #[test]
+    fn short_button_state_responses_are_rejected() {
+        let mut device = MockDevice::new();
+        device.respond_with(Command::GetButtonStates, &BUTTON_STATES_RESPONSE[..8]);
+        assert!(device.get_hardware_state().is_err());
+    }
+}
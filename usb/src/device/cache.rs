@@ -0,0 +1,219 @@
+use crate::commands::Command;
+use crate::device::base::{ExecutableGoXLR, GoXLRCommands, UsbData};
+use anyhow::{bail, Result};
+use log::debug;
+use std::time::{Duration, Instant};
+
+struct CachedResponse {
+    command: Command,
+    body: Vec<u8>,
+    response: Vec<u8>,
+    fetched: Instant,
+}
+
+/**
+ * Wraps a device, and caches the responses to read only commands which aren't expected to change
+ * (such as the serial number and firmware version), so repeated reads don't go to the device.
+ *
+ * Only commands added with cache() are cached, each with an optional time to live (None caches
+ * until invalidated). Any command which changes the state of the device always goes straight
+ * through, and clears the whole cache on the way.
+ */
+pub struct CachedCommands<T: ExecutableGoXLR> {
+    inner: T,
+    cacheable: Vec<(Command, Option<Duration>)>,
+    responses: Vec<CachedResponse>,
+}
+
+impl<T: ExecutableGoXLR> CachedCommands<T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            cacheable: vec![],
+            responses: vec![],
+        }
+    }
+
+    /// Caches responses to a command, read only commands are the only ones accepted.
+    pub fn cache(&mut self, command: Command, ttl: Option<Duration>) -> Result<()> {
+        if !command.is_read_only() {
+            bail!("{:?} changes the device, and can't be cached", command);
+        }
+
+        self.cacheable.retain(|(cached, _)| *cached != command);
+        self.cacheable.push((command, ttl));
+        Ok(())
+    }
+
+    pub fn invalidate(&mut self, command: Command) {
+        self.responses.retain(|cached| cached.command != command);
+    }
+
+    pub fn invalidate_all(&mut self) {
+        self.responses.clear();
+    }
+
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn ttl(&self, command: Command) -> Option<Option<Duration>> {
+        self.cacheable
+            .iter()
+            .find(|(cached, _)| *cached == command)
+            .map(|(_, ttl)| *ttl)
+    }
+}
+
+impl<T: ExecutableGoXLR> ExecutableGoXLR for CachedCommands<T> {
+    fn request_data(&mut self, command: Command, body: &[u8]) -> Result<Vec<u8>> {
+        if !command.is_read_only() {
+            self.invalidate_all();
+            return self.inner.request_data(command, body);
+        }
+
+        let ttl = match self.ttl(command) {
+            Some(ttl) => ttl,
+            None => return self.inner.request_data(command, body),
+        };
+
+        // Drop anything which has expired, then see if we've still got an answer..
+        self.responses.retain(|cached| {
+            cached.command != command || ttl.is_none_or(|ttl| cached.fetched.elapsed() < ttl)
+        });
+        if let Some(cached) = self
+            .responses
+            .iter()
+            .find(|cached| cached.command == command && cached.body == body)
+        {
+            debug!("Using cached response for {:?}", command);
+            return Ok(cached.response.clone());
+        }
+
+        let response = self.inner.request_data(command, body)?;
+        self.responses.push(CachedResponse {
+            command,
+            body: body.to_vec(),
+            response: response.clone(),
+            fetched: Instant::now(),
+        });
+        Ok(response)
+    }
+
+    fn perform_request(&mut self, command: Command, body: &[u8], retry: bool) -> Result<Vec<u8>> {
+        if !command.is_read_only() {
+            self.invalidate_all();
+        }
+        self.inner.perform_request(command, body, retry)
+    }
+
+    fn get_descriptor(&self) -> Result<UsbData> {
+        self.inner.get_descriptor()
+    }
+
+    fn describe(&self) -> String {
+        self.inner.describe()
+    }
+
+    fn retry_count(&self) -> u64 {
+        self.inner.retry_count()
+    }
+
+    fn resync_count(&self) -> u64 {
+        self.inner.resync_count()
+    }
+
+    fn reset_request_counters(&mut self) {
+        self.inner.reset_request_counters()
+    }
+}
+
+impl<T: ExecutableGoXLR> GoXLRCommands for CachedCommands<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::HardwareInfoCommand;
+    use crate::device::mock::MockDevice;
+    use goxlr_types::{ChannelName, FaderName};
+
+    const SERIAL: Command = Command::GetHardwareInfo(HardwareInfoCommand::SerialNumber);
+
+    fn cached_serial(ttl: Option<Duration>) -> CachedCommands<MockDevice> {
+        let mut device = MockDevice::new();
+        device.respond_with(SERIAL, b"S210600000AB");
+        device.respond_with(SERIAL, b"S210600000CD");
+
+        let mut cached = CachedCommands::new(device);
+        cached.cache(SERIAL, ttl).unwrap();
+        cached
+    }
+
+    fn sent(cached: &CachedCommands<MockDevice>) -> usize {
+        cached.inner().requests().len()
+    }
+
+    #[test]
+    fn repeated_reads_are_answered_from_the_cache() {
+        let mut cached = cached_serial(None);
+        assert_eq!(cached.request_data(SERIAL, &[]).unwrap(), b"S210600000AB");
+        assert_eq!(cached.request_data(SERIAL, &[]).unwrap(), b"S210600000AB");
+        assert_eq!(sent(&cached), 1);
+    }
+
+    #[test]
+    fn reads_which_miss_the_cache_go_to_the_device() {
+        let mut cached = cached_serial(None);
+        cached.request_data(SERIAL, &[]).unwrap();
+
+        // A different body is a different question..
+        assert_eq!(cached.request_data(SERIAL, &[1]).unwrap(), b"S210600000CD");
+        assert_eq!(sent(&cached), 2);
+
+        // As is a command which was never marked as cacheable..
+        cached
+            .request_data(Command::GetMicrophoneLevel, &[])
+            .unwrap();
+        cached
+            .request_data(Command::GetMicrophoneLevel, &[])
+            .unwrap();
+        assert_eq!(sent(&cached), 4);
+    }
+
+    #[test]
+    fn expired_responses_are_fetched_again() {
+        let mut cached = cached_serial(Some(Duration::ZERO));
+        assert_eq!(cached.request_data(SERIAL, &[]).unwrap(), b"S210600000AB");
+        assert_eq!(cached.request_data(SERIAL, &[]).unwrap(), b"S210600000CD");
+        assert_eq!(sent(&cached), 2);
+    }
+
+    #[test]
+    fn writes_invalidate_the_cache() {
+        let mut cached = cached_serial(None);
+        cached.request_data(SERIAL, &[]).unwrap();
+
+        cached.set_fader(FaderName::A, ChannelName::Mic).unwrap();
+        assert_eq!(cached.request_data(SERIAL, &[]).unwrap(), b"S210600000CD");
+        assert_eq!(sent(&cached), 3);
+
+        cached.invalidate(SERIAL);
+        cached.request_data(SERIAL, &[]).unwrap();
+        assert_eq!(sent(&cached), 4);
+    }
+
+    #[test]
+    fn only_read_only_commands_can_be_cached() {
+        let mut cached = CachedCommands::new(MockDevice::new());
+        assert!(cached.cache(Command::GetButtonStates, None).is_ok());
+        assert!(cached.cache(Command::SetColourMap(), None).is_err());
+    }
+}
@@ -1,23 +1,56 @@
 use crate::commands::Command;
+#[cfg(feature = "unsafe-raw")]
+use crate::device::base::RawGoXLR;
 use crate::device::base::{
-    AttachGoXLR, ExecutableGoXLR, FullGoXLRDevice, GoXLRCommands, GoXLRDevice, UsbData,
+    detect_goxlrs, AttachGoXLR, DeviceIdentifier, DeviceState, ExecutableGoXLR, FullGoXLRDevice,
+    GoXLRCommands, GoXLRDevice, ScanOptions, UsbData,
 };
-use crate::{PID_GOXLR_FULL, PID_GOXLR_MINI, VID_GOXLR};
-use anyhow::{anyhow, bail, Error, Result};
-use byteorder::{ByteOrder, LittleEndian};
+use crate::error::ConnectError;
+use crate::frame::{self, CommandIndex};
+use crate::{PID_GOXLR_MINI, PID_GOXLR_UPDATE_MODE};
+use anyhow::{anyhow, bail, Context, Error, Result};
 use log::{debug, error, info, warn};
 use rusb::Error::Pipe;
 use rusb::{
-    Device, DeviceDescriptor, DeviceHandle, Direction, GlobalContext, Language, Recipient,
-    RequestType,
+    Device, DeviceDescriptor, DeviceHandle, Direction, GlobalContext, Recipient, RequestType,
 };
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc::Sender;
 use tokio::task;
 
+// The GoXLR only has a single configuration, with the vendor commands going to interface 0..
+const EXPECTED_CONFIGURATION: u8 = 1;
+const VENDOR_INTERFACE: u8 = 0;
+
+/// The parts of the device handle used to check the configuration, split out so the check can
+/// be run without a GoXLR attached.
+trait ConfigurationHandle {
+    fn active_configuration(&self) -> rusb::Result<u8>;
+    fn set_active_configuration(&mut self, config: u8) -> rusb::Result<()>;
+
+    /// The number of the active configuration, and the interfaces it has.
+    fn active_interfaces(&self) -> rusb::Result<(u8, Vec<u8>)>;
+}
+
+impl ConfigurationHandle for DeviceHandle<GlobalContext> {
+    fn active_configuration(&self) -> rusb::Result<u8> {
+        DeviceHandle::active_configuration(self)
+    }
+
+    fn set_active_configuration(&mut self, config: u8) -> rusb::Result<()> {
+        DeviceHandle::set_active_configuration(self, config)
+    }
+
+    fn active_interfaces(&self) -> rusb::Result<(u8, Vec<u8>)> {
+        let config = self.device().active_config_descriptor()?;
+        let interfaces = config.interfaces().map(|interface| interface.number());
+        Ok((config.number(), interfaces.collect()))
+    }
+}
+
 pub struct GoXLRUSB {
     handle: DeviceHandle<GlobalContext>,
     device: Device<GlobalContext>,
@@ -26,6 +59,7 @@ pub struct GoXLRUSB {
     disconnect_sender: Sender<String>,
     event_sender: Sender<String>,
     identifier: Option<String>,
+    device_identifier: DeviceIdentifier,
 
     pause_polling: Arc<AtomicBool>,
     stop_polling: Arc<AtomicBool>,
@@ -33,9 +67,16 @@ pub struct GoXLRUSB {
     stopping: Arc<AtomicBool>,
     disconnecting: bool,
 
-    language: Language,
-    command_count: u16,
+    command_index: CommandIndex,
     timeout: Duration,
+
+    // Diagnostics, see ExecutableGoXLR::retry_count and resync_count..
+    retry_count: u64,
+    resync_count: u64,
+
+    // String descriptors are read once on attach, they won't change while we're connected..
+    manufacturer: Option<String>,
+    product_name: Option<String>,
 }
 
 impl GoXLRUSB {
@@ -54,6 +95,52 @@ impl GoXLRUSB {
         bail!("Specified Device not Found!")
     }
 
+    // Not every device provides string descriptors, so treat any failure as 'absent'..
+    fn read_string_descriptor(
+        handle: &DeviceHandle<GlobalContext>,
+        index: Option<u8>,
+    ) -> Option<String> {
+        let index = index?;
+        match handle.read_string_descriptor_ascii(index) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                debug!("Unable to read String Descriptor {}: {}", index, e);
+                None
+            }
+        }
+    }
+
+    /// The manufacturer string descriptor read on attach, `None` if the device didn't provide one
+    /// (or it couldn't be read), as opposed to `Some("")` for a descriptor that was empty.
+    pub fn manufacturer(&self) -> Option<&str> {
+        self.manufacturer.as_deref()
+    }
+
+    /// The product string descriptor read on attach, `None` if the device didn't provide one.
+    pub fn product_name(&self) -> Option<&str> {
+        self.product_name.as_deref()
+    }
+
+    /// Makes sure the device is in the configuration we expect, and that the vendor interface is
+    /// present. Some systems bring the GoXLR back from suspend in a different configuration, at
+    /// which point every control transfer fails with a Pipe error.
+    fn check_configuration(handle: &mut impl ConfigurationHandle) -> Result<()> {
+        let active = handle.active_configuration()?;
+        if active != EXPECTED_CONFIGURATION {
+            warn!(
+                "GoXLR is in configuration {}, switching to {}",
+                active, EXPECTED_CONFIGURATION
+            );
+            handle.set_active_configuration(EXPECTED_CONFIGURATION)?;
+        }
+
+        let (config, interfaces) = handle.active_interfaces()?;
+        if !interfaces.contains(&VENDOR_INTERFACE) {
+            return Err(ConnectError::InterfaceNotFound(VENDOR_INTERFACE, config).into());
+        }
+        Ok(())
+    }
+
     fn trigger_disconnect(&mut self) -> Result<()> {
         // If this function has already been called further up the stack, don't run it.
         if self.disconnecting {
@@ -144,37 +231,55 @@ impl AttachGoXLR for GoXLRUSB {
         event_sender: Sender<String>,
         _skip_pause: bool,
     ) -> Result<Box<(dyn FullGoXLRDevice)>> {
+        // A device in update mode won't respond to regular commands..
+        if device.state() == DeviceState::UpdateMode {
+            return Err(ConnectError::InUpdateMode.into());
+        }
+
         // Firstly, we need to locate the USB device based on the location..
         let (device, descriptor) = GoXLRUSB::find_device(device)?;
         let mut handle = device.open()?;
 
         let timeout = Duration::from_secs(1);
 
+        // Devices without any string languages aren't a GoXLR..
         let languages = handle.read_languages(timeout)?;
-        let language = languages
-            .get(0)
-            .ok_or_else(|| anyhow!("Not GoXLR?"))?
-            .to_owned();
+        if languages.is_empty() {
+            bail!("Not GoXLR?");
+        }
 
         let device = handle.device();
         info!("Connected to possible GoXLR device at {:?}", device);
 
-        let device_is_claimed = handle.claim_interface(0).is_ok();
+        GoXLRUSB::check_configuration(&mut handle)?;
+        let device_is_claimed = handle.claim_interface(VENDOR_INTERFACE).is_ok();
+
+        let manufacturer =
+            GoXLRUSB::read_string_descriptor(&handle, descriptor.manufacturer_string_index());
+        let product_name =
+            GoXLRUSB::read_string_descriptor(&handle, descriptor.product_string_index());
+        let serial =
+            GoXLRUSB::read_string_descriptor(&handle, descriptor.serial_number_string_index());
+        let device_identifier = DeviceIdentifier::new(serial.as_deref(), &port_path(&device));
 
         let mut goxlr = Self {
             device: handle.device(),
             handle,
             descriptor,
-            language,
             disconnect_sender,
             event_sender,
             identifier: None,
-            command_count: 0,
+            device_identifier,
+            command_index: CommandIndex::default(),
+            retry_count: 0,
+            resync_count: 0,
             stopping: Arc::new(AtomicBool::new(false)),
             disconnecting: false,
             timeout,
             pause_polling: Arc::new(AtomicBool::new(false)),
             stop_polling: Arc::new(AtomicBool::new(false)),
+            manufacturer,
+            product_name,
         };
 
         // Resets the state of the device (unconfirmed - Might just be the command id counter)
@@ -219,12 +324,21 @@ impl AttachGoXLR for GoXLRUSB {
 
         // Force command pipe activation in all cases.
         debug!("Handling initial request");
-        goxlr.read_control(3, 0, 0, 1040)?;
+        goxlr.read_control(
+            frame::REQUEST_READ_RESPONSE,
+            0,
+            0,
+            frame::MAX_RESPONSE_LENGTH,
+        )?;
 
         // Set the local serial number..
         Ok(Box::new(goxlr))
     }
 
+    fn device_identifier(&self) -> DeviceIdentifier {
+        self.device_identifier.clone()
+    }
+
     fn set_unique_identifier(&mut self, identifier: String) {
         let event_id = identifier.clone();
         self.identifier = Some(identifier);
@@ -283,34 +397,54 @@ impl AttachGoXLR for GoXLRUSB {
         warn!("Disabling GoXLR Value Polling");
         self.stop_polling.store(true, Ordering::Relaxed);
     }
+
+    fn reinitialise(&mut self) -> Result<()> {
+        info!("Reinitialising GoXLR at {:?}", self.device);
+        GoXLRUSB::check_configuration(&mut self.handle)?;
+
+        // The claim may have been lost along with the configuration..
+        let _ = self.handle.claim_interface(VENDOR_INTERFACE);
+
+        self.command_index.reset();
+        self.write_control(1, 0, 0, &[])?;
+        self.read_control(
+            frame::REQUEST_READ_RESPONSE,
+            0,
+            0,
+            frame::MAX_RESPONSE_LENGTH,
+        )?;
+        Ok(())
+    }
 }
 
 impl ExecutableGoXLR for GoXLRUSB {
     fn perform_request(&mut self, command: Command, body: &[u8], retry: bool) -> Result<Vec<u8>> {
+        frame::check_body_length(command, body)?;
         self.pause_polling.store(true, Ordering::Relaxed);
 
-        if command == Command::ResetCommandIndex {
-            self.command_count = 0;
-        } else {
-            if self.command_count == u16::MAX {
+        let command_index = match self.command_index.next(command) {
+            Some(index) => index,
+            None => {
                 let result = self.request_data(Command::ResetCommandIndex, &[]);
                 if result.is_err() {
                     self.pause_polling.store(false, Ordering::Relaxed);
                     return result;
                 }
-            }
-            self.command_count += 1;
-        }
 
-        let command_index = self.command_count;
-        let mut full_request = vec![0; 16];
-        LittleEndian::write_u32(&mut full_request[0..4], command.command_id());
-        LittleEndian::write_u16(&mut full_request[4..6], body.len() as u16);
-        LittleEndian::write_u16(&mut full_request[6..8], command_index);
-        full_request.extend(body);
+                // The reset request unpauses polling when it completes..
+                self.pause_polling.store(true, Ordering::Relaxed);
+                self.command_index
+                    .next(command)
+                    .context("Command index wasn't reset")?
+            }
+        };
+        let full_request = frame::build_request(command, body, command_index);
 
-        if let Err(error) = self.write_control(2, 0, 0, &full_request) {
-            debug!("Error when attempting to write control.");
+        if let Err(error) = self.write_control(frame::REQUEST_SEND_COMMAND, 0, 0, &full_request) {
+            debug!(
+                "[{}] Error when attempting to write control.",
+                self.describe()
+            );
             self.pause_polling.store(false, Ordering::Relaxed);
             self.trigger_disconnect()?;
             bail!(error);
@@ -326,23 +460,36 @@ impl ExecutableGoXLR for GoXLRUSB {
 
         let mut response = vec![];
         for i in 0..20 {
-            let response_value = self.read_control(3, 0, 0, 1040);
+            let response_value = self.read_control(
+                frame::REQUEST_READ_RESPONSE,
+                0,
+                0,
+                frame::MAX_RESPONSE_LENGTH,
+            );
             if response_value == Err(Pipe) {
                 if i < 19 {
-                    debug!("Response not arrived yet for {:?}, sleeping and retrying (Attempt {} of 20)", command, i + 1);
+                    self.retry_count += 1;
+                    debug!("[{}] Response not arrived yet for {:?}, sleeping and retrying (Attempt {} of 20)", self.describe(), command, i + 1);
                     sleep(sleep_time);
                     continue;
                 } else {
                     // We can't read from this GoXLR, flag as disconnected.
                     self.pause_polling.store(false, Ordering::Relaxed);
                     self.trigger_disconnect()?;
-                    warn!("Failed to receive response (Attempt 20 of 20), possible Dead GoXLR?");
+                    warn!(
+                        "[{}] Failed to receive response (Attempt 20 of 20), possible Dead GoXLR?",
+                        self.describe()
+                    );
                     return Err(Error::from(response_value.err().unwrap()));
                 }
             }
             if response_value.is_err() {
                 let err = response_value.err().unwrap();
-                debug!("Error Occurred during packet read: {}", err);
+                debug!(
+                    "[{}] Error Occurred during packet read: {}",
+                    self.describe(),
+                    err
+                );
 
                 self.pause_polling.store(false, Ordering::Relaxed);
                 self.trigger_disconnect()?;
@@ -350,46 +497,57 @@ impl ExecutableGoXLR for GoXLRUSB {
             }
 
             let mut response_header = response_value.unwrap();
-            if response_header.len() < 16 {
-                error!(
-                    "Invalid Response received from the GoXLR, Expected: 16, Received: {}",
-                    response_header.len()
-                );
-                self.pause_polling.store(false, Ordering::Relaxed);
-                self.trigger_disconnect()?;
-                return Err(Error::from(Pipe));
-            }
+            let header = match frame::parse_header(&response_header) {
+                Ok(header) => header,
+                Err(error) => {
+                    error!(
+                        "[{}] Invalid Response received from the GoXLR: {}",
+                        self.describe(),
+                        error
+                    );
+                    self.pause_polling.store(false, Ordering::Relaxed);
+                    self.trigger_disconnect()?;
+                    return Err(Error::from(Pipe));
+                }
+            };
 
-            response = response_header.split_off(16);
-            let response_length = LittleEndian::read_u16(&response_header[4..6]);
-            let response_command_index = LittleEndian::read_u16(&response_header[6..8]);
+            response = response_header.split_off(frame::HEADER_LENGTH);
+            let response_length = header.body_length;
+            let response_command_index = header.command_index;
 
             if response_command_index != command_index {
-                debug!("Mismatched Command Indexes..");
+                debug!("[{}] Mismatched Command Indexes..", self.describe());
                 debug!(
-                    "Expected {}, received: {}",
-                    command_index, response_command_index
+                    "[{}] Expected {}, received: {}",
+                    self.describe(),
+                    command_index,
+                    response_command_index
                 );
-                debug!("Full Request: {:?}", full_request);
-                debug!("Response Header: {:?}", response_header);
-                debug!("Response Body: {:?}", response);
+                debug!("[{}] Full Request: {:?}", self.describe(), full_request);
+                debug!(
+                    "[{}] Response Header: {:?}",
+                    self.describe(),
+                    response_header
+                );
+                debug!("[{}] Response Body: {:?}", self.describe(), response);
 
                 return if !retry {
-                    debug!("Attempting Resync and Retry");
+                    debug!("[{}] Attempting Resync and Retry", self.describe());
+                    self.resync_count += 1;
                     let result = self.perform_request(Command::ResetCommandIndex, &[], true);
                     if result.is_err() {
                         self.pause_polling.store(false, Ordering::Relaxed);
                         return result;
                     }
 
-                    debug!("Resync complete, retrying Command..");
+                    debug!("[{}] Resync complete, retrying Command..", self.describe());
                     let result = self.perform_request(command, body, true);
                     if result.is_err() {
                         self.pause_polling.store(false, Ordering::Relaxed);
                     }
                     return result;
                 } else {
-                    debug!("Resync Failed, Throwing Error..");
+                    debug!("[{}] Resync Failed, Throwing Error..", self.describe());
                     self.pause_polling.store(false, Ordering::Relaxed);
                     self.trigger_disconnect()?;
                     Err(Error::from(rusb::Error::Other))
@@ -408,53 +566,179 @@ impl ExecutableGoXLR for GoXLRUSB {
         let version = self.descriptor.usb_version();
         let usb_version = (version.0, version.1, version.2);
 
-        let device_manufacturer = self.handle.read_manufacturer_string(
-            self.language,
-            &self.descriptor,
-            Duration::from_millis(100),
-        )?;
-
-        let product_name = self.handle.read_product_string(
-            self.language,
-            &self.descriptor,
-            Duration::from_millis(100),
-        )?;
-
         Ok(UsbData {
             vendor_id: self.descriptor.vendor_id(),
             product_id: self.descriptor.product_id(),
             device_version: usb_version,
-            device_manufacturer,
-            product_name,
+            // UsbData reports an absent descriptor as an empty string, manufacturer() and
+            // product_name() keep the distinction for anything that needs it..
+            device_manufacturer: self.manufacturer().unwrap_or_default().to_string(),
+            product_name: self.product_name().unwrap_or_default().to_string(),
         })
     }
+
+    fn describe(&self) -> String {
+        format!(
+            "GoXLR {} (bus {}, address {})",
+            self.device_identifier,
+            self.device.bus_number(),
+            self.device.address()
+        )
+    }
+
+    fn retry_count(&self) -> u64 {
+        self.retry_count
+    }
+
+    fn resync_count(&self) -> u64 {
+        self.resync_count
+    }
+
+    fn reset_request_counters(&mut self) {
+        self.retry_count = 0;
+        self.resync_count = 0;
+    }
 }
 
 impl GoXLRCommands for GoXLRUSB {}
-impl FullGoXLRDevice for GoXLRUSB {}
+impl FullGoXLRDevice for GoXLRUSB {
+    #[cfg(feature = "unsafe-raw")]
+    fn raw(&mut self) -> Option<&mut dyn RawGoXLR> {
+        Some(self)
+    }
+}
+
+#[cfg(feature = "unsafe-raw")]
+impl RawGoXLR for GoXLRUSB {
+    fn write_control(&mut self, request: u8, value: u16, index: u16, data: &[u8]) -> Result<()> {
+        Ok(GoXLRUSB::write_control(self, request, value, index, data)?)
+    }
+
+    fn read_control(
+        &mut self,
+        request: u8,
+        value: u16,
+        index: u16,
+        length: usize,
+    ) -> Result<Vec<u8>> {
+        Ok(GoXLRUSB::read_control(self, request, value, index, length)?)
+    }
+}
 
 pub fn find_devices() -> Vec<GoXLRDevice> {
+    find_devices_with_options(&ScanOptions::default())
+}
+
+pub fn find_devices_with_options(options: &ScanOptions) -> Vec<GoXLRDevice> {
     let mut found_devices: Vec<GoXLRDevice> = Vec::new();
+    let deadline = options.timeout.map(|timeout| Instant::now() + timeout);
 
+    // The descriptors are cached by libusb, so this doesn't need to talk to the devices..
+    let mut descriptors = vec![];
     if let Ok(devices) = rusb::devices() {
         for device in devices.iter() {
             if let Ok(descriptor) = device.device_descriptor() {
-                let bus_number = device.bus_number();
-                let address = device.address();
-
-                if descriptor.vendor_id() == VID_GOXLR
-                    && (descriptor.product_id() == PID_GOXLR_FULL
-                        || descriptor.product_id() == PID_GOXLR_MINI)
-                {
-                    found_devices.push(GoXLRDevice {
-                        bus_number,
-                        address,
-                        identifier: None,
-                    });
-                }
+                let (vendor_id, product_id) = (descriptor.vendor_id(), descriptor.product_id());
+                descriptors.push(((device, descriptor), vendor_id, product_id));
             }
         }
     }
 
+    for ((device, descriptor), state) in detect_goxlrs(descriptors, PID_GOXLR_UPDATE_MODE) {
+        if deadline.is_some_and(|deadline| Instant::now() > deadline) {
+            warn!("Device scan timed out, some devices may be missing");
+            break;
+        }
+
+        // Grab the serial if the device has one, we don't need to claim for this..
+        let serial = match descriptor.serial_number_string_index() {
+            Some(_) if options.read_serials => device.open().ok().and_then(|handle| {
+                GoXLRUSB::read_string_descriptor(&handle, descriptor.serial_number_string_index())
+            }),
+            _ => None,
+        };
+
+        found_devices.push(GoXLRDevice {
+            bus_number: device.bus_number(),
+            address: device.address(),
+            identifier: None,
+            port_path: port_path(&device),
+            product_id: Some(descriptor.product_id()),
+            serial,
+            state,
+        });
+    }
+
     found_devices
 }
+
+// The bus followed by the port numbers through any hubs, this stays the same across a replug..
+fn port_path(device: &Device<GlobalContext>) -> Vec<u8> {
+    let mut path = vec![device.bus_number()];
+    path.extend(device.port_numbers().unwrap_or_default());
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MockHandle {
+        active: u8,
+        interfaces: Vec<u8>,
+        switched_to: Vec<u8>,
+    }
+
+    impl MockHandle {
+        fn new(active: u8, interfaces: &[u8]) -> Self {
+            Self {
+                active,
+                interfaces: interfaces.to_vec(),
+                switched_to: vec![],
+            }
+        }
+    }
+
+    impl ConfigurationHandle for MockHandle {
+        fn active_configuration(&self) -> rusb::Result<u8> {
+            Ok(self.active)
+        }
+
+        fn set_active_configuration(&mut self, config: u8) -> rusb::Result<()> {
+            self.switched_to.push(config);
+            self.active = config;
+            Ok(())
+        }
+
+        fn active_interfaces(&self) -> rusb::Result<(u8, Vec<u8>)> {
+            Ok((self.active, self.interfaces.clone()))
+        }
+    }
+
+    #[test]
+    fn the_expected_configuration_is_left_alone() {
+        let mut handle = MockHandle::new(EXPECTED_CONFIGURATION, &[VENDOR_INTERFACE, 1]);
+        GoXLRUSB::check_configuration(&mut handle).unwrap();
+        assert!(handle.switched_to.is_empty());
+    }
+
+    #[test]
+    fn the_wrong_configuration_is_switched_back() {
+        let mut handle = MockHandle::new(2, &[VENDOR_INTERFACE, 1]);
+        GoXLRUSB::check_configuration(&mut handle).unwrap();
+        assert_eq!(handle.switched_to, vec![EXPECTED_CONFIGURATION]);
+    }
+
+    #[test]
+    fn a_missing_vendor_interface_is_reported() {
+        let mut handle = MockHandle::new(2, &[1, 2]);
+        let error = GoXLRUSB::check_configuration(&mut handle).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<ConnectError>(),
+            Some(ConnectError::InterfaceNotFound(
+                VENDOR_INTERFACE,
+                EXPECTED_CONFIGURATION
+            ))
+        ));
+    }
+}
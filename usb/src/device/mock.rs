@@ -0,0 +1,168 @@
+use crate::commands::Command;
+#[cfg(feature = "unsafe-raw")]
+use crate::device::base::RawGoXLR;
+use crate::device::base::{DeviceIdentifier, ExecutableGoXLR, GoXLRCommands, UsbData};
+use crate::frame::{self, CommandIndex};
+use crate::{PID_GOXLR_FULL, VID_GOXLR};
+use anyhow::{bail, Context, Result};
+
+/**
+ * A device which records the requests it's sent rather than talking to any hardware, used to test
+ * the command encoders and anything layered on top of them. Responses can be queued up against a
+ * command, anything without one gets an empty response. With the unsafe-raw feature it also
+ * accepts raw control transfers, so diagnostic tools can be tried out without a GoXLR attached.
+ */
+pub struct MockDevice {
+    identifier: DeviceIdentifier,
+    product_id: u16,
+    requests: Vec<(Command, Vec<u8>)>,
+    responses: Vec<(Command, Vec<u8>)>,
+    failures: usize,
+    command_index: CommandIndex,
+    indexes: Vec<u16>,
+    #[cfg(feature = "unsafe-raw")]
+    raw_response: Option<Vec<u8>>,
+}
+
+impl MockDevice {
+    pub fn new() -> Self {
+        Self::with_product_id(PID_GOXLR_FULL)
+    }
+
+    /// A device which describes itself with the given product ID, such as a Mini.
+    pub fn with_product_id(product_id: u16) -> Self {
+        Self {
+            identifier: DeviceIdentifier::Serial(String::from("S210600000AB")),
+            product_id,
+            requests: vec![],
+            responses: vec![],
+            failures: 0,
+            command_index: CommandIndex::default(),
+            indexes: vec![],
+            #[cfg(feature = "unsafe-raw")]
+            raw_response: None,
+        }
+    }
+
+    /// Every request that's reached the 'wire', in the order they were sent..
+    pub fn requests(&self) -> &[(Command, Vec<u8>)] {
+        &self.requests
+    }
+
+    /// The command index each of the requests was sent with.
+    pub fn indexes(&self) -> &[u16] {
+        &self.indexes
+    }
+
+    /// Queues up the response to the next request for a command.
+    pub fn respond_with(&mut self, command: Command, body: &[u8]) {
+        self.responses.push((command, body.to_vec()));
+    }
+
+    // Logs the request, and hands back whatever response was queued for it..
+    fn record(&mut self, command: Command, body: &[u8], index: u16) -> Vec<u8> {
+        self.requests.push((command, body.to_vec()));
+        self.indexes.push(index);
+        match self
+            .responses
+            .iter()
+            .position(|(queued, _)| *queued == command)
+        {
+            Some(index) => self.responses.remove(index).1,
+            None => vec![],
+        }
+    }
+
+    /// Fails the next number of requests, as if the device had stopped responding.
+    pub fn fail_next(&mut self, count: usize) {
+        self.failures = count;
+    }
+}
+
+impl Default for MockDevice {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExecutableGoXLR for MockDevice {
+    fn perform_request(&mut self, command: Command, body: &[u8], _retry: bool) -> Result<Vec<u8>> {
+        if self.failures > 0 {
+            self.failures -= 1;
+            bail!("Pipe");
+        }
+
+        // The same as the backends, oversized bodies never reach the wire, and the index is
+        // reset before carrying on if it's run out..
+        frame::check_body_length(command, body)?;
+        let index = match self.command_index.next(command) {
+            Some(index) => index,
+            None => {
+                self.request_data(Command::ResetCommandIndex, &[])?;
+                self.command_index
+                    .next(command)
+                    .context("Command index wasn't reset")?
+            }
+        };
+
+        Ok(self.record(command, body, index))
+    }
+
+    fn get_descriptor(&self) -> Result<UsbData> {
+        Ok(UsbData {
+            vendor_id: VID_GOXLR,
+            product_id: self.product_id,
+            device_version: (1, 0, 0),
+            device_manufacturer: String::from("TC-Helicon"),
+            product_name: String::from("GoXLR"),
+        })
+    }
+
+    fn describe(&self) -> String {
+        format!("GoXLR {} (mock)", self.identifier)
+    }
+}
+
+impl GoXLRCommands for MockDevice {}
+
+/// Raw requests are recorded with the index they were sent with, and the response (echoing the
+/// command and index back, like the device does) is held until it's read.
+#[cfg(feature = "unsafe-raw")]
+impl RawGoXLR for MockDevice {
+    fn write_control(&mut self, request: u8, _value: u16, _index: u16, data: &[u8]) -> Result<()> {
+        if request != frame::REQUEST_SEND_COMMAND {
+            bail!("Unexpected control request {}", request);
+        }
+
+        let header = frame::parse_header(data)?;
+        let command = match Command::from_command_id(header.command_id) {
+            Some(command) => command,
+            None => bail!("Unknown command {:#x}", header.command_id),
+        };
+        let body = &data[frame::HEADER_LENGTH..];
+
+        let response = self.record(command, body, header.command_index);
+        let framed = frame::build_request(command, &response, header.command_index);
+        self.raw_response = Some(framed);
+        Ok(())
+    }
+
+    fn read_control(
+        &mut self,
+        request: u8,
+        _value: u16,
+        _index: u16,
+        length: usize,
+    ) -> Result<Vec<u8>> {
+        if request != frame::REQUEST_READ_RESPONSE {
+            bail!("Unexpected control request {}", request);
+        }
+
+        let mut response = self
+            .raw_response
+            .take()
+            .context("No command has been sent")?;
+        response.truncate(length);
+        Ok(response)
+    }
+}
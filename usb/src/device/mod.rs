@@ -1,10 +1,20 @@
 use crate::device::base::AttachGoXLR;
+use crate::device::base::DeviceIdentifier;
 use crate::device::base::FullGoXLRDevice;
 use crate::device::base::GoXLRDevice;
+use crate::device::base::ScanOptions;
 use anyhow::Result;
 use tokio::sync::mpsc::Sender;
 
 pub mod base;
+pub mod cache;
+#[cfg(any(test, feature = "unsafe-raw"))]
+pub mod mock;
+
+/// Re-locates a device by its identifier, for example after it's been unplugged and replugged.
+pub fn find_device_by_identifier(id: &DeviceIdentifier) -> Option<GoXLRDevice> {
+    GoXLRDevice::from_identifier(id)
+}
 
 cfg_if::cfg_if! {
     if #[cfg(target_os = "windows")] {
@@ -16,6 +26,10 @@ cfg_if::cfg_if! {
             device::find_devices()
         }
 
+        pub fn find_devices_with_options(options: &ScanOptions) -> Vec<GoXLRDevice> {
+            device::find_devices_with_options(options)
+        }
+
         pub fn from_device(
             device: GoXLRDevice,
             disconnect_sender: Sender<String>,
@@ -33,6 +47,10 @@ cfg_if::cfg_if! {
             device::find_devices()
         }
 
+        pub fn find_devices_with_options(options: &ScanOptions) -> Vec<GoXLRDevice> {
+            device::find_devices_with_options(options)
+        }
+
         pub fn from_device(
             device: GoXLRDevice,
             disconnect_sender: Sender<String>,
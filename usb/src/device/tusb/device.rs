@@ -1,12 +1,15 @@
 use crate::commands::Command;
+#[cfg(feature = "unsafe-raw")]
+use crate::device::base::RawGoXLR;
 use crate::device::base::{
-    AttachGoXLR, ExecutableGoXLR, FullGoXLRDevice, GoXLRCommands, GoXLRDevice, UsbData,
+    AttachGoXLR, DeviceIdentifier, DeviceState, ExecutableGoXLR, FullGoXLRDevice, GoXLRCommands,
+    GoXLRDevice, ScanOptions, UsbData,
 };
 use crate::device::tusb::tusbaudio::{
     get_devices, DeviceHandle, EventChannelReceiver, EventChannelSender, TUSB_INTERFACE,
 };
-use anyhow::{bail, Result};
-use byteorder::{ByteOrder, LittleEndian};
+use crate::frame::{self, CommandIndex};
+use anyhow::{bail, Context, Result};
 use log::{debug, error, warn};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
@@ -21,7 +24,12 @@ pub struct TUSBAudioGoXLR {
     // Basic Device Information..
     handle: DeviceHandle,
     identifier: Option<String>,
-    command_count: u16,
+    device_identifier: DeviceIdentifier,
+    command_index: CommandIndex,
+
+    // Diagnostics, see ExecutableGoXLR::retry_count and resync_count..
+    retry_count: u64,
+    resync_count: u64,
 
     // Event Handlers..
     event_receivers: EventChannelReceiver,
@@ -120,6 +128,7 @@ impl AttachGoXLR for TUSBAudioGoXLR {
             device_identifier = Some(identifier.clone());
         }
 
+        let stable_identifier = device.device_identifier();
         let handle = DeviceHandle::from_device(device)?;
 
         // Spawn the Event handler thread..
@@ -133,8 +142,11 @@ impl AttachGoXLR for TUSBAudioGoXLR {
         let mut goxlr = Box::new(Self {
             handle,
             identifier: device_identifier,
+            device_identifier: stable_identifier,
 
-            command_count: 0,
+            command_index: CommandIndex::default(),
+            retry_count: 0,
+            resync_count: 0,
 
             event_receivers,
             disconnect_sender,
@@ -204,6 +216,10 @@ impl AttachGoXLR for TUSBAudioGoXLR {
         Ok(goxlr)
     }
 
+    fn device_identifier(&self) -> DeviceIdentifier {
+        self.device_identifier.clone()
+    }
+
     fn set_unique_identifier(&mut self, identifier: String) {
         // Spawn Notification Thread..
         let mut local_identifier = self.daemon_identifier.lock().unwrap();
@@ -221,6 +237,10 @@ impl AttachGoXLR for TUSBAudioGoXLR {
                 bus_number: 0,
                 address: 0,
                 identifier: self.identifier.clone(),
+                port_path: vec![],
+                product_id: None,
+                serial: None,
+                state: DeviceState::Normal,
             });
 
             if new_handle.is_err() {
@@ -241,27 +261,26 @@ impl AttachGoXLR for TUSBAudioGoXLR {
 
 impl ExecutableGoXLR for TUSBAudioGoXLR {
     fn perform_request(&mut self, command: Command, body: &[u8], retry: bool) -> Result<Vec<u8>> {
-        if command == Command::ResetCommandIndex {
-            self.command_count = 0;
-        } else {
-            if self.command_count == u16::MAX {
+        frame::check_body_length(command, body)?;
+        let command_index = match self.command_index.next(command) {
+            Some(index) => index,
+            None => {
                 let _ = self.request_data(Command::ResetCommandIndex, &[])?;
+                self.command_index
+                    .next(command)
+                    .context("Command index wasn't reset")?
             }
-            self.command_count += 1;
-        }
-
-        let command_index = self.command_count;
-        let mut full_request = vec![0; 16];
-        LittleEndian::write_u32(&mut full_request[0..4], command.command_id());
-        LittleEndian::write_u16(&mut full_request[4..6], body.len() as u16);
-        LittleEndian::write_u16(&mut full_request[6..8], command_index);
-        full_request.extend(body);
+        };
+        let full_request = frame::build_request(command, body, command_index);
 
-        if let Err(error) = self.write_control(2, 0, 0, &full_request) {
+        if let Err(error) = self.write_control(frame::REQUEST_SEND_COMMAND, 0, 0, &full_request) {
             if error.to_string() == "TSTATUS_INVALID_HANDLE" {
                 if self.is_connected() {
                     // Try again..
-                    if let Err(error) = self.write_control(2, 0, 0, &full_request) {
+                    self.retry_count += 1;
+                    if let Err(error) =
+                        self.write_control(frame::REQUEST_SEND_COMMAND, 0, 0, &full_request)
+                    {
                         self.trigger_disconnect();
                         bail!(
                             "Recovered Handle, but still unable to send command: {}",
@@ -285,11 +304,22 @@ impl ExecutableGoXLR for TUSBAudioGoXLR {
             bail!("Event handler has ended, Disconnecting.");
         }
 
-        let mut response_value = self.read_control(3, 0, 0, 1040);
+        let mut response_value = self.read_control(
+            frame::REQUEST_READ_RESPONSE,
+            0,
+            0,
+            frame::MAX_RESPONSE_LENGTH,
+        );
         if let Err(error) = response_value {
             if error.to_string() == "TSTATUS_INVALID_HANDLE" {
                 if self.is_connected() {
-                    response_value = self.read_control(3, 0, 0, 1040);
+                    self.retry_count += 1;
+                    response_value = self.read_control(
+                        frame::REQUEST_READ_RESPONSE,
+                        0,
+                        0,
+                        frame::MAX_RESPONSE_LENGTH,
+                    );
                     if let Err(error) = response_value {
                         self.trigger_disconnect();
                         bail!(
@@ -308,36 +338,47 @@ impl ExecutableGoXLR for TUSBAudioGoXLR {
         }
 
         let mut response_header = response_value?;
-        if response_header.len() < 16 {
-            error!(
-                "Invalid Response received from the GoXLR, Expected: 16, Received: {}",
-                response_header.len()
-            );
-            bail!("Invalid Response");
-        }
+        let header = match frame::parse_header(&response_header) {
+            Ok(header) => header,
+            Err(error) => {
+                error!(
+                    "[{}] Invalid Response received from the GoXLR: {}",
+                    self.describe(),
+                    error
+                );
+                bail!("Invalid Response");
+            }
+        };
 
-        let response = response_header.split_off(16);
-        let response_length = LittleEndian::read_u16(&response_header[4..6]);
-        let response_command_index = LittleEndian::read_u16(&response_header[6..8]);
+        let response = response_header.split_off(frame::HEADER_LENGTH);
+        let response_length = header.body_length;
+        let response_command_index = header.command_index;
 
         if response_command_index != command_index {
-            debug!("Mismatched Command Indexes..");
+            debug!("[{}] Mismatched Command Indexes..", self.describe());
             debug!(
-                "Expected {}, received: {}",
-                command_index, response_command_index
+                "[{}] Expected {}, received: {}",
+                self.describe(),
+                command_index,
+                response_command_index
             );
-            debug!("Full Request: {:?}", full_request);
-            debug!("Response Header: {:?}", response_header);
-            debug!("Response Body: {:?}", response);
+            debug!("[{}] Full Request: {:?}", self.describe(), full_request);
+            debug!(
+                "[{}] Response Header: {:?}",
+                self.describe(),
+                response_header
+            );
+            debug!("[{}] Response Body: {:?}", self.describe(), response);
 
             return if !retry {
-                debug!("Attempting Resync and Retry");
+                debug!("[{}] Attempting Resync and Retry", self.describe());
+                self.resync_count += 1;
                 self.perform_request(Command::ResetCommandIndex, &[], true)?;
 
-                debug!("Resync complete, retrying Command..");
+                debug!("[{}] Resync complete, retrying Command..", self.describe());
                 self.perform_request(command, body, true)
             } else {
-                debug!("Resync Failed, Throwing Error..");
+                debug!("[{}] Resync Failed, Throwing Error..", self.describe());
                 self.trigger_disconnect();
                 bail!("Invalid Response received from GoXLR, disconnecting!");
             };
@@ -358,11 +399,55 @@ impl ExecutableGoXLR for TUSBAudioGoXLR {
             product_name: properties.model()?,
         })
     }
+
+    fn describe(&self) -> String {
+        format!("GoXLR {}", self.device_identifier)
+    }
+
+    fn retry_count(&self) -> u64 {
+        self.retry_count
+    }
+
+    fn resync_count(&self) -> u64 {
+        self.resync_count
+    }
+
+    fn reset_request_counters(&mut self) {
+        self.retry_count = 0;
+        self.resync_count = 0;
+    }
 }
 
 impl GoXLRCommands for TUSBAudioGoXLR {}
-impl FullGoXLRDevice for TUSBAudioGoXLR {}
+impl FullGoXLRDevice for TUSBAudioGoXLR {
+    #[cfg(feature = "unsafe-raw")]
+    fn raw(&mut self) -> Option<&mut dyn RawGoXLR> {
+        Some(self)
+    }
+}
+
+#[cfg(feature = "unsafe-raw")]
+impl RawGoXLR for TUSBAudioGoXLR {
+    fn write_control(&mut self, request: u8, value: u16, index: u16, data: &[u8]) -> Result<()> {
+        TUSBAudioGoXLR::write_control(self, request, value, index, data)
+    }
+
+    fn read_control(
+        &mut self,
+        request: u8,
+        value: u16,
+        index: u16,
+        length: usize,
+    ) -> Result<Vec<u8>> {
+        TUSBAudioGoXLR::read_control(self, request, value, index, length)
+    }
+}
 
 pub fn find_devices() -> Vec<GoXLRDevice> {
     get_devices()
 }
+
+// The driver already hands us a list of GoXLRs, so there's nothing to bound or skip here..
+pub fn find_devices_with_options(_options: &ScanOptions) -> Vec<GoXLRDevice> {
+    get_devices()
+}
@@ -1,7 +1,8 @@
-use crate::device::base::GoXLRDevice;
-use crate::{PID_GOXLR_FULL, PID_GOXLR_MINI, VID_GOXLR};
+use crate::device::base::{DeviceState, GoXLRDevice};
+use crate::is_goxlr;
 use anyhow::{anyhow, bail, Result};
 use byteorder::{ByteOrder, LittleEndian};
+use goxlr_types::DeviceType;
 use lazy_static::lazy_static;
 use libloading::{Library, Symbol};
 use log::{debug, error, info, warn};
@@ -565,10 +566,10 @@ impl TUSBAudio<'_> {
                             let bus_number = device.bus_number();
                             let address = device.address();
 
-                            if descriptor.vendor_id() == VID_GOXLR
-                                && (descriptor.product_id() == PID_GOXLR_FULL
-                                    || descriptor.product_id() == PID_GOXLR_MINI)
-                            {
+                            // Devices in update mode can't be attached, so leave them out..
+                            let device_type =
+                                is_goxlr(descriptor.vendor_id(), descriptor.product_id());
+                            if matches!(device_type, Some(DeviceType::Full | DeviceType::Mini)) {
                                 found_devices.push(USBDevice {
                                     bus_number,
                                     address,
@@ -717,6 +718,10 @@ pub fn get_devices() -> Vec<GoXLRDevice> {
             bus_number: 0,
             address: 0,
             identifier: Some(device),
+            port_path: vec![],
+            product_id: None,
+            serial: None,
+            state: DeviceState::Normal,
         })
     }
     list
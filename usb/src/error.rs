@@ -1,3 +1,5 @@
+use crate::commands::Command;
+
 #[derive(thiserror::Error, Debug)]
 pub enum ConnectError {
     #[error("No GoXLR device was found")]
@@ -11,6 +13,12 @@ pub enum ConnectError {
 
     #[error("Unable to Claim Interface")]
     DeviceNotClaimed,
+
+    #[error("Device is in firmware update mode")]
+    InUpdateMode,
+
+    #[error("Vendor interface {0} not found in configuration {1}")]
+    InterfaceNotFound(u8, u8),
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -20,4 +28,17 @@ pub enum CommandError {
 
     #[error("Malformed response from GoXLR")]
     MalformedResponse(#[from] std::io::Error),
+
+    #[error("Invalid response header, Expected: 16 bytes, Received: {0}")]
+    InvalidHeader(usize),
+
+    #[error("Response too short, Expected: {0} bytes, Received: {1}")]
+    ShortResponse(usize, usize),
+
+    #[error("Body too large for {command:?}, {len} bytes (max: {max})")]
+    BodyTooLarge {
+        command: Command,
+        len: usize,
+        max: usize,
+    },
 }
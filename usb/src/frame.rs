@@ -0,0 +1,252 @@
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::commands::Command;
+use crate::error::CommandError;
+
+/// Every request sent to, and response received from, the GoXLR starts with a 16 byte header.
+pub const HEADER_LENGTH: usize = 16;
+
+/// The vendor control request used to send a command to the GoXLR.
+pub const REQUEST_SEND_COMMAND: u8 = 2;
+
+/// The vendor control request used to read the response to the last command.
+pub const REQUEST_READ_RESPONSE: u8 = 3;
+
+/// The largest response the GoXLR will send, including the header.
+pub const MAX_RESPONSE_LENGTH: usize = 1040;
+
+/// The largest body the firmware accepts in a single command, anything longer is silently
+/// truncated by the device. Some commands have a smaller limit, see Command::max_body_len.
+pub const MAX_BODY_LENGTH: usize = MAX_RESPONSE_LENGTH - HEADER_LENGTH;
+
+/// Firmware packets carry their own header (a type and the offset) in front of the data.
+pub const FIRMWARE_PACKET_HEADER_LENGTH: usize = 12;
+
+/**
+ * The header layout is shared by both requests and responses:
+ *   0..4  - Command ID (u32, little endian)
+ *   4..6  - Body Length (u16, little endian)
+ *   6..8  - Command Index (u16, little endian), echoed back by the device in the response
+ *   8..16 - Unused, always 0
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ResponseHeader {
+    pub command_id: u32,
+    pub body_length: u16,
+    pub command_index: u16,
+}
+
+pub fn pack_header(command: Command, body_len: u16, index: u16) -> [u8; HEADER_LENGTH] {
+    let mut header = [0; HEADER_LENGTH];
+    LittleEndian::write_u32(&mut header[0..4], command.command_id());
+    LittleEndian::write_u16(&mut header[4..6], body_len);
+    LittleEndian::write_u16(&mut header[6..8], index);
+    header
+}
+
+/// Returns the index the next command should be sent with, or None if the index needs to be
+/// reset first. Index 0 belongs to ResetCommandIndex, so regular commands only ever use
+/// 1..=u16::MAX, after a reset the next command is sent as 1.
+pub fn next_command_index(current: u16) -> Option<u16> {
+    current.checked_add(1)
+}
+
+/**
+ * The command index counter each backend keeps for its device. When the counter runs out, next()
+ * returns None, and the backend needs to send ResetCommandIndex (which always goes out as 0)
+ * before trying again.
+ */
+#[derive(Debug, Default)]
+pub struct CommandIndex {
+    current: u16,
+}
+
+impl CommandIndex {
+    /// The index to send a command with, see next_command_index.
+    pub fn next(&mut self, command: Command) -> Option<u16> {
+        let index = match command {
+            Command::ResetCommandIndex => 0,
+            _ => next_command_index(self.current)?,
+        };
+        self.current = index;
+        Some(index)
+    }
+
+    /// Used when the device has been reinitialised, which resets its counter as well.
+    pub fn reset(&mut self) {
+        self.current = 0;
+    }
+}
+
+/// Rejects a body which is too long for the command, this needs to happen before anything is
+/// sent, as the device will accept the request and act on a truncated body.
+pub fn check_body_length(command: Command, body: &[u8]) -> Result<(), CommandError> {
+    let max = command.max_body_len();
+    if body.len() > max {
+        return Err(CommandError::BodyTooLarge {
+            command,
+            len: body.len(),
+            max,
+        });
+    }
+    Ok(())
+}
+
+/// Splits data which needs to be streamed to the device across several commands, leaving room
+/// in each for a header of header_len bytes which the caller prepends (such as the firmware
+/// packet's offset). Returns each chunk alongside its offset in the data.
+pub fn chunk_body(
+    command: Command,
+    data: &[u8],
+    header_len: usize,
+) -> impl Iterator<Item = (u64, &[u8])> {
+    let chunk_size = command.max_body_len().saturating_sub(header_len).max(1);
+    data.chunks(chunk_size)
+        .enumerate()
+        .map(move |(index, chunk)| ((index * chunk_size) as u64, chunk))
+}
+
+/// Builds a full request (header followed by the body) ready to be sent to the device.
+pub fn build_request(command: Command, body: &[u8], index: u16) -> Vec<u8> {
+    let mut request = Vec::with_capacity(HEADER_LENGTH + body.len());
+    request.extend(pack_header(command, body.len() as u16, index));
+    request.extend(body);
+    request
+}
+
+pub fn parse_header(data: &[u8]) -> Result<ResponseHeader, CommandError> {
+    if data.len() < HEADER_LENGTH {
+        return Err(CommandError::InvalidHeader(data.len()));
+    }
+
+    Ok(ResponseHeader {
+        command_id: LittleEndian::read_u32(&data[0..4]),
+        body_length: LittleEndian::read_u16(&data[4..6]),
+        command_index: LittleEndian::read_u16(&data[6..8]),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::FirmwareAction;
+    use crate::device::base::{ExecutableGoXLR, GoXLRCommands};
+    use crate::device::mock::MockDevice;
+    use goxlr_types::{ChannelName, FaderName};
+
+    #[test]
+    fn command_indexes_stop_at_the_end_of_the_range() {
+        assert_eq!(next_command_index(0), Some(1));
+        assert_eq!(next_command_index(u16::MAX - 1), Some(u16::MAX));
+        assert_eq!(next_command_index(u16::MAX), None);
+    }
+
+    #[test]
+    fn command_indexes_wrap_through_a_reset() {
+        let mut device = MockDevice::new();
+        let commands = u16::MAX as usize + 2;
+        for _ in 0..commands {
+            device.set_volume(ChannelName::Mic, 0).unwrap();
+        }
+
+        // Every index is used once, then the reset goes out as 0 and the count starts over..
+        let indexes = device.indexes();
+        let end = u16::MAX as usize;
+        assert_eq!(indexes.len(), commands + 1);
+        assert!(indexes[..end].iter().copied().eq(1..=u16::MAX));
+        assert_eq!(indexes[end..], [0, 1, 2]);
+
+        let requests = device.requests();
+        assert_eq!(
+            requests[end - 1].0,
+            Command::SetChannelVolume(ChannelName::Mic)
+        );
+        assert_eq!(requests[end].0, Command::ResetCommandIndex);
+        assert_eq!(
+            requests[end + 1].0,
+            Command::SetChannelVolume(ChannelName::Mic)
+        );
+    }
+
+    #[test]
+    fn resetting_restarts_the_count() {
+        let mut index = CommandIndex::default();
+        assert_eq!(index.next(Command::SetMonitoredMix), Some(1));
+        assert_eq!(index.next(Command::SetMonitoredMix), Some(2));
+        assert_eq!(index.next(Command::ResetCommandIndex), Some(0));
+        assert_eq!(index.next(Command::SetMonitoredMix), Some(1));
+
+        index.next(Command::SetMonitoredMix);
+        index.reset();
+        assert_eq!(index.next(Command::SetMonitoredMix), Some(1));
+    }
+
+    #[test]
+    fn bodies_are_checked_at_the_boundary() {
+        for (command, max) in [
+            (Command::SetMonitoredMix, MAX_BODY_LENGTH),
+            (Command::SetScribble(FaderName::A), 1024),
+            (Command::SetColourMap(), 520),
+        ] {
+            assert!(check_body_length(command, &vec![0; max]).is_ok());
+            assert!(matches!(
+                check_body_length(command, &vec![0; max + 1]),
+                Err(CommandError::BodyTooLarge { command: rejected, len, max: limit })
+                    if rejected == command && len == max + 1 && limit == max
+            ));
+        }
+    }
+
+    #[test]
+    fn oversized_bodies_never_reach_the_device() {
+        let mut device = MockDevice::new();
+        let command = Command::SetColourMap();
+        assert!(device.request_data(command, &[0; 521]).is_err());
+        assert!(device.requests().is_empty());
+
+        // The rejected body didn't use up a command index..
+        device.request_data(command, &[0; 520]).unwrap();
+        assert_eq!(device.indexes(), &[1]);
+    }
+
+    #[test]
+    fn firmware_chunks_fit_alongside_their_header() {
+        let command = Command::ExecuteFirmwareUpdateAction(FirmwareAction::SEND);
+        let chunk_size = MAX_BODY_LENGTH - FIRMWARE_PACKET_HEADER_LENGTH;
+
+        let data = vec![0; chunk_size];
+        let chunks: Vec<_> = chunk_body(command, &data, FIRMWARE_PACKET_HEADER_LENGTH).collect();
+        assert_eq!(chunks.len(), 1);
+
+        let data = vec![0; chunk_size + 1];
+        let chunks: Vec<_> = chunk_body(command, &data, FIRMWARE_PACKET_HEADER_LENGTH).collect();
+        let offsets: Vec<_> = chunks.iter().map(|(offset, _)| *offset).collect();
+        let lengths: Vec<_> = chunks.iter().map(|(_, chunk)| chunk.len()).collect();
+        assert_eq!(offsets, [0, chunk_size as u64]);
+        assert_eq!(lengths, [chunk_size, 1]);
+
+        // Once the packet header is added, each chunk is still small enough to send..
+        let mut device = MockDevice::new();
+        for (offset, chunk) in chunks {
+            device.send_firmware_packet(offset, chunk).unwrap();
+        }
+        assert_eq!(device.requests()[0].1.len(), MAX_BODY_LENGTH);
+    }
+
+    #[test]
+    fn headers_round_trip() {
+        let command = Command::SetMonitoredMix;
+        let request = build_request(command, &[1], 0x1234);
+        assert_eq!(request.len(), HEADER_LENGTH + 1);
+
+        let header = parse_header(&request).unwrap();
+        assert_eq!(header.command_id, command.command_id());
+        assert_eq!(header.body_length, 1);
+        assert_eq!(header.command_index, 0x1234);
+    }
+
+    #[test]
+    fn short_headers_are_rejected() {
+        assert!(parse_header(&[0; HEADER_LENGTH - 1]).is_err());
+    }
+}
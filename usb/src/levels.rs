@@ -0,0 +1,76 @@
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::error::CommandError;
+
+/// The quietest level reported, a raw value of 1 (20 * log10(1 / 4096)).
+pub const MIN_LEVEL_DB: f64 = -72.2;
+
+/**
+ * The GoXLR only reports a single meter, the microphone input level (before any processing),
+ * as a little endian u16 linear amplitude where 4096 is full scale. This is converted to dBFS
+ * as 20 * log10(raw) - 72.2, clamped to MIN_LEVEL_DB..0. Per-channel and post-processing meters
+ * aren't exposed by the firmware.
+ */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Levels {
+    pub microphone_raw: u16,
+    pub microphone: f64,
+}
+
+impl Levels {
+    pub fn from_response(data: &[u8]) -> Result<Self, CommandError> {
+        if data.len() < 2 {
+            return Err(CommandError::ShortResponse(2, data.len()));
+        }
+
+        let raw = LittleEndian::read_u16(&data[0..2]);
+        Ok(Self {
+            microphone_raw: raw,
+            microphone: raw_to_db(raw),
+        })
+    }
+}
+
+pub fn raw_to_db(raw: u16) -> f64 {
+    ((f64::log10(raw.into()) * 20.) + MIN_LEVEL_DB).clamp(MIN_LEVEL_DB, 0.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(value: f64, expected: f64) {
+        assert!((value - expected).abs() < 0.01, "{value} != {expected}");
+    }
+
+    #[test]
+    fn raw_levels_convert_to_dbfs() {
+        assert_close(raw_to_db(1), MIN_LEVEL_DB);
+        assert_close(raw_to_db(2), MIN_LEVEL_DB + 6.02);
+        assert_close(raw_to_db(2048), -5.97);
+        assert_close(raw_to_db(4096), 0.);
+    }
+
+    #[test]
+    fn levels_are_clamped() {
+        assert_eq!(raw_to_db(0), MIN_LEVEL_DB);
+        assert_eq!(raw_to_db(u16::MAX), 0.);
+    }
+
+    #[test]
+    fn responses_are_little_endian() {
+        let levels = Levels::from_response(&[0x00, 0x08, 0xFF]).unwrap();
+        assert_eq!(levels.microphone_raw, 2048);
+        assert_close(levels.microphone, -5.97);
+    }
+
+    #[test]
+    fn short_responses_are_errors() {
+        for data in [&[][..], &[0x10]] {
+            match Levels::from_response(data) {
+                Err(CommandError::ShortResponse(2, len)) => assert_eq!(len, data.len()),
+                result => panic!("Unexpected result: {result:?}"),
+            }
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use goxlr_types::DeviceType;
+
 pub use rusb;
 pub mod buttonstate;
 pub mod channelstate;
@@ -6,13 +8,62 @@ pub mod commands;
 pub mod dcp;
 pub mod devices;
 pub mod error;
+pub mod frame;
+pub mod levels;
 pub mod microphone;
+pub mod ramp;
+pub mod requests;
 pub mod routing;
 
 pub mod animation;
+pub mod coalesce;
 pub mod colours;
 pub mod device;
 
 pub const VID_GOXLR: u16 = 0x1220;
 pub const PID_GOXLR_MINI: u16 = 0x8fe4;
 pub const PID_GOXLR_FULL: u16 = 0x8fe0;
+
+// The product ID a GoXLR enumerates with while in firmware update (DFU) mode. This hasn't been
+// captured from a real device yet, so until it's filled in update mode devices aren't detected.
+pub const PID_GOXLR_UPDATE_MODE: Option<u16> = None;
+
+/// Checks a USB vendor and product ID against the known GoXLR IDs, returning the type of device
+/// if it's a GoXLR. Devices in update mode report as Unknown, as the product ID doesn't say
+/// which model they are.
+pub fn is_goxlr(vid: u16, pid: u16) -> Option<DeviceType> {
+    if vid != VID_GOXLR {
+        return None;
+    }
+
+    match pid {
+        PID_GOXLR_FULL => Some(DeviceType::Full),
+        PID_GOXLR_MINI => Some(DeviceType::Mini),
+        pid if PID_GOXLR_UPDATE_MODE == Some(pid) => Some(DeviceType::Unknown),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_product_ids_are_goxlrs() {
+        assert_eq!(is_goxlr(VID_GOXLR, PID_GOXLR_FULL), Some(DeviceType::Full));
+        assert_eq!(is_goxlr(VID_GOXLR, PID_GOXLR_MINI), Some(DeviceType::Mini));
+        if let Some(pid) = PID_GOXLR_UPDATE_MODE {
+            assert_eq!(is_goxlr(VID_GOXLR, pid), Some(DeviceType::Unknown));
+        }
+    }
+
+    #[test]
+    fn other_devices_are_not_goxlrs() {
+        assert_eq!(is_goxlr(VID_GOXLR, 0x8fe1), None);
+        assert_eq!(is_goxlr(VID_GOXLR, 0), None);
+
+        // The product IDs only mean anything alongside TC-Helicon's vendor ID..
+        assert_eq!(is_goxlr(0x1221, PID_GOXLR_FULL), None);
+        assert_eq!(is_goxlr(0, PID_GOXLR_MINI), None);
+    }
+}
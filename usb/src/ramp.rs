@@ -0,0 +1,39 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use goxlr_types::ramp::ramp_steps;
+use goxlr_types::ChannelName;
+
+use crate::device::base::GoXLRCommands;
+
+/**
+ * Jumping a channel straight from 255 to 0 can pop audibly, so this walks the volume there over
+ * a period of time instead, the same way the official app does. The ramp can be cancelled by
+ * setting the cancel token (for example, if the user grabs the fader), in which case the volume
+ * is left wherever it had reached and false is returned.
+ */
+pub fn ramp_volume(
+    commands: &mut (impl GoXLRCommands + ?Sized),
+    channel: ChannelName,
+    from: u8,
+    to: u8,
+    duration: Duration,
+    step_interval: Duration,
+    cancel: &AtomicBool,
+) -> Result<bool> {
+    let start = Instant::now();
+    for (offset, volume) in ramp_steps(from, to, duration, step_interval) {
+        let elapsed = start.elapsed();
+        if offset > elapsed {
+            sleep(offset - elapsed);
+        }
+
+        if cancel.load(Ordering::Relaxed) {
+            return Ok(false);
+        }
+        commands.set_volume(channel, volume)?;
+    }
+    Ok(true)
+}
@@ -0,0 +1,152 @@
+use anyhow::{bail, Result};
+use goxlr_types::{ChannelName, FaderName, Mix, OutputDevice, SubMixChannelName};
+
+use crate::animation::{AnimationMode, WaterFallDir};
+use crate::buttonstate::ButtonStates;
+use crate::channelstate::ChannelState;
+use crate::commands::Command;
+
+/**
+ * The command and body sent by each of the GoXLRCommands setters, built without a device. The
+ * setters are implemented on top of these, so anything which needs the commands up front (such
+ * as listing everything needed to apply a profile) sends exactly the same bytes.
+ */
+pub type Request = (Command, Vec<u8>);
+
+// Firmware before 1.3.40 takes a shorter colour map, which is the start of the newer one.
+pub const COLOUR_MAP_LENGTH: usize = 328;
+pub const COLOUR_MAP_LENGTH_1_3_40: usize = 520;
+
+// Used to fill the unused slots in the channel mixes..
+const MIX_UNASSIGNED: u8 = 0x0c;
+
+pub fn set_fader(fader: FaderName, channel: ChannelName) -> Request {
+    // Channel ID, unknown, unknown, unknown
+    (
+        Command::SetFader(fader),
+        vec![channel as u8, 0x00, 0x00, 0x00],
+    )
+}
+
+pub fn set_volume(channel: ChannelName, volume: u8) -> Request {
+    (Command::SetChannelVolume(channel), vec![volume])
+}
+
+pub fn set_channel_state(channel: ChannelName, state: ChannelState) -> Request {
+    (Command::SetChannelState(channel), vec![state.id()])
+}
+
+pub fn set_button_states(data: [ButtonStates; 24]) -> Request {
+    (
+        Command::SetButtonStates(),
+        data.map(|state| state as u8).to_vec(),
+    )
+}
+
+pub fn set_colour_map(map: &[u8; COLOUR_MAP_LENGTH_1_3_40], format_1_3_40: bool) -> Request {
+    let body = match format_1_3_40 {
+        true => map.to_vec(),
+        false => map[..COLOUR_MAP_LENGTH].to_vec(),
+    };
+    (Command::SetColourMap(), body)
+}
+
+pub fn set_fader_display_mode(fader: FaderName, gradient: bool, meter: bool) -> Request {
+    (
+        Command::SetFaderDisplayMode(fader),
+        vec![u8::from(gradient), u8::from(meter)],
+    )
+}
+
+pub fn set_sub_volume(channel: SubMixChannelName, volume: u8) -> Request {
+    (Command::SetSubChannelVolume(channel), vec![volume])
+}
+
+/// Places each output on its mix, in the order given. The headphones aren't part of this, they
+/// follow whichever mix is being monitored (see set_monitored_mix).
+pub fn set_channel_mixes(outputs: &[(OutputDevice, Mix)]) -> Request {
+    let mut mix_a = [MIX_UNASSIGNED; 4];
+    let mut mix_b = [MIX_UNASSIGNED; 4];
+
+    let outputs = outputs
+        .iter()
+        .filter(|(device, _)| *device != OutputDevice::Headphones);
+    for (index, (device, mix)) in outputs.take(4).enumerate() {
+        match mix {
+            Mix::A => mix_a[index] = (*device as u8) * 2,
+            Mix::B => mix_b[index] = (*device as u8) * 2,
+        }
+    }
+    (Command::SetChannelMixes, [mix_a, mix_b].concat())
+}
+
+pub fn set_monitored_mix(mix: Mix) -> Request {
+    (Command::SetMonitoredMix, vec![mix as u8])
+}
+
+pub fn set_animation_mode(
+    enabled: bool,
+    mode: AnimationMode,
+    modifier1: u8,
+    modifier2: u8,
+    waterfall: WaterFallDir,
+) -> Result<Request> {
+    if modifier1 > 100 || modifier2 > 100 {
+        bail!("Modifiers should be < 100");
+    }
+
+    let body = vec![
+        enabled as u8,
+        mode as u8,
+        modifier1,
+        modifier2,
+        waterfall as u8,
+    ];
+    Ok((Command::SetAnimationMode, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colour_map_is_cut_down_for_older_firmware() {
+        let mut map = [0; COLOUR_MAP_LENGTH_1_3_40];
+        map[COLOUR_MAP_LENGTH - 1] = 1;
+        map[COLOUR_MAP_LENGTH] = 2;
+
+        let (command, body) = set_colour_map(&map, false);
+        assert_eq!(command, Command::SetColourMap());
+        assert_eq!(body.len(), COLOUR_MAP_LENGTH);
+        assert_eq!(body.last(), Some(&1));
+
+        let (_, body) = set_colour_map(&map, true);
+        assert_eq!(body, map.to_vec());
+    }
+
+    #[test]
+    fn channel_mixes_place_outputs_on_their_mix() {
+        let (command, body) = set_channel_mixes(&[
+            (OutputDevice::Headphones, Mix::B),
+            (OutputDevice::BroadcastMix, Mix::A),
+            (OutputDevice::ChatMic, Mix::B),
+            (OutputDevice::Sampler, Mix::A),
+            (OutputDevice::LineOut, Mix::B),
+        ]);
+
+        // The headphones are skipped, everything else keeps its slot on whichever mix it's on..
+        assert_eq!(command, Command::SetChannelMixes);
+        assert_eq!(body, vec![0x02, 0x0c, 0x06, 0x0c, 0x0c, 0x04, 0x0c, 0x08]);
+    }
+
+    #[test]
+    fn animation_modifiers_are_range_checked() {
+        let request = set_animation_mode(true, AnimationMode::Simple, 100, 0, WaterFallDir::Up);
+        let (command, body) = request.unwrap();
+        assert_eq!(command, Command::SetAnimationMode);
+        assert_eq!(body, vec![1, AnimationMode::Simple as u8, 100, 0, 1]);
+
+        let request = set_animation_mode(true, AnimationMode::Simple, 101, 0, WaterFallDir::Up);
+        assert!(request.is_err());
+    }
+}
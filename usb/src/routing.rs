@@ -1,4 +1,5 @@
 use goxlr_types::{InputDevice as BasicInputDevice, OutputDevice as BasicOutputDevice};
+use strum::EnumIter;
 
 #[derive(Copy, Clone, Debug)]
 pub enum OutputDevice {
@@ -48,7 +49,7 @@ impl OutputDevice {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, EnumIter, PartialEq, Eq)]
 pub enum InputDevice {
     MicrophoneRight,
     MicrophoneLeft,